@@ -1,5 +1,7 @@
 use std::io;
+use std::path::PathBuf;
 
+use game_case_parser::LibError;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -9,4 +11,10 @@ pub enum CliError{
 
     #[error("Confy config error {0}")]
     ConfigError(#[from] confy::ConfyError),
+
+    #[error("GameCase error: {0}")]
+    Lib(#[from] LibError),
+
+    #[error("{} already exists", .0.display())]
+    AlreadyExists(PathBuf),
 }
\ No newline at end of file
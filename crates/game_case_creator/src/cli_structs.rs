@@ -1,11 +1,15 @@
 use std::path::{PathBuf};
 
+use game_case_parser::DigestAlgorithm;
 use serde::{Serialize, Deserialize};
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct AppConfig{
     pub region: String,
-    pub default_browse_directory: PathBuf
+    pub default_browse_directory: PathBuf,
+    /// Digests stored for every ROM, by element name, e.g. "SHA1".
+    #[serde(default = "default_digests")]
+    pub digests: Vec<String>,
 }
 
 impl Default for AppConfig {
@@ -16,10 +20,30 @@ impl Default for AppConfig {
                 shellexpand::tilde("~")
                 .to_string()
             ),
+            digests: default_digests(),
         }
     }
 }
 
+impl AppConfig {
+    /// Returns the configured digest algorithms, ignoring unknown names.
+    pub fn digest_algorithms(&self) -> Vec<DigestAlgorithm> {
+        DigestAlgorithm::ALL
+            .into_iter()
+            .filter(|algorithm| {
+                self.digests
+                    .iter()
+                    .any(|name| DigestAlgorithm::from_name(name) == Some(*algorithm))
+            })
+            .collect()
+    }
+}
+
+/// The digests No-Intro and Redump DAT files list for every ROM.
+fn default_digests() -> Vec<String> {
+    ["CRC32", "MD5", "SHA1"].map(String::from).to_vec()
+}
+
 #[derive(Clone, Debug, PartialEq, Default)]
 pub enum BuildStep {
     #[default]
@@ -28,7 +52,7 @@ pub enum BuildStep {
     //RomhackSelection,
     //ImageSelection,
     //VideoSelection,
-    //MetaDataInput
+    MetaDataInput,
 }
 
 #[derive(Debug, Default, Clone)]
@@ -39,6 +63,10 @@ pub struct BuildState {
     pub staged_rom_hacks: Vec<PathBuf>,
     pub staged_images: Vec<PathBuf>,
     pub staged_videos: Vec<PathBuf>,
+    /// The mandatory `Game` metadata entered in the last step.
+    pub title: String,
+    pub developer: String,
+    pub game_system: String,
 }
 
 #[derive(Default, Clone)]
@@ -1,5 +1,7 @@
 use std::{
-    path::{Path},
+    env,
+    path::{Path, PathBuf},
+    process,
     sync::{Arc, Mutex},
 };
 
@@ -17,7 +19,7 @@ use cli_structs::{
 
 mod modes;
 use modes::{
-    run_options, run_case_builder
+    run_options, run_case_builder, run_verify_digests, verify_from_args
 };
 
 mod ui_elements;
@@ -25,6 +27,20 @@ mod ui_elements;
 mod storage_io;
 
 fn main() -> Result<(), CliError>{
+    //Verify cases without starting the interface, e.g. for scheduled runs.
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.first().is_some_and(|arg| arg == "verify") {
+        let paths: Vec<PathBuf> = args[1..].iter().map(PathBuf::from).collect();
+        if paths.is_empty() {
+            eprintln!("Usage: game_case_creator verify <case.gcase>...");
+            process::exit(2);
+        }
+        if !verify_from_args(&paths) {
+            process::exit(1);
+        }
+        return Ok(());
+    }
+
     //Load config on start
     let cfg: AppConfig = match confy::load("boxer", "boxer-config") {
         Ok(config) => config,
@@ -56,6 +72,7 @@ fn main() -> Result<(), CliError>{
     let options = vec![
         "Create and build a GameCase",
         "Read GameCase File Info",
+        "Verify GameCase Digests",
         "Options",
     ];
 
@@ -68,9 +85,12 @@ fn main() -> Result<(), CliError>{
             }
             "Read GameCase File Info" => {
 
+            }
+            "Verify GameCase Digests" => {
+                run_verify_digests(s);
             }
             "Options" => {
-                run_options(s, cfg.clone());
+                run_options(s);
             }
             _ => {} //Should not happen but is required.
         }
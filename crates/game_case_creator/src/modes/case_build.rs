@@ -1,13 +1,27 @@
-use std::path::PathBuf;
+use std::fs;
+use std::path::{Path, PathBuf};
 
 use cursive::{
     Cursive
 };
+use cursive::view::{Nameable, Resizable};
+use cursive::views::{Dialog, EditView, LinearLayout, TextView};
+
+use game_case_parser::{
+    Game, GameCase, GameData, GameDataEntry, GameDataPayload, ManualEntry,
+    Manuals, RawData, UidAllocator, UidStrategy,
+};
+
+use crate::back_to_main_menu;
+
+use crate::cli_error_handling::CliError;
 
 use crate::cli_structs::{
     AppState, BuildStep
 };
 
+use crate::storage_io::{read_rom, write_case};
+
 use crate::ui_elements::{
     file_and_directory_selector, show_build_screen
 };
@@ -35,6 +49,7 @@ pub fn run_case_builder(
             "Step 2: Add manuals",
             "Select a manual file".to_string(),
         ),
+        BuildStep::MetaDataInput => show_metadata_screen(siv),
     }
 
 }
@@ -50,6 +65,7 @@ fn show_selection_screen(
             let staged_files = match step {
                 BuildStep::RomSelection => app_state.build_state.staged_roms.clone(),
                 BuildStep::ManualSelection => app_state.build_state.staged_manuals.clone(),
+                BuildStep::MetaDataInput => Vec::new(),
             };
             (staged_files, app_state.config.default_browse_directory.clone())
         })
@@ -68,6 +84,7 @@ fn show_selection_screen(
                             let staged_files_mut = match current_step_clone {
                                 BuildStep::RomSelection => &mut app_state.build_state.staged_roms,
                                 BuildStep::ManualSelection => &mut app_state.build_state.staged_manuals,
+                                BuildStep::MetaDataInput => return,
                             };
                             staged_files_mut.extend(paths);
                         })
@@ -92,7 +109,8 @@ fn show_selection_screen(
         s.with_user_data(|app_state: &mut AppState| {
             app_state.build_state.current_step = match on_next_step.clone() {
                 BuildStep::RomSelection => BuildStep::ManualSelection,
-                BuildStep::ManualSelection => BuildStep::ManualSelection,
+                BuildStep::ManualSelection
+                | BuildStep::MetaDataInput => BuildStep::MetaDataInput,
             };
         })
         .expect("Could not get AppState");
@@ -108,4 +126,224 @@ fn show_selection_screen(
         on_add,
         on_next,
     );
-}
\ No newline at end of file
+}
+
+/// Asks for the mandatory `Game` metadata, then builds the case.
+///
+/// The title defaults to the name of the first staged ROM.
+fn show_metadata_screen(
+    siv: &mut Cursive
+) {
+    let build_state = &siv.user_data::<AppState>().unwrap().build_state;
+    let title = match build_state.title.is_empty() {
+        true => build_state.staged_roms
+            .first()
+            .and_then(|path| path.file_stem())
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default(),
+        false => build_state.title.clone(),
+    };
+
+    let fields = [
+        ("Title", "title", title),
+        ("Developer", "developer", build_state.developer.clone()),
+        ("Game System", "game_system", build_state.game_system.clone()),
+    ];
+
+    let mut layout = LinearLayout::vertical();
+    for (label, name, content) in fields {
+        layout.add_child(TextView::new(label));
+        layout.add_child(
+            EditView::new()
+                .content(content)
+                .with_name(name)
+                .fixed_width(40),
+        );
+    }
+
+    siv.add_layer(
+        Dialog::around(layout)
+            .title("Step 3: Describe the game")
+            .button("Build", |s| {
+                let text = |s: &mut Cursive, name: &str| {
+                    s.call_on_name(name, |view: &mut EditView| {
+                        view.get_content().trim().to_string()
+                    })
+                    .unwrap_or_default()
+                };
+                let title = text(s, "title");
+                let developer = text(s, "developer");
+                let game_system = text(s, "game_system");
+
+                if title.is_empty()
+                    || developer.is_empty()
+                    || game_system.is_empty()
+                {
+                    s.add_layer(Dialog::info(
+                        "Title, Developer and Game System are required."
+                    ));
+                    return;
+                }
+
+                s.with_user_data(|app_state: &mut AppState| {
+                    app_state.build_state.title = title;
+                    app_state.build_state.developer = developer;
+                    app_state.build_state.game_system = game_system;
+                })
+                .expect("Could not get AppState");
+
+                run_build(s);
+            })
+            .button("Cancel", |s| {
+                back_to_main_menu(s);
+            }),
+    );
+}
+
+/// Asks for an output directory and writes the staged files as a case.
+fn run_build(
+    siv: &mut Cursive
+) {
+    let cb_sink = siv.cb_sink().clone();
+    let app_state = siv.user_data::<AppState>().unwrap().clone();
+
+    let on_selection_callback = move |selected_paths: Option<Vec<PathBuf>>| {
+        if let Some(paths) = selected_paths {
+            cb_sink.send(Box::new(|s| {
+                s.add_layer(Dialog::text("Building, please wait..."));
+            })).expect("Could not send callback to UI thread");
+
+            //Reading and hashing happens on the selector's worker thread.
+            let message = match build_and_write(&app_state, &paths[0]) {
+                Ok(path) => format!("Case written to {}", path.display()),
+                Err(e) => format!("Could not build the case: {e}"),
+            };
+
+            cb_sink.send(Box::new(move |s| {
+                s.with_user_data(|app_state: &mut AppState| {
+                    app_state.build_state = Default::default();
+                });
+                back_to_main_menu(s);
+                s.add_layer(Dialog::info(message));
+            })).expect("Could not send callback to UI thread");
+        }
+    };
+
+    file_and_directory_selector(
+        siv.cb_sink().clone(),
+        "Select the directory to write the case to".to_string(),
+        true,
+        on_selection_callback,
+    );
+}
+
+/// Builds a case from the staged files and writes it to `output_dir`.
+///
+/// The case is named after its title, see `case_file_name`. An existing
+/// file is never replaced.
+///
+/// # Returns
+///
+/// A `Result` containing the path of the written case.
+fn build_and_write(
+    app_state: &AppState,
+    output_dir: &Path,
+) -> Result<PathBuf, CliError> {
+    let path = output_dir.join(case_file_name(&app_state.build_state.title));
+    //Checked up front so no ROM is read for a case that cannot be written.
+    if path.exists() {
+        return Err(CliError::AlreadyExists(path));
+    }
+
+    let case = build_case(app_state)?;
+    write_case(&path, &case)?;
+
+    Ok(path)
+}
+
+/// Returns the file name of a case titled `title`.
+///
+/// Path separators and characters some systems do not allow in file names
+/// are replaced, and leading dots are dropped, so the title can neither
+/// point outside of the chosen directory nor name a hidden file.
+fn case_file_name(title: &str) -> String {
+    let name: String = title
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    let name = name.trim_start_matches('.').trim_end_matches(['.', ' ']);
+
+    match name.trim().is_empty() {
+        true => "case.gcase".to_string(),
+        false => format!("{}.gcase", name.trim()),
+    }
+}
+
+/// Builds a case holding every staged ROM and manual.
+///
+/// Every ROM gets its `ROMFileSize` and the digests selected in the
+/// options, computed in the same pass that reads the ROM, see `read_rom`.
+/// Entries are numbered by a sequential `UidAllocator`.
+fn build_case(app_state: &AppState) -> Result<GameCase, CliError> {
+    let config = &app_state.config;
+    let build_state = &app_state.build_state;
+    let algorithms = config.digest_algorithms();
+    let languages = vec![region_language(&config.region).to_string()];
+
+    let mut game_data = GameData::default();
+    for path in &build_state.staged_roms {
+        let (rom_data, mut file_properties) = read_rom(path, &algorithms)?;
+        file_properties.region = Some(config.region.clone());
+        file_properties.languages = languages.clone();
+
+        game_data.entries.push(GameDataEntry {
+            payload: GameDataPayload::Raw(RawData {
+                file_properties,
+                rom_data,
+                ..Default::default()
+            }),
+            ..Default::default()
+        });
+    }
+
+    let mut manuals = Manuals::default();
+    for path in &build_state.staged_manuals {
+        manuals.entries.push(ManualEntry {
+            region: config.region.clone(),
+            languages: languages.clone(),
+            manual_format: path
+                .extension()
+                .map(|ext| ext.to_string_lossy().to_uppercase())
+                .unwrap_or_default(),
+            manual_data: fs::read(path)?,
+            ..Default::default()
+        });
+    }
+
+    let mut case = GameCase {
+        game: Game {
+            title: build_state.title.clone(),
+            developers: vec![build_state.developer.clone()],
+            game_system: build_state.game_system.clone(),
+            game_data: Some(game_data),
+            ..Default::default()
+        },
+        manuals: (!manuals.entries.is_empty()).then_some(manuals),
+        ..Default::default()
+    };
+    UidAllocator::new(UidStrategy::Sequential).assign(&mut case)?;
+
+    Ok(case)
+}
+
+/// Returns the ISO 639-1 code of the main language of a region setting.
+fn region_language(region: &str) -> &'static str {
+    match region {
+        "Japan" => "ja",
+        _ => "en",
+    }
+}
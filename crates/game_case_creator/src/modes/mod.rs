@@ -1,7 +1,9 @@
 mod case_build;
 mod options;
 mod read_info;
+mod verify_digests;
 
 pub use options::*;
 pub use case_build::*;
-pub use read_info::*;
\ No newline at end of file
+pub use read_info::*;
+pub use verify_digests::*;
\ No newline at end of file
@@ -5,10 +5,13 @@ use std::{
 
 use cursive::Cursive;
 use cursive::align::HAlign;
+use cursive::view::Nameable;
 use cursive::views::{
     Dialog, SelectView,
 };
 
+use game_case_parser::DigestAlgorithm;
+
 use crate::cli_structs::{
     AppState, AppConfig
 };
@@ -24,13 +27,13 @@ use crate::{
 
 pub fn run_options(
     siv: &mut Cursive,
-    cfg: AppConfig
 ) {
     
     
     let options = vec![
         "Set Preferred Region",
         "Set Default Browse Directory",
+        "Set ROM Digests",
         "Option 9001",
     ];
     
@@ -45,11 +48,14 @@ pub fn run_options(
     select.set_on_submit(move |s, selection: &str| {
         match selection {
             "Set Preferred Region" => {
-                set_region(s, cfg.clone());
+                set_region(s);
             }
             "Set Default Browse Directory" =>{
                 set_def_dir(s);
             }
+            "Set ROM Digests" => {
+                set_digests(s);
+            }
             _ => {} //Should not happen but is required.
         }
     });
@@ -67,7 +73,6 @@ pub fn run_options(
 
 fn set_region(
     siv: &mut Cursive,
-    cfg: AppConfig
 ) {
     let regions = vec![
         "Europe",
@@ -85,7 +90,8 @@ fn set_region(
     region.add_all_str(regions);
 
     region.set_on_submit(move |s, selection: &str| {
-        let mut config_data = cfg.clone();
+        //Start from the current settings so other options are kept.
+        let mut config_data = s.user_data::<AppState>().unwrap().config.clone();
 
         match selection {
             "Europe" => {
@@ -106,8 +112,12 @@ fn set_region(
         confy::store(
             "boxer", 
             "boxer-config", 
-            config_data
+            config_data.clone()
         ).unwrap();
+
+        s.with_user_data(|app_state: &mut AppState| {
+            app_state.config = config_data;
+        });
     
         s.pop_layer();
     });
@@ -164,4 +174,77 @@ fn set_def_dir(
         true,
         on_selection_callback
     );
-}
\ No newline at end of file
+}
+
+/// Lists every digest algorithm and toggles the one that is submitted.
+fn set_digests(
+    siv: &mut Cursive,
+) {
+    let config = siv.user_data::<AppState>().unwrap().config.clone();
+
+    let mut digests = SelectView::new()
+        //Center the text horizontally
+        .h_align(HAlign::Center)
+        //Use keyboard to jump to the pressed letters
+        .autojump();
+
+    fill_digest_select(&mut digests, &config);
+
+    digests.set_on_submit(|s, algorithm: &DigestAlgorithm| {
+        let algorithm = *algorithm;
+
+        let config_data = s.with_user_data(|app_state: &mut AppState| {
+            let selected = app_state.config
+                .digest_algorithms()
+                .contains(&algorithm);
+
+            if selected {
+                app_state.config.digests.retain(|name| {
+                    DigestAlgorithm::from_name(name) != Some(algorithm)
+                });
+            } else {
+                app_state.config.digests.push(algorithm.name().to_string());
+            }
+
+            app_state.config.clone()
+        })
+        .expect("Could not get AppState");
+
+        confy::store(
+            "boxer", 
+            "boxer-config", 
+            config_data.clone()
+        ).unwrap();
+
+        s.call_on_name("digests", |view: &mut SelectView<DigestAlgorithm>| {
+            let focus = view.selected_id().unwrap_or_default();
+            fill_digest_select(view, &config_data);
+            let _ = view.set_selection(focus);
+        });
+    });
+
+    siv.add_layer(
+        Dialog::around(digests.with_name("digests"))
+            .title("Toggle the digests stored for every ROM.")
+            .button("Go Back", |s| {
+                s.pop_layer();
+            })
+            .button("Main Menu", |s| {
+                back_to_main_menu(s);
+            }),
+    );
+}
+
+/// Lists every digest algorithm, marking the ones that are enabled.
+fn fill_digest_select(
+    view: &mut SelectView<DigestAlgorithm>,
+    cfg: &AppConfig
+) {
+    let selected = cfg.digest_algorithms();
+
+    view.clear();
+    for algorithm in DigestAlgorithm::ALL {
+        let mark = if selected.contains(&algorithm) { "[x]" } else { "[ ]" };
+        view.add_item(format!("{mark} {algorithm}"), algorithm);
+    }
+}
@@ -0,0 +1,128 @@
+use std::path::{Path, PathBuf};
+
+use cursive::Cursive;
+use cursive::view::{Resizable, Scrollable};
+use cursive::views::{Dialog, TextView};
+
+use game_case_parser::FileStatus;
+
+use crate::storage_io::verify_case_file;
+
+use crate::ui_elements::{
+    file_and_directory_selector
+};
+
+use crate::{
+    back_to_main_menu
+};
+
+/// Lets the user pick `.gcase` files and shows whether their stored files
+/// still match their digests.
+pub fn run_verify_digests(
+    siv: &mut Cursive
+) {
+    let cb_sink = siv.cb_sink().clone();
+
+    let on_selection_callback = move |selected_paths: Option<Vec<PathBuf>>| {
+        if let Some(paths) = selected_paths {
+            //Selecting a directory hands over every file within it.
+            let paths: Vec<PathBuf> = paths
+                .into_iter()
+                .filter(|path| is_case_file(path))
+                .collect();
+
+            cb_sink.send(Box::new(|s| {
+                s.add_layer(Dialog::text("Verifying, please wait..."));
+            })).expect("Could not send callback to UI thread");
+
+            //Hashing happens on the selector's worker thread.
+            let report = match paths.is_empty() {
+                true => "No .gcase files were selected.\n".to_string(),
+                false => verification_report(&paths).0,
+            };
+
+            cb_sink.send(Box::new(move |s| {
+                s.pop_layer();
+                s.add_layer(
+                    Dialog::around(
+                        TextView::new(report)
+                            .scrollable()
+                            .max_size((76, 20)),
+                    )
+                    .title("Verification Results")
+                    .button("Main Menu", |s| {
+                        back_to_main_menu(s);
+                    }),
+                );
+            })).expect("Could not send callback to UI thread");
+        }
+    };
+
+    file_and_directory_selector(
+        siv.cb_sink().clone(),
+        "Select a .gcase file or a directory of them".to_string(),
+        false,
+        on_selection_callback,
+    );
+}
+
+/// Verifies the given cases without the interface and prints the results.
+///
+/// Intended for scheduled runs, e.g. `game_case_creator verify <files>`.
+///
+/// # Returns
+///
+/// `true` if no file failed verification and every case could be read.
+pub fn verify_from_args(paths: &[PathBuf]) -> bool {
+    let (report, all_ok) = verification_report(paths);
+    print!("{report}");
+
+    all_ok
+}
+
+/// Returns whether `path` has the `.gcase` extension, ignoring case.
+fn is_case_file(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("gcase"))
+}
+
+/// Verifies every case in `paths` and builds a plain text report.
+///
+/// # Returns
+///
+/// A tuple with the report and whether every case verified without a
+/// failure. Files without stored digests do not count as failures.
+fn verification_report(paths: &[PathBuf]) -> (String, bool) {
+    let mut report = String::new();
+    let mut all_ok = true;
+    let (mut passed, mut failed, mut missing) = (0, 0, 0);
+
+    for path in paths {
+        report.push_str(&format!("{}\n", path.display()));
+
+        match verify_case_file(path) {
+            Ok(files) => {
+                for file in files {
+                    match file.status() {
+                        FileStatus::Pass => passed += 1,
+                        FileStatus::Fail => failed += 1,
+                        FileStatus::Missing => missing += 1,
+                    }
+                    for line in file.to_string().lines() {
+                        report.push_str(&format!("  {line}\n"));
+                    }
+                }
+            }
+            Err(e) => {
+                all_ok = false;
+                report.push_str(&format!("  error: {e}\n"));
+            }
+        }
+    }
+
+    report.push_str(&format!(
+        "\n{passed} passed, {failed} failed, {missing} without digests\n"
+    ));
+
+    (report, all_ok && failed == 0)
+}
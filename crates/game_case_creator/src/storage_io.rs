@@ -1,5 +1,11 @@
 use std::path::{Path, PathBuf};
-use std::fs::{self};
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Write};
+
+use game_case_parser::{
+    copy_with_digests, DigestAlgorithm, FileProperties, FileVerification,
+    GameCase, GameCaseReader, GameCaseWriter,
+};
 
 use crate::cli_error_handling::CliError;
 
@@ -91,4 +97,79 @@ pub fn organize_paths(
 
     /*Return tuple containing the file and directory path vectors. */
     Ok((file_paths, dir_paths))
-}
\ No newline at end of file
+}
+
+/// Verifies every file stored in a `.gcase` file against its digests.
+///
+/// # Arguments
+///
+/// * `path`: The `.gcase` file to verify.
+///
+/// # Returns
+///
+/// A `Result` containing one `FileVerification` per file stored in the
+/// case, or a `CliError` if the case could not be opened or read.
+pub fn verify_case_file(path: &Path) -> Result<Vec<FileVerification>, CliError> {
+    let mut reader = GameCaseReader::open(BufReader::new(File::open(path)?))?;
+
+    Ok(reader.verify(|_, _| {})?)
+}
+
+/// Reads a ROM file, computing its size and digests in the same pass.
+///
+/// The file is streamed through the hashers in small chunks, so it is
+/// read only once and never held twice in memory.
+///
+/// # Arguments
+///
+/// * `path`: The ROM file to read.
+/// * `algorithms`: The digests to compute.
+///
+/// # Returns
+///
+/// A `Result` containing the contents of the file and its
+/// `FileProperties` with `ROMFileName`, `ROMFileSize` and the requested
+/// digests filled in.
+pub fn read_rom(
+    path: &Path,
+    algorithms: &[DigestAlgorithm],
+) -> Result<(Vec<u8>, FileProperties), CliError> {
+    let file = File::open(path)?;
+    //Sized up front so the copy never reallocates the whole ROM.
+    let mut rom_data = Vec::with_capacity(file.metadata()?.len() as usize);
+    let mut source = BufReader::new(file);
+
+    let (size, digests) = copy_with_digests(
+        &mut source,
+        &mut rom_data,
+        algorithms
+    )?;
+
+    let mut properties = FileProperties {
+        rom_file_name: path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string()),
+        rom_file_size: size,
+        ..Default::default()
+    };
+    for (algorithm, value) in digests {
+        algorithm.store(&mut properties, value);
+    }
+
+    Ok((rom_data, properties))
+}
+
+/// Writes a case to `path`, refusing to replace an existing file.
+pub fn write_case(path: &Path, case: &GameCase) -> Result<(), CliError> {
+    let file = File::create_new(path).map_err(|e| match e.kind() {
+        io::ErrorKind::AlreadyExists => {
+            CliError::AlreadyExists(path.to_path_buf())
+        }
+        _ => e.into(),
+    })?;
+    let mut writer = GameCaseWriter::new(BufWriter::new(file));
+    writer.write(case)?;
+    writer.into_inner().flush()?;
+
+    Ok(())
+}
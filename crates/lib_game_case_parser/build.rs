@@ -0,0 +1,30 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+/// Generates the C header for the C ABI in src/ffi.rs.
+///
+/// The header is written to `OUT_DIR`. Setting
+/// `GAME_CASE_PARSER_INCLUDE_DIR` copies it to that directory as well, so C
+/// projects can find it at a stable path.
+fn main() {
+    println!("cargo:rerun-if-changed=src/ffi.rs");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+    println!("cargo:rerun-if-env-changed=GAME_CASE_PARSER_INCLUDE_DIR");
+
+    let crate_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
+    let header = PathBuf::from(env::var("OUT_DIR").unwrap())
+        .join("game_case_parser.h");
+
+    cbindgen::generate(&crate_dir)
+        .expect("Unable to generate the C header")
+        .write_to_file(&header);
+
+    if let Some(include_dir) = env::var_os("GAME_CASE_PARSER_INCLUDE_DIR") {
+        let include_dir = PathBuf::from(include_dir);
+        fs::create_dir_all(&include_dir)
+            .expect("Unable to create the include directory");
+        fs::copy(&header, include_dir.join("game_case_parser.h"))
+            .expect("Unable to copy the C header");
+    }
+}
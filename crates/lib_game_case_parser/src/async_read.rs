@@ -0,0 +1,622 @@
+use std::io::{self, SeekFrom};
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+
+use ebml_iterable::specs::{EbmlSpecification, Master, TagDataType};
+use tokio::io::{
+    AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt, AsyncWrite, ReadBuf,
+};
+
+use crate::ebml_tools::{parse_element_header, ElementHeader};
+use crate::gc_ebml_spec::{
+    element_path, master_id, DocTypeVersions, GCEbmlSpec,
+};
+use crate::lib_error_handling::LibError;
+use crate::lib_structs::{Game, Index, IndexEntry};
+use crate::limits::{LimitTracker, ReaderLimits};
+use crate::parse::{
+    check_ebml_header, decode_elements, parent_path, read_game, read_index,
+    ElementNode,
+};
+use crate::payload::{is_payload, PayloadLocation};
+use crate::read::{decode_entry, entry_offset, CaseEntry, IndexQuery};
+
+/// Random access reader for `.gcase` files on top of an async source.
+///
+/// The async counterpart of `GameCaseReader`, decoding into the same typed
+/// model. Only element headers, the `Index` and the entries asked for are
+/// read, and payloads are streamed through `AsyncPayloadReader`, so no
+/// read blocks the executor for longer than a single element.
+pub struct AsyncGameCaseReader<R: AsyncRead + AsyncSeek + Unpin> {
+    source: R,
+    game_case_start: u64,
+    index: Index,
+    doc_type_versions: DocTypeVersions,
+    limits: ReaderLimits,
+}
+
+impl<R: AsyncRead + AsyncSeek + Unpin> AsyncGameCaseReader<R> {
+    /// Opens a case by reading its header and `Index`.
+    ///
+    /// # Arguments
+    ///
+    /// * `source`: A seekable reader positioned at the start of the file.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the reader, or a `LibError` if the file does
+    /// not start with an EBML header followed by a `GameCase` whose first
+    /// child is the `Index`.
+    pub async fn open(source: R) -> Result<Self, LibError> {
+        Self::open_with_limits(source, ReaderLimits::default()).await
+    }
+
+    /// Opens a case by reading its header and `Index`, checking every
+    /// element loaded through the reader against `limits`.
+    ///
+    /// # Arguments
+    ///
+    /// * `source`: A seekable reader positioned at the start of the file.
+    /// * `limits`: The limits every loaded element is checked against.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the reader, or a `LibError` if the header or
+    /// `Index` break a limit or the file is not laid out as `open`
+    /// expects.
+    pub async fn open_with_limits(
+        mut source: R,
+        limits: ReaderLimits,
+    ) -> Result<Self, LibError> {
+        let start = source.stream_position().await?;
+        let header = load_element(
+            &mut source,
+            start,
+            master_id(GCEbmlSpec::EBML),
+            limits,
+        ).await?;
+        let doc_type_versions = check_ebml_header(&header)?;
+
+        let game_case_start = source.stream_position().await?;
+        expect_header(
+            &mut source,
+            game_case_start,
+            master_id(GCEbmlSpec::GameCase),
+        ).await?;
+
+        let index_start = source.stream_position().await?;
+        let index = read_index(load_element(
+            &mut source,
+            index_start,
+            master_id(GCEbmlSpec::Index),
+            limits,
+        ).await?);
+
+        Ok(AsyncGameCaseReader {
+            source,
+            game_case_start,
+            index,
+            doc_type_versions,
+            limits,
+        })
+    }
+
+    /// Returns the `Index` read when the case was opened.
+    pub fn index(&self) -> &Index {
+        &self.index
+    }
+
+    /// Returns the `DocTypeVersion` and `DocTypeReadVersion` declared by
+    /// the file.
+    pub fn doc_type_versions(&self) -> DocTypeVersions {
+        self.doc_type_versions
+    }
+
+    /// Returns the absolute offset of the `GameCase` element, which every
+    /// `IndexPosition` is relative to.
+    pub fn game_case_start(&self) -> u64 {
+        self.game_case_start
+    }
+
+    /// Returns every index entry matching `query`, in index order.
+    pub fn find<'a>(
+        &'a self,
+        query: &'a IndexQuery,
+    ) -> impl Iterator<Item = &'a IndexEntry> + 'a {
+        self.index.entries.iter().filter(|entry| query.matches(entry))
+    }
+
+    /// Loads the entry an index entry points to.
+    pub async fn read_entry(
+        &mut self,
+        entry: &IndexEntry
+    ) -> Result<CaseEntry, LibError> {
+        let node = load_element(
+            &mut self.source,
+            entry_offset(self.game_case_start, entry)?,
+            entry.target_id,
+            self.limits,
+        ).await?;
+
+        decode_entry(node)
+    }
+
+    /// Loads the entry with the given `EntryUID`.
+    pub async fn read_by_uid(
+        &mut self,
+        uid: u64
+    ) -> Result<CaseEntry, LibError> {
+        let entry = self.index.entries
+            .iter()
+            .find(|entry| entry.uid == uid)
+            .cloned()
+            .ok_or(LibError::EntryNotFound(uid))?;
+
+        self.read_entry(&entry).await
+    }
+
+    /// Loads the first entry matching `query`.
+    ///
+    /// # Returns
+    ///
+    /// `Ok(None)` if no index entry matches the query.
+    pub async fn read_first(
+        &mut self,
+        query: &IndexQuery
+    ) -> Result<Option<CaseEntry>, LibError> {
+        let entry = self.find(query).next().cloned();
+
+        match entry {
+            Some(entry) => self.read_entry(&entry).await.map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Loads the `Game` metadata, such as the title and developers.
+    ///
+    /// `GameData` is skipped, so no ROM is loaded. Its entries are read
+    /// through the `Index` instead and `game_data` is always `None`.
+    pub async fn read_game_info(&mut self) -> Result<Game, LibError> {
+        let game_case_id = master_id(GCEbmlSpec::GameCase);
+        let game_id = master_id(GCEbmlSpec::Game);
+
+        let header = expect_header(
+            &mut self.source,
+            self.game_case_start,
+            game_case_id,
+        ).await?;
+        let end = end_of(self.game_case_start, &header)?;
+        let mut position = self.game_case_start + header.header_len;
+        let mut game = None;
+
+        while position < end {
+            let child = header_at(&mut self.source, position).await?;
+            if child.id == game_id {
+                game = Some((position, child));
+                break;
+            }
+            position = end_of(position, &child)?;
+        }
+
+        let (game_offset, header) = game.ok_or_else(|| {
+            LibError::MissingElement {
+                element: "Game",
+                offset: self.game_case_start,
+                path: element_path(game_case_id),
+            }
+        })?;
+        let end = end_of(game_offset, &header)?;
+        let mut position = game_offset + header.header_len;
+        let mut children = Vec::new();
+
+        while position < end {
+            let child = header_at(&mut self.source, position).await?;
+
+            if child.id != master_id(GCEbmlSpec::GameData) {
+                children.push(load_element(
+                    &mut self.source,
+                    position,
+                    child.id,
+                    self.limits,
+                ).await?);
+            }
+            position = end_of(position, &child)?;
+        }
+
+        read_game(ElementNode {
+            tag: GCEbmlSpec::Game(Master::Start),
+            offset: game_offset,
+            path: element_path(game_id),
+            children,
+        })
+    }
+
+    /// Lists the binary payloads stored within an indexed entry.
+    ///
+    /// Only element headers are read, so this is cheap even for entries
+    /// holding several gigabytes of data.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the payload locations in file order.
+    pub async fn payloads(
+        &mut self,
+        entry: &IndexEntry
+    ) -> Result<Vec<PayloadLocation>, LibError> {
+        let offset = entry_offset(self.game_case_start, entry)?;
+        let header =
+            expect_header(&mut self.source, offset, entry.target_id).await?;
+
+        //Masters still being walked, as the position of their next child
+        //and their end.
+        let mut open = vec![
+            (offset + header.header_len, end_of(offset, &header)?)
+        ];
+        let mut payloads = Vec::new();
+
+        while let Some((position, end)) = open.last_mut() {
+            if *position >= *end {
+                open.pop();
+                continue;
+            }
+
+            let child_offset = *position;
+            let child = header_at(&mut self.source, child_offset).await?;
+            let data_start = child_offset + child.header_len;
+            *position = end_of(child_offset, &child)?;
+
+            if is_payload(child.id) {
+                payloads.push(PayloadLocation {
+                    id: child.id,
+                    offset: data_start,
+                    size: child.size,
+                });
+            } else if GCEbmlSpec::get_tag_data_type(child.id)
+                == Some(TagDataType::Master)
+            {
+                open.push((data_start, data_start + child.size));
+            }
+        }
+
+        Ok(payloads)
+    }
+
+    /// Opens a bounded reader over a single payload.
+    ///
+    /// The payload is streamed from the source as it is read and is never
+    /// buffered as a whole.
+    pub async fn open_payload(
+        &mut self,
+        payload: &PayloadLocation
+    ) -> Result<AsyncPayloadReader<'_, R>, LibError> {
+        self.source.seek(SeekFrom::Start(payload.offset)).await?;
+
+        Ok(AsyncPayloadReader::new(&mut self.source, payload.size))
+    }
+
+    /// Copies a single payload to `dest`.
+    ///
+    /// # Arguments
+    ///
+    /// * `payload`: The payload to copy.
+    /// * `dest`: Where the payload is written to.
+    /// * `progress`: Called with the number of bytes copied so far and the
+    ///   total size of the payload.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the number of bytes copied, or a `LibError` if
+    /// the source ends before the payload does.
+    pub async fn copy_payload<W, F>(
+        &mut self,
+        payload: &PayloadLocation,
+        dest: &mut W,
+        progress: F,
+    ) -> Result<u64, LibError>
+    where
+        W: AsyncWrite + Unpin,
+        F: FnMut(u64, u64) + Send,
+    {
+        let mut reader =
+            self.open_payload(payload).await?.with_progress(progress);
+        let copied = tokio::io::copy(&mut reader, dest).await?;
+
+        if copied != payload.size {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+
+        Ok(copied)
+    }
+
+    /// Consumes the reader and returns the underlying source.
+    pub fn into_inner(self) -> R {
+        self.source
+    }
+}
+
+/// Callback receiving the number of bytes streamed so far and the total
+/// size of the payload.
+///
+/// Unlike `ProgressCallback` it has to be `Send`, so readers can be moved
+/// between the threads of a runtime.
+pub type AsyncProgressCallback<'a> = Box<dyn FnMut(u64, u64) + Send + 'a>;
+
+/// A bounded async reader over a single binary payload.
+///
+/// The async counterpart of `PayloadReader`. Reads never go past the end
+/// of the payload.
+pub struct AsyncPayloadReader<'a, R: AsyncRead + Unpin> {
+    source: &'a mut R,
+    size: u64,
+    remaining: u64,
+    progress: Option<AsyncProgressCallback<'a>>,
+}
+
+impl<'a, R: AsyncRead + Unpin> AsyncPayloadReader<'a, R> {
+    /// Wraps `source`, which must already be positioned at the start of
+    /// the payload.
+    pub(crate) fn new(source: &'a mut R, size: u64) -> Self {
+        AsyncPayloadReader {
+            source,
+            size,
+            remaining: size,
+            progress: None,
+        }
+    }
+
+    /// Registers a callback that is invoked after every read.
+    ///
+    /// # Arguments
+    ///
+    /// * `progress`: Called with the number of bytes read so far and the
+    ///   total size of the payload.
+    pub fn with_progress<F>(mut self, progress: F) -> Self
+    where
+        F: FnMut(u64, u64) + Send + 'a,
+    {
+        self.progress = Some(Box::new(progress));
+        self
+    }
+
+    /// Total size of the payload in bytes.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Number of payload bytes that have not been read yet.
+    pub fn remaining(&self) -> u64 {
+        self.remaining
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for AsyncPayloadReader<'_, R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        if this.remaining == 0 || buf.remaining() == 0 {
+            return Poll::Ready(Ok(()));
+        }
+
+        //Reads are limited to the rest of the payload.
+        let limit = buf.remaining().min(
+            usize::try_from(this.remaining).unwrap_or(usize::MAX)
+        );
+        let mut limited = ReadBuf::new(buf.initialize_unfilled_to(limit));
+        ready!(Pin::new(&mut *this.source).poll_read(cx, &mut limited))?;
+
+        let read = limited.filled().len();
+        buf.advance(read);
+        this.remaining -= read as u64;
+
+        if let Some(progress) = this.progress.as_mut().filter(|_| read > 0) {
+            progress(this.size - this.remaining, this.size);
+        }
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// Reads the element header at the current position of `source`.
+///
+/// # Returns
+///
+/// `Ok(None)` if `source` is already at its end, otherwise the parsed
+/// header with `source` positioned at the start of the element's data.
+async fn read_element_header<R: AsyncRead + Unpin>(
+    source: &mut R
+) -> io::Result<Option<ElementHeader>> {
+    let mut buffer = [0u8; 12];
+    if source.read(&mut buffer[..1]).await? == 0 {
+        return Ok(None);
+    }
+
+    let invalid = || io::Error::new(
+        io::ErrorKind::InvalidData, "invalid EBML element header"
+    );
+
+    let id_len = buffer[0].leading_zeros() as usize + 1;
+    if id_len > 4 {
+        return Err(invalid());
+    }
+    source.read_exact(&mut buffer[1..id_len + 1]).await?;
+
+    let size_len = buffer[id_len].leading_zeros() as usize + 1;
+    if size_len > 8 {
+        return Err(invalid());
+    }
+    source.read_exact(&mut buffer[id_len + 1..id_len + size_len]).await?;
+
+    parse_element_header(&buffer[..id_len + size_len])
+        .map(Some)
+        .ok_or_else(invalid)
+}
+
+/// Returns the offset of the end of the element whose header was read at
+/// `offset`, or `LibError::TruncatedMaster` if it would end past the
+/// largest possible file.
+fn end_of(offset: u64, header: &ElementHeader) -> Result<u64, LibError> {
+    offset
+        .checked_add(header.total_len())
+        .ok_or_else(|| LibError::TruncatedMaster {
+            offset,
+            path: element_path(header.id),
+        })
+}
+
+/// Reads the element header at `offset`, failing at the end of `source`.
+async fn header_at<R: AsyncRead + AsyncSeek + Unpin>(
+    source: &mut R,
+    offset: u64,
+) -> Result<ElementHeader, LibError> {
+    source.seek(SeekFrom::Start(offset)).await?;
+
+    read_element_header(source)
+        .await?
+        .ok_or(io::Error::from(io::ErrorKind::UnexpectedEof).into())
+}
+
+/// Reads the element header at `offset` and checks that it has the
+/// expected ID.
+async fn expect_header<R: AsyncRead + AsyncSeek + Unpin>(
+    source: &mut R,
+    offset: u64,
+    expected: u64,
+) -> Result<ElementHeader, LibError> {
+    let header = header_at(source, offset).await?;
+
+    if header.id != expected {
+        return Err(LibError::UnexpectedElement {
+            expected,
+            found: header.id,
+            offset,
+            path: element_path(expected),
+        });
+    }
+
+    Ok(header)
+}
+
+/// Reads and decodes the complete element starting at `offset`.
+///
+/// The element is read into memory before it is decoded, so decoding never
+/// waits on the source. It is checked against `limits` while it is read,
+/// and memory grows with the bytes actually read rather than the size the
+/// element claims.
+async fn load_element<R: AsyncRead + AsyncSeek + Unpin>(
+    source: &mut R,
+    offset: u64,
+    expected: u64,
+    limits: ReaderLimits,
+) -> Result<ElementNode, LibError> {
+    let header = expect_header(source, offset, expected).await?;
+
+    let mut tracker = LimitTracker::new(limits, offset, &parent_path(expected));
+    let mut bytes = Vec::new();
+    let mut chunk = vec![0u8; 64 * 1024];
+    source.seek(SeekFrom::Start(offset)).await?;
+    while (bytes.len() as u64) < header.total_len() {
+        let wanted = (header.total_len() - bytes.len() as u64)
+            .min(chunk.len() as u64) as usize;
+        let read = source.read(&mut chunk[..wanted]).await?;
+        if read == 0 {
+            return Err(LibError::Io(io::ErrorKind::UnexpectedEof.into()));
+        }
+        tracker.feed(&chunk[..read])?;
+        bytes.extend_from_slice(&chunk[..read]);
+    }
+
+    decode_elements(&bytes, offset)?
+        .into_iter()
+        .next()
+        .ok_or(LibError::Io(io::ErrorKind::UnexpectedEof.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future::Future;
+    use std::io::{Cursor, Read};
+    use std::task::Waker;
+
+    use super::*;
+    use crate::read::GameCaseReader;
+    use crate::write::tests::{encode_case, sample_case};
+
+    /// Polls `future` to completion. Reading from memory never waits, so
+    /// no runtime is needed.
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = std::pin::pin!(future);
+        let mut context = Context::from_waker(Waker::noop());
+
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut context) {
+                return output;
+            }
+        }
+    }
+
+    #[test]
+    fn async_reader_agrees_with_the_reader() {
+        let bytes = encode_case(&sample_case());
+        let mut reader =
+            GameCaseReader::open(Cursor::new(bytes.clone())).unwrap();
+        let mut async_reader =
+            block_on(AsyncGameCaseReader::open(Cursor::new(bytes))).unwrap();
+
+        assert_eq!(async_reader.index(), reader.index());
+        assert_eq!(
+            block_on(async_reader.read_game_info()).unwrap(),
+            reader.read_game_info().unwrap()
+        );
+
+        for entry in reader.index().entries.clone() {
+            assert_eq!(
+                block_on(async_reader.read_entry(&entry)).unwrap(),
+                reader.read_entry(&entry).unwrap()
+            );
+
+            let payloads = block_on(async_reader.payloads(&entry)).unwrap();
+            assert_eq!(payloads, reader.payloads(&entry).unwrap());
+
+            for payload in &payloads {
+                let mut expected = Vec::new();
+                reader
+                    .open_payload(payload)
+                    .unwrap()
+                    .read_to_end(&mut expected)
+                    .unwrap();
+                let mut data = Vec::new();
+                block_on(async {
+                    let mut payload_reader =
+                        async_reader.open_payload(payload).await?;
+                    payload_reader.read_to_end(&mut data).await?;
+                    Ok::<_, LibError>(())
+                })
+                .unwrap();
+                assert_eq!(data, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn position_past_any_file_is_not_found() {
+        let bytes = encode_case(&sample_case());
+        let mut reader =
+            block_on(AsyncGameCaseReader::open(Cursor::new(bytes))).unwrap();
+        let entry = IndexEntry {
+            position: u64::MAX,
+            ..reader.index().entries[0].clone()
+        };
+
+        assert!(matches!(
+            block_on(reader.read_entry(&entry)),
+            Err(LibError::EntryNotFound(1))
+        ));
+        assert!(matches!(
+            block_on(reader.payloads(&entry)),
+            Err(LibError::EntryNotFound(1))
+        ));
+    }
+}
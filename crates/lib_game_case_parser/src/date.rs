@@ -0,0 +1,470 @@
+use std::fmt;
+use std::str::FromStr;
+
+#[cfg(feature = "serde")]
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+/// Nanoseconds in one day.
+const NANOS_PER_DAY: i64 = 86_400_000_000_000;
+
+/// Days from 1970-01-01 to 2001-01-01, the EBML Date epoch.
+const EPOCH_DAYS: i64 = 11_323;
+
+/// A calendar date which may only be known to the year or the month.
+///
+/// Old releases frequently only have a known year or year and month, so
+/// `day` can only be set if `month` is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct GameDate {
+    pub year: i32,
+    pub month: Option<u8>,
+    pub day: Option<u8>,
+}
+
+impl GameDate {
+    /// Creates a date known to the day.
+    ///
+    /// # Returns
+    ///
+    /// `None` if the month or day does not exist.
+    pub fn from_ymd(year: i32, month: u8, day: u8) -> Option<GameDate> {
+        let valid = (1..=12).contains(&month)
+            && day >= 1
+            && day <= days_in_month(year, month);
+
+        valid.then_some(GameDate {
+            year,
+            month: Some(month),
+            day: Some(day),
+        })
+    }
+
+    /// Creates a date of which only the year and month are known.
+    ///
+    /// # Returns
+    ///
+    /// `None` if the month does not exist.
+    pub fn from_year_month(year: i32, month: u8) -> Option<GameDate> {
+        (1..=12).contains(&month).then_some(GameDate {
+            year,
+            month: Some(month),
+            day: None,
+        })
+    }
+
+    /// Creates a date of which only the year is known.
+    pub fn from_year(year: i32) -> GameDate {
+        GameDate { year, month: None, day: None }
+    }
+
+    /// Returns whether the date is known to the day.
+    pub fn is_complete(&self) -> bool {
+        self.day.is_some()
+    }
+
+    /// Converts an EBML Date to the day it falls on, dropping the time of
+    /// day.
+    pub fn from_nanos(nanos: i64) -> GameDate {
+        let (year, month, day) =
+            civil_from_days(nanos.div_euclid(NANOS_PER_DAY) + EPOCH_DAYS);
+
+        GameDate { year, month: Some(month), day: Some(day) }
+    }
+
+    /// Converts the date to an EBML Date, the nanoseconds from
+    /// 2001-01-01T00:00:00 UTC to the start of the day.
+    ///
+    /// An unknown month or day is taken as the first one.
+    ///
+    /// # Returns
+    ///
+    /// `None` if the date lies outside of the range an EBML Date can hold,
+    /// roughly the years 1709 to 2293.
+    pub fn to_nanos(&self) -> Option<i64> {
+        let days = days_from_civil(
+            self.year,
+            self.month.unwrap_or(1),
+            self.day.unwrap_or(1),
+        );
+
+        (days - EPOCH_DAYS).checked_mul(NANOS_PER_DAY)
+    }
+}
+
+impl Default for GameDate {
+    /// The EBML Date epoch, 2001-01-01.
+    fn default() -> Self {
+        GameDate { year: 2001, month: Some(1), day: Some(1) }
+    }
+}
+
+impl fmt::Display for GameDate {
+    /// Formats the date as ISO 8601 with reduced precision for partial
+    /// dates, e.g. "1998", "1998-11" or "1998-11-21".
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04}", self.year)?;
+        if let Some(month) = self.month {
+            write!(f, "-{month:02}")?;
+        }
+        if let Some(day) = self.day {
+            write!(f, "-{day:02}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for GameDate {
+    type Err = ();
+
+    /// Parses "YYYY", "YYYY-MM", "YYYY-MM-DD" or "YYYYMMDD".
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if !s.is_ascii() {
+            return Err(());
+        }
+
+        let number = |part: &str| -> Result<u32, ()> {
+            if part.is_empty() || !part.bytes().all(|b| b.is_ascii_digit()) {
+                return Err(());
+            }
+            part.parse().map_err(|_| ())
+        };
+
+        let parts: Vec<&str> = match s.len() {
+            8 if !s.contains('-') => vec![&s[..4], &s[4..6], &s[6..]],
+            _ => s.split('-').collect(),
+        };
+        if parts[0].len() != 4 {
+            return Err(());
+        }
+        let year = number(parts[0])? as i32;
+
+        match parts[1..] {
+            [] => Ok(GameDate::from_year(year)),
+            [month] if month.len() == 2 => {
+                GameDate::from_year_month(year, number(month)? as u8).ok_or(())
+            }
+            [month, day] if month.len() == 2 && day.len() == 2 => {
+                GameDate::from_ymd(
+                    year,
+                    number(month)? as u8,
+                    number(day)? as u8,
+                )
+                .ok_or(())
+            }
+            _ => Err(()),
+        }
+    }
+}
+
+/// Serialized as ISO 8601 text, see `Display`.
+#[cfg(feature = "serde")]
+impl Serialize for GameDate {
+    fn serialize<S: Serializer>(
+        &self,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for GameDate {
+    fn deserialize<D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Self, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        text.parse().map_err(|()| {
+            de::Error::custom(format!("\"{text}\" is not an ISO 8601 date"))
+        })
+    }
+}
+
+/// The value of a `ReleaseDate` element and its `ReleaseDatePrecision`.
+///
+/// Dates are stored as an EBML Date, 8 bytes holding the signed
+/// nanoseconds since 2001-01-01T00:00:00 UTC. Partial dates are stored as
+/// the first day they cover, e.g. 1998-11-01 for "1998-11", with
+/// `ReleaseDatePrecision` telling how much of it is known.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(from = "ReleaseDateRepr", into = "ReleaseDateRepr")
+)]
+pub enum ReleaseDate {
+    Date(GameDate),
+    /// Data written before `ReleaseDate` was a proper Date that could not
+    /// be interpreted. It is kept as is so it survives a round trip.
+    Legacy(Vec<u8>),
+}
+
+impl ReleaseDate {
+    /// `ReleaseDatePrecision` of a date known to the day, the default.
+    pub const PRECISION_DAY: u64 = 0;
+    /// `ReleaseDatePrecision` of a date known to the month.
+    pub const PRECISION_MONTH: u64 = 1;
+    /// `ReleaseDatePrecision` of a date known to the year.
+    pub const PRECISION_YEAR: u64 = 2;
+
+    /// Decodes the data of a `ReleaseDate` element known to the day.
+    ///
+    /// Besides EBML Dates, older files may contain ISO 8601 or "YYYYMMDD"
+    /// text. Anything else is returned as `Legacy`.
+    pub fn from_bytes(data: &[u8]) -> ReleaseDate {
+        /*Text is tried first. Eight ASCII digits read as an EBML Date would
+        lie past the year 2100, so they are not mistaken for one. */
+        let text = std::str::from_utf8(data).ok().and_then(|text| {
+            text.trim_end_matches('\0').parse::<GameDate>().ok()
+        });
+
+        match (text, data.len()) {
+            (Some(date), _) => ReleaseDate::Date(date),
+            //An empty Date is the epoch itself.
+            (None, 0) => ReleaseDate::Date(GameDate::default()),
+            (None, 8) => {
+                let mut nanos = [0u8; 8];
+                nanos.copy_from_slice(data);
+                ReleaseDate::Date(GameDate::from_nanos(i64::from_be_bytes(nanos)))
+            }
+            (None, _) => ReleaseDate::Legacy(data.to_vec()),
+        }
+    }
+
+    /// Decodes a `ReleaseDate` element together with its
+    /// `ReleaseDatePrecision`.
+    ///
+    /// # Arguments
+    ///
+    /// * `data`: The data of the `ReleaseDate` element.
+    /// * `precision`: The value of `ReleaseDatePrecision`, or
+    ///   `PRECISION_DAY` if the element is absent. Unknown values are
+    ///   taken as `PRECISION_YEAR`.
+    pub fn from_element(data: &[u8], precision: u64) -> ReleaseDate {
+        match ReleaseDate::from_bytes(data) {
+            ReleaseDate::Date(date) => {
+                let date = match precision {
+                    ReleaseDate::PRECISION_DAY => date,
+                    ReleaseDate::PRECISION_MONTH => {
+                        GameDate { day: None, ..date }
+                    }
+                    _ => GameDate::from_year(date.year),
+                };
+                ReleaseDate::Date(date)
+            }
+            legacy => legacy,
+        }
+    }
+
+    /// Encodes the date as the data of a `ReleaseDate` element, see
+    /// `precision` for the rest of it.
+    ///
+    /// Dates an EBML Date cannot hold are clamped to the earliest or latest
+    /// one it can. `Legacy` data is written back unchanged.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            ReleaseDate::Date(date) => {
+                let nanos = date.to_nanos().unwrap_or(match date.year {
+                    ..2001 => i64::MIN,
+                    _ => i64::MAX,
+                });
+                nanos.to_be_bytes().to_vec()
+            }
+            ReleaseDate::Legacy(data) => data.clone(),
+        }
+    }
+
+    /// Returns the value of the `ReleaseDatePrecision` element for the
+    /// date, `PRECISION_DAY` unless it is partial.
+    pub fn precision(&self) -> u64 {
+        match self {
+            ReleaseDate::Date(GameDate { day: Some(_), .. })
+            | ReleaseDate::Legacy(_) => ReleaseDate::PRECISION_DAY,
+            ReleaseDate::Date(GameDate { month: Some(_), .. }) => {
+                ReleaseDate::PRECISION_MONTH
+            }
+            ReleaseDate::Date(_) => ReleaseDate::PRECISION_YEAR,
+        }
+    }
+
+    /// Returns the date, unless the element held uninterpretable data.
+    pub fn date(&self) -> Option<GameDate> {
+        match self {
+            ReleaseDate::Date(date) => Some(*date),
+            ReleaseDate::Legacy(_) => None,
+        }
+    }
+}
+
+impl Default for ReleaseDate {
+    fn default() -> Self {
+        ReleaseDate::Date(GameDate::default())
+    }
+}
+
+impl From<GameDate> for ReleaseDate {
+    fn from(date: GameDate) -> Self {
+        ReleaseDate::Date(date)
+    }
+}
+
+/// Serialized form of a `ReleaseDate`. Dates are plain text, legacy data is
+/// a table holding it hex encoded, so it cannot be mistaken for a date.
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum ReleaseDateRepr {
+    Date(GameDate),
+    Legacy {
+        #[serde(with = "crate::metadata::hex")]
+        legacy: Vec<u8>,
+    },
+}
+
+#[cfg(feature = "serde")]
+impl From<ReleaseDateRepr> for ReleaseDate {
+    fn from(repr: ReleaseDateRepr) -> Self {
+        match repr {
+            ReleaseDateRepr::Date(date) => ReleaseDate::Date(date),
+            ReleaseDateRepr::Legacy { legacy } => ReleaseDate::Legacy(legacy),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<ReleaseDate> for ReleaseDateRepr {
+    fn from(date: ReleaseDate) -> Self {
+        match date {
+            ReleaseDate::Date(date) => ReleaseDateRepr::Date(date),
+            ReleaseDate::Legacy(legacy) => ReleaseDateRepr::Legacy { legacy },
+        }
+    }
+}
+
+fn is_leap_year(year: i32) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+fn days_in_month(year: i32, month: u8) -> u8 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Days since 1970-01-01 of a proleptic Gregorian date.
+///
+/// Uses the algorithm described in Howard Hinnant's "chrono-Compatible
+/// Low-Level Date Algorithms".
+fn days_from_civil(year: i32, month: u8, day: u8) -> i64 {
+    let year = i64::from(year) - i64::from(month <= 2);
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = i64::from(month);
+    let day_of_year =
+        (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5
+        + i64::from(day) - 1;
+    let day_of_era =
+        year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Inverse of `days_from_civil`.
+fn civil_from_days(days: i64) -> (i32, u8, u8) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524
+        - day_of_era / 146_096) / 365;
+    let day_of_year =
+        day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    (year as i32, month as u8, day as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nanos_count_from_2001() {
+        let epoch = GameDate::from_ymd(2001, 1, 1).unwrap();
+        let next_day = GameDate::from_ymd(2001, 1, 2).unwrap();
+
+        assert_eq!(epoch.to_nanos(), Some(0));
+        assert_eq!(next_day.to_nanos(), Some(NANOS_PER_DAY));
+        assert_eq!(GameDate::from_nanos(0), epoch);
+    }
+
+    #[test]
+    fn dates_before_2001_are_negative() {
+        let unix_epoch = GameDate::from_ymd(1970, 1, 1).unwrap();
+        let nanos = -978_307_200_000_000_000;
+
+        assert_eq!(unix_epoch.to_nanos(), Some(nanos));
+        assert_eq!(GameDate::from_nanos(nanos), unix_epoch);
+        //The time of day is dropped, even one nanosecond before midnight.
+        assert_eq!(
+            GameDate::from_nanos(-1),
+            GameDate::from_ymd(2000, 12, 31).unwrap()
+        );
+    }
+
+    #[test]
+    fn nanos_round_trip() {
+        let dates = [
+            GameDate::from_ymd(1985, 9, 13).unwrap(),
+            GameDate::from_ymd(2000, 2, 29).unwrap(),
+            GameDate::from_ymd(2024, 2, 29).unwrap(),
+            GameDate::from_ymd(1900, 3, 1).unwrap(),
+        ];
+
+        for date in dates {
+            assert_eq!(GameDate::from_nanos(date.to_nanos().unwrap()), date);
+        }
+    }
+
+    #[test]
+    fn partial_dates_keep_their_precision() {
+        let dates = [
+            GameDate::from_year(1983),
+            GameDate::from_year_month(1998, 11).unwrap(),
+            GameDate::from_ymd(1996, 6, 23).unwrap(),
+        ];
+
+        for date in dates {
+            let release_date = ReleaseDate::from(date);
+            let bytes = release_date.to_bytes();
+
+            assert_eq!(bytes.len(), 8);
+            assert_eq!(
+                ReleaseDate::from_element(&bytes, release_date.precision()),
+                release_date
+            );
+        }
+    }
+
+    #[test]
+    fn legacy_data_is_still_read() {
+        let date = GameDate::from_ymd(1998, 11, 21).unwrap();
+
+        assert_eq!(ReleaseDate::from_bytes(b"19981121"), date.into());
+        assert_eq!(ReleaseDate::from_bytes(b"1998-11-21"), date.into());
+        assert_eq!(
+            ReleaseDate::from_bytes(b"Nov 98"),
+            ReleaseDate::Legacy(b"Nov 98".to_vec())
+        );
+        assert_eq!(ReleaseDate::from_bytes(&[]), ReleaseDate::default());
+    }
+}
@@ -0,0 +1,242 @@
+use std::fmt;
+use std::io::{self, Read, Write};
+
+use md5::Md5;
+use sha1::Sha1;
+use sha2::{Digest, Sha256, Sha512};
+
+use crate::lib_structs::FileProperties;
+
+/// The digests a `FileProperties` element can carry.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum DigestAlgorithm {
+    Crc32,
+    Md5,
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+impl DigestAlgorithm {
+    /// Every algorithm, in the order the elements appear in the
+    /// specification.
+    pub const ALL: [DigestAlgorithm; 5] = [
+        DigestAlgorithm::Crc32,
+        DigestAlgorithm::Md5,
+        DigestAlgorithm::Sha1,
+        DigestAlgorithm::Sha256,
+        DigestAlgorithm::Sha512,
+    ];
+
+    /// Returns the name of the element holding this digest, e.g. "SHA1".
+    pub fn name(&self) -> &'static str {
+        match self {
+            DigestAlgorithm::Crc32 => "CRC32",
+            DigestAlgorithm::Md5 => "MD5",
+            DigestAlgorithm::Sha1 => "SHA1",
+            DigestAlgorithm::Sha256 => "SHA256",
+            DigestAlgorithm::Sha512 => "SHA512",
+        }
+    }
+
+    /// Returns the algorithm with the given element name, ignoring case.
+    pub fn from_name(name: &str) -> Option<DigestAlgorithm> {
+        DigestAlgorithm::ALL
+            .into_iter()
+            .find(|algorithm| algorithm.name().eq_ignore_ascii_case(name))
+    }
+
+    /// Returns the value of this digest stored in `properties`, if any.
+    pub fn stored<'a>(&self, properties: &'a FileProperties) -> Option<&'a [u8]> {
+        match self {
+            DigestAlgorithm::Crc32 => properties.crc32.as_deref(),
+            DigestAlgorithm::Md5 => properties.md5.as_deref(),
+            DigestAlgorithm::Sha1 => properties.sha1.as_deref(),
+            DigestAlgorithm::Sha256 => properties.sha256.as_deref(),
+            DigestAlgorithm::Sha512 => properties.sha512.as_deref(),
+        }
+    }
+
+    /// Stores `value` as this digest in `properties`.
+    pub fn store(&self, properties: &mut FileProperties, value: Vec<u8>) {
+        let field = match self {
+            DigestAlgorithm::Crc32 => &mut properties.crc32,
+            DigestAlgorithm::Md5 => &mut properties.md5,
+            DigestAlgorithm::Sha1 => &mut properties.sha1,
+            DigestAlgorithm::Sha256 => &mut properties.sha256,
+            DigestAlgorithm::Sha512 => &mut properties.sha512,
+        };
+
+        *field = Some(value);
+    }
+}
+
+impl fmt::Display for DigestAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Computed digests paired with the algorithm that produced them.
+pub type Digests = Vec<(DigestAlgorithm, Vec<u8>)>;
+
+/// Computes several digests over the same data in a single pass.
+///
+/// Data is fed in with `update` or by writing to the hasher, so a file can
+/// be hashed with `io::copy` without ever being held in memory as a whole.
+#[derive(Clone, Default)]
+pub struct MultiHasher {
+    crc32: Option<crc32fast::Hasher>,
+    md5: Option<Md5>,
+    sha1: Option<Sha1>,
+    sha256: Option<Sha256>,
+    sha512: Option<Sha512>,
+}
+
+impl MultiHasher {
+    /// Creates a hasher computing every algorithm in `algorithms`.
+    pub fn new(algorithms: &[DigestAlgorithm]) -> Self {
+        let mut hasher = MultiHasher::default();
+
+        for algorithm in algorithms {
+            match algorithm {
+                DigestAlgorithm::Crc32 => {
+                    hasher.crc32 = Some(crc32fast::Hasher::new())
+                }
+                DigestAlgorithm::Md5 => hasher.md5 = Some(Md5::new()),
+                DigestAlgorithm::Sha1 => hasher.sha1 = Some(Sha1::new()),
+                DigestAlgorithm::Sha256 => hasher.sha256 = Some(Sha256::new()),
+                DigestAlgorithm::Sha512 => hasher.sha512 = Some(Sha512::new()),
+            }
+        }
+
+        hasher
+    }
+
+    /// Feeds the next chunk of data to every digest.
+    pub fn update(&mut self, data: &[u8]) {
+        if let Some(crc32) = self.crc32.as_mut() {
+            crc32.update(data);
+        }
+        if let Some(md5) = self.md5.as_mut() {
+            md5.update(data);
+        }
+        if let Some(sha1) = self.sha1.as_mut() {
+            sha1.update(data);
+        }
+        if let Some(sha256) = self.sha256.as_mut() {
+            sha256.update(data);
+        }
+        if let Some(sha512) = self.sha512.as_mut() {
+            sha512.update(data);
+        }
+    }
+
+    /// Finishes every digest.
+    ///
+    /// # Returns
+    ///
+    /// The computed digests in specification order. `CRC32` is returned in
+    /// big endian byte order, as it is listed in DAT files.
+    pub fn finalize(self) -> Digests {
+        let mut digests = Vec::new();
+
+        if let Some(crc32) = self.crc32 {
+            digests.push((
+                DigestAlgorithm::Crc32,
+                crc32.finalize().to_be_bytes().to_vec(),
+            ));
+        }
+        if let Some(md5) = self.md5 {
+            digests.push((DigestAlgorithm::Md5, md5.finalize().to_vec()));
+        }
+        if let Some(sha1) = self.sha1 {
+            digests.push((DigestAlgorithm::Sha1, sha1.finalize().to_vec()));
+        }
+        if let Some(sha256) = self.sha256 {
+            digests.push((DigestAlgorithm::Sha256, sha256.finalize().to_vec()));
+        }
+        if let Some(sha512) = self.sha512 {
+            digests.push((DigestAlgorithm::Sha512, sha512.finalize().to_vec()));
+        }
+
+        digests
+    }
+}
+
+impl Write for MultiHasher {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Copies `source` to `dest` while computing digests of the copied data.
+///
+/// The data is only read once, so the size and every digest of a file are
+/// available as soon as it has been copied.
+///
+/// # Arguments
+///
+/// * `source`: The data to copy, e.g. a ROM file.
+/// * `dest`: Where the data is written to.
+/// * `algorithms`: The digests to compute.
+///
+/// # Returns
+///
+/// A `Result` containing the number of bytes copied and the computed
+/// digests in specification order.
+pub fn copy_with_digests<R: Read, W: Write>(
+    source: &mut R,
+    dest: &mut W,
+    algorithms: &[DigestAlgorithm],
+) -> io::Result<(u64, Digests)> {
+    let mut hasher = MultiHasher::new(algorithms);
+    let mut buffer = vec![0u8; 64 * 1024];
+    let mut copied = 0;
+
+    loop {
+        let read = match source.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+
+        hasher.update(&buffer[..read]);
+        dest.write_all(&buffer[..read])?;
+        copied += read as u64;
+    }
+
+    Ok((copied, hasher.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn copy_computes_known_digests() {
+        let mut dest = Vec::new();
+        let (copied, digests) = copy_with_digests(
+            &mut &b"123456789"[..],
+            &mut dest,
+            &[DigestAlgorithm::Sha1, DigestAlgorithm::Crc32],
+        )
+        .unwrap();
+
+        assert_eq!(copied, 9);
+        assert_eq!(dest, b"123456789");
+        //Digests come back in specification order, CRC32 big endian.
+        assert_eq!(
+            digests[0],
+            (DigestAlgorithm::Crc32, vec![0xCB, 0xF4, 0x39, 0x26])
+        );
+        assert_eq!(digests[1].0, DigestAlgorithm::Sha1);
+        assert_eq!(digests[1].1[..4], [0xF7, 0xC3, 0xBC, 0x1D]);
+    }
+}
@@ -0,0 +1,336 @@
+use std::io::{self, Read, Seek, SeekFrom};
+
+use ebml_iterable::specs::{EbmlSpecification, TagDataType};
+use ebml_iterable::tools::read_vint;
+
+use crate::gc_ebml_spec::{element_path, GCEbmlSpec};
+use crate::lib_error_handling::LibError;
+use crate::limits::limit_error;
+
+/// The ID and size prefix of a single EBML element.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct ElementHeader {
+    pub id: u64,
+    /// Size of the element's data in bytes.
+    pub size: u64,
+    /// Combined length of the encoded ID and size.
+    pub header_len: u64,
+}
+
+impl ElementHeader {
+    /// Total length of the element, header included.
+    pub fn total_len(&self) -> u64 {
+        self.header_len + self.size
+    }
+
+    /// Length of the encoded ID.
+    fn id_len(&self) -> u64 {
+        (u64::BITS - self.id.leading_zeros()).div_ceil(8) as u64
+    }
+
+    /// Returns whether the size has every value bit set, marking it
+    /// unknown.
+    pub fn is_unknown_size(&self) -> bool {
+        let size_len = self.header_len - self.id_len();
+
+        self.size == (1 << (7 * size_len)) - 1
+    }
+
+    /// Encodes the header exactly as it was read, so offsets within an
+    /// element decoded from these bytes match the file.
+    pub fn encode(&self) -> Vec<u8> {
+        let id_len = self.id_len() as usize;
+        let size_len = self.header_len as usize - id_len;
+
+        let mut bytes = self.id.to_be_bytes()[8 - id_len..].to_vec();
+        let mut size = self.size.to_be_bytes()[8 - size_len..].to_vec();
+        size[0] |= 0x80 >> (size_len - 1);
+        bytes.extend(size);
+
+        bytes
+    }
+}
+
+/// An element located by a `ChildWalker`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct ElementSpan {
+    /// Absolute offset of the element's header.
+    pub offset: u64,
+    pub header: ElementHeader,
+    /// Absolute offset of the first byte after the element, `None` if its
+    /// size is unknown and it is not within a master of known size.
+    pub end: Option<u64>,
+}
+
+impl ElementSpan {
+    /// An element whose header was read at `offset`, outside of any
+    /// master of known size.
+    pub fn new(offset: u64, header: ElementHeader) -> Self {
+        let end = match header.is_unknown_size() {
+            true => None,
+            false => Some(offset.saturating_add(header.total_len())),
+        };
+
+        ElementSpan { offset, header, end }
+    }
+
+    /// Absolute offset of the element's data.
+    pub fn data_start(&self) -> u64 {
+        self.offset + self.header.header_len
+    }
+
+    /// Returns a walker over the children of the element, reporting errors
+    /// with the element's specification path.
+    pub fn children(&self) -> ChildWalker {
+        self.children_at(element_path(self.header.id))
+    }
+
+    /// Returns a walker over the children of the element at `path`.
+    pub fn children_at(&self, path: String) -> ChildWalker {
+        ChildWalker {
+            offset: self.offset,
+            path,
+            position: self.data_start(),
+            end: self.end,
+        }
+    }
+}
+
+/// Walks the headers of the children of one master, or of the top level
+/// elements of a stream.
+///
+/// Only headers are read, the caller decides what to do with the data of
+/// every child. Every child is checked to fit within the master, so a
+/// hostile size can not point a reader outside of it, and a master of
+/// unknown size extends to the end of its parent.
+pub(crate) struct ChildWalker {
+    /// Offset of the master, for errors.
+    offset: u64,
+    path: String,
+    position: u64,
+    /// Offset of the end of the master, `None` for a master of unknown
+    /// size or the top level, which end with the stream.
+    end: Option<u64>,
+}
+
+impl ChildWalker {
+    /// A walker over the top level elements of a stream, the first of
+    /// which starts at `origin`.
+    pub fn top_level(origin: u64) -> Self {
+        ChildWalker {
+            offset: origin,
+            path: String::new(),
+            position: origin,
+            end: None,
+        }
+    }
+
+    /// Absolute offset of the next child, which is where `next` expects
+    /// the source to be.
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    /// Reads the header of the next child from `source`, which must be
+    /// positioned at `position()`.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the child with `source` positioned at the
+    /// start of its data, `None` once the master or the stream ended, or
+    /// a `LibError` if the header is invalid or the child does not fit
+    /// within the master.
+    pub fn next<R: Read>(
+        &mut self,
+        source: &mut R,
+    ) -> Result<Option<ElementSpan>, LibError> {
+        if self.end.is_some_and(|end| self.position >= end) {
+            return Ok(None);
+        }
+
+        let offset = self.position;
+        let header = match read_element_header(source) {
+            Ok(Some(header)) => header,
+            Ok(None) if self.end.is_none() => return Ok(None),
+            Ok(None) => return Err(self.truncated()),
+            Err(error) => return Err(match limit_error(error) {
+                LibError::Io(error)
+                    if error.kind() == io::ErrorKind::UnexpectedEof =>
+                {
+                    self.truncated()
+                }
+                LibError::Io(error)
+                    if error.kind() == io::ErrorKind::InvalidData =>
+                {
+                    LibError::InvalidVint { offset, path: self.path.clone() }
+                }
+                error => error,
+            }),
+        };
+
+        let end = match header.is_unknown_size() {
+            true if GCEbmlSpec::get_tag_data_type(header.id)
+                != Some(TagDataType::Master) =>
+            {
+                return Err(LibError::InvalidVint {
+                    offset,
+                    path: self.path.clone(),
+                });
+            }
+            true => self.end,
+            false => Some(
+                offset
+                    .checked_add(header.total_len())
+                    .ok_or_else(|| self.truncated())?,
+            ),
+        };
+        if let (Some(end), Some(parent_end)) = (end, self.end)
+            && end > parent_end
+        {
+            return Err(self.truncated());
+        }
+
+        //Nothing can follow a top level master of unknown size.
+        self.position = end.unwrap_or(u64::MAX);
+
+        Ok(Some(ElementSpan { offset, header, end }))
+    }
+
+    /// Seeks `source` to the next child and reads its header, see `next`.
+    pub fn seek_next<R: Read + Seek>(
+        &mut self,
+        source: &mut R,
+    ) -> Result<Option<ElementSpan>, LibError> {
+        if self.end.is_some_and(|end| self.position >= end) {
+            return Ok(None);
+        }
+        source.seek(SeekFrom::Start(self.position))?;
+
+        self.next(source)
+    }
+
+    /// Returns the error reporting the master as cut short.
+    pub fn truncated(&self) -> LibError {
+        LibError::TruncatedMaster {
+            offset: self.offset,
+            path: self.path.clone(),
+        }
+    }
+}
+
+/// Parses the element header at the start of `buffer`.
+///
+/// # Returns
+///
+/// `None` if the buffer is too short to hold a complete header or does not
+/// start with a valid VINT.
+pub(crate) fn parse_element_header(buffer: &[u8]) -> Option<ElementHeader> {
+    let first = *buffer.first()?;
+    if first == 0 {
+        return None;
+    }
+
+    //Element IDs keep their VINT marker bit, unlike sizes.
+    let id_len = first.leading_zeros() as usize + 1;
+    if id_len > 4 || buffer.len() < id_len {
+        return None;
+    }
+    let id = buffer[..id_len]
+        .iter()
+        .fold(0u64, |acc, byte| (acc << 8) | *byte as u64);
+
+    let (size, size_len) = read_vint(&buffer[id_len..]).ok()??;
+
+    Some(ElementHeader {
+        id,
+        size,
+        header_len: (id_len + size_len) as u64,
+    })
+}
+
+/// Reads the element header at the current position of `source`.
+///
+/// # Returns
+///
+/// `Ok(None)` if `source` is already at its end, otherwise the parsed
+/// header with `source` positioned at the start of the element's data.
+pub(crate) fn read_element_header<R: Read>(
+    source: &mut R
+) -> io::Result<Option<ElementHeader>> {
+    let mut buffer = [0u8; 12];
+    if source.read(&mut buffer[..1])? == 0 {
+        return Ok(None);
+    }
+
+    let invalid = || io::Error::new(
+        io::ErrorKind::InvalidData, "invalid EBML element header"
+    );
+
+    let id_len = buffer[0].leading_zeros() as usize + 1;
+    if id_len > 4 {
+        return Err(invalid());
+    }
+    source.read_exact(&mut buffer[1..id_len + 1])?;
+
+    let size_len = buffer[id_len].leading_zeros() as usize + 1;
+    if size_len > 8 {
+        return Err(invalid());
+    }
+    source.read_exact(&mut buffer[id_len + 1..id_len + size_len])?;
+
+    parse_element_header(&buffer[..id_len + size_len])
+        .map(Some)
+        .ok_or_else(invalid)
+}
+
+/// Encodes an element header using an 8 byte size VINT.
+///
+/// The fixed width lets a size be patched later without moving any of the
+/// data that follows it.
+pub(crate) fn encode_element_header(id: u64, size: u64) -> Vec<u8> {
+    let mut header: Vec<u8> = id
+        .to_be_bytes()
+        .into_iter()
+        .skip_while(|byte| *byte == 0)
+        .collect();
+    header.push(0x01);
+    header.extend_from_slice(&size.to_be_bytes()[1..]);
+
+    header
+}
+
+/// The ID of the EBML `Void` element.
+pub(crate) const VOID_ID: u64 = 0xEC;
+
+/// The ID of the EBML `CRC-32` element.
+pub(crate) const CRC32_ID: u64 = 0xBF;
+
+/// Splits the total length of a `Void` element into the width of its size
+/// VINT and the length of its data.
+///
+/// Lengths up to 128 bytes use a 1 byte size, longer ones an 8 byte size,
+/// so every length of two or more can be filled exactly. A length of one
+/// is rounded up to two.
+pub(crate) fn void_layout(len: u64) -> (usize, u64) {
+    match len.max(2) {
+        len @ 2..=128 => (1, len - 2),
+        len => (8, len - 9),
+    }
+}
+
+/// Encodes a `Void` element of exactly `len` bytes, or nothing if `len` is
+/// zero.
+pub(crate) fn encode_void(len: u64) -> Vec<u8> {
+    if len == 0 {
+        return Vec::new();
+    }
+
+    let (size_width, data_len) = void_layout(len);
+    let mut void = match size_width {
+        1 => vec![VOID_ID as u8, 0x80 | data_len as u8],
+        _ => encode_element_header(VOID_ID, data_len),
+    };
+    void.resize(void.len() + data_len as usize, 0);
+
+    void
+}
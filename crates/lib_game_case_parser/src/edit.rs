@@ -0,0 +1,793 @@
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use crate::ebml_tools::{
+    encode_element_header, encode_void, ElementSpan, VOID_ID,
+};
+use crate::gc_ebml_spec::{element_path, master_id, GCEbmlSpec};
+use crate::lib_error_handling::LibError;
+use crate::lib_structs::{
+    Game, GameCase, ImageCollection, ImageEntry, IndexEntry, ManualEntry,
+    Manuals, Media, RomHackEntry, RomHacks, VideoCollection, VideoEntry,
+};
+use crate::parse::{check_ebml_header, read_game_data, read_index};
+use crate::payload::load_metadata;
+use crate::read::{expect_header, find_child, load_element, GameCaseReader};
+use crate::repair::{rebuild_case, used_uids, Addition, CaseChanges};
+use crate::uid::{UidAllocator, UidStrategy};
+use crate::write::{
+    encode_extras, encode_game_metadata, encode_index_entries, index_targets,
+    locate_entries, EntryRef,
+};
+
+/// How `update_game_file` stored the new `Game` metadata.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GameUpdate {
+    /// The metadata fit into the space in front of `GameData` and was
+    /// overwritten without moving anything else.
+    InPlace,
+    /// The metadata did not fit and the whole case was written again.
+    Rewritten,
+}
+
+/// Replaces the `Game` metadata of a case without moving any other data.
+///
+/// Every child of `Game` in front of `GameData`, including the `Void`
+/// padding reserved by `GameCaseWriter`, is overwritten with the new
+/// metadata followed by whatever padding is left. The length of `Game` and
+/// the position of every entry stay the same, so the `Index` remains valid.
+///
+/// # Arguments
+///
+/// * `file`: The case, positioned at the start of its EBML header.
+/// * `game`: The new metadata. Its `game_data` is ignored, the stored game
+///   data is kept.
+///
+/// # Returns
+///
+/// A `Result` containing `true` if the metadata was written, or `false` if
+/// it does not fit, in which case nothing was changed.
+pub fn update_game_in_place<F: Read + Write + Seek>(
+    file: &mut F,
+    game: &Game,
+) -> Result<bool, LibError> {
+    let start = file.stream_position()?;
+    let header = load_element(file, start, master_id(GCEbmlSpec::EBML))?;
+    check_ebml_header(&header)?;
+
+    let game_case_start = file.stream_position()?;
+    let game_offset = find_child(
+        file,
+        game_case_start,
+        master_id(GCEbmlSpec::GameCase),
+        master_id(GCEbmlSpec::Game),
+    )?
+    .ok_or_else(|| LibError::MissingElement {
+        element: "Game",
+        offset: game_case_start,
+        path: element_path(master_id(GCEbmlSpec::GameCase)),
+    })?;
+
+    let Some((region_start, region_end)) = metadata_region(file, game_offset)?
+    else {
+        return Ok(false);
+    };
+
+    let metadata = encode_game_metadata(game)?;
+    /*Whatever is left is filled with a Void element, which is at least two
+    bytes long. */
+    let padding = match (region_end - region_start)
+        .checked_sub(metadata.len() as u64)
+    {
+        Some(padding) if padding != 1 => padding,
+        _ => return Ok(false),
+    };
+
+    file.seek(SeekFrom::Start(region_start))?;
+    file.write_all(&metadata)?;
+    file.write_all(&encode_void(padding))?;
+    file.flush()?;
+
+    Ok(true)
+}
+
+/// Replaces the `Game` metadata of the case at `path`.
+///
+/// The metadata is written in place whenever it fits, see
+/// `update_game_in_place`. Otherwise the case is copied to a temporary file
+/// next to it with the new metadata, which then replaces the original.
+/// Payloads are copied without being loaded, so this works for cases of
+/// any size. The rewritten case gets a fresh `Index` and the default
+/// padding.
+///
+/// # Arguments
+///
+/// * `path`: The `.gcase` file to update.
+/// * `game`: The new metadata. Its `game_data` is ignored.
+///
+/// # Returns
+///
+/// A `Result` containing how the metadata was stored.
+pub fn update_game_file(
+    path: &Path,
+    game: &Game,
+) -> Result<GameUpdate, LibError> {
+    let mut file = File::options().read(true).write(true).open(path)?;
+    if update_game_in_place(&mut file, game)? {
+        return Ok(GameUpdate::InPlace);
+    }
+
+    drop(file);
+    rewrite_file(path, &CaseChanges {
+        game_metadata: Some(encode_game_metadata(game)?),
+        ..Default::default()
+    })?;
+
+    Ok(GameUpdate::Rewritten)
+}
+
+/// An entry that can be added to an existing case with `append_entry`.
+///
+/// Its `entry_uid` is replaced by a fresh one when it is appended.
+#[derive(Clone, Debug, PartialEq)]
+pub enum NewEntry {
+    Manual(ManualEntry),
+    RomHack(RomHackEntry),
+    /// An image for the `ImageCollection` with the given category, which
+    /// is created if the case does not have one yet.
+    Image { category: String, entry: ImageEntry },
+    /// A video for the `VideoCollection` with the given category, which
+    /// is created if the case does not have one yet.
+    Video { category: String, entry: VideoEntry },
+}
+
+impl NewEntry {
+    /// Returns the element ID of the entry, e.g. that of `ManualEntry`.
+    pub fn target_id(&self) -> u64 {
+        match self {
+            NewEntry::Manual(_) => master_id(GCEbmlSpec::ManualEntry),
+            NewEntry::RomHack(_) => master_id(GCEbmlSpec::RomHackEntry),
+            NewEntry::Image { .. } => master_id(GCEbmlSpec::ImageEntry),
+            NewEntry::Video { .. } => master_id(GCEbmlSpec::VideoEntry),
+        }
+    }
+
+    fn entry_ref(&self) -> EntryRef<'_> {
+        match self {
+            NewEntry::Manual(entry) => EntryRef::Manual(entry),
+            NewEntry::RomHack(entry) => EntryRef::RomHack(entry),
+            NewEntry::Image { entry, .. } => EntryRef::Image(entry),
+            NewEntry::Video { entry, .. } => EntryRef::Video(entry),
+        }
+    }
+
+    /// Returns the category of the collection of an image or a video.
+    fn category(&self) -> Option<&str> {
+        match self {
+            NewEntry::Image { category, .. }
+            | NewEntry::Video { category, .. } => Some(category),
+            _ => None,
+        }
+    }
+
+    fn set_uid(&mut self, uid: u64) {
+        match self {
+            NewEntry::Manual(entry) => entry.entry_uid = uid,
+            NewEntry::RomHack(entry) => entry.entry_uid = uid,
+            NewEntry::Image { entry, .. } => entry.entry_uid = uid,
+            NewEntry::Video { entry, .. } => entry.entry_uid = uid,
+        }
+    }
+
+    /// Adds the entry to the end of its container within `game_case`.
+    fn add_to(self, game_case: &mut GameCase) {
+        match self {
+            NewEntry::Manual(entry) => game_case.manuals
+                .get_or_insert_with(Manuals::default)
+                .entries
+                .push(entry),
+            NewEntry::RomHack(entry) => game_case.rom_hacks
+                .get_or_insert_with(RomHacks::default)
+                .entries
+                .push(entry),
+            NewEntry::Image { category, entry } => {
+                let collections = &mut game_case.media
+                    .get_or_insert_with(Media::default)
+                    .image_collections;
+                match collections
+                    .iter_mut()
+                    .find(|collection| collection.category == category)
+                {
+                    Some(collection) => collection.entries.push(entry),
+                    None => collections.push(ImageCollection {
+                        category,
+                        entries: vec![entry],
+                        ..Default::default()
+                    }),
+                }
+            }
+            NewEntry::Video { category, entry } => {
+                let collections = &mut game_case.media
+                    .get_or_insert_with(Media::default)
+                    .video_collections;
+                match collections
+                    .iter_mut()
+                    .find(|collection| collection.category == category)
+                {
+                    Some(collection) => collection.entries.push(entry),
+                    None => collections.push(VideoCollection {
+                        category,
+                        entries: vec![entry],
+                        ..Default::default()
+                    }),
+                }
+            }
+        }
+    }
+}
+
+/// The outcome of `append_entry`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AppendedEntry {
+    /// The `IndexEntry` pointing at the new entry, holding its `EntryUID`.
+    pub index_entry: IndexEntry,
+    /// Whether the whole case had to be written again.
+    pub rewritten: bool,
+}
+
+/// Adds a new entry to the case at `path` and indexes it.
+///
+/// The entry is given an `EntryUID` not used by any other entry, one more
+/// than the largest in use, see `append_entry_with`. When the case only
+/// has to grow the entry is appended to the end of the file and the
+/// `Index` is overwritten, so no other data is touched. This requires
+/// `Game` to directly follow the `Index` and its padding, nothing to follow
+/// `Game`, `GameCase` to end the file and the new `Index` to fit into the
+/// old one and its padding. Otherwise the case is copied with the entry
+/// added to its container, like `update_game_file` does, as relocating
+/// `Manuals`, `RomHacks` or `Media` in place could not survive a crash.
+///
+/// # Arguments
+///
+/// * `path`: The `.gcase` file to add the entry to.
+/// * `entry`: The entry to add.
+///
+/// # Returns
+///
+/// A `Result` containing the `IndexEntry` of the new entry and whether the
+/// case was rewritten.
+pub fn append_entry(
+    path: &Path,
+    entry: NewEntry,
+) -> Result<AppendedEntry, LibError> {
+    append_entry_with(path, entry, UidStrategy::Sequential)
+}
+
+/// Adds a new entry to the case at `path` like `append_entry`, picking its
+/// `EntryUID` with `strategy`.
+///
+/// # Arguments
+///
+/// * `path`: The `.gcase` file to add the entry to.
+/// * `entry`: The entry to add. Its `EntryUID` is replaced.
+/// * `strategy`: How the new `EntryUID` is picked.
+///
+/// # Returns
+///
+/// A `Result` containing the `IndexEntry` of the new entry and whether the
+/// case was rewritten.
+pub fn append_entry_with(
+    path: &Path,
+    entry: NewEntry,
+    strategy: UidStrategy,
+) -> Result<AppendedEntry, LibError> {
+    let mut file = File::options().read(true).write(true).open(path)?;
+    if let Some(index_entry) =
+        append_in_place(&mut file, entry.clone(), strategy)?
+    {
+        return Ok(AppendedEntry { index_entry, rewritten: false });
+    }
+
+    drop(file);
+    let mut allocator = UidAllocator::new(strategy);
+    for uid in used_uids(&mut BufReader::new(File::open(path)?))? {
+        allocator.reserve(uid);
+    }
+    let mut entry = entry;
+    entry.set_uid(0);
+    let uid = allocator.allocate_for(entry.entry_ref())?;
+    let target_id = entry.target_id();
+    entry.set_uid(uid);
+    let category = entry.category().map(str::to_string);
+
+    let mut tail = GameCase::default();
+    entry.add_to(&mut tail);
+    let addition = Addition {
+        bytes: encode_extras(&tail)?,
+        category,
+        target: index_targets(&tail).remove(0),
+    };
+    rewrite_file(path, &CaseChanges {
+        addition: Some(addition),
+        ..Default::default()
+    })?;
+
+    let reader = GameCaseReader::open(BufReader::new(File::open(path)?))?;
+    let index_entry = reader.index().entries
+        .iter()
+        .find(|index_entry| {
+            index_entry.uid == uid && index_entry.target_id == target_id
+        })
+        .cloned()
+        .ok_or(LibError::EntryNotFound(uid))?;
+
+    Ok(AppendedEntry { index_entry, rewritten: true })
+}
+
+/// Appends `entry` behind everything else in the case, rewriting only the
+/// `Index` in place.
+///
+/// This is only done when nothing follows `Game`, so the new `Manuals`,
+/// `RomHacks` or `Media` holding the entry lands past the end of the file
+/// and no existing byte but those of the `Index` and the size of
+/// `GameCase` is overwritten. The writes are ordered so that a crash
+/// leaves a readable case:
+///
+/// 1. The new elements are written past the end of the file. Until
+///    `GameCase` grows they are trailing bytes no reader looks at.
+/// 2. The size of `GameCase` is patched to cover them. The entry now
+///    exists but is not indexed yet, which `repair_index` fixes.
+/// 3. The `Index` and its padding are overwritten with the same length.
+///
+/// Each step is synced to disk before the next one starts. Only a crash
+/// during the last write, which covers a few kilobytes at most, can leave
+/// the `Index` torn, and `repair_index` rebuilds it from the entries.
+///
+/// # Returns
+///
+/// A `Result` containing the `IndexEntry` of the new entry, or `None` if
+/// the layout of the file does not allow it, in which case nothing was
+/// changed.
+fn append_in_place(
+    file: &mut File,
+    mut entry: NewEntry,
+    strategy: UidStrategy,
+) -> Result<Option<IndexEntry>, LibError> {
+    let game_case_id = master_id(GCEbmlSpec::GameCase);
+    let index_id = master_id(GCEbmlSpec::Index);
+    let game_id = master_id(GCEbmlSpec::Game);
+    let game_data_id = master_id(GCEbmlSpec::GameData);
+
+    file.rewind()?;
+    let header = load_element(file, 0, master_id(GCEbmlSpec::EBML))?;
+    check_ebml_header(&header)?;
+
+    let game_case_start = file.stream_position()?;
+    let case_header = expect_header(file, game_case_id)?;
+    let case_end = game_case_start + case_header.total_len();
+
+    //The size of GameCase is patched, so it has to use an 8 byte VINT.
+    let header_len = encode_element_header(game_case_id, 0).len() as u64;
+    if case_header.header_len != header_len
+        || case_end != file.metadata()?.len()
+    {
+        return Ok(None);
+    }
+
+    let children =
+        list_children(file, ElementSpan::new(game_case_start, case_header))?;
+    let Some(game) = children.iter().position(|(_, id, _)| *id == game_id)
+    else {
+        return Ok(None);
+    };
+    //Anything following Game would have to be overwritten.
+    let layout_ok = children[0].1 == index_id
+        && children[1..game].iter().all(|(_, id, _)| *id == VOID_ID)
+        && game + 1 == children.len();
+    if !layout_ok {
+        return Ok(None);
+    }
+
+    let index_start = children[0].0;
+    let index_end = children[game].0;
+    let game_end = children[game].0 + children[game].2;
+
+    //Entries of GameData missing from the Index still hold their EntryUID.
+    let index = read_index(load_element(file, index_start, index_id)?);
+    let mut existing = GameCase::default();
+    if let Some(offset) =
+        find_child(file, children[game].0, game_id, game_data_id)?
+    {
+        existing.game.game_data = Some(read_game_data(
+            load_metadata(file, offset, game_data_id)?
+        )?);
+    }
+
+    let mut tail = GameCase::default();
+    let mut allocator = UidAllocator::for_case(strategy, &existing);
+    for index_entry in &index.entries {
+        allocator.reserve(index_entry.uid);
+    }
+    entry.set_uid(0);
+    let uid = allocator.allocate_for(entry.entry_ref())?;
+    let target_id = entry.target_id();
+    entry.set_uid(uid);
+    entry.add_to(&mut tail);
+
+    let tail_bytes = encode_extras(&tail)?;
+    let tail_start = game_end - game_case_start;
+    let mut entry_offsets = Vec::new();
+    locate_entries(&tail_bytes, tail_start, &mut entry_offsets);
+
+    let entries: Vec<IndexEntry> = index.entries
+        .into_iter()
+        .chain(
+            index_targets(&tail)
+                .into_iter()
+                .zip(entry_offsets)
+                .map(|(target, position)| IndexEntry { position, ..target }),
+        )
+        .collect();
+    let new_entry = entries
+        .iter()
+        .find(|index_entry| {
+            index_entry.uid == uid && index_entry.target_id == target_id
+        })
+        .cloned()
+        .ok_or(LibError::EntryNotFound(uid))?;
+
+    let index_bytes = encode_index_entries(&entries)?;
+    let padding = match (index_end - index_start)
+        .checked_sub(index_bytes.len() as u64)
+    {
+        Some(padding) if padding != 1 => padding,
+        _ => return Ok(None),
+    };
+
+    file.seek(SeekFrom::Start(game_end))?;
+    file.write_all(&tail_bytes)?;
+    let new_end = game_end + tail_bytes.len() as u64;
+    file.sync_data()?;
+
+    file.seek(SeekFrom::Start(game_case_start))?;
+    file.write_all(&encode_element_header(
+        game_case_id,
+        new_end - game_case_start - header_len,
+    ))?;
+    file.sync_data()?;
+
+    file.seek(SeekFrom::Start(index_start))?;
+    file.write_all(&index_bytes)?;
+    file.write_all(&encode_void(padding))?;
+    file.sync_data()?;
+
+    Ok(Some(new_entry))
+}
+
+/// Lists the children of `element` as their offset, ID and total length.
+pub(crate) fn list_children<R: Read + Seek>(
+    source: &mut R,
+    element: ElementSpan,
+) -> Result<Vec<(u64, u64, u64)>, LibError> {
+    let mut walker = element.children();
+    let mut children = Vec::new();
+
+    while let Some(child) = walker.seek_next(source)? {
+        children.push((child.offset, child.header.id, child.header.total_len()));
+    }
+
+    Ok(children)
+}
+
+/// Copies the case at `path` with `changes` applied to a temporary file
+/// next to it, which then replaces the file at `path`, see `rebuild_case`.
+fn rewrite_file(path: &Path, changes: &CaseChanges) -> Result<(), LibError> {
+    let mut source = BufReader::new(File::open(path)?);
+
+    replace_file(path, |temp| {
+        rebuild_case(&mut source, &mut BufWriter::new(temp), changes)?;
+        Ok(())
+    })
+}
+
+/// Creates a temporary file next to `path` and hands it to `write_fn`.
+/// Once it succeeds the temporary file replaces the file at `path`,
+/// otherwise it is removed and the original is left untouched.
+pub(crate) fn replace_file<F>(path: &Path, write_fn: F) -> Result<(), LibError>
+where
+    F: FnOnce(File) -> Result<(), LibError>,
+{
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let temp_path = path.with_file_name(format!(".{file_name}.tmp"));
+
+    let written = File::create(&temp_path)
+        .map_err(LibError::from)
+        .and_then(write_fn);
+    if let Err(e) = written {
+        let _ = fs::remove_file(&temp_path);
+        return Err(e);
+    }
+
+    fs::rename(&temp_path, path)?;
+
+    Ok(())
+}
+
+/// Finds the bytes of `Game` holding its metadata, everything in front of
+/// `GameData`.
+///
+/// # Returns
+///
+/// A `Result` containing the absolute start and end of the metadata, or
+/// `None` if `GameData` is followed by anything but `Void` elements, as
+/// that data could not be overwritten without being lost.
+fn metadata_region<R: Read + Seek>(
+    source: &mut R,
+    game_offset: u64,
+) -> Result<Option<(u64, u64)>, LibError> {
+    source.seek(SeekFrom::Start(game_offset))?;
+    let header = expect_header(source, master_id(GCEbmlSpec::Game))?;
+    let start = game_offset + header.header_len;
+    let end = game_offset + header.total_len();
+
+    let mut walker = ElementSpan::new(game_offset, header).children();
+    let mut game_data = None;
+
+    while let Some(child) = walker.seek_next(source)? {
+        let id = child.header.id;
+
+        if id == master_id(GCEbmlSpec::GameData) && game_data.is_none() {
+            game_data = Some(child.offset);
+        } else if game_data.is_some() && id != VOID_ID {
+            return Ok(None);
+        }
+    }
+
+    Ok(Some((start, game_data.unwrap_or(end))))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::lib_structs::GameDataPayload;
+    use crate::limits::ReaderLimits;
+    use crate::parse::process_ebml_data;
+    use crate::repair::check_index;
+    use crate::write::tests::{encode_case, rewrite_index, sample_case};
+    use crate::write::{GameCaseWriter, DEFAULT_GAME_PADDING};
+
+    /// Writes `bytes` to a file in the temporary directory, named after the
+    /// test so tests running in parallel do not share it.
+    fn temp_case(name: &str, bytes: &[u8]) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "game_case_parser_{name}_{}.gcase",
+            std::process::id()
+        ));
+        fs::write(&path, bytes).unwrap();
+        path
+    }
+
+    fn manual() -> NewEntry {
+        NewEntry::Manual(ManualEntry {
+            region: "EUR".to_string(),
+            languages: vec!["de".to_string()],
+            manual_format: "PDF".to_string(),
+            manual_data: vec![4; 20],
+            ..Default::default()
+        })
+    }
+
+    /// Checks that every `IndexEntry` of `bytes` points at its entry.
+    fn assert_index_valid(bytes: &[u8]) {
+        let mut reader = GameCaseReader::open(Cursor::new(bytes)).unwrap();
+
+        for index_entry in reader.index().entries.clone() {
+            let entry = reader.read_entry(&index_entry).unwrap();
+            assert_eq!(entry.entry_uid(), index_entry.uid);
+        }
+    }
+
+    #[test]
+    fn game_is_updated_in_place_when_it_fits() {
+        let bytes = encode_case(&sample_case());
+        let game = Game {
+            title: "The Legend of Zelda".to_string(),
+            ..sample_case().game
+        };
+
+        let mut file = Cursor::new(bytes.clone());
+        assert!(update_game_in_place(&mut file, &game).unwrap());
+
+        let edited = file.into_inner();
+        assert_eq!(edited.len(), bytes.len());
+        assert_index_valid(&edited);
+
+        let mut reader = GameCaseReader::open(Cursor::new(edited)).unwrap();
+        assert_eq!(reader.read_game_info().unwrap().title, game.title);
+    }
+
+    #[test]
+    fn game_too_large_for_the_padding_is_left_alone() {
+        let bytes = encode_case(&sample_case());
+        let game = Game {
+            description: Some("x".repeat(2 * DEFAULT_GAME_PADDING as usize)),
+            ..sample_case().game
+        };
+
+        let mut file = Cursor::new(bytes.clone());
+        assert!(!update_game_in_place(&mut file, &game).unwrap());
+        assert_eq!(file.into_inner(), bytes);
+    }
+
+    #[test]
+    fn game_too_large_for_the_padding_is_rewritten() {
+        let path = temp_case("rewrite_game", &encode_case(&sample_case()));
+        let game = Game {
+            description: Some("x".repeat(2 * DEFAULT_GAME_PADDING as usize)),
+            ..sample_case().game
+        };
+
+        let update = update_game_file(&path, &game);
+        let edited = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(update.unwrap(), GameUpdate::Rewritten);
+        assert_index_valid(&edited);
+        let mut reader = GameCaseReader::open(Cursor::new(edited)).unwrap();
+        let description = reader.read_game_info().unwrap().description;
+        assert_eq!(description, game.description);
+    }
+
+    #[test]
+    fn entry_is_appended_in_place_when_the_case_only_grows() {
+        let game_case = GameCase {
+            game: sample_case().game,
+            ..Default::default()
+        };
+        let bytes = encode_case(&game_case);
+        let path = temp_case("append_in_place", &bytes);
+
+        let appended = append_entry(&path, manual());
+        let edited = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let appended = appended.unwrap();
+        assert!(!appended.rewritten);
+        assert_eq!(appended.index_entry.uid, 2);
+        //Everything up to the end of Game is kept apart from the Index.
+        let reader = GameCaseReader::open(Cursor::new(&edited)).unwrap();
+        let game_start = reader.index().entries[0].position as usize;
+        assert_eq!(edited[game_start..bytes.len()], bytes[game_start..]);
+        assert_eq!(reader.index().entries.len(), 2);
+        assert_index_valid(&edited);
+    }
+
+    #[test]
+    fn uids_of_unindexed_game_data_are_not_reused() {
+        let mut game = sample_case().game;
+        if let Some(game_data) = &mut game.game_data {
+            let mut rom = game_data.entries[0].clone();
+            rom.entry_uid = 2;
+            game_data.entries.push(rom);
+        }
+        let game_case = GameCase { game, ..Default::default() };
+        let mut bytes = encode_case(&game_case);
+        rewrite_index(&mut bytes, |entries| {
+            entries.retain(|entry| entry.uid != 2)
+        });
+        let path = temp_case("append_unindexed", &bytes);
+
+        let appended = append_entry(&path, manual());
+        fs::remove_file(&path).unwrap();
+
+        //The second ROM uses EntryUID 2 without being indexed.
+        assert_eq!(appended.unwrap().index_entry.uid, 3);
+    }
+
+    #[test]
+    fn entry_is_appended_by_rewriting_when_extras_follow_game() {
+        let path = temp_case("append_rewrite", &encode_case(&sample_case()));
+
+        let appended = append_entry(&path, manual());
+        let edited = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let appended = appended.unwrap();
+        assert!(appended.rewritten);
+        assert_eq!(appended.index_entry.uid, 4);
+        assert_index_valid(&edited);
+
+        //The manual joins the existing Manuals, everything else is kept.
+        let game_case = process_ebml_data(&edited[..]).unwrap();
+        let mut expected = sample_case();
+        manual().add_to(&mut expected);
+        if let Some(manuals) = &mut expected.manuals {
+            manuals.entries[1].entry_uid = 4;
+        }
+        assert_eq!(game_case.game, expected.game);
+        assert_eq!(game_case.manuals, expected.manuals);
+        assert_eq!(game_case.media, expected.media);
+    }
+
+    #[test]
+    fn image_joins_the_collection_of_its_category() {
+        let path = temp_case("append_image", &encode_case(&sample_case()));
+        let image = |category: &str| NewEntry::Image {
+            category: category.to_string(),
+            entry: ImageEntry {
+                image_format: "PNG".to_string(),
+                image_data: vec![5; 8],
+                ..Default::default()
+            },
+        };
+
+        let box_art = append_entry(&path, image("BoxArt"));
+        let screenshot = append_entry(&path, image("Screenshot"));
+        let edited = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(box_art.unwrap().index_entry.uid, 4);
+        assert_eq!(screenshot.unwrap().index_entry.uid, 5);
+        assert_index_valid(&edited);
+        let media = process_ebml_data(&edited[..]).unwrap().media.unwrap();
+        let collections: Vec<(&str, usize)> = media.image_collections
+            .iter()
+            .map(|collection| {
+                (collection.category.as_str(), collection.entries.len())
+            })
+            .collect();
+        assert_eq!(collections, [("BoxArt", 2), ("Screenshot", 1)]);
+    }
+
+    #[test]
+    fn payloads_too_large_to_load_are_copied_when_rewriting() {
+        //Too large for process_ebml_data, but never touched in memory.
+        let rom_len = ReaderLimits::default().max_binary_size as usize + 1;
+        let mut game_case = sample_case();
+        let game_data = game_case.game.game_data.as_mut().unwrap();
+        if let GameDataPayload::Raw(raw) = &mut game_data.entries[0].payload {
+            raw.rom_data = vec![0; rom_len];
+        }
+        let path = std::env::temp_dir().join(format!(
+            "game_case_parser_large_rewrite_{}.gcase",
+            std::process::id()
+        ));
+        let mut writer =
+            GameCaseWriter::new(BufWriter::new(File::create(&path).unwrap()));
+        writer.write(&game_case).unwrap();
+        writer.into_inner().flush().unwrap();
+        drop(game_case);
+
+        let game = Game {
+            description: Some("x".repeat(2 * DEFAULT_GAME_PADDING as usize)),
+            ..sample_case().game
+        };
+        let update = update_game_file(&path, &game);
+        let appended = append_entry(&path, manual());
+        let mut file = BufReader::new(File::open(&path).unwrap());
+        let mismatches = check_index(&mut file);
+        file.rewind().unwrap();
+        let mut reader = GameCaseReader::open(file).unwrap();
+        let description = reader.read_game_info().unwrap().description;
+        let manual_entry = reader.read_by_uid(4);
+        let rom = reader.index().entries[0].clone();
+        let rom_size = reader
+            .payloads(&rom)
+            .map(|payloads| payloads[0].size);
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(update.unwrap(), GameUpdate::Rewritten);
+        assert!(appended.unwrap().rewritten);
+        assert_eq!(mismatches.unwrap(), []);
+        assert_eq!(description, game.description);
+        assert!(manual_entry.is_ok());
+        assert_eq!(rom_size.unwrap(), rom_len as u64);
+    }
+}
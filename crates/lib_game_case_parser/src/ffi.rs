@@ -0,0 +1,628 @@
+/*C ABI for the staticlib build.
+
+Every function returns a GcStatus and hands results back through out
+parameters. Cases are opaque GcCase handles which have to be released with
+gc_case_free. Strings returned by the API are owned by the handle they came
+from and stay valid until it is freed.
+
+Panics are not caught, the release profile aborts on panic, so a bug in
+the library ends the process instead of unwinding into C code.
+
+The C header game_case_parser.h is generated from this module by the build
+script. */
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ffi::{c_char, CStr, CString};
+use std::fs::File;
+use std::io::{self, BufReader, Cursor, Read, Seek, SeekFrom};
+use std::ptr;
+use std::slice;
+
+use crate::lib_error_handling::LibError;
+use crate::lib_structs::Game;
+use crate::payload::PayloadLocation;
+use crate::read::GameCaseReader;
+
+/// Result of every C API call.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GcStatus {
+    Ok = 0,
+    /// A required pointer argument was null.
+    NullArgument = 1,
+    /// A path was not valid UTF-8.
+    InvalidUtf8 = 2,
+    /// Reading the file or buffer failed.
+    Io = 3,
+    /// The data is not a valid `.gcase` file.
+    Malformed = 4,
+    /// An index, entry or payload number is out of range.
+    OutOfRange = 5,
+}
+
+/// Selects a text field of the `Game` element.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum GcGameField {
+    Title = 0,
+    Developer = 1,
+    Genre = 2,
+    GameSystem = 3,
+    Description = 4,
+}
+
+/// A single `IndexEntry`.
+///
+/// `category` and `region` are null if the entry has none.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct GcIndexEntry {
+    pub uid: u64,
+    /// Element ID of the target element, e.g. the ID of `ManualEntry`.
+    pub target_id: u64,
+    /// Offset of the target from the start of the `GameCase` element.
+    pub position: u64,
+    pub target: *const c_char,
+    pub category: *const c_char,
+    pub region: *const c_char,
+}
+
+/// A binary payload, e.g. a ROM, within an indexed entry.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct GcPayloadInfo {
+    /// Element ID of the binary element, e.g. the ID of `ROMData`.
+    pub id: u64,
+    /// Length of the payload in bytes.
+    pub size: u64,
+}
+
+/// The source a case was opened from.
+enum CaseSource {
+    File(BufReader<File>),
+    Buffer(Cursor<Vec<u8>>),
+}
+
+impl Read for CaseSource {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            CaseSource::File(file) => file.read(buf),
+            CaseSource::Buffer(buffer) => buffer.read(buf),
+        }
+    }
+}
+
+impl Seek for CaseSource {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        match self {
+            CaseSource::File(file) => file.seek(pos),
+            CaseSource::Buffer(buffer) => buffer.seek(pos),
+        }
+    }
+}
+
+/// Strings of a single index entry, kept alive for `GcIndexEntry`.
+struct IndexStrings {
+    target: CString,
+    category: Option<CString>,
+    region: Option<CString>,
+}
+
+/// An open case. Opaque to C callers.
+pub struct GcCase {
+    reader: GameCaseReader<CaseSource>,
+    index_strings: Vec<IndexStrings>,
+    game_fields: HashMap<GcGameField, Vec<CString>>,
+    /// Payload locations per index entry, filled on first use.
+    payloads: HashMap<usize, Vec<PayloadLocation>>,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// Returns a description of the last error on the calling thread, or null
+/// if no call has failed yet.
+///
+/// The string stays valid until the next failing call on the same thread.
+#[unsafe(no_mangle)]
+pub extern "C" fn gc_last_error_message() -> *const c_char {
+    LAST_ERROR.with(|last| {
+        last.borrow().as_ref().map_or(ptr::null(), |message| message.as_ptr())
+    })
+}
+
+/// Opens the case at `path`.
+///
+/// # Safety
+///
+/// `path` must be a null terminated string and `out_case` a valid pointer
+/// to write the handle to.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gc_case_open_path(
+    path: *const c_char,
+    out_case: *mut *mut GcCase,
+) -> GcStatus {
+    guard(|| {
+        let out_case = unsafe { out_case.as_mut() }
+            .ok_or_else(null_argument)?;
+        if path.is_null() {
+            return Err(null_argument());
+        }
+
+        let path = unsafe { CStr::from_ptr(path) }
+            .to_str()
+            .map_err(|_| {
+                (GcStatus::InvalidUtf8, "Path is not valid UTF-8".to_string())
+            })?;
+        let file = File::open(path).map_err(|e| lib_error(e.into()))?;
+
+        *out_case = open_case(CaseSource::File(BufReader::new(file)))?;
+        Ok(())
+    })
+}
+
+/// Opens a case held in memory. The data is copied, so the buffer may be
+/// released as soon as the call returns.
+///
+/// # Safety
+///
+/// `data` must point to `len` readable bytes and `out_case` must be a valid
+/// pointer to write the handle to.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gc_case_open_buffer(
+    data: *const u8,
+    len: usize,
+    out_case: *mut *mut GcCase,
+) -> GcStatus {
+    guard(|| {
+        let out_case = unsafe { out_case.as_mut() }
+            .ok_or_else(null_argument)?;
+        if data.is_null() {
+            return Err(null_argument());
+        }
+
+        let data = unsafe { slice::from_raw_parts(data, len) }.to_vec();
+
+        *out_case = open_case(CaseSource::Buffer(Cursor::new(data)))?;
+        Ok(())
+    })
+}
+
+/// Releases a case and every string obtained from it. Null is ignored.
+///
+/// # Safety
+///
+/// `case` must be null or a handle returned by one of the open functions
+/// that has not been freed yet.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gc_case_free(case: *mut GcCase) {
+    if !case.is_null() {
+        drop(unsafe { Box::from_raw(case) });
+    }
+}
+
+/// Returns the number of `IndexEntry` elements.
+///
+/// # Safety
+///
+/// `case` must be a valid handle and `out_count` a valid pointer.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gc_case_index_count(
+    case: *const GcCase,
+    out_count: *mut usize,
+) -> GcStatus {
+    guard(|| {
+        let case = unsafe { case.as_ref() }.ok_or_else(null_argument)?;
+        let out_count = unsafe { out_count.as_mut() }
+            .ok_or_else(null_argument)?;
+
+        *out_count = case.index_strings.len();
+        Ok(())
+    })
+}
+
+/// Returns the index entry at position `index`.
+///
+/// # Safety
+///
+/// `case` must be a valid handle and `out_entry` a valid pointer.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gc_case_index_entry(
+    case: *const GcCase,
+    index: usize,
+    out_entry: *mut GcIndexEntry,
+) -> GcStatus {
+    guard(|| {
+        let case = unsafe { case.as_ref() }.ok_or_else(null_argument)?;
+        let out_entry = unsafe { out_entry.as_mut() }
+            .ok_or_else(null_argument)?;
+
+        let entry = case.reader.index().entries
+            .get(index)
+            .ok_or_else(|| out_of_range("Index entry", index))?;
+        let strings = &case.index_strings[index];
+
+        *out_entry = GcIndexEntry {
+            uid: entry.uid,
+            target_id: entry.target_id,
+            position: entry.position,
+            target: strings.target.as_ptr(),
+            category: optional_ptr(&strings.category),
+            region: optional_ptr(&strings.region),
+        };
+        Ok(())
+    })
+}
+
+/// Returns how many values a `Game` field has, e.g. the number of
+/// developers. Optional fields have zero or one value.
+///
+/// # Safety
+///
+/// `case` must be a valid handle and `out_count` a valid pointer.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gc_case_game_field_count(
+    case: *const GcCase,
+    field: GcGameField,
+    out_count: *mut usize,
+) -> GcStatus {
+    guard(|| {
+        let case = unsafe { case.as_ref() }.ok_or_else(null_argument)?;
+        let out_count = unsafe { out_count.as_mut() }
+            .ok_or_else(null_argument)?;
+
+        *out_count = case.game_fields.get(&field).map_or(0, Vec::len);
+        Ok(())
+    })
+}
+
+/// Returns the value at position `index` of a `Game` field.
+///
+/// # Safety
+///
+/// `case` must be a valid handle and `out_value` a valid pointer.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gc_case_game_field(
+    case: *const GcCase,
+    field: GcGameField,
+    index: usize,
+    out_value: *mut *const c_char,
+) -> GcStatus {
+    guard(|| {
+        let case = unsafe { case.as_ref() }.ok_or_else(null_argument)?;
+        let out_value = unsafe { out_value.as_mut() }
+            .ok_or_else(null_argument)?;
+
+        let value = case.game_fields
+            .get(&field)
+            .and_then(|values| values.get(index))
+            .ok_or_else(|| out_of_range(&format!("{field:?} value"), index))?;
+
+        *out_value = value.as_ptr();
+        Ok(())
+    })
+}
+
+/// Returns the number of binary payloads within an indexed entry.
+///
+/// # Safety
+///
+/// `case` must be a valid handle and `out_count` a valid pointer.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gc_case_payload_count(
+    case: *mut GcCase,
+    entry: usize,
+    out_count: *mut usize,
+) -> GcStatus {
+    guard(|| {
+        let case = unsafe { case.as_mut() }.ok_or_else(null_argument)?;
+        let out_count = unsafe { out_count.as_mut() }
+            .ok_or_else(null_argument)?;
+
+        *out_count = case.payloads_of(entry)?.len();
+        Ok(())
+    })
+}
+
+/// Describes payload number `payload` of an indexed entry.
+///
+/// # Safety
+///
+/// `case` must be a valid handle and `out_info` a valid pointer.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gc_case_payload_info(
+    case: *mut GcCase,
+    entry: usize,
+    payload: usize,
+    out_info: *mut GcPayloadInfo,
+) -> GcStatus {
+    guard(|| {
+        let case = unsafe { case.as_mut() }.ok_or_else(null_argument)?;
+        let out_info = unsafe { out_info.as_mut() }
+            .ok_or_else(null_argument)?;
+
+        let location = case.payload(entry, payload)?;
+
+        *out_info = GcPayloadInfo {
+            id: location.id,
+            size: location.size,
+        };
+        Ok(())
+    })
+}
+
+/// Copies part of a payload into `buffer`.
+///
+/// Payloads are streamed by calling this repeatedly with an increasing
+/// `offset` until `out_read` is zero.
+///
+/// # Arguments
+///
+/// * `offset`: Position within the payload to start reading at.
+/// * `buffer`, `buffer_len`: Where up to `buffer_len` bytes are written.
+/// * `out_read`: Receives the number of bytes written, which is only less
+///   than `buffer_len` at the end of the payload.
+///
+/// # Safety
+///
+/// `case` must be a valid handle, `buffer` must point to `buffer_len`
+/// writable bytes and `out_read` must be a valid pointer.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gc_case_payload_read(
+    case: *mut GcCase,
+    entry: usize,
+    payload: usize,
+    offset: u64,
+    buffer: *mut u8,
+    buffer_len: usize,
+    out_read: *mut usize,
+) -> GcStatus {
+    guard(|| {
+        let case = unsafe { case.as_mut() }.ok_or_else(null_argument)?;
+        let out_read = unsafe { out_read.as_mut() }
+            .ok_or_else(null_argument)?;
+        if buffer.is_null() {
+            return Err(null_argument());
+        }
+        let buffer = unsafe { slice::from_raw_parts_mut(buffer, buffer_len) };
+
+        let location = case.payload(entry, payload)?;
+        let start = offset.min(location.size);
+        let remaining = PayloadLocation {
+            id: location.id,
+            offset: location.offset + start,
+            size: location.size - start,
+        };
+
+        let mut reader = case.reader
+            .open_payload(&remaining)
+            .map_err(lib_error)?;
+        let mut read = 0;
+        while read < buffer.len() {
+            match reader.read(&mut buffer[read..]) {
+                Ok(0) => break,
+                Ok(count) => read += count,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(lib_error(e.into())),
+            }
+        }
+
+        *out_read = read;
+        Ok(())
+    })
+}
+
+impl GcCase {
+    /// Returns the payloads of index entry `entry`, locating them first if
+    /// needed.
+    fn payloads_of(
+        &mut self,
+        entry: usize,
+    ) -> Result<&[PayloadLocation], (GcStatus, String)> {
+        if !self.payloads.contains_key(&entry) {
+            let index_entry = self.reader.index().entries
+                .get(entry)
+                .cloned()
+                .ok_or_else(|| out_of_range("Index entry", entry))?;
+            let payloads = self.reader
+                .payloads(&index_entry)
+                .map_err(lib_error)?;
+            self.payloads.insert(entry, payloads);
+        }
+
+        Ok(&self.payloads[&entry])
+    }
+
+    fn payload(
+        &mut self,
+        entry: usize,
+        payload: usize,
+    ) -> Result<PayloadLocation, (GcStatus, String)> {
+        self.payloads_of(entry)?
+            .get(payload)
+            .copied()
+            .ok_or_else(|| out_of_range("Payload", payload))
+    }
+}
+
+/// Opens a case and prepares the strings handed out to C callers.
+fn open_case(source: CaseSource) -> Result<*mut GcCase, (GcStatus, String)> {
+    let mut reader = GameCaseReader::open(source).map_err(lib_error)?;
+    let game = reader.read_game_info().map_err(lib_error)?;
+
+    let index_strings = reader.index().entries
+        .iter()
+        .map(|entry| IndexStrings {
+            target: c_string(&entry.target),
+            category: entry.category.as_deref().map(c_string),
+            region: entry.region.as_deref().map(c_string),
+        })
+        .collect();
+
+    let case = GcCase {
+        reader,
+        index_strings,
+        game_fields: game_fields(&game),
+        payloads: HashMap::new(),
+    };
+
+    Ok(Box::into_raw(Box::new(case)))
+}
+
+fn game_fields(game: &Game) -> HashMap<GcGameField, Vec<CString>> {
+    let strings = |values: Vec<&String>| {
+        values.into_iter().map(|value| c_string(value)).collect()
+    };
+
+    HashMap::from([
+        (GcGameField::Title, strings(vec![&game.title])),
+        (GcGameField::Developer, strings(game.developers.iter().collect())),
+        (GcGameField::Genre, strings(game.genre.iter().collect())),
+        (GcGameField::GameSystem, strings(vec![&game.game_system])),
+        (GcGameField::Description, strings(game.description.iter().collect())),
+    ])
+}
+
+/// Runs the body of an API call, recording any error for
+/// `gc_last_error_message`.
+fn guard<F>(body: F) -> GcStatus
+where
+    F: FnOnce() -> Result<(), (GcStatus, String)>,
+{
+    match body() {
+        Ok(()) => GcStatus::Ok,
+        Err((status, message)) => {
+            LAST_ERROR.with(|last| {
+                *last.borrow_mut() = Some(c_string(&message));
+            });
+            status
+        }
+    }
+}
+
+fn lib_error(error: LibError) -> (GcStatus, String) {
+    let status = match error {
+        LibError::Io(_) => GcStatus::Io,
+        LibError::EntryNotFound(_) => GcStatus::OutOfRange,
+        _ => GcStatus::Malformed,
+    };
+
+    (status, error.to_string())
+}
+
+fn null_argument() -> (GcStatus, String) {
+    (GcStatus::NullArgument, "Required argument is null".to_string())
+}
+
+fn out_of_range(what: &str, index: usize) -> (GcStatus, String) {
+    (GcStatus::OutOfRange, format!("{what} {index} does not exist"))
+}
+
+/// Converts text to a C string, dropping interior null bytes.
+fn c_string(value: &str) -> CString {
+    CString::new(value.replace('\0', "")).unwrap_or_default()
+}
+
+fn optional_ptr(value: &Option<CString>) -> *const c_char {
+    value.as_ref().map_or(ptr::null(), |value| value.as_ptr())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::write::tests::{encode_case, sample_case};
+
+    #[test]
+    fn case_is_opened_read_and_freed() {
+        let bytes = encode_case(&sample_case());
+        let mut case = ptr::null_mut();
+        let status = unsafe {
+            gc_case_open_buffer(bytes.as_ptr(), bytes.len(), &mut case)
+        };
+        assert_eq!(status, GcStatus::Ok);
+        //The data was copied, so the buffer may go away.
+        drop(bytes);
+
+        let mut count = 0;
+        let status = unsafe { gc_case_index_count(case, &mut count) };
+        assert_eq!((status, count), (GcStatus::Ok, 3));
+
+        let mut title = ptr::null();
+        let status = unsafe {
+            gc_case_game_field(case, GcGameField::Title, 0, &mut title)
+        };
+        assert_eq!(status, GcStatus::Ok);
+        assert_eq!(unsafe { CStr::from_ptr(title) }, c"Zelda");
+
+        let mut info = GcPayloadInfo { id: 0, size: 0 };
+        let status = unsafe { gc_case_payload_info(case, 0, 0, &mut info) };
+        assert_eq!(status, GcStatus::Ok);
+        assert_eq!(info.size, 300);
+
+        //The ROM is streamed in chunks until nothing is left.
+        let mut rom = Vec::new();
+        let mut chunk = [0u8; 128];
+        loop {
+            let mut read = 0;
+            let status = unsafe {
+                gc_case_payload_read(
+                    case,
+                    0,
+                    0,
+                    rom.len() as u64,
+                    chunk.as_mut_ptr(),
+                    chunk.len(),
+                    &mut read,
+                )
+            };
+            assert_eq!(status, GcStatus::Ok);
+            if read == 0 {
+                break;
+            }
+            rom.extend_from_slice(&chunk[..read]);
+        }
+        assert_eq!(rom, [7; 300]);
+
+        unsafe { gc_case_free(case) };
+    }
+
+    #[test]
+    fn failures_are_reported_through_the_status() {
+        let bytes = encode_case(&sample_case());
+        let mut case = ptr::null_mut();
+        let status = unsafe {
+            gc_case_open_buffer(bytes.as_ptr(), bytes.len(), ptr::null_mut())
+        };
+        assert_eq!(status, GcStatus::NullArgument);
+
+        let garbage = b"not a case";
+        let status = unsafe {
+            gc_case_open_buffer(garbage.as_ptr(), garbage.len(), &mut case)
+        };
+        assert_ne!(status, GcStatus::Ok);
+        assert!(case.is_null());
+        assert!(!gc_last_error_message().is_null());
+
+        let status = unsafe {
+            gc_case_open_buffer(bytes.as_ptr(), bytes.len(), &mut case)
+        };
+        assert_eq!(status, GcStatus::Ok);
+        let mut entry = GcIndexEntry {
+            uid: 0,
+            target_id: 0,
+            position: 0,
+            target: ptr::null(),
+            category: ptr::null(),
+            region: ptr::null(),
+        };
+        let status = unsafe { gc_case_index_entry(case, 3, &mut entry) };
+        assert_eq!(status, GcStatus::OutOfRange);
+
+        unsafe {
+            gc_case_free(case);
+            gc_case_free(ptr::null_mut());
+        }
+    }
+}
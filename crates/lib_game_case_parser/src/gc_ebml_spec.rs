@@ -1,4 +1,53 @@
-use ebml_iterable::specs::{ebml_specification, TagDataType};
+use ebml_iterable::specs::{
+    ebml_specification, EbmlSpecification, EbmlTag, Master, PathPart,
+    TagDataType,
+};
+
+/// The DocType string of every `.gcase` file.
+pub const DOC_TYPE: &str = "GameCase";
+
+/// The EBML version written to the EBML header.
+pub const EBML_VERSION: u64 = 1;
+
+/// The version of the GameCase DocType written by this library.
+pub const DOC_TYPE_VERSION: u64 = 1;
+
+/// The minimum DocType version a parser needs to read files written by this
+/// library.
+pub const DOC_TYPE_READ_VERSION: u64 = 1;
+
+/// The `DocTypeVersion` and `DocTypeReadVersion` of a GameCase document.
+///
+/// A file may be read by any parser supporting at least its `read_version`,
+/// even if it was written for a newer `version`. Elements such a parser
+/// does not know are then skipped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct DocTypeVersions {
+    pub version: u64,
+    pub read_version: u64,
+}
+
+impl DocTypeVersions {
+    /// The versions written by this library.
+    pub const CURRENT: DocTypeVersions = DocTypeVersions {
+        version: DOC_TYPE_VERSION,
+        read_version: DOC_TYPE_READ_VERSION,
+    };
+
+    /// Returns whether this library is able to read a file declaring these
+    /// versions, i.e. whether `read_version` is no newer than
+    /// `DOC_TYPE_VERSION`.
+    pub fn is_readable(&self) -> bool {
+        self.read_version <= DOC_TYPE_VERSION
+    }
+}
+
+impl Default for DocTypeVersions {
+    /// The values EBML defines for a header without the version elements.
+    fn default() -> Self {
+        DocTypeVersions { version: 1, read_version: 1 }
+    }
+}
 
 #[ebml_specification]
 #[derive(Clone, Debug, PartialEq)]
@@ -10,18 +59,37 @@ pub enum GCEbmlSpec {
 
     #[id(0x4286)]
     #[data_type(TagDataType::UnsignedInt)]
+    #[doc_path(EBML)]
     EBMLVersion,
 
+    #[id(0x42F7)]
+    #[data_type(TagDataType::UnsignedInt)]
+    #[doc_path(EBML)]
+    EBMLReadVersion,
+
+    #[id(0x42F2)]
+    #[data_type(TagDataType::UnsignedInt)]
+    #[doc_path(EBML)]
+    EBMLMaxIDLength,
+
+    #[id(0x42F3)]
+    #[data_type(TagDataType::UnsignedInt)]
+    #[doc_path(EBML)]
+    EBMLMaxSizeLength,
+
     #[id(0x4282)]
     #[data_type(TagDataType::Utf8)]
+    #[doc_path(EBML)]
     DocType,
 
     #[id(0x4287)]
     #[data_type(TagDataType::UnsignedInt)]
+    #[doc_path(EBML)]
     DocTypeVersion,
 
     #[id(0x4285)]
     #[data_type(TagDataType::UnsignedInt)]
+    #[doc_path(EBML)]
     DocTypeReadVersion,
     
     /*Root Element */
@@ -32,175 +100,227 @@ pub enum GCEbmlSpec {
     /*Children of GameCase */
     #[id(0x5B01)]
     #[data_type(TagDataType::Master)]
+    #[doc_path(GameCase)]
     Index,
 
     #[id(0x5B02)]
     #[data_type(TagDataType::Master)]
+    #[doc_path(GameCase)]
     Game,
 
     #[id(0x5B03)]
     #[data_type(TagDataType::Master)]
+    #[doc_path(GameCase)]
     Manuals,
 
     #[id(0x5B04)]
     #[data_type(TagDataType::Master)]
+    #[doc_path(GameCase)]
     RomHacks,
 
     #[id(0x5B05)]
     #[data_type(TagDataType::Master)]
+    #[doc_path(GameCase)]
     Media,
 
     /*Index */
     #[id(0x5B06)]
     #[data_type(TagDataType::Master)]
+    #[doc_path(GameCase/Index)]
     IndexEntry,
 
     /*Children of IndexEntry */
     #[id(0x5B07)]
     #[data_type(TagDataType::UnsignedInt)]
+    #[doc_path(GameCase/Index/IndexEntry)]
     IndexTargetID,
 
     #[id(0x5B08)]
     #[data_type(TagDataType::UnsignedInt)]
+    #[doc_path(GameCase/Index/IndexEntry)]
     IndexPosition,
 
     #[id(0x5B09)]
     #[data_type(TagDataType::Utf8)]
+    #[doc_path(GameCase/Index/IndexEntry)]
     IndexTarget,
 
     #[id(0x5B0A)]
     #[data_type(TagDataType::Utf8)]
+    #[doc_path(GameCase/Index/IndexEntry)]
     IndexTargetCategory,
 
     #[id(0x5B0B)]
     #[data_type(TagDataType::Utf8)]
+    #[doc_path(GameCase/Index/IndexEntry)]
     IndexTargetRegion,
 
     #[id(0x5B0C)]
     #[data_type(TagDataType::UnsignedInt)]
+    #[doc_path(GameCase/Index/IndexEntry)]
     IndexTargetUID,
 
     /*Game Element */
     #[id(0x2E8A00)]
     #[data_type(TagDataType::Utf8)]
+    #[doc_path(GameCase/(1-2))]
     Title,
 
     #[id(0x2E8A01)]
     #[data_type(TagDataType::Utf8)]
+    #[doc_path(GameCase/(1-2))]
     Developer,
 
     #[id(0x2E8A02)]
     #[data_type(TagDataType::Utf8)]
+    #[doc_path(GameCase/Game)]
     Genre,
 
     #[id(0x2E8A03)]
     #[data_type(TagDataType::UnsignedInt)]
+    #[doc_path(GameCase/Game)]
     MinPlayers,
 
     #[id(0x2E8A04)]
     #[data_type(TagDataType::UnsignedInt)]
+    #[doc_path(GameCase/Game)]
     MaxPlayers,
 
     #[id(0x2E8A05)]
     #[data_type(TagDataType::Utf8)]
+    #[doc_path(GameCase/Game)]
     GameSystem,
 
     #[id(0x2E8A1C)]
     #[data_type(TagDataType::Utf8)]
+    #[doc_path(GameCase/Game)]
     Description,
 
     #[id(0x2E8A06)]
     #[data_type(TagDataType::Master)]
+    #[doc_path(GameCase/Game)]
     Rating,
 
     #[id(0x2E8A07)]
     #[data_type(TagDataType::Master)]
+    #[doc_path(GameCase/Game)]
     RegionInfo,
 
     #[id(0x2E8A08)]
     #[data_type(TagDataType::Master)]
+    #[doc_path(GameCase/Game)]
     GameData,
 
     /*Children of Rating */
     #[id(0x1E8A9B00)]
     #[data_type(TagDataType::Utf8)]
+    #[doc_path(GameCase/Game/Rating)]
     RatingValue,
 
     #[id(0x1E8A9B01)]
     #[data_type(TagDataType::Utf8)]
+    #[doc_path(GameCase/Game/Rating)]
     RatingSource,
 
     /*Children of RegionInfo */
     #[id(0x1E8A9B02)]
     #[data_type(TagDataType::Utf8)]
+    #[doc_path(GameCase/(1-))]
     Region,
 
     #[id(0x1E8A9B03)]
     #[data_type(TagDataType::Utf8)]
+    #[doc_path(GameCase/(1-))]
     Language,
 
     #[id(0x1E8A9B04)]
-    #[data_type(TagDataType::Binary)]//Meant to be type Date
+    /*ebml-iterable has no Date type, the value is decoded by
+    date::ReleaseDate. */
+    #[data_type(TagDataType::Binary)]
+    #[doc_path(GameCase/Game/RegionInfo)]
     ReleaseDate,
 
+    #[id(0x1E8A9B30)]
+    #[data_type(TagDataType::UnsignedInt)]
+    #[doc_path(GameCase/Game/RegionInfo)]
+    ReleaseDatePrecision,
+
     #[id(0x1E8A9B05)]
     #[data_type(TagDataType::Utf8)]
+    #[doc_path(GameCase/Game/RegionInfo)]
     Publisher,
 
     #[id(0x1E8A9B06)]
     #[data_type(TagDataType::Utf8)]
+    #[doc_path(GameCase/Game/RegionInfo)]
     AgeRating,
 
     /*Children of GameData Element */
     #[id(0x2E8A09)]
     #[data_type(TagDataType::Master)]
+    #[doc_path(GameCase/Game/GameData)]
     GameDataEntry,
 
     /*Children of GataDataEntry Element */
     #[id(0x5B0D)]
     #[data_type(TagDataType::UnsignedInt)]
+    #[doc_path(GameCase/(1-))]
     EntryUID,
 
     #[id(0x2E8A0A)]
     #[data_type(TagDataType::Utf8)]
+    #[doc_path(GameCase/Game/GameData/GameDataEntry)]
     DataFormat,
 
     #[id(0x2E8A0B)]
     #[data_type(TagDataType::Master)]
+    #[doc_path(GameCase/Game/GameData/GameDataEntry)]
     RawData,
 
     #[id(0x2E8A0C)]
     #[data_type(TagDataType::Master)]
+    #[doc_path(GameCase/Game/GameData/GameDataEntry)]
     ArchiveData,
 
     #[id(0x2E8A0D)]
     #[data_type(TagDataType::Master)]
+    #[doc_path(GameCase/Game/GameData/GameDataEntry)]
     ChdData,
 
     #[id(0x2E8A0E)]
     #[data_type(TagDataType::Master)]
+    #[doc_path(GameCase/Game/GameData/GameDataEntry)]
     BinCueData,
 
     #[id(0x2E8A0F)]
     #[data_type(TagDataType::Master)]
+    #[doc_path(GameCase/Game/GameData/GameDataEntry)]
     SSMCData,
 
     /*RawData Element */
     #[id(0x2E8A10)]
     #[data_type(TagDataType::Master)]
+    #[doc_path(GameCase/Game/GameData/GameDataEntry/(1-3))]
     FileProperties,
     
     #[id(0x1E8AA000)]
     #[data_type(TagDataType::Binary)]
+    #[doc_path(GameCase/Game/GameData/GameDataEntry/RawData)]
     ROMData,
 
-    /*Children of FileProperties */
+    /*Children of FileProperties
+    ebml-iterable counts the FileProperties element itself towards the
+    global range of its children, so (1-3) is needed for FileProperties up
+    to two levels below GameDataEntry, e.g. in ChdData/CHDEntry. The parent
+    path has to match the children's. */
     #[id(0x1E8A9B07)]
     #[data_type(TagDataType::Utf8)]
+    #[doc_path(GameCase/Game/GameData/GameDataEntry/(1-3)/FileProperties)]
     ROMFileName,
 
     #[id(0x1E8A9B08)]
     #[data_type(TagDataType::UnsignedInt)]
+    #[doc_path(GameCase/Game/GameData/GameDataEntry/(1-3)/FileProperties)]
     ROMFileSize,
 
     /* Region already declared and is reused here.
@@ -217,43 +337,53 @@ pub enum GCEbmlSpec {
 
     #[id(0x1E8A9B0B)]
     #[data_type(TagDataType::Binary)]
+    #[doc_path(GameCase/Game/GameData/GameDataEntry/(1-3)/FileProperties)]
     CRC32,
 
     #[id(0x1E8A9B0C)]
     #[data_type(TagDataType::Binary)]
+    #[doc_path(GameCase/Game/GameData/GameDataEntry/(1-3)/FileProperties)]
     MD5,
 
     #[id(0x1E8A9B0D)]
     #[data_type(TagDataType::Binary)]
+    #[doc_path(GameCase/Game/GameData/GameDataEntry/(1-3)/FileProperties)]
     SHA1,
 
     #[id(0x1E8A9B0E)]
     #[data_type(TagDataType::Binary)]
+    #[doc_path(GameCase/Game/GameData/GameDataEntry/(1-3)/FileProperties)]
     SHA256,
 
     #[id(0x1E8A9B0F)]
     #[data_type(TagDataType::Binary)]
+    #[doc_path(GameCase/Game/GameData/GameDataEntry/(1-3)/FileProperties)]
     SHA512,
 
     #[id(0x1E8A9B10)]
     #[data_type(TagDataType::UnsignedInt)]
+    #[doc_path(GameCase/Game/GameData/GameDataEntry/(1-3)/FileProperties)]
     SSMCIndex,
 
     /*Children of ArchiveData */
     #[id(0x1E8A9B11)]
     #[data_type(TagDataType::Utf8)]
+    #[doc_path(GameCase/Game/GameData/GameDataEntry/ArchiveData)]
     CompressionAlgorithm,
 
     #[id(0x1E8A9B12)]
     #[data_type(TagDataType::UnsignedInt)]
+    #[doc_path(GameCase/Game/GameData/GameDataEntry/(1-1))]
     ArchivedFileCount,
 
     #[id(0x2E8A11)]
     #[data_type(TagDataType::Master)]
+    #[doc_path(GameCase/Game/GameData/GameDataEntry/ArchiveData)]
     ArchiveFileMetadata,
 
     #[id(0x1E8AA001)]
     #[data_type(TagDataType::Binary)]
+    #[doc_path(GameCase/Game/GameData/GameDataEntry/ArchiveData)]
     ArchiveBinary,
 
     /*Children of ArchivedFileMetadata */
@@ -278,15 +408,18 @@ pub enum GCEbmlSpec {
 
     #[id(0x1E8A9B14)]
     #[data_type(TagDataType::UnsignedInt)]
+    #[doc_path(GameCase/Game/GameData/GameDataEntry/ChdData)]
     CHDCount,
 
     #[id(0x2E8A12)]
     #[data_type(TagDataType::Master)]
+    #[doc_path(GameCase/Game/GameData/GameDataEntry/ChdData)]
     CHDEntry,
 
     /*Children of CHDEntry */
     #[id(0x1E8A9B15)]
     #[data_type(TagDataType::UnsignedInt)]
+    #[doc_path(GameCase/Game/GameData/GameDataEntry/(2-2))]
     DiscNum,
 
     /*FileProperties already declared and is reused here.
@@ -297,6 +430,7 @@ pub enum GCEbmlSpec {
 
     #[id(0x1E8AA002)]
     #[data_type(TagDataType::Binary)]
+    #[doc_path(GameCase/Game/GameData/GameDataEntry/ChdData/CHDEntry)]
     CHDBinary,
 
     /*Children of BinCueData */
@@ -314,10 +448,12 @@ pub enum GCEbmlSpec {
 
     #[id(0x1E8A9B16)]
     #[data_type(TagDataType::UnsignedInt)]
+    #[doc_path(GameCase/Game/GameData/GameDataEntry/BinCueData)]
     BinCueCount,
 
     #[id(0x2E8A13)]
     #[data_type(TagDataType::Master)]
+    #[doc_path(GameCase/Game/GameData/GameDataEntry/BinCueData)]
     BinCueEntry,
 
     /*Children of BinCueEntry */
@@ -329,6 +465,7 @@ pub enum GCEbmlSpec {
 
     #[id(0x1E8A9B17)]
     #[data_type(TagDataType::Utf8)]
+    #[doc_path(GameCase/Game/GameData/GameDataEntry/BinCueData/BinCueEntry)]
     CueSheet,
 
     /*FileProperties already declared and is reused here.
@@ -339,6 +476,7 @@ pub enum GCEbmlSpec {
 
     #[id(0x1E8AA003)]
     #[data_type(TagDataType::Binary)]
+    #[doc_path(GameCase/Game/GameData/GameDataEntry/BinCueData/BinCueEntry)]
     BinBinary,
 
     /*Children of SSMCData */
@@ -350,10 +488,12 @@ pub enum GCEbmlSpec {
 
     #[id(0x2E8A14)]
     #[data_type(TagDataType::Master)]
+    #[doc_path(GameCase/Game/GameData/GameDataEntry/SSMCData)]
     SSMCEntry,
 
     #[id(0x1E8AA004)]
     #[data_type(TagDataType::Binary)]
+    #[doc_path(GameCase/Game/GameData/GameDataEntry/SSMCData)]
     SSMCBinary,
 
     /*Children of SSMCEntry */
@@ -366,6 +506,7 @@ pub enum GCEbmlSpec {
     /*Manuals Element */
     #[id(0x2E8A15)]
     #[data_type(TagDataType::Master)]
+    #[doc_path(GameCase/Manuals)]
     ManualEntry,
 
     /*Children of ManualEntry */
@@ -389,23 +530,28 @@ pub enum GCEbmlSpec {
 
     #[id(0x1E8A9B1A)]
     #[data_type(TagDataType::UnsignedInt)]
+    #[doc_path(GameCase/Manuals/ManualEntry)]
     PageCount,
 
     #[id(0x1E8A9B1B)]
     #[data_type(TagDataType::Utf8)]
+    #[doc_path(GameCase/Manuals/ManualEntry)]
     Revision,
 
     #[id(0x1E8A9B1C)]
     #[data_type(TagDataType::Utf8)]
+    #[doc_path(GameCase/Manuals/ManualEntry)]
     ManualFormat,
 
     #[id(0x1E8AA005)]
     #[data_type(TagDataType::Binary)]
+    #[doc_path(GameCase/Manuals/ManualEntry)]
     ManualData,
 
     /*RomHacks Element */
     #[id(0x2E8A16)]
     #[data_type(TagDataType::Master)]
+    #[doc_path(GameCase/RomHacks)]
     RomHackEntry,
 
     /*Children of RomHackEntry */
@@ -429,49 +575,60 @@ pub enum GCEbmlSpec {
 
     #[id(0x1E8A9B1F)]
     #[data_type(TagDataType::Utf8)]
+    #[doc_path(GameCase/RomHacks/RomHackEntry)]
     HackVersion,
 
     #[id(0x1E8A9B20)]
     #[data_type(TagDataType::Utf8)]
+    #[doc_path(GameCase/RomHacks/RomHackEntry)]
     PatchFormat,
 
     #[id(0x2E8A17)]
     #[data_type(TagDataType::Master)]
+    #[doc_path(GameCase/RomHacks/RomHackEntry)]
     TargetHash,
 
     #[id(0x1E8A9B21)]
     #[data_type(TagDataType::Utf8)]
+    #[doc_path(GameCase/RomHacks/RomHackEntry)]
     HackDescription,
 
     #[id(0x1E8AA006)]
     #[data_type(TagDataType::Binary)]
+    #[doc_path(GameCase/RomHacks/RomHackEntry)]
     PatchData,
 
     /*Children of TargetHash */
     #[id(0x1E8A9B22)]
     #[data_type(TagDataType::Utf8)]
+    #[doc_path(GameCase/RomHacks/RomHackEntry/TargetHash)]
     HashFormat,
 
     #[id(0x1E8A9B23)]
     #[data_type(TagDataType::Binary)]
+    #[doc_path(GameCase/RomHacks/RomHackEntry/TargetHash)]
     HashValue,
 
     /*Media Element */
     #[id(0x2E8A18)]
     #[data_type(TagDataType::Master)]
+    #[doc_path(GameCase/Media)]
     ImageCollection,
 
     #[id(0x2E8A1A)]
     #[data_type(TagDataType::Master)]
+    #[doc_path(GameCase/Media)]
     VideoCollection,
 
     /*Children of ImageCollection */
     #[id(0x1E8A9B24)]
     #[data_type(TagDataType::Utf8)]
+    #[doc_path(GameCase/Media/(1-1))]
     Category,
 
     #[id(0x2E8A19)]
     #[data_type(TagDataType::Master)]
+    #[doc_path(GameCase/Media/ImageCollection)]
     ImageEntry,
 
     /*Children of ImageEntry */
@@ -483,10 +640,12 @@ pub enum GCEbmlSpec {
 
     #[id(0x1E8A9B25)]
     #[data_type(TagDataType::Utf8)]
+    #[doc_path(GameCase/Media/ImageCollection/ImageEntry)]
     ImageTitle,
 
     #[id(0x1E8A9B26)]
     #[data_type(TagDataType::Utf8)]
+    #[doc_path(GameCase/Media/ImageCollection/ImageEntry)]
     Artist,
 
     /*Region already declared and is reused here.
@@ -497,22 +656,27 @@ pub enum GCEbmlSpec {
 
     #[id(0x1E8A9B28)]
     #[data_type(TagDataType::Utf8)]
+    #[doc_path(GameCase/Media/(2-2))]
     ImageFormat,
 
     #[id(0x1E8A9B29)]
     #[data_type(TagDataType::UnsignedInt)]
+    #[doc_path(GameCase/Media/(2-2))]
     Width,
 
     #[id(0x1E8A9B2A)]
     #[data_type(TagDataType::UnsignedInt)]
+    #[doc_path(GameCase/Media/(2-2))]
     Height,
 
     #[id(0x1E8A9B2F)]
     #[data_type(TagDataType::Utf8)]
+    #[doc_path(GameCase/Media/ImageCollection/ImageEntry)]
     CategoryDetail,
 
     #[id(0x1E8AA007)]
     #[data_type(TagDataType::Binary)]
+    #[doc_path(GameCase/Media/ImageCollection/ImageEntry)]
     ImageData,
 
     /*Children of VideoCollection */
@@ -524,6 +688,7 @@ pub enum GCEbmlSpec {
 
     #[id(0x2E8A1B)]
     #[data_type(TagDataType::Master)]
+    #[doc_path(GameCase/Media/VideoCollection)]
     VideoEntry,
 
     /*Children of VideoEntry */
@@ -535,6 +700,7 @@ pub enum GCEbmlSpec {
 
     #[id(0x1E8A9B2B)]
     #[data_type(TagDataType::Utf8)]
+    #[doc_path(GameCase/Media/VideoCollection/VideoEntry)]
     VideoTitle,
 
     /*Language already declared and is reused here.
@@ -545,10 +711,12 @@ pub enum GCEbmlSpec {
 
     #[id(0x1E8A9B2D)]
     #[data_type(TagDataType::UnsignedInt)]
+    #[doc_path(GameCase/Media/VideoCollection/VideoEntry)]
     Duration,
 
     #[id(0x1E8A9B2E)]
     #[data_type(TagDataType::Utf8)]
+    #[doc_path(GameCase/Media/VideoCollection/VideoEntry)]
     VideoFormat,
 
     /*Width already declared and is reused here.
@@ -575,7 +743,113 @@ pub enum GCEbmlSpec {
     ImageData,
     */
 
+    #[id(0x1E8AA008)]
+    #[data_type(TagDataType::Binary)]
+    #[doc_path(GameCase/Media/VideoCollection/VideoEntry)]
+    Thumbnail,
+
     #[id(0x1E8AA009)]
     #[data_type(TagDataType::Binary)]
+    #[doc_path(GameCase/Media/VideoCollection/VideoEntry)]
     VideoData,
 }
+
+
+/// Returns the element ID of a master element variant.
+///
+/// Allows IDs to be looked up by variant, e.g.
+/// `master_id(GCEbmlSpec::ManualEntry)`, without repeating the raw value.
+pub(crate) fn master_id(tag: fn(Master<GCEbmlSpec>) -> GCEbmlSpec) -> u64 {
+    tag(Master::Start).get_id()
+}
+
+/// Returns the element ID of a binary element variant.
+pub(crate) fn binary_id(tag: fn(Vec<u8>) -> GCEbmlSpec) -> u64 {
+    tag(Vec::new()).get_id()
+}
+
+/// Returns the element ID of a UTF-8 element variant.
+pub(crate) fn utf8_id(tag: fn(String) -> GCEbmlSpec) -> u64 {
+    tag(String::new()).get_id()
+}
+
+/// Returns the element ID of an unsigned integer element variant.
+pub(crate) fn uint_id(tag: fn(u64) -> GCEbmlSpec) -> u64 {
+    tag(0).get_id()
+}
+
+/// Returns the IDs of the entry masters, each of which carries an
+/// `EntryUID` and is listed in the `Index`.
+pub(crate) fn entry_ids() -> [u64; 5] {
+    [
+        master_id(GCEbmlSpec::GameDataEntry),
+        master_id(GCEbmlSpec::ManualEntry),
+        master_id(GCEbmlSpec::RomHackEntry),
+        master_id(GCEbmlSpec::ImageEntry),
+        master_id(GCEbmlSpec::VideoEntry),
+    ]
+}
+
+/// Returns the IDs of the masters holding entries, directly such as
+/// `Manuals` or through other containers such as `Game`.
+pub(crate) fn container_ids() -> [u64; 7] {
+    [
+        master_id(GCEbmlSpec::Game),
+        master_id(GCEbmlSpec::GameData),
+        master_id(GCEbmlSpec::Manuals),
+        master_id(GCEbmlSpec::RomHacks),
+        master_id(GCEbmlSpec::Media),
+        master_id(GCEbmlSpec::ImageCollection),
+        master_id(GCEbmlSpec::VideoCollection),
+    ]
+}
+
+/// Returns whether `id` is the ID of an entry master, see `entry_ids`.
+pub(crate) fn is_entry_id(id: u64) -> bool {
+    entry_ids().contains(&id)
+}
+
+/// Returns the name of the element with the given ID, e.g. "ReleaseDate".
+///
+/// IDs that are not part of the specification are named by their hex
+/// value, e.g. "0x1F43B675".
+pub(crate) fn element_name(id: u64) -> String {
+    let tag = match GCEbmlSpec::get_tag_data_type(id) {
+        Some(TagDataType::Master) => {
+            GCEbmlSpec::get_master_tag(id, Master::Start)
+        }
+        Some(TagDataType::UnsignedInt) => {
+            GCEbmlSpec::get_unsigned_int_tag(id, 0)
+        }
+        Some(TagDataType::Integer) => GCEbmlSpec::get_signed_int_tag(id, 0),
+        Some(TagDataType::Utf8) => {
+            GCEbmlSpec::get_utf8_tag(id, String::new())
+        }
+        Some(TagDataType::Binary) => GCEbmlSpec::get_binary_tag(id, &[]),
+        Some(TagDataType::Float) => GCEbmlSpec::get_float_tag(id, 0.0),
+        None => None,
+    };
+
+    //The variant name is the Debug output up to its data.
+    tag.and_then(|tag| {
+        format!("{tag:?}").split('(').next().map(str::to_string)
+    })
+    .unwrap_or_else(|| format!("0x{id:X}"))
+}
+
+/// Returns the path the specification defines for the element with the
+/// given ID, e.g. "GameCase/Manuals/ManualEntry".
+///
+/// Parts of the path that may be any element, like the parent of `Region`,
+/// are left out.
+pub(crate) fn element_path(id: u64) -> String {
+    GCEbmlSpec::get_path_by_id(id)
+        .iter()
+        .filter_map(|part| match part {
+            PathPart::Id(parent) => Some(element_name(*parent)),
+            PathPart::Global(_) => None,
+        })
+        .chain(std::iter::once(element_name(id)))
+        .collect::<Vec<_>>()
+        .join("/")
+}
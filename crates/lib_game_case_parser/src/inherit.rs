@@ -0,0 +1,179 @@
+use crate::lib_structs::{
+    BinCueData, BinCueEntry, ChdData, ChdEntry, FileProperties,
+};
+
+/// Where an effective value of a collection entry was taken from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ValueSource {
+    /// Set on the `ChdData` or `BinCueData` collection and not overridden.
+    Inherited,
+    /// Set in the `FileProperties` of the entry itself.
+    Own,
+}
+
+/// A value of a collection entry together with where it came from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Resolved<T> {
+    pub value: T,
+    pub source: ValueSource,
+}
+
+impl<T> Resolved<T> {
+    /// Returns whether the value was taken from the collection.
+    pub fn is_inherited(&self) -> bool {
+        self.source == ValueSource::Inherited
+    }
+}
+
+/// The region and languages that apply to a single `CHDEntry` or
+/// `BinCueEntry`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EffectiveLocale {
+    pub region: Resolved<String>,
+    pub languages: Resolved<Vec<String>>,
+}
+
+/// Resolves the locale of an entry from its `FileProperties` and the
+/// `Region` and `Language` of its collection.
+///
+/// The region and the language list are resolved independently. A
+/// `Region` in `FileProperties` overrides the collection's region, and
+/// any `Language` in `FileProperties` replaces the collection's whole
+/// language list rather than adding to it.
+///
+/// # Arguments
+///
+/// * `properties`: The `FileProperties` of the entry.
+/// * `region`: The `Region` of the collection.
+/// * `languages`: The `Language` values of the collection.
+pub fn resolve_locale(
+    properties: &FileProperties,
+    region: &str,
+    languages: &[String],
+) -> EffectiveLocale {
+    let region = match &properties.region {
+        Some(own) => Resolved {
+            value: own.clone(),
+            source: ValueSource::Own,
+        },
+        None => Resolved {
+            value: region.to_string(),
+            source: ValueSource::Inherited,
+        },
+    };
+
+    let languages = if properties.languages.is_empty() {
+        Resolved {
+            value: languages.to_vec(),
+            source: ValueSource::Inherited,
+        }
+    } else {
+        Resolved {
+            value: properties.languages.clone(),
+            source: ValueSource::Own,
+        }
+    };
+
+    EffectiveLocale { region, languages }
+}
+
+impl ChdData {
+    /// Returns the effective locale of `entry` within this collection.
+    pub fn entry_locale(&self, entry: &ChdEntry) -> EffectiveLocale {
+        resolve_locale(&entry.file_properties, &self.region, &self.languages)
+    }
+
+    /// Returns every entry paired with its effective locale, in file
+    /// order.
+    pub fn resolved_entries(&self) -> Vec<(&ChdEntry, EffectiveLocale)> {
+        self.entries
+            .iter()
+            .map(|entry| (entry, self.entry_locale(entry)))
+            .collect()
+    }
+}
+
+impl BinCueData {
+    /// Returns the effective locale of `entry` within this collection.
+    pub fn entry_locale(&self, entry: &BinCueEntry) -> EffectiveLocale {
+        resolve_locale(&entry.file_properties, &self.region, &self.languages)
+    }
+
+    /// Returns every entry paired with its effective locale, in file
+    /// order.
+    pub fn resolved_entries(&self) -> Vec<(&BinCueEntry, EffectiveLocale)> {
+        self.entries
+            .iter()
+            .map(|entry| (entry, self.entry_locale(entry)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    #[test]
+    fn entries_inherit_what_they_do_not_override() {
+        let data = ChdData {
+            region: "Europe".to_string(),
+            languages: strings(&["en", "fr"]),
+            chd_count: 2,
+            entries: vec![
+                ChdEntry {
+                    disc_num: 1,
+                    ..Default::default()
+                },
+                ChdEntry {
+                    disc_num: 2,
+                    file_properties: FileProperties {
+                        region: Some("Germany".to_string()),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+
+        let resolved = data.resolved_entries();
+
+        let inherited = &resolved[0].1;
+        assert_eq!(inherited.region.value, "Europe");
+        assert!(inherited.region.is_inherited());
+        assert_eq!(inherited.languages.value, ["en", "fr"]);
+        assert!(inherited.languages.is_inherited());
+
+        //Only the region is overridden, the languages are still inherited.
+        let overridden = &resolved[1].1;
+        assert_eq!(overridden.region.value, "Germany");
+        assert_eq!(overridden.region.source, ValueSource::Own);
+        assert!(overridden.languages.is_inherited());
+    }
+
+    #[test]
+    fn own_languages_replace_the_whole_list() {
+        let data = BinCueData {
+            region: "Europe".to_string(),
+            languages: strings(&["en", "fr"]),
+            ..Default::default()
+        };
+        let entry = BinCueEntry {
+            file_properties: FileProperties {
+                languages: strings(&["de"]),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let locale = data.entry_locale(&entry);
+
+        assert!(locale.region.is_inherited());
+        assert_eq!(locale.languages.value, ["de"]);
+        assert_eq!(locale.languages.source, ValueSource::Own);
+    }
+}
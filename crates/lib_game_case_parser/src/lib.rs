@@ -1,3 +1,71 @@
-mod lib_error_handling;
-mod parse;
-mod gc_ebml_spec;
\ No newline at end of file
+#[cfg(feature = "async")]
+pub mod async_read;
+pub mod date;
+pub mod digest;
+pub mod edit;
+mod ebml_tools;
+pub mod ffi;
+pub mod inherit;
+pub mod lib_error_handling;
+pub mod lib_structs;
+pub mod limits;
+pub mod mapped;
+#[cfg(feature = "serde")]
+pub mod metadata;
+pub mod parse;
+pub mod payload;
+pub mod query;
+pub mod gc_ebml_spec;
+pub mod read;
+pub mod repair;
+pub mod salvage;
+pub mod uid;
+pub mod validate;
+pub mod verify;
+pub mod visit;
+pub mod write;
+
+#[cfg(feature = "async")]
+pub use async_read::{
+    AsyncGameCaseReader, AsyncPayloadReader, AsyncProgressCallback,
+};
+pub use date::{GameDate, ReleaseDate};
+pub use digest::{copy_with_digests, DigestAlgorithm, Digests, MultiHasher};
+pub use edit::{
+    append_entry, append_entry_with, update_game_file, update_game_in_place,
+    AppendedEntry, GameUpdate, NewEntry,
+};
+pub use gc_ebml_spec::{DocTypeVersions, GCEbmlSpec};
+pub use inherit::{EffectiveLocale, Resolved, ValueSource};
+pub use lib_error_handling::LibError;
+pub use lib_structs::*;
+pub use limits::ReaderLimits;
+pub use mapped::{
+    Children, ElementRef, GameInfoRef, ImageEntryRef, ManualEntryRef,
+    MappedGameCase, RatingRef, RegionInfoRef, VideoEntryRef,
+};
+#[cfg(feature = "serde")]
+pub use metadata::{
+    export_json, export_toml, payload_refs, CaseMetadata, PayloadRef,
+};
+pub use parse::{process_ebml_data, process_ebml_data_with_limits};
+pub use payload::{PayloadLocation, PayloadReader};
+pub use query::{select, CaseNode, CasePath};
+pub use read::{CaseEntry, GameCaseReader, IndexQuery};
+pub use repair::{
+    check_index, repair_index, repair_index_file, IndexMismatch,
+};
+pub use salvage::{
+    salvage, salvage_file, salvage_with_limits, LossReason, LostElement,
+    SalvageReport, SalvagedElement, UNKNOWN_CATEGORY,
+};
+pub use uid::{UidAllocator, UidStrategy};
+pub use validate::{validate, validate_with_limits, Diagnostic, Severity};
+pub use verify::{DigestResult, FileStatus, FileVerification};
+pub use visit::{
+    visit, visit_with_limits, BinaryChunk, EntryEnd, EntryStart, Field,
+    GameCaseVisitor,
+};
+pub use write::{
+    GameCaseWriter, DEFAULT_GAME_PADDING, DEFAULT_INDEX_PADDING,
+};
@@ -1,6 +1,181 @@
+use std::io;
+
+use ebml_iterable::error::TagWriterError;
 use thiserror::Error;
 
+/// Errors raised while reading or writing `.gcase` files.
+///
+/// Errors caused by the contents of a file carry the byte `offset` of the
+/// offending element from the start of the stream being read and its
+/// element `path`, e.g. `GameCase/Game/RegionInfo[1]/ReleaseDate`. A path
+/// segment is suffixed with `[n]` when it is the n-th (zero based) of
+/// several elements with the same ID under one parent.
 #[derive(Error, Debug)]
 pub enum LibError{
-    
-}
\ No newline at end of file
+    #[error("I/O Error: {0}")]
+    Io(#[from] io::Error),
+
+    #[error("EBML write error: {0}")]
+    TagWriter(#[from] TagWriterError),
+
+    /// An element ID or size could not be decoded.
+    #[error("Invalid VINT at byte {offset} ({path})")]
+    InvalidVint { offset: u64, path: String },
+
+    /// An element ID that is not part of the GameCase specification.
+    #[error("Unknown element 0x{id:X} at byte {offset} ({path})")]
+    UnknownElement { id: u64, offset: u64, path: String },
+
+    /// A known element found outside of its defined parent.
+    #[error("Element 0x{id:X} is not allowed at byte {offset} ({path})")]
+    MisplacedElement { id: u64, offset: u64, path: String },
+
+    /// The data of a known element does not match its data type, e.g. an
+    /// unsigned integer longer than 8 bytes or a string that is not valid
+    /// UTF-8.
+    ///
+    /// If the element itself could not be located the offset is that of
+    /// its parent.
+    #[error("Element 0x{id:X} at byte {offset} ({path}) has invalid data for \
+        its type")]
+    WrongDataType { id: u64, offset: u64, path: String },
+
+    /// A master element ended before its declared size, either because the
+    /// stream ended or a child did not fit inside it.
+    #[error("Master element at byte {offset} ({path}) is truncated")]
+    TruncatedMaster { offset: u64, path: String },
+
+    /// An element larger than the reader is willing to load.
+    #[error("Element 0x{id:X} at byte {offset} ({path}) is too large \
+        ({size} bytes)")]
+    ElementTooLarge { id: u64, size: u64, offset: u64, path: String },
+
+    /// A master nested deeper than the reader's limits allow.
+    #[error("Element at byte {offset} ({path}) is nested {depth} levels \
+        deep")]
+    NestingTooDeep { depth: usize, offset: u64, path: String },
+
+    /// A master with more children than the reader's limits allow.
+    #[error("Master element at byte {offset} ({path}) has at least {count} \
+        children, more than allowed")]
+    TooManyChildren { count: usize, offset: u64, path: String },
+
+    /// A file with more entries than the reader's limits allow.
+    #[error("Entry at byte {offset} ({path}) is entry number {count}, more \
+        than allowed")]
+    TooManyEntries { count: usize, offset: u64, path: String },
+
+    /// The file requires a newer version of the GameCase DocType.
+    #[error("DocTypeReadVersion {version} at byte {offset} ({path}) is not \
+        supported, the newest supported version is {supported}")]
+    UnsupportedReadVersion {
+        version: u64,
+        supported: u64,
+        offset: u64,
+        path: String,
+    },
+
+    /// The EBML header declares a DocType other than "GameCase".
+    #[error("DocType \"{found}\" at byte {offset} ({path}) is not \
+        \"GameCase\"")]
+    WrongDocType { found: String, offset: u64, path: String },
+
+    /// `DataFormat` holds a value the specification does not define.
+    #[error("Unknown DataFormat \"{value}\" at byte {offset} ({path})")]
+    UnknownDataFormat { value: String, offset: u64, path: String },
+
+    /// A `GameDataEntry` holds data of a different format than its
+    /// `DataFormat` declares.
+    #[error("{element} at byte {offset} ({path}) does not match DataFormat \
+        \"{data_format}\"")]
+    DataFormatMismatch {
+        data_format: String,
+        element: String,
+        offset: u64,
+        path: String,
+    },
+
+    /// A mandatory child is missing from the element at `offset`.
+    #[error("Missing mandatory element {element} in element at byte \
+        {offset} ({path})")]
+    MissingElement { element: &'static str, offset: u64, path: String },
+
+    #[error("Expected element 0x{expected:X} but found 0x{found:X} at byte \
+        {offset} ({path})")]
+    UnexpectedElement {
+        expected: u64,
+        found: u64,
+        offset: u64,
+        path: String,
+    },
+
+    #[error("No indexed entry with UID {0}")]
+    EntryNotFound(u64),
+
+    #[error("EntryUID {0} is used by more than one entry")]
+    DuplicateEntryUid(u64),
+
+    /// Metadata could not be exported to or imported from JSON or TOML.
+    #[error("Metadata could not be converted: {0}")]
+    Metadata(String),
+
+    /// A payload reference of imported metadata does not match the payload
+    /// of the source case, or the source case has no such payload.
+    #[error("No {element} of entry {uid} in the source case matches its \
+        reference")]
+    PayloadMismatch { uid: u64, element: &'static str },
+
+    /// An entry of imported metadata has a payload without a reference.
+    #[error("The {element} of entry {uid} has no reference")]
+    MissingPayload { uid: u64, element: &'static str },
+
+    /// A path expression could not be parsed, see `CasePath`.
+    #[error("Invalid path expression at character {position}: {reason}")]
+    InvalidQuery { position: usize, reason: &'static str },
+}
+
+impl LibError {
+    /// Returns the byte offset the error refers to, if it has one.
+    pub fn offset(&self) -> Option<u64> {
+        match self {
+            LibError::InvalidVint { offset, .. }
+            | LibError::UnknownElement { offset, .. }
+            | LibError::MisplacedElement { offset, .. }
+            | LibError::WrongDataType { offset, .. }
+            | LibError::TruncatedMaster { offset, .. }
+            | LibError::ElementTooLarge { offset, .. }
+            | LibError::NestingTooDeep { offset, .. }
+            | LibError::TooManyChildren { offset, .. }
+            | LibError::TooManyEntries { offset, .. }
+            | LibError::UnsupportedReadVersion { offset, .. }
+            | LibError::WrongDocType { offset, .. }
+            | LibError::UnknownDataFormat { offset, .. }
+            | LibError::DataFormatMismatch { offset, .. }
+            | LibError::MissingElement { offset, .. }
+            | LibError::UnexpectedElement { offset, .. } => Some(*offset),
+            _ => None,
+        }
+    }
+
+    /// Returns the element path the error refers to, if it has one.
+    pub fn path(&self) -> Option<&str> {
+        match self {
+            LibError::InvalidVint { path, .. }
+            | LibError::UnknownElement { path, .. }
+            | LibError::MisplacedElement { path, .. }
+            | LibError::WrongDataType { path, .. }
+            | LibError::TruncatedMaster { path, .. }
+            | LibError::ElementTooLarge { path, .. }
+            | LibError::NestingTooDeep { path, .. }
+            | LibError::TooManyChildren { path, .. }
+            | LibError::TooManyEntries { path, .. }
+            | LibError::UnsupportedReadVersion { path, .. }
+            | LibError::WrongDocType { path, .. }
+            | LibError::UnknownDataFormat { path, .. }
+            | LibError::DataFormatMismatch { path, .. }
+            | LibError::MissingElement { path, .. }
+            | LibError::UnexpectedElement { path, .. } => Some(path),
+            _ => None,
+        }
+    }
+}
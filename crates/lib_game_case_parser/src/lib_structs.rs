@@ -0,0 +1,496 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::date::ReleaseDate;
+
+/// A fully decoded `.gcase` document.
+///
+/// Mirrors the `GameCase` root element described in SPECIFICATION.md. The
+/// mandatory `Index` and `Game` children are always present, the remaining
+/// children are optional.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct GameCase {
+    pub index: Index,
+    pub game: Game,
+    pub manuals: Option<Manuals>,
+    pub rom_hacks: Option<RomHacks>,
+    pub media: Option<Media>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Vec::is_empty")
+    )]
+    pub unknown: Vec<UnknownElement>,
+}
+
+/// The `Index` element, used for fast seeking within the file.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct Index {
+    pub entries: Vec<IndexEntry>,
+}
+
+/// A single pointer from the `Index` to an entry within the file.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct IndexEntry {
+    /// Element ID of the target element (e.g. `ManualEntry`).
+    pub target_id: u64,
+    /// Byte offset of the target element from the start of the `GameCase`
+    /// element.
+    pub position: u64,
+    pub target: String,
+    pub category: Option<String>,
+    pub region: Option<String>,
+    /// The `EntryUID` of the target element.
+    #[cfg_attr(feature = "serde", serde(with = "crate::metadata::uid"))]
+    pub uid: u64,
+}
+
+/// The `Game` element holding the metadata of the subject game.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct Game {
+    pub title: String,
+    pub developers: Vec<String>,
+    pub genre: Option<String>,
+    pub min_players: Option<u64>,
+    pub max_players: Option<u64>,
+    pub game_system: String,
+    pub description: Option<String>,
+    pub rating: Option<Rating>,
+    pub region_info: Vec<RegionInfo>,
+    pub game_data: Option<GameData>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Vec::is_empty")
+    )]
+    pub unknown: Vec<UnknownElement>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct Rating {
+    pub value: String,
+    pub source: String,
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Vec::is_empty")
+    )]
+    pub unknown: Vec<UnknownElement>,
+}
+
+/// Region specific information about the game.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct RegionInfo {
+    pub region: String,
+    pub languages: Vec<String>,
+    pub release_date: ReleaseDate,
+    pub publisher: String,
+    pub age_rating: String,
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Vec::is_empty")
+    )]
+    pub unknown: Vec<UnknownElement>,
+}
+
+/// Container for one or more game data entries.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct GameData {
+    pub entries: Vec<GameDataEntry>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Vec::is_empty")
+    )]
+    pub unknown: Vec<UnknownElement>,
+}
+
+/// A single instance of game data, e.g. one ROM, a CHD set or an archive.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct GameDataEntry {
+    #[cfg_attr(feature = "serde", serde(with = "crate::metadata::uid"))]
+    pub entry_uid: u64,
+    /// The game data itself. Its variant determines the `DataFormat`
+    /// written to the file.
+    pub payload: GameDataPayload,
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Vec::is_empty")
+    )]
+    pub unknown: Vec<UnknownElement>,
+}
+
+/// The data of a `GameDataEntry`, one variant per `DataFormat`.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum GameDataPayload {
+    /// "RAW"
+    Raw(RawData),
+    /// "ARCHIVE"
+    Archive(ArchiveData),
+    /// "CHD"
+    Chd(ChdData),
+    /// "BIN/CUE"
+    BinCue(BinCueData),
+    /// "SSMC"
+    Ssmc(SsmcData),
+}
+
+impl GameDataPayload {
+    /// Returns the `DataFormat` value matching this payload.
+    pub fn data_format(&self) -> &'static str {
+        match self {
+            GameDataPayload::Raw(_) => "RAW",
+            GameDataPayload::Archive(_) => "ARCHIVE",
+            GameDataPayload::Chd(_) => "CHD",
+            GameDataPayload::BinCue(_) => "BIN/CUE",
+            GameDataPayload::Ssmc(_) => "SSMC",
+        }
+    }
+}
+
+impl Default for GameDataPayload {
+    fn default() -> Self {
+        GameDataPayload::Raw(RawData::default())
+    }
+}
+
+/// Properties of a single file, shared by every kind of game data.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct FileProperties {
+    pub rom_file_name: Option<String>,
+    pub rom_file_size: u64,
+    pub region: Option<String>,
+    pub languages: Vec<String>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::metadata::opt_hex"))]
+    pub crc32: Option<Vec<u8>>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::metadata::opt_hex"))]
+    pub md5: Option<Vec<u8>>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::metadata::opt_hex"))]
+    pub sha1: Option<Vec<u8>>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::metadata::opt_hex"))]
+    pub sha256: Option<Vec<u8>>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::metadata::opt_hex"))]
+    pub sha512: Option<Vec<u8>>,
+    pub ssmc_index: Option<u64>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Vec::is_empty")
+    )]
+    pub unknown: Vec<UnknownElement>,
+}
+
+/// Game data stored in the "RAW" format.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct RawData {
+    pub file_properties: FileProperties,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub rom_data: Vec<u8>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Vec::is_empty")
+    )]
+    pub unknown: Vec<UnknownElement>,
+}
+
+/// Game data stored in the "ARCHIVE" format.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct ArchiveData {
+    pub compression_algorithm: String,
+    pub archived_file_count: u64,
+    pub file_metadata: Vec<FileProperties>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub archive_binary: Vec<u8>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Vec::is_empty")
+    )]
+    pub unknown: Vec<UnknownElement>,
+}
+
+/// Game data stored in the "CHD" format.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct ChdData {
+    pub region: String,
+    pub languages: Vec<String>,
+    pub chd_count: u64,
+    pub entries: Vec<ChdEntry>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Vec::is_empty")
+    )]
+    pub unknown: Vec<UnknownElement>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct ChdEntry {
+    pub disc_num: u64,
+    pub file_properties: FileProperties,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub chd_binary: Vec<u8>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Vec::is_empty")
+    )]
+    pub unknown: Vec<UnknownElement>,
+}
+
+/// Game data stored in the "BIN/CUE" format.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct BinCueData {
+    pub region: String,
+    pub languages: Vec<String>,
+    pub bin_cue_count: u64,
+    pub entries: Vec<BinCueEntry>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Vec::is_empty")
+    )]
+    pub unknown: Vec<UnknownElement>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct BinCueEntry {
+    pub disc_num: u64,
+    pub cue_sheet: String,
+    pub file_properties: FileProperties,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub bin_binary: Vec<u8>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Vec::is_empty")
+    )]
+    pub unknown: Vec<UnknownElement>,
+}
+
+/// Game data stored as a SpriteShrink MultiCart ("SSMC").
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct SsmcData {
+    pub archived_file_count: u64,
+    pub entries: Vec<FileProperties>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub ssmc_binary: Vec<u8>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Vec::is_empty")
+    )]
+    pub unknown: Vec<UnknownElement>,
+}
+
+/// The `Manuals` element.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct Manuals {
+    pub entries: Vec<ManualEntry>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Vec::is_empty")
+    )]
+    pub unknown: Vec<UnknownElement>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct ManualEntry {
+    #[cfg_attr(feature = "serde", serde(with = "crate::metadata::uid"))]
+    pub entry_uid: u64,
+    pub region: String,
+    pub languages: Vec<String>,
+    pub page_count: u64,
+    pub revision: String,
+    pub manual_format: String,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub manual_data: Vec<u8>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Vec::is_empty")
+    )]
+    pub unknown: Vec<UnknownElement>,
+}
+
+/// The `RomHacks` element.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct RomHacks {
+    pub entries: Vec<RomHackEntry>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Vec::is_empty")
+    )]
+    pub unknown: Vec<UnknownElement>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct RomHackEntry {
+    #[cfg_attr(feature = "serde", serde(with = "crate::metadata::uid"))]
+    pub entry_uid: u64,
+    pub title: String,
+    pub developers: Vec<String>,
+    pub hack_version: Option<String>,
+    pub patch_format: Option<String>,
+    pub target_hashes: Vec<TargetHash>,
+    pub hack_description: Option<String>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub patch_data: Vec<u8>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Vec::is_empty")
+    )]
+    pub unknown: Vec<UnknownElement>,
+}
+
+/// The hash of the ROM a hack is meant to be applied to.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct TargetHash {
+    pub hash_format: String,
+    #[cfg_attr(feature = "serde", serde(with = "crate::metadata::hex"))]
+    pub hash_value: Vec<u8>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Vec::is_empty")
+    )]
+    pub unknown: Vec<UnknownElement>,
+}
+
+/// The `Media` element.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct Media {
+    pub image_collections: Vec<ImageCollection>,
+    pub video_collections: Vec<VideoCollection>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Vec::is_empty")
+    )]
+    pub unknown: Vec<UnknownElement>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct ImageCollection {
+    pub category: String,
+    pub entries: Vec<ImageEntry>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Vec::is_empty")
+    )]
+    pub unknown: Vec<UnknownElement>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct ImageEntry {
+    #[cfg_attr(feature = "serde", serde(with = "crate::metadata::uid"))]
+    pub entry_uid: u64,
+    pub image_title: Option<String>,
+    pub artists: Vec<String>,
+    pub region: Option<String>,
+    pub image_format: String,
+    pub width: u64,
+    pub height: u64,
+    pub category_detail: Option<String>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub image_data: Vec<u8>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Vec::is_empty")
+    )]
+    pub unknown: Vec<UnknownElement>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct VideoCollection {
+    pub category: String,
+    pub entries: Vec<VideoEntry>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Vec::is_empty")
+    )]
+    pub unknown: Vec<UnknownElement>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct VideoEntry {
+    #[cfg_attr(feature = "serde", serde(with = "crate::metadata::uid"))]
+    pub entry_uid: u64,
+    pub video_title: Option<String>,
+    pub languages: Vec<String>,
+    pub duration: u64,
+    pub video_format: String,
+    pub width: u64,
+    pub height: u64,
+    pub image_format: String,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub thumbnail: Option<Vec<u8>>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub video_data: Vec<u8>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Vec::is_empty")
+    )]
+    pub unknown: Vec<UnknownElement>,
+}
+
+/// An element this version of the library does not know, such as one added
+/// by a newer revision of the specification.
+///
+/// Every struct mirroring a master element keeps its unknown children in
+/// `unknown`, so writing a case again does not lose data written by newer
+/// tools. Unknown children of the `Index`, which is rebuilt on every write,
+/// and of `ArchiveFileMetadata` and `SSMCEntry` are not kept. Readers
+/// reject unknown elements instead if
+/// `ReaderLimits::reject_unknown_elements` is set.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct UnknownElement {
+    pub id: u64,
+    /// The data of the element, without its header.
+    #[cfg_attr(feature = "serde", serde(with = "crate::metadata::hex"))]
+    pub data: Vec<u8>,
+    /// Number of known children of the parent in front of the element. The
+    /// element is written back after as many known children.
+    pub position: u64,
+}
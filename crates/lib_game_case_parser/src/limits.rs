@@ -0,0 +1,495 @@
+use std::collections::HashMap;
+use std::io::{self, Read};
+
+use ebml_iterable::specs::{EbmlSpecification, TagDataType};
+
+use crate::ebml_tools::{parse_element_header, ElementHeader, VOID_ID};
+use crate::gc_ebml_spec::{
+    element_name, is_entry_id, master_id, GCEbmlSpec,
+};
+use crate::lib_error_handling::LibError;
+use crate::payload::is_payload;
+
+/// Upper bounds on what a reader accepts from a file.
+///
+/// Every element header is checked against the limits as it is read, so
+/// a file claiming a huge element or nesting masters very deep is rejected
+/// with a typed error before anything is allocated for it. The defaults
+/// are meant for untrusted cases, such as ones shared by other collectors.
+///
+/// Payloads streamed through `PayloadReader` or `visit`, or borrowed from
+/// a `MappedGameCase`, are never copied into memory and are not affected
+/// by `max_binary_size`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ReaderLimits {
+    /// Largest string element in bytes, 1 MiB by default.
+    pub max_string_size: u64,
+    /// Largest binary element loaded into memory in bytes, 256 MiB by
+    /// default. Elements unknown to the specification count as binary.
+    /// Raise it to load cases holding larger ROMs as a whole, or stream
+    /// their payloads instead.
+    pub max_binary_size: u64,
+    /// Deepest allowed nesting of masters, 32 by default. Top level
+    /// elements such as `GameCase` are at depth 1.
+    pub max_depth: usize,
+    /// Most children a single master may hold, 65536 by default.
+    pub max_children: usize,
+    /// Most entries a file may hold, 65536 by default. Entries and the
+    /// `IndexEntry`s pointing at them are counted separately.
+    pub max_entries: usize,
+    /// Whether an element unknown to the specification is rejected with
+    /// `LibError::UnknownElement`, false by default. Unknown elements are
+    /// otherwise kept and written back, see `UnknownElement`.
+    pub reject_unknown_elements: bool,
+}
+
+impl Default for ReaderLimits {
+    fn default() -> Self {
+        ReaderLimits {
+            max_string_size: 1 << 20,
+            max_binary_size: 256 << 20,
+            max_depth: 32,
+            max_children: 65_536,
+            max_entries: 65_536,
+            reject_unknown_elements: false,
+        }
+    }
+}
+
+impl ReaderLimits {
+    /// Limits that accept anything, for files from a trusted source.
+    pub fn unlimited() -> Self {
+        ReaderLimits {
+            max_string_size: u64::MAX,
+            max_binary_size: u64::MAX,
+            max_depth: usize::MAX,
+            max_children: usize::MAX,
+            max_entries: usize::MAX,
+            reject_unknown_elements: false,
+        }
+    }
+}
+
+/// A master whose children are being counted.
+struct OpenMaster {
+    offset: u64,
+    path: String,
+    /// Offset of the end of the master, `None` if its size is unknown.
+    end: Option<u64>,
+    children: usize,
+    siblings: HashMap<u64, usize>,
+}
+
+impl OpenMaster {
+    /// Counts a new child and returns its path.
+    fn add_child(&mut self, id: u64) -> String {
+        self.children += 1;
+        let index = self.siblings.entry(id).or_default();
+        let mut path = match self.path.as_str() {
+            "" => element_name(id),
+            parent => format!("{parent}/{}", element_name(id)),
+        };
+        if *index > 0 {
+            path.push_str(&format!("[{index}]"));
+        }
+        *index += 1;
+
+        path
+    }
+}
+
+/// Checks a stream of encoded elements against `ReaderLimits` while it is
+/// being read.
+///
+/// The tracker is fed the bytes in the order they are read and decodes
+/// every element header as soon as it is complete. Data of elements other
+/// than masters is skipped. On an invalid header the tracker stops
+/// checking and leaves reporting the damage to the decoder.
+pub(crate) struct LimitTracker {
+    limits: ReaderLimits,
+    position: u64,
+    header: Vec<u8>,
+    /// Data bytes of the current element still to be skipped.
+    skip: u64,
+    root: OpenMaster,
+    open: Vec<OpenMaster>,
+    base_depth: usize,
+    entries: usize,
+    index_entries: usize,
+    stopped: bool,
+    /// Whether payloads and unknown elements are streamed rather than
+    /// loaded, exempting them from `max_binary_size`.
+    streamed: bool,
+    /// Whether payloads are skipped rather than loaded, exempting them
+    /// from `max_binary_size`.
+    payloads_skipped: bool,
+}
+
+impl LimitTracker {
+    /// Creates a tracker for a stream starting at `origin` within the file.
+    ///
+    /// # Arguments
+    ///
+    /// * `limits`: The limits to enforce.
+    /// * `origin`: Offset of the first byte fed to the tracker.
+    /// * `base`: Path of the parent of the first element, empty when
+    ///   reading a whole document.
+    pub fn new(limits: ReaderLimits, origin: u64, base: &str) -> Self {
+        LimitTracker {
+            limits,
+            position: origin,
+            header: Vec::with_capacity(12),
+            skip: 0,
+            root: OpenMaster {
+                offset: origin,
+                path: base.to_string(),
+                end: None,
+                children: 0,
+                siblings: HashMap::new(),
+            },
+            open: Vec::new(),
+            base_depth: base.split('/').filter(|p| !p.is_empty()).count(),
+            entries: 0,
+            index_entries: 0,
+            stopped: false,
+            streamed: false,
+            payloads_skipped: false,
+        }
+    }
+
+    /// Exempts payloads, `Void` elements and elements unknown to the
+    /// specification from `max_binary_size`, for readers that stream or
+    /// skip them instead of loading them.
+    pub fn streaming_payloads(mut self) -> Self {
+        self.streamed = true;
+        self
+    }
+
+    /// Exempts payloads from `max_binary_size`, for readers that skip them
+    /// but load every other element.
+    pub fn skipping_payloads(mut self) -> Self {
+        self.payloads_skipped = true;
+        self
+    }
+
+    /// Checks an element header read at `offset` by a reader that seeks
+    /// past element data rather than feeding it to the tracker.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` that holds the `LibError` describing how the element
+    /// breaks a limit, if it does.
+    pub fn check_header(
+        &mut self,
+        offset: u64,
+        header: &ElementHeader,
+    ) -> Result<(), LibError> {
+        if self.stopped {
+            return Ok(());
+        }
+
+        self.position = offset + header.header_len;
+        let unknown = header.is_unknown_size();
+        self.enter(header.id, header.size, unknown, offset)?;
+        self.skip = 0;
+
+        Ok(())
+    }
+
+    /// Checks the next bytes of the stream.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` that holds the `LibError` describing the first element
+    /// breaking a limit, if any.
+    pub fn feed(&mut self, mut bytes: &[u8]) -> Result<(), LibError> {
+        while !bytes.is_empty() && !self.stopped {
+            if self.skip > 0 {
+                let skipped = self.skip.min(bytes.len() as u64);
+                self.skip -= skipped;
+                self.position += skipped;
+                bytes = &bytes[skipped as usize..];
+                continue;
+            }
+
+            self.header.push(bytes[0]);
+            self.position += 1;
+            bytes = &bytes[1..];
+
+            let id_len = self.header[0].leading_zeros() as usize + 1;
+            if self.header[0] == 0 || id_len > 4 {
+                self.stopped = true;
+                continue;
+            }
+            let Some(size_byte) = self.header.get(id_len) else {
+                continue;
+            };
+            let size_len = size_byte.leading_zeros() as usize + 1;
+            if size_len > 8 {
+                self.stopped = true;
+                continue;
+            }
+            if self.header.len() < id_len + size_len {
+                continue;
+            }
+
+            match parse_element_header(&self.header) {
+                Some(header) => {
+                    let offset = self.position - header.header_len;
+                    //A size with every value bit set is unknown.
+                    let unknown = header.size == (1 << (7 * size_len)) - 1;
+                    self.header.clear();
+                    self.enter(header.id, header.size, unknown, offset)?;
+                }
+                None => self.stopped = true,
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks a newly read element header against the limits.
+    fn enter(
+        &mut self,
+        id: u64,
+        size: u64,
+        unknown: bool,
+        offset: u64,
+    ) -> Result<(), LibError> {
+        //Close the masters that end before this element.
+        while let Some(master) = self.open.last() {
+            match master.end {
+                Some(end) if end <= offset => {
+                    self.open.pop();
+                }
+                _ => break,
+            }
+        }
+
+        let parent = self.open.last_mut().unwrap_or(&mut self.root);
+        let path = parent.add_child(id);
+        if parent.children > self.limits.max_children {
+            return Err(LibError::TooManyChildren {
+                count: parent.children,
+                offset: parent.offset,
+                path: parent.path.clone(),
+            });
+        }
+        let parent_end = parent.end;
+
+        let data_type = GCEbmlSpec::get_tag_data_type(id);
+        if data_type.is_none() && self.limits.reject_unknown_elements {
+            return Err(LibError::UnknownElement { id, offset, path });
+        }
+        if unknown && data_type != Some(TagDataType::Master) {
+            //The decoder rejects unknown sizes on anything but masters.
+            self.stopped = true;
+            return Ok(());
+        }
+
+        let limit = match data_type {
+            Some(TagDataType::Master) => {
+                return self.open_master(
+                    id, size, unknown, offset, path, parent_end,
+                );
+            }
+            Some(TagDataType::Utf8) => self.limits.max_string_size,
+            Some(TagDataType::Binary)
+                if self.streamed && (is_payload(id) || id == VOID_ID) =>
+            {
+                u64::MAX
+            }
+            Some(TagDataType::Binary)
+                if self.payloads_skipped && is_payload(id) =>
+            {
+                u64::MAX
+            }
+            None if self.streamed => u64::MAX,
+            Some(TagDataType::Binary) | None => self.limits.max_binary_size,
+            //Numbers and dates never take more than eight bytes.
+            Some(_) => 8,
+        };
+        if size > limit {
+            return Err(LibError::ElementTooLarge { id, size, offset, path });
+        }
+        self.skip = size;
+
+        Ok(())
+    }
+
+    /// Opens a master after checking its depth and the number of entries.
+    fn open_master(
+        &mut self,
+        id: u64,
+        size: u64,
+        unknown: bool,
+        offset: u64,
+        path: String,
+        parent_end: Option<u64>,
+    ) -> Result<(), LibError> {
+        let depth = self.base_depth + self.open.len() + 1;
+        if depth > self.limits.max_depth {
+            return Err(LibError::NestingTooDeep { depth, offset, path });
+        }
+
+        let count = match id {
+            id if is_entry_id(id) => Some(&mut self.entries),
+            id if id == master_id(GCEbmlSpec::IndexEntry) => {
+                Some(&mut self.index_entries)
+            }
+            _ => None,
+        };
+        if let Some(count) = count {
+            *count += 1;
+            if *count > self.limits.max_entries {
+                return Err(LibError::TooManyEntries {
+                    count: *count,
+                    offset,
+                    path,
+                });
+            }
+        }
+
+        let header_len = self.position - offset;
+        self.open.push(OpenMaster {
+            offset,
+            path,
+            end: match unknown {
+                true => parent_end,
+                false => Some(offset + header_len + size),
+            },
+            children: 0,
+            siblings: HashMap::new(),
+        });
+
+        Ok(())
+    }
+}
+
+/// A reader that checks everything read through it against
+/// `ReaderLimits`.
+///
+/// A broken limit is returned as an `io::Error` wrapping the `LibError`,
+/// see `limit_error`, before the offending header is handed to the caller.
+pub(crate) struct LimitedReader<R: Read> {
+    source: R,
+    tracker: LimitTracker,
+}
+
+impl<R: Read> LimitedReader<R> {
+    pub fn new(source: R, tracker: LimitTracker) -> Self {
+        LimitedReader { source, tracker }
+    }
+}
+
+impl<R: Read> Read for LimitedReader<R> {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        let read = self.source.read(buffer)?;
+        self.tracker.feed(&buffer[..read]).map_err(io::Error::other)?;
+
+        Ok(read)
+    }
+}
+
+/// Recovers the `LibError` wrapped by `LimitedReader` from an `io::Error`,
+/// or wraps any other `io::Error` in `LibError::Io`.
+pub(crate) fn limit_error(error: io::Error) -> LibError {
+    let wrapped = error
+        .get_ref()
+        .is_some_and(|inner| inner.is::<LibError>());
+    if !wrapped {
+        return LibError::Io(error);
+    }
+
+    match error.into_inner().map(|inner| inner.downcast::<LibError>()) {
+        Some(Ok(error)) => *error,
+        Some(Err(inner)) => LibError::Io(io::Error::other(inner)),
+        None => LibError::Io(io::ErrorKind::Other.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::ebml_tools::encode_element_header;
+    use crate::gc_ebml_spec::binary_id;
+    use crate::parse::process_ebml_data_with_limits;
+    use crate::read::GameCaseReader;
+    use crate::write::tests::{encode_case, sample_case};
+
+    /// Returns a small file whose `ROMData` claims to hold `size` bytes,
+    /// within masters of unknown size.
+    fn claimed_rom(size: u64) -> Vec<u8> {
+        let valid = encode_case(&sample_case());
+        let reader = GameCaseReader::open(Cursor::new(&valid)).unwrap();
+        let mut bytes = valid[..reader.game_case_start() as usize].to_vec();
+
+        for master in [
+            GCEbmlSpec::GameCase,
+            GCEbmlSpec::Game,
+            GCEbmlSpec::GameData,
+            GCEbmlSpec::GameDataEntry,
+            GCEbmlSpec::RawData,
+        ] {
+            let mut header = encode_element_header(master_id(master), 0);
+            //An all ones size of one byte stands for an unknown size.
+            *header.last_mut().unwrap() = 0xFF;
+            bytes.extend(header);
+        }
+        bytes.extend(encode_element_header(
+            binary_id(GCEbmlSpec::ROMData),
+            size,
+        ));
+        bytes.extend([7; 16]);
+        bytes
+    }
+
+    #[test]
+    fn huge_claimed_element_is_rejected() {
+        let bytes = claimed_rom(4 << 30);
+        let result =
+            process_ebml_data_with_limits(&bytes[..], ReaderLimits::default());
+
+        match result {
+            Err(LibError::ElementTooLarge { id, size, .. }) => {
+                assert_eq!(id, binary_id(GCEbmlSpec::ROMData));
+                assert_eq!(size, 4 << 30);
+            }
+            other => panic!("expected ElementTooLarge, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn long_string_is_rejected() {
+        let bytes = encode_case(&sample_case());
+        let limits = ReaderLimits {
+            max_string_size: 3,
+            ..Default::default()
+        };
+
+        assert!(matches!(
+            process_ebml_data_with_limits(&bytes[..], limits),
+            Err(LibError::ElementTooLarge { .. })
+        ));
+    }
+
+    #[test]
+    fn deep_nesting_is_rejected() {
+        let bytes = encode_case(&sample_case());
+        let limits = ReaderLimits {
+            max_depth: 2,
+            ..Default::default()
+        };
+
+        assert!(matches!(
+            process_ebml_data_with_limits(&bytes[..], limits),
+            Err(LibError::NestingTooDeep { depth: 3, .. })
+        ));
+        assert!(matches!(
+            GameCaseReader::open_with_limits(Cursor::new(&bytes), limits),
+            Err(LibError::NestingTooDeep { depth: 3, .. })
+        ));
+    }
+}
@@ -0,0 +1,874 @@
+use std::fs::File;
+use std::io;
+use std::path::Path;
+use std::str;
+
+use ebml_iterable::specs::{EbmlSpecification, TagDataType};
+use ebml_iterable::tools::arr_to_u64;
+use memmap2::Mmap;
+
+use crate::date::ReleaseDate;
+use crate::ebml_tools::{
+    parse_element_header, ChildWalker, ElementHeader, ElementSpan,
+};
+use crate::gc_ebml_spec::{
+    binary_id, element_name, element_path, master_id, uint_id, utf8_id,
+    DocTypeVersions, GCEbmlSpec,
+};
+use crate::lib_error_handling::LibError;
+use crate::lib_structs::{Index, IndexEntry};
+use crate::parse::{
+    check_ebml_header, decode_elements, read_index, ElementNode,
+};
+use crate::payload::{is_payload, PayloadLocation};
+use crate::read::{entry_offset, IndexQuery};
+
+/// Random access reader for `.gcase` files backed by a memory map.
+///
+/// Like `GameCaseReader` only the EBML header and the `Index` are decoded
+/// when a case is opened. Entries are then read in place: strings and
+/// binary data are returned as `&str` and `&[u8]` views into the mapped
+/// file rather than being copied, so listing the metadata or pulling the
+/// thumbnails out of many cases allocates little more than the `Index`.
+///
+/// Reading never seeks, so every method takes `&self` and the reader can
+/// be shared between threads, e.g. to extract several `ImageData` blobs of
+/// one case at once.
+///
+/// The file must not be modified or truncated while it is mapped. Changes
+/// made by other processes would show through the returned views, and
+/// truncation makes accessing them fail.
+pub struct MappedGameCase {
+    map: Mmap,
+    game_case_start: u64,
+    index: Index,
+    doc_type_versions: DocTypeVersions,
+}
+
+impl MappedGameCase {
+    /// Maps the case at `path` and reads its header and `Index`.
+    pub fn open(path: &Path) -> Result<Self, LibError> {
+        Self::map(&File::open(path)?)
+    }
+
+    /// Maps an open case and reads its header and `Index`.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the reader, or a `LibError` if the file does
+    /// not start with an EBML header followed by a `GameCase` whose first
+    /// child is the `Index`.
+    pub fn map(file: &File) -> Result<Self, LibError> {
+        //Safety: the file is only ever read through the map, and the caller
+        //is told not to modify it while it is mapped.
+        let map = unsafe { Mmap::map(file)? };
+
+        let header = ElementRef::expect_at(
+            &map,
+            0,
+            master_id(GCEbmlSpec::EBML),
+        )?;
+        let doc_type_versions = check_ebml_header(&header.decode()?)?;
+
+        let game_case_start = header.end();
+        let game_case = game_case_at(&map, game_case_start)?;
+        let index = ElementRef::expect_at(
+            &map,
+            game_case.data_start(),
+            master_id(GCEbmlSpec::Index),
+        )?;
+        let index = read_index(index.decode()?);
+
+        Ok(MappedGameCase {
+            map,
+            game_case_start,
+            index,
+            doc_type_versions,
+        })
+    }
+
+    /// Returns the `Index` read when the case was opened.
+    pub fn index(&self) -> &Index {
+        &self.index
+    }
+
+    /// Returns the `DocTypeVersion` and `DocTypeReadVersion` declared by
+    /// the file.
+    pub fn doc_type_versions(&self) -> DocTypeVersions {
+        self.doc_type_versions
+    }
+
+    /// Returns the absolute offset of the `GameCase` element, which every
+    /// `IndexPosition` is relative to.
+    pub fn game_case_start(&self) -> u64 {
+        self.game_case_start
+    }
+
+    /// Returns every index entry matching `query`, in index order.
+    pub fn find<'a>(
+        &'a self,
+        query: &'a IndexQuery,
+    ) -> impl Iterator<Item = &'a IndexEntry> + 'a {
+        self.index.entries.iter().filter(|entry| query.matches(entry))
+    }
+
+    /// Returns the whole mapped file.
+    pub fn bytes(&self) -> &[u8] {
+        &self.map
+    }
+
+    /// Returns the element starting at the absolute `offset`.
+    pub fn element_at(&self, offset: u64) -> Result<ElementRef<'_>, LibError> {
+        ElementRef::at(&self.map, offset)
+    }
+
+    /// Returns the element an index entry points to.
+    pub fn entry(
+        &self,
+        entry: &IndexEntry,
+    ) -> Result<ElementRef<'_>, LibError> {
+        ElementRef::expect_at(
+            &self.map,
+            entry_offset(self.game_case_start, entry)?,
+            entry.target_id,
+        )
+    }
+
+    /// Returns the `Game` metadata, such as the title and developers.
+    ///
+    /// `GameData` is skipped, its entries are read through the `Index`.
+    pub fn game_info(&self) -> Result<GameInfoRef<'_>, LibError> {
+        let game = game_case_at(&self.map, self.game_case_start)?
+            .child(master_id(GCEbmlSpec::Game))?
+            .ok_or_else(|| LibError::MissingElement {
+                element: "Game",
+                offset: self.game_case_start,
+                path: element_path(master_id(GCEbmlSpec::GameCase)),
+            })?;
+
+        GameInfoRef::read(game)
+    }
+
+    /// Returns the `ManualEntry` an index entry points to.
+    pub fn manual(
+        &self,
+        entry: &IndexEntry,
+    ) -> Result<ManualEntryRef<'_>, LibError> {
+        ManualEntryRef::read(self.expect_entry(
+            entry,
+            master_id(GCEbmlSpec::ManualEntry),
+        )?)
+    }
+
+    /// Returns the `ImageEntry` an index entry points to.
+    pub fn image(
+        &self,
+        entry: &IndexEntry,
+    ) -> Result<ImageEntryRef<'_>, LibError> {
+        ImageEntryRef::read(self.expect_entry(
+            entry,
+            master_id(GCEbmlSpec::ImageEntry),
+        )?)
+    }
+
+    /// Returns the `VideoEntry` an index entry points to.
+    pub fn video(
+        &self,
+        entry: &IndexEntry,
+    ) -> Result<VideoEntryRef<'_>, LibError> {
+        VideoEntryRef::read(self.expect_entry(
+            entry,
+            master_id(GCEbmlSpec::VideoEntry),
+        )?)
+    }
+
+    /// Lists the binary payloads stored within an indexed entry.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the payload locations in file order.
+    pub fn payloads(
+        &self,
+        entry: &IndexEntry,
+    ) -> Result<Vec<PayloadLocation>, LibError> {
+        let mut payloads = Vec::new();
+        collect_payloads(self.entry(entry)?, &mut payloads)?;
+
+        Ok(payloads)
+    }
+
+    /// Returns the data of a single payload.
+    pub fn payload(
+        &self,
+        payload: &PayloadLocation,
+    ) -> Result<&[u8], LibError> {
+        slice(&self.map, payload.offset, payload.size)
+    }
+
+    /// Returns the element an index entry points to, checking that it is
+    /// of the kind asked for.
+    fn expect_entry(
+        &self,
+        entry: &IndexEntry,
+        expected: u64,
+    ) -> Result<ElementRef<'_>, LibError> {
+        if entry.target_id != expected {
+            return Err(LibError::UnexpectedElement {
+                expected,
+                found: entry.target_id,
+                offset: entry_offset(self.game_case_start, entry)?,
+                path: element_path(expected),
+            });
+        }
+
+        self.entry(entry)
+    }
+}
+
+/// A single element viewed in place within a mapped case.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ElementRef<'a> {
+    id: u64,
+    offset: u64,
+    header_len: usize,
+    /// The whole element, header included.
+    raw: &'a [u8],
+}
+
+impl<'a> ElementRef<'a> {
+    /// Parses the element starting at the absolute `offset` of `bytes`.
+    fn at(bytes: &'a [u8], offset: u64) -> Result<Self, LibError> {
+        let (header, _) = header_at(bytes, offset)?;
+
+        Ok(ElementRef {
+            id: header.id,
+            offset,
+            header_len: header.header_len as usize,
+            raw: slice(bytes, offset, header.total_len())?,
+        })
+    }
+
+    /// Parses the element at `offset` and checks that it has the expected
+    /// ID.
+    fn expect_at(
+        bytes: &'a [u8],
+        offset: u64,
+        expected: u64,
+    ) -> Result<Self, LibError> {
+        let element = Self::at(bytes, offset)?;
+
+        if element.id != expected {
+            return Err(LibError::UnexpectedElement {
+                expected,
+                found: element.id,
+                offset,
+                path: element_path(expected),
+            });
+        }
+
+        Ok(element)
+    }
+
+    /// Element ID, e.g. the ID of `ImageData`.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Name of the element, e.g. "ImageData".
+    pub fn name(&self) -> String {
+        element_name(self.id)
+    }
+
+    /// Absolute offset of the element's ID within the file.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// The data of the element, without its header.
+    pub fn data(&self) -> &'a [u8] {
+        &self.raw[self.header_len..]
+    }
+
+    /// Returns the data of a UTF-8 element as a string.
+    pub fn as_str(&self) -> Result<&'a str, LibError> {
+        str::from_utf8(self.data()).map_err(|_| self.wrong_data_type())
+    }
+
+    /// Returns the value of an unsigned integer element.
+    pub fn as_uint(&self) -> Result<u64, LibError> {
+        arr_to_u64(self.data()).map_err(|_| self.wrong_data_type())
+    }
+
+    /// Iterates over the children of a master element.
+    ///
+    /// Iteration ends after the first child that cannot be read, which is
+    /// returned as an error.
+    pub fn children(&self) -> Children<'a> {
+        let span = ElementSpan {
+            offset: self.offset,
+            header: ElementHeader {
+                id: self.id,
+                size: self.data().len() as u64,
+                header_len: self.header_len as u64,
+            },
+            end: Some(self.end()),
+        };
+
+        Children { parent: *self, walker: span.children(), failed: false }
+    }
+
+    /// Returns the first child with the ID `id`.
+    pub fn child(&self, id: u64) -> Result<Option<ElementRef<'a>>, LibError> {
+        for child in self.children() {
+            let child = child?;
+            if child.id == id {
+                return Ok(Some(child));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Absolute offset of the element's data.
+    fn data_start(&self) -> u64 {
+        self.offset + self.header_len as u64
+    }
+
+    /// Absolute offset of the first byte after the element.
+    fn end(&self) -> u64 {
+        self.offset + self.raw.len() as u64
+    }
+
+    /// Decodes the element into an owned tree.
+    fn decode(&self) -> Result<ElementNode, LibError> {
+        decode_elements(self.raw, self.offset)?
+            .into_iter()
+            .next()
+            .ok_or(LibError::Io(io::ErrorKind::UnexpectedEof.into()))
+    }
+
+    fn wrong_data_type(&self) -> LibError {
+        LibError::WrongDataType {
+            id: self.id,
+            offset: self.offset,
+            path: element_path(self.id),
+        }
+    }
+}
+
+/// Iterator over the children of an `ElementRef`.
+pub struct Children<'a> {
+    parent: ElementRef<'a>,
+    walker: ChildWalker,
+    failed: bool,
+}
+
+impl<'a> Iterator for Children<'a> {
+    type Item = Result<ElementRef<'a>, LibError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+
+        let raw = self.parent.raw;
+        let start = (self.walker.position() - self.parent.offset) as usize;
+        let mut rest = raw.get(start..).unwrap_or_default();
+
+        let child = match self.walker.next(&mut rest) {
+            Ok(Some(child)) => child,
+            Ok(None) => return None,
+            Err(error) => {
+                self.failed = true;
+                return Some(Err(error));
+            }
+        };
+        let end = child.end.map_or(raw.len(), |end| {
+            (end - self.parent.offset) as usize
+        });
+
+        Some(Ok(ElementRef {
+            id: child.header.id,
+            offset: child.offset,
+            header_len: child.header.header_len as usize,
+            raw: &raw[start..end],
+        }))
+    }
+}
+
+/// The `Rating` of a game, viewed in place.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RatingRef<'a> {
+    pub value: &'a str,
+    pub source: &'a str,
+}
+
+/// Region specific information about a game, viewed in place.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RegionInfoRef<'a> {
+    pub region: &'a str,
+    pub languages: Vec<&'a str>,
+    /// The encoded `ReleaseDate`, see `release_date`.
+    pub release_date: &'a [u8],
+    /// The `ReleaseDatePrecision`, `ReleaseDate::PRECISION_DAY` if absent.
+    pub release_date_precision: u64,
+    pub publisher: &'a str,
+    pub age_rating: &'a str,
+}
+
+impl RegionInfoRef<'_> {
+    /// Decodes the release date together with its precision.
+    pub fn release_date(&self) -> ReleaseDate {
+        ReleaseDate::from_element(self.release_date, self.release_date_precision)
+    }
+}
+
+/// The `Game` metadata, viewed in place.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct GameInfoRef<'a> {
+    pub title: &'a str,
+    pub developers: Vec<&'a str>,
+    pub genre: Option<&'a str>,
+    pub min_players: Option<u64>,
+    pub max_players: Option<u64>,
+    pub game_system: &'a str,
+    pub description: Option<&'a str>,
+    pub rating: Option<RatingRef<'a>>,
+    pub region_info: Vec<RegionInfoRef<'a>>,
+}
+
+impl<'a> GameInfoRef<'a> {
+    fn read(game: ElementRef<'a>) -> Result<Self, LibError> {
+        let mut info = GameInfoRef::default();
+        let mut title = None;
+        let mut game_system = None;
+
+        for child in game.children() {
+            let child = child?;
+            match child.id {
+                id if id == utf8_id(GCEbmlSpec::Title) => {
+                    title = Some(child.as_str()?)
+                }
+                id if id == utf8_id(GCEbmlSpec::Developer) => {
+                    info.developers.push(child.as_str()?)
+                }
+                id if id == utf8_id(GCEbmlSpec::Genre) => {
+                    info.genre = Some(child.as_str()?)
+                }
+                id if id == uint_id(GCEbmlSpec::MinPlayers) => {
+                    info.min_players = Some(child.as_uint()?)
+                }
+                id if id == uint_id(GCEbmlSpec::MaxPlayers) => {
+                    info.max_players = Some(child.as_uint()?)
+                }
+                id if id == utf8_id(GCEbmlSpec::GameSystem) => {
+                    game_system = Some(child.as_str()?)
+                }
+                id if id == utf8_id(GCEbmlSpec::Description) => {
+                    info.description = Some(child.as_str()?)
+                }
+                id if id == master_id(GCEbmlSpec::Rating) => {
+                    info.rating = Some(read_rating(child)?)
+                }
+                id if id == master_id(GCEbmlSpec::RegionInfo) => {
+                    info.region_info.push(read_region_info(child)?)
+                }
+                _ => {}
+            }
+        }
+
+        let missing = |element| LibError::MissingElement {
+            element,
+            offset: game.offset,
+            path: element_path(game.id),
+        };
+        info.title = title.ok_or_else(|| missing("Title"))?;
+        info.game_system = game_system.ok_or_else(|| missing("GameSystem"))?;
+
+        Ok(info)
+    }
+}
+
+fn read_rating(rating: ElementRef<'_>) -> Result<RatingRef<'_>, LibError> {
+    let mut view = RatingRef::default();
+
+    for child in rating.children() {
+        let child = child?;
+        match child.id {
+            id if id == utf8_id(GCEbmlSpec::RatingValue) => {
+                view.value = child.as_str()?
+            }
+            id if id == utf8_id(GCEbmlSpec::RatingSource) => {
+                view.source = child.as_str()?
+            }
+            _ => {}
+        }
+    }
+
+    Ok(view)
+}
+
+fn read_region_info(
+    region_info: ElementRef<'_>,
+) -> Result<RegionInfoRef<'_>, LibError> {
+    let mut view = RegionInfoRef::default();
+
+    for child in region_info.children() {
+        let child = child?;
+        match child.id {
+            id if id == utf8_id(GCEbmlSpec::Region) => {
+                view.region = child.as_str()?
+            }
+            id if id == utf8_id(GCEbmlSpec::Language) => {
+                view.languages.push(child.as_str()?)
+            }
+            id if id == binary_id(GCEbmlSpec::ReleaseDate) => {
+                view.release_date = child.data()
+            }
+            id if id == uint_id(GCEbmlSpec::ReleaseDatePrecision) => {
+                view.release_date_precision = child.as_uint()?
+            }
+            id if id == utf8_id(GCEbmlSpec::Publisher) => {
+                view.publisher = child.as_str()?
+            }
+            id if id == utf8_id(GCEbmlSpec::AgeRating) => {
+                view.age_rating = child.as_str()?
+            }
+            _ => {}
+        }
+    }
+
+    Ok(view)
+}
+
+/// A `ManualEntry`, viewed in place.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ManualEntryRef<'a> {
+    pub entry_uid: u64,
+    pub region: &'a str,
+    pub languages: Vec<&'a str>,
+    pub page_count: u64,
+    pub revision: &'a str,
+    pub manual_format: &'a str,
+    pub manual_data: &'a [u8],
+}
+
+impl<'a> ManualEntryRef<'a> {
+    fn read(entry: ElementRef<'a>) -> Result<Self, LibError> {
+        let mut view = ManualEntryRef::default();
+
+        for child in entry.children() {
+            let child = child?;
+            match child.id {
+                id if id == uint_id(GCEbmlSpec::EntryUID) => {
+                    view.entry_uid = child.as_uint()?
+                }
+                id if id == utf8_id(GCEbmlSpec::Region) => {
+                    view.region = child.as_str()?
+                }
+                id if id == utf8_id(GCEbmlSpec::Language) => {
+                    view.languages.push(child.as_str()?)
+                }
+                id if id == uint_id(GCEbmlSpec::PageCount) => {
+                    view.page_count = child.as_uint()?
+                }
+                id if id == utf8_id(GCEbmlSpec::Revision) => {
+                    view.revision = child.as_str()?
+                }
+                id if id == utf8_id(GCEbmlSpec::ManualFormat) => {
+                    view.manual_format = child.as_str()?
+                }
+                id if id == binary_id(GCEbmlSpec::ManualData) => {
+                    view.manual_data = child.data()
+                }
+                _ => {}
+            }
+        }
+
+        Ok(view)
+    }
+}
+
+/// An `ImageEntry`, viewed in place.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ImageEntryRef<'a> {
+    pub entry_uid: u64,
+    pub image_title: Option<&'a str>,
+    pub artists: Vec<&'a str>,
+    pub region: Option<&'a str>,
+    pub image_format: &'a str,
+    pub width: u64,
+    pub height: u64,
+    pub category_detail: Option<&'a str>,
+    pub image_data: &'a [u8],
+}
+
+impl<'a> ImageEntryRef<'a> {
+    fn read(entry: ElementRef<'a>) -> Result<Self, LibError> {
+        let mut view = ImageEntryRef::default();
+
+        for child in entry.children() {
+            let child = child?;
+            match child.id {
+                id if id == uint_id(GCEbmlSpec::EntryUID) => {
+                    view.entry_uid = child.as_uint()?
+                }
+                id if id == utf8_id(GCEbmlSpec::ImageTitle) => {
+                    view.image_title = Some(child.as_str()?)
+                }
+                id if id == utf8_id(GCEbmlSpec::Artist) => {
+                    view.artists.push(child.as_str()?)
+                }
+                id if id == utf8_id(GCEbmlSpec::Region) => {
+                    view.region = Some(child.as_str()?)
+                }
+                id if id == utf8_id(GCEbmlSpec::ImageFormat) => {
+                    view.image_format = child.as_str()?
+                }
+                id if id == uint_id(GCEbmlSpec::Width) => {
+                    view.width = child.as_uint()?
+                }
+                id if id == uint_id(GCEbmlSpec::Height) => {
+                    view.height = child.as_uint()?
+                }
+                id if id == utf8_id(GCEbmlSpec::CategoryDetail) => {
+                    view.category_detail = Some(child.as_str()?)
+                }
+                id if id == binary_id(GCEbmlSpec::ImageData) => {
+                    view.image_data = child.data()
+                }
+                _ => {}
+            }
+        }
+
+        Ok(view)
+    }
+}
+
+/// A `VideoEntry`, viewed in place.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct VideoEntryRef<'a> {
+    pub entry_uid: u64,
+    pub video_title: Option<&'a str>,
+    pub languages: Vec<&'a str>,
+    pub duration: u64,
+    pub video_format: &'a str,
+    pub width: u64,
+    pub height: u64,
+    pub image_format: &'a str,
+    pub thumbnail: Option<&'a [u8]>,
+    pub video_data: &'a [u8],
+}
+
+impl<'a> VideoEntryRef<'a> {
+    fn read(entry: ElementRef<'a>) -> Result<Self, LibError> {
+        let mut view = VideoEntryRef::default();
+
+        for child in entry.children() {
+            let child = child?;
+            match child.id {
+                id if id == uint_id(GCEbmlSpec::EntryUID) => {
+                    view.entry_uid = child.as_uint()?
+                }
+                id if id == utf8_id(GCEbmlSpec::VideoTitle) => {
+                    view.video_title = Some(child.as_str()?)
+                }
+                id if id == utf8_id(GCEbmlSpec::Language) => {
+                    view.languages.push(child.as_str()?)
+                }
+                id if id == uint_id(GCEbmlSpec::Duration) => {
+                    view.duration = child.as_uint()?
+                }
+                id if id == utf8_id(GCEbmlSpec::VideoFormat) => {
+                    view.video_format = child.as_str()?
+                }
+                id if id == uint_id(GCEbmlSpec::Width) => {
+                    view.width = child.as_uint()?
+                }
+                id if id == uint_id(GCEbmlSpec::Height) => {
+                    view.height = child.as_uint()?
+                }
+                id if id == utf8_id(GCEbmlSpec::ImageFormat) => {
+                    view.image_format = child.as_str()?
+                }
+                id if id == binary_id(GCEbmlSpec::Thumbnail) => {
+                    view.thumbnail = Some(child.data())
+                }
+                id if id == binary_id(GCEbmlSpec::VideoData) => {
+                    view.video_data = child.data()
+                }
+                _ => {}
+            }
+        }
+
+        Ok(view)
+    }
+}
+
+/// Parses the header of the element starting at `offset`.
+///
+/// # Returns
+///
+/// A `Result` containing the header and every byte from `offset` to the end
+/// of `bytes`.
+fn header_at(
+    bytes: &[u8],
+    offset: u64,
+) -> Result<(ElementHeader, &[u8]), LibError> {
+    let rest = usize::try_from(offset)
+        .ok()
+        .and_then(|offset| bytes.get(offset..))
+        .filter(|rest| !rest.is_empty())
+        .ok_or(io::Error::from(io::ErrorKind::UnexpectedEof))?;
+    let header = parse_element_header(rest).ok_or_else(|| {
+        LibError::InvalidVint { offset, path: String::new() }
+    })?;
+
+    Ok((header, rest))
+}
+
+/// Returns the `GameCase` element starting at `offset`.
+///
+/// Only the part present in `bytes` is returned, so the `Index` and `Game`
+/// of a case whose end was cut off can still be read.
+fn game_case_at(
+    bytes: &[u8],
+    offset: u64,
+) -> Result<ElementRef<'_>, LibError> {
+    let (header, rest) = header_at(bytes, offset)?;
+
+    let expected = master_id(GCEbmlSpec::GameCase);
+    if header.id != expected {
+        return Err(LibError::UnexpectedElement {
+            expected,
+            found: header.id,
+            offset,
+            path: element_path(expected),
+        });
+    }
+
+    let len = usize::try_from(header.total_len()).unwrap_or(usize::MAX);
+    Ok(ElementRef {
+        id: header.id,
+        offset,
+        header_len: header.header_len as usize,
+        raw: &rest[..len.min(rest.len())],
+    })
+}
+
+/// Collects the payloads within `element` in file order.
+fn collect_payloads(
+    element: ElementRef<'_>,
+    payloads: &mut Vec<PayloadLocation>,
+) -> Result<(), LibError> {
+    for child in element.children() {
+        let child = child?;
+
+        if is_payload(child.id) {
+            payloads.push(PayloadLocation {
+                id: child.id,
+                offset: child.data_start(),
+                size: child.data().len() as u64,
+            });
+        } else if GCEbmlSpec::get_tag_data_type(child.id)
+            == Some(TagDataType::Master)
+        {
+            collect_payloads(child, payloads)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns `len` bytes of `bytes` starting at `offset`, failing if they run
+/// past its end.
+fn slice(bytes: &[u8], offset: u64, len: u64) -> Result<&[u8], LibError> {
+    usize::try_from(offset)
+        .ok()
+        .zip(usize::try_from(len).ok())
+        .and_then(|(offset, len)| bytes.get(offset..offset.checked_add(len)?))
+        .ok_or(io::Error::from(io::ErrorKind::UnexpectedEof).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::io::{Cursor, Read};
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::read::{CaseEntry, GameCaseReader};
+    use crate::write::tests::{encode_case, sample_case};
+
+    /// Writes the sample case to a file named after the test and maps it,
+    /// returning the reader along with the encoded case and the file.
+    fn mapped_sample(name: &str) -> (MappedGameCase, Vec<u8>, PathBuf) {
+        let bytes = encode_case(&sample_case());
+        let path = std::env::temp_dir().join(format!(
+            "game_case_parser_{name}_{}.gcase",
+            std::process::id()
+        ));
+        fs::write(&path, &bytes).unwrap();
+        let mapped = MappedGameCase::open(&path).unwrap();
+
+        (mapped, bytes, path)
+    }
+
+    #[test]
+    fn mapped_case_agrees_with_the_reader() {
+        let (mapped, bytes, path) = mapped_sample("mapped_agrees");
+        let mut reader = GameCaseReader::open(Cursor::new(bytes)).unwrap();
+
+        assert_eq!(mapped.index(), reader.index());
+        let game = reader.read_game_info().unwrap();
+        assert_eq!(mapped.game_info().unwrap().title, game.title);
+
+        for entry in reader.index().entries.clone() {
+            let payloads = mapped.payloads(&entry).unwrap();
+            assert_eq!(payloads, reader.payloads(&entry).unwrap());
+
+            for payload in &payloads {
+                let mut data = Vec::new();
+                reader
+                    .open_payload(payload)
+                    .unwrap()
+                    .read_to_end(&mut data)
+                    .unwrap();
+                assert_eq!(mapped.payload(payload).unwrap(), data);
+            }
+        }
+
+        let entry = &mapped.index().entries[1];
+        let CaseEntry::Manual(manual) = reader.read_entry(entry).unwrap()
+        else {
+            panic!("expected the manual");
+        };
+        let view = mapped.manual(entry).unwrap();
+        assert_eq!(view.manual_data, manual.manual_data);
+        assert_eq!(view.revision, manual.revision);
+
+        drop(mapped);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn position_past_any_file_is_not_found() {
+        let (mapped, _, path) = mapped_sample("mapped_past_any_file");
+        let entry = IndexEntry {
+            position: u64::MAX,
+            ..mapped.index().entries[1].clone()
+        };
+
+        assert!(matches!(
+            mapped.entry(&entry),
+            Err(LibError::EntryNotFound(2))
+        ));
+        assert!(matches!(
+            mapped.manual(&entry),
+            Err(LibError::EntryNotFound(2))
+        ));
+
+        drop(mapped);
+        fs::remove_file(path).unwrap();
+    }
+}
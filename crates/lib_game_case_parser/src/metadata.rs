@@ -0,0 +1,505 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::lib_error_handling::LibError;
+use crate::lib_structs::{GameCase, GameDataPayload};
+use crate::write::{check_unique_uids, index_targets};
+
+/// Names of the elements holding binary payloads.
+const PAYLOAD_ELEMENTS: [&str; 10] = [
+    "ROMData",
+    "ArchiveBinary",
+    "CHDBinary",
+    "BinBinary",
+    "SSMCBinary",
+    "ManualData",
+    "PatchData",
+    "ImageData",
+    "Thumbnail",
+    "VideoData",
+];
+
+/// Stands in for a binary payload in exported metadata.
+///
+/// A payload is identified by the `EntryUID` of its entry, the name of its
+/// element and `ordinal`, and checked against its size and digest when the
+/// metadata is applied again.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PayloadRef {
+    #[serde(with = "uid")]
+    pub uid: u64,
+    /// Name of the payload element, e.g. `ROMData`.
+    pub element: String,
+    /// Position of the payload among those of its entry with the same
+    /// element, counting from 0. Only CHD and BIN/CUE entries hold more
+    /// than one.
+    pub ordinal: u64,
+    pub size: u64,
+    #[serde(with = "hex")]
+    pub sha256: Vec<u8>,
+}
+
+/// Everything in a case except its binary payloads, which are replaced by
+/// references.
+///
+/// The `Index` is exported for reference only. Writing the case generates
+/// a new one from its entries.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct CaseMetadata {
+    pub game_case: GameCase,
+    pub payloads: Vec<PayloadRef>,
+}
+
+/// The serialized form of `CaseMetadata`, borrowing the case.
+#[derive(Serialize)]
+struct MetadataView<'a> {
+    game_case: &'a GameCase,
+    payloads: Vec<PayloadRef>,
+}
+
+impl CaseMetadata {
+    pub fn from_json(text: &str) -> Result<CaseMetadata, LibError> {
+        serde_json::from_str(text)
+            .map_err(|error| LibError::Metadata(error.to_string()))
+    }
+
+    pub fn from_toml(text: &str) -> Result<CaseMetadata, LibError> {
+        toml::from_str(text)
+            .map_err(|error| LibError::Metadata(error.to_string()))
+    }
+
+    /// Combines the metadata with the payloads of `source`, usually the case
+    /// it was exported from.
+    ///
+    /// Every payload of the metadata is moved over from the entry of
+    /// `source` with the same `EntryUID`, once its size and digest have
+    /// been checked against the reference. Entries can be edited, reordered
+    /// or removed, but entries without references cannot be added.
+    ///
+    /// # Arguments
+    ///
+    /// * `source`: The case holding the payloads.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the edited case, or a `LibError` if a
+    /// reference does not match the payload in `source` or a payload of the
+    /// metadata has no reference.
+    pub fn apply(self, mut source: GameCase) -> Result<GameCase, LibError> {
+        check_unique_uids(&index_targets(&source))?;
+
+        let mut available = HashMap::new();
+        for (key, mut slot) in payload_slots(&mut source) {
+            if let Some(data) = slot.take() {
+                available.insert(key, data);
+            }
+        }
+
+        let mut game_case = self.game_case;
+        check_unique_uids(&index_targets(&game_case))?;
+
+        let mut refs = HashMap::new();
+        for payload in self.payloads {
+            let Some(element) = PAYLOAD_ELEMENTS
+                .into_iter()
+                .find(|element| *element == payload.element)
+            else {
+                return Err(LibError::Metadata(format!(
+                    "Unknown payload element \"{}\"",
+                    payload.element
+                )));
+            };
+            refs.insert((payload.uid, element, payload.ordinal), payload);
+        }
+
+        for (key, mut slot) in payload_slots(&mut game_case) {
+            let (uid, element, _) = key;
+            let Some(payload) = refs.get(&key) else {
+                match slot {
+                    //A thumbnail without a reference was removed.
+                    Slot::Optional(_) => continue,
+                    Slot::Required(_) => {
+                        return Err(LibError::MissingPayload { uid, element });
+                    }
+                }
+            };
+
+            let data = available
+                .remove(&key)
+                .filter(|data| payload_ref(key, data) == *payload)
+                .ok_or(LibError::PayloadMismatch { uid, element })?;
+            slot.set(data);
+        }
+
+        Ok(game_case)
+    }
+}
+
+/// Exports everything in `game_case` except its binary payloads as pretty
+/// printed JSON, see `CaseMetadata`.
+///
+/// # Returns
+///
+/// A `Result` containing the JSON text, or a `LibError` if two entries
+/// share an `EntryUID`, which would make the payload references ambiguous.
+pub fn export_json(game_case: &GameCase) -> Result<String, LibError> {
+    serde_json::to_string_pretty(&metadata_view(game_case)?)
+        .map_err(|error| LibError::Metadata(error.to_string()))
+}
+
+/// Exports everything in `game_case` except its binary payloads as TOML,
+/// see `export_json`.
+pub fn export_toml(game_case: &GameCase) -> Result<String, LibError> {
+    toml::to_string_pretty(&metadata_view(game_case)?)
+        .map_err(|error| LibError::Metadata(error.to_string()))
+}
+
+/// Returns a reference to every binary payload of `game_case`, in the
+/// order they are written.
+pub fn payload_refs(game_case: &GameCase) -> Vec<PayloadRef> {
+    let mut refs = Vec::new();
+    let mut add = |uid, element, ordinal, data: &[u8]| {
+        refs.push(payload_ref((uid, element, ordinal), data));
+    };
+
+    if let Some(game_data) = &game_case.game.game_data {
+        for entry in &game_data.entries {
+            let uid = entry.entry_uid;
+            match &entry.payload {
+                GameDataPayload::Raw(raw) => {
+                    add(uid, "ROMData", 0, &raw.rom_data);
+                }
+                GameDataPayload::Archive(archive) => {
+                    add(uid, "ArchiveBinary", 0, &archive.archive_binary);
+                }
+                GameDataPayload::Chd(chd) => {
+                    for (ordinal, disc) in chd.entries.iter().enumerate() {
+                        add(uid, "CHDBinary", ordinal as u64, &disc.chd_binary);
+                    }
+                }
+                GameDataPayload::BinCue(bin_cue) => {
+                    for (ordinal, disc) in bin_cue.entries.iter().enumerate() {
+                        add(uid, "BinBinary", ordinal as u64, &disc.bin_binary);
+                    }
+                }
+                GameDataPayload::Ssmc(ssmc) => {
+                    add(uid, "SSMCBinary", 0, &ssmc.ssmc_binary);
+                }
+            }
+        }
+    }
+
+    if let Some(manuals) = &game_case.manuals {
+        for entry in &manuals.entries {
+            add(entry.entry_uid, "ManualData", 0, &entry.manual_data);
+        }
+    }
+
+    if let Some(rom_hacks) = &game_case.rom_hacks {
+        for entry in &rom_hacks.entries {
+            add(entry.entry_uid, "PatchData", 0, &entry.patch_data);
+        }
+    }
+
+    if let Some(media) = &game_case.media {
+        for collection in &media.image_collections {
+            for entry in &collection.entries {
+                add(entry.entry_uid, "ImageData", 0, &entry.image_data);
+            }
+        }
+
+        for collection in &media.video_collections {
+            for entry in &collection.entries {
+                if let Some(thumbnail) = &entry.thumbnail {
+                    add(entry.entry_uid, "Thumbnail", 0, thumbnail);
+                }
+                add(entry.entry_uid, "VideoData", 0, &entry.video_data);
+            }
+        }
+    }
+
+    refs
+}
+
+fn metadata_view(game_case: &GameCase) -> Result<MetadataView<'_>, LibError> {
+    check_unique_uids(&index_targets(game_case))?;
+
+    Ok(MetadataView {
+        game_case,
+        payloads: payload_refs(game_case),
+    })
+}
+
+/// `EntryUID`, element name and ordinal of a payload, see `PayloadRef`.
+type PayloadKey = (u64, &'static str, u64);
+
+fn payload_ref(key: PayloadKey, data: &[u8]) -> PayloadRef {
+    let (uid, element, ordinal) = key;
+
+    PayloadRef {
+        uid,
+        element: element.to_string(),
+        ordinal,
+        size: data.len() as u64,
+        sha256: Sha256::digest(data).to_vec(),
+    }
+}
+
+/// A payload field of an entry.
+enum Slot<'a> {
+    Required(&'a mut Vec<u8>),
+    /// A payload that may be absent, such as `Thumbnail`.
+    Optional(&'a mut Option<Vec<u8>>),
+}
+
+impl Slot<'_> {
+    fn take(&mut self) -> Option<Vec<u8>> {
+        match self {
+            Slot::Required(data) => Some(std::mem::take(*data)),
+            Slot::Optional(data) => data.take(),
+        }
+    }
+
+    fn set(&mut self, value: Vec<u8>) {
+        match self {
+            Slot::Required(data) => **data = value,
+            Slot::Optional(data) => **data = Some(value),
+        }
+    }
+}
+
+/// Lists every payload field of `game_case`, including absent thumbnails.
+fn payload_slots(game_case: &mut GameCase) -> Vec<(PayloadKey, Slot<'_>)> {
+    let mut slots = Vec::new();
+
+    if let Some(game_data) = &mut game_case.game.game_data {
+        for entry in &mut game_data.entries {
+            let uid = entry.entry_uid;
+            match &mut entry.payload {
+                GameDataPayload::Raw(raw) => slots.push((
+                    (uid, "ROMData", 0),
+                    Slot::Required(&mut raw.rom_data),
+                )),
+                GameDataPayload::Archive(archive) => slots.push((
+                    (uid, "ArchiveBinary", 0),
+                    Slot::Required(&mut archive.archive_binary),
+                )),
+                GameDataPayload::Chd(chd) => {
+                    for (ordinal, disc) in chd.entries.iter_mut().enumerate() {
+                        slots.push((
+                            (uid, "CHDBinary", ordinal as u64),
+                            Slot::Required(&mut disc.chd_binary),
+                        ));
+                    }
+                }
+                GameDataPayload::BinCue(bin_cue) => {
+                    for (ordinal, disc) in
+                        bin_cue.entries.iter_mut().enumerate()
+                    {
+                        slots.push((
+                            (uid, "BinBinary", ordinal as u64),
+                            Slot::Required(&mut disc.bin_binary),
+                        ));
+                    }
+                }
+                GameDataPayload::Ssmc(ssmc) => slots.push((
+                    (uid, "SSMCBinary", 0),
+                    Slot::Required(&mut ssmc.ssmc_binary),
+                )),
+            }
+        }
+    }
+
+    if let Some(manuals) = &mut game_case.manuals {
+        for entry in &mut manuals.entries {
+            slots.push((
+                (entry.entry_uid, "ManualData", 0),
+                Slot::Required(&mut entry.manual_data),
+            ));
+        }
+    }
+
+    if let Some(rom_hacks) = &mut game_case.rom_hacks {
+        for entry in &mut rom_hacks.entries {
+            slots.push((
+                (entry.entry_uid, "PatchData", 0),
+                Slot::Required(&mut entry.patch_data),
+            ));
+        }
+    }
+
+    if let Some(media) = &mut game_case.media {
+        for collection in &mut media.image_collections {
+            for entry in &mut collection.entries {
+                slots.push((
+                    (entry.entry_uid, "ImageData", 0),
+                    Slot::Required(&mut entry.image_data),
+                ));
+            }
+        }
+
+        for collection in &mut media.video_collections {
+            for entry in &mut collection.entries {
+                slots.push((
+                    (entry.entry_uid, "Thumbnail", 0),
+                    Slot::Optional(&mut entry.thumbnail),
+                ));
+                slots.push((
+                    (entry.entry_uid, "VideoData", 0),
+                    Slot::Required(&mut entry.video_data),
+                ));
+            }
+        }
+    }
+
+    slots
+}
+
+/// Serializes an `EntryUID` as a number, or as decimal text if it does not
+/// fit into the signed integers of TOML.
+pub(crate) mod uid {
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        uid: &u64,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match i64::try_from(*uid) {
+            Ok(_) => serializer.serialize_u64(*uid),
+            Err(_) => serializer.collect_str(uid),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<u64, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Uid {
+            Number(u64),
+            Text(String),
+        }
+
+        match Uid::deserialize(deserializer)? {
+            Uid::Number(uid) => Ok(uid),
+            Uid::Text(text) => text.parse().map_err(|_| {
+                de::Error::custom(format!("\"{text}\" is not an EntryUID"))
+            }),
+        }
+    }
+}
+
+/// Serializes binary data such as digests as lowercase hex text.
+pub(crate) mod hex {
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        data: &[u8],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&encode(data))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<u8>, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        decode(&text).ok_or_else(|| {
+            de::Error::custom(format!("\"{text}\" is not hex encoded data"))
+        })
+    }
+
+    pub(crate) fn encode(data: &[u8]) -> String {
+        data.iter().map(|byte| format!("{byte:02x}")).collect()
+    }
+
+    pub(crate) fn decode(text: &str) -> Option<Vec<u8>> {
+        if !text.len().is_multiple_of(2) || !text.is_ascii() {
+            return None;
+        }
+
+        (0..text.len())
+            .step_by(2)
+            .map(|start| u8::from_str_radix(&text[start..start + 2], 16).ok())
+            .collect()
+    }
+}
+
+/// Like `hex`, for optional data.
+pub(crate) mod opt_hex {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        data: &Option<Vec<u8>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match data {
+            Some(data) => serializer.serialize_some(&super::hex::encode(data)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Vec<u8>>, D::Error> {
+        match Option::<String>::deserialize(deserializer)? {
+            Some(text) => super::hex::decode(&text).map(Some).ok_or_else(|| {
+                serde::de::Error::custom(format!(
+                    "\"{text}\" is not hex encoded data"
+                ))
+            }),
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::write::tests::sample_case;
+
+    #[test]
+    fn json_round_trip_keeps_payload_references() {
+        let source = sample_case();
+        let json = export_json(&source).unwrap();
+        //The payloads themselves are left out.
+        assert!(!json.contains("rom_data"));
+
+        let mut metadata = CaseMetadata::from_json(&json).unwrap();
+        assert_eq!(metadata.payloads, payload_refs(&source));
+        metadata.game_case.game.title = "The Legend of Zelda".to_string();
+
+        let edited = metadata.apply(source.clone()).unwrap();
+
+        let mut expected = source;
+        expected.game.title = "The Legend of Zelda".to_string();
+        assert_eq!(edited, expected);
+    }
+
+    #[test]
+    fn toml_round_trip_keeps_payload_references() {
+        let source = sample_case();
+        let toml = export_toml(&source).unwrap();
+
+        let metadata = CaseMetadata::from_toml(&toml).unwrap();
+
+        assert_eq!(metadata.apply(source.clone()).unwrap(), source);
+    }
+
+    #[test]
+    fn changed_payload_is_refused() {
+        let metadata =
+            CaseMetadata::from_json(&export_json(&sample_case()).unwrap())
+                .unwrap();
+        let mut source = sample_case();
+        source.manuals.as_mut().unwrap().entries[0].manual_data[0] = 2;
+
+        assert!(matches!(
+            metadata.apply(source),
+            Err(LibError::PayloadMismatch { uid: 2, element: "ManualData" })
+        ));
+    }
+}
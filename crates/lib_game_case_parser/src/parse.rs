@@ -1,15 +1,1499 @@
-use std::io::{Read};
+use std::io::{self, Read};
 
 use ebml_iterable::{TagIterator};
+use ebml_iterable::error::{CorruptedFileError, TagIteratorError};
+use ebml_iterable::iterator::AllowableErrors;
+use ebml_iterable::specs::{
+    EbmlSpecification, EbmlTag, Master, PathPart, TagDataType,
+};
 
-use crate::gc_ebml_spec::GCEbmlSpec;
+use crate::date::ReleaseDate;
+use crate::ebml_tools::{parse_element_header, ChildWalker, ElementSpan};
+use crate::gc_ebml_spec::{
+    element_name, element_path, master_id, DocTypeVersions, GCEbmlSpec,
+    DOC_TYPE, DOC_TYPE_VERSION,
+};
 use crate::lib_error_handling::LibError;
+use crate::lib_structs::{
+    ArchiveData, BinCueData, BinCueEntry, ChdData, ChdEntry, FileProperties,
+    Game, GameCase, GameData, GameDataEntry, GameDataPayload, ImageCollection,
+    ImageEntry, Index, IndexEntry, ManualEntry, Manuals, Media, Rating,
+    RawData, RegionInfo, RomHackEntry, RomHacks, SsmcData, TargetHash,
+    UnknownElement, VideoCollection, VideoEntry,
+};
+use crate::limits::{limit_error, LimitTracker, LimitedReader, ReaderLimits};
 
+/// A single element read from the tag stream.
+///
+/// Master elements hold their decoded children, every other element holds
+/// its value inside `tag`.
+#[derive(Clone, Debug)]
+pub(crate) struct ElementNode {
+    pub tag: GCEbmlSpec,
+    /// Offset of the element's ID within the stream it was read from.
+    pub offset: u64,
+    /// Element path, e.g. `GameCase/Game/RegionInfo[1]`.
+    pub path: String,
+    pub children: Vec<ElementNode>,
+}
+
+/// Removes the data of every child of `node` that is unknown to
+/// `GCEbmlSpec` and returns them in file order.
+///
+/// `Void` and `CRC-32` elements are neither kept nor counted as known
+/// children, as the writer places those on its own.
+fn take_unknown(node: &mut ElementNode) -> Vec<UnknownElement> {
+    let mut unknown = Vec::new();
+    let mut known = 0;
+
+    for child in &mut node.children {
+        match &mut child.tag {
+            GCEbmlSpec::RawTag(id, data) => unknown.push(UnknownElement {
+                id: *id,
+                data: std::mem::take(data),
+                position: known,
+            }),
+            GCEbmlSpec::Void(_) | GCEbmlSpec::Crc32(_) => {}
+            _ => known += 1,
+        }
+    }
+
+    unknown
+}
+
+/// Builds the error for a mandatory child missing from an element.
+fn missing(element: &'static str, offset: u64, path: &str) -> LibError {
+    LibError::MissingElement { element, offset, path: path.to_string() }
+}
+
+/// Reads an EBML `.gcase` stream and decodes it into a [`GameCase`].
+///
+/// # Arguments
+///
+/// * `source`: Any reader positioned at the start of the EBML header.
+///
+/// # Returns
+///
+/// A `Result` containing the decoded `GameCase`, or a `LibError` if the
+/// stream is not valid EBML, is not a GameCase document, or a mandatory
+/// element is missing.
 pub fn process_ebml_data<R: Read>(
     source: R
-) -> Result<bool, LibError> {
-    let mut tag_iterator: TagIterator<_, GCEbmlSpec> = 
-        TagIterator::new(source, &[]);
+) -> Result<GameCase, LibError> {
+    process_ebml_data_with_limits(source, ReaderLimits::default())
+}
+
+/// Reads an EBML `.gcase` stream and decodes it into a [`GameCase`],
+/// rejecting any element that breaks `limits` before it is loaded.
+///
+/// # Arguments
+///
+/// * `source`: Any reader positioned at the start of the EBML header.
+/// * `limits`: The limits every element is checked against.
+///
+/// # Returns
+///
+/// A `Result` containing the decoded `GameCase`, or a `LibError` if the
+/// stream breaks a limit, is not a GameCase document, or a mandatory
+/// element is missing.
+pub fn process_ebml_data_with_limits<R: Read>(
+    source: R,
+    limits: ReaderLimits,
+) -> Result<GameCase, LibError> {
+    let tracker = LimitTracker::new(limits, 0, "");
+    let mut source = LimitedReader::new(source, tracker);
+
+    /*No element larger than the limits allow for a single value is read
+    into a buffer of its own, which is all the tag iterator may allocate.
+    Larger masters, such as a GameCase holding big ROMs, are walked. */
+    let cap = limits.max_binary_size.max(limits.max_string_size);
+    let roots = load_children(
+        &mut source,
+        &mut ChildWalker::top_level(0),
+        &mut Vec::new(),
+        "",
+        cap,
+    )?;
+
+    let header = roots
+        .iter()
+        .find(|node| matches!(node.tag, GCEbmlSpec::EBML(_)))
+        .ok_or_else(|| missing("EBML", 0, ""))?;
+    check_ebml_header(header)?;
+
+    let game_case = roots
+        .into_iter()
+        .find(|node| matches!(node.tag, GCEbmlSpec::GameCase(_)))
+        .ok_or_else(|| missing("GameCase", 0, ""))?;
+
+    read_game_case(game_case)
+}
+
+/// Loads the children `walker` finds, decoding every child of at most
+/// `cap` bytes from a buffer of its own and walking larger masters.
+///
+/// # Arguments
+///
+/// * `source`: The stream, positioned at the next child.
+/// * `walker`: Walks the children of the master being loaded.
+/// * `ancestors`: IDs of the masters enclosing the children, outermost
+///   first.
+/// * `path`: Path of the enclosing master, empty at the top level.
+/// * `cap`: Largest element decoded as a whole.
+fn load_children<R: Read>(
+    source: &mut R,
+    walker: &mut ChildWalker,
+    ancestors: &mut Vec<u64>,
+    path: &str,
+    cap: u64,
+) -> Result<Vec<ElementNode>, LibError> {
+    let mut children = Vec::new();
+
+    while let Some(child) = walker.next(source)? {
+        let ElementSpan { offset, header, .. } = child;
+        let id = header.id;
+        let path = child_path(&[], &children, path, id);
+
+        if !allowed_within(id, ancestors) {
+            return Err(LibError::MisplacedElement { id, offset, path });
+        }
+
+        let node = if !header.is_unknown_size() && header.total_len() <= cap {
+            let mut bytes = header.encode();
+            let read = source
+                .take(header.size)
+                .read_to_end(&mut bytes)
+                .map_err(limit_error)?;
+
+            /*The cap is what the iterator may allocate. A truncated element
+            is still decoded, so the innermost master that was cut short is
+            reported. */
+            let mut tag_iterator = tag_iterator(&bytes[..]);
+            tag_iterator.set_max_allowable_tag_size(Some(cap as usize));
+            let mut node = match read_partial_elements(
+                &mut tag_iterator,
+                &parent_path(id),
+                offset,
+            ) {
+                (_, Some(error)) => return Err(error),
+                (roots, None) if read as u64 == header.size => roots
+                    .into_iter()
+                    .next()
+                    .ok_or(LibError::Io(io::ErrorKind::UnexpectedEof.into()))?,
+                (_, None) => return Err(walker.truncated()),
+            };
+            let decoded_path = node.path.clone();
+            rebase_paths(&mut node, &decoded_path, &path);
+            node
+        } else if GCEbmlSpec::get_tag_data_type(id) == Some(TagDataType::Master)
+        {
+            ancestors.push(id);
+            let grandchildren = load_children(
+                source,
+                &mut child.children_at(path.clone()),
+                ancestors,
+                &path,
+                cap,
+            )?;
+            ancestors.pop();
+
+            ElementNode {
+                tag: GCEbmlSpec::get_master_tag(id, Master::Start).ok_or_else(
+                    || LibError::UnknownElement {
+                        id,
+                        offset,
+                        path: path.clone(),
+                    },
+                )?,
+                offset,
+                path,
+                children: grandchildren,
+            }
+        } else {
+            return Err(LibError::ElementTooLarge {
+                id,
+                size: header.size,
+                offset,
+                path,
+            });
+        };
+
+        children.push(node);
+    }
+
+    Ok(children)
+}
+
+/// Returns whether the specification allows the element with the ID `id`
+/// within the masters `ancestors`, outermost first. Elements unknown to
+/// the specification are allowed anywhere.
+fn allowed_within(id: u64, ancestors: &[u64]) -> bool {
+    fn matches(path: &[PathPart], ancestors: &[u64]) -> bool {
+        match path.split_first() {
+            None => ancestors.is_empty(),
+            Some((PathPart::Id(id), rest)) => {
+                ancestors.first() == Some(id) && matches(rest, &ancestors[1..])
+            }
+            Some((PathPart::Global((min, max)), rest)) => {
+                let min = min.unwrap_or(0) as usize;
+                let max = max
+                    .map_or(ancestors.len(), |max| max as usize)
+                    .min(ancestors.len());
+
+                (min..=max).any(|skip| matches(rest, &ancestors[skip..]))
+            }
+        }
+    }
+
+    GCEbmlSpec::get_tag_data_type(id).is_none()
+        || matches(GCEbmlSpec::get_path_by_id(id), ancestors)
+}
+
+/// Replaces the leading `from` of the paths of `node` and its children with
+/// `to`.
+fn rebase_paths(node: &mut ElementNode, from: &str, to: &str) {
+    if let Some(rest) = node.path.strip_prefix(from) {
+        node.path = format!("{to}{rest}");
+    }
+
+    for child in &mut node.children {
+        rebase_paths(child, from, to);
+    }
+}
+
+/// Checks that an EBML header describes a GameCase document this library
+/// is able to read.
+///
+/// Files written for a newer `DocTypeVersion` are accepted as long as their
+/// `DocTypeReadVersion` is supported.
+///
+/// # Returns
+///
+/// A `Result` containing the versions declared by the header, or a
+/// `LibError` if the DocType is wrong or the file requires a newer version
+/// of it.
+pub(crate) fn check_ebml_header(
+    header: &ElementNode
+) -> Result<DocTypeVersions, LibError> {
+    let mut doc_type = None;
+    let mut versions = DocTypeVersions::default();
+    let mut read_version = None;
+
+    for child in &header.children {
+        match &child.tag {
+            GCEbmlSpec::DocType(value) => doc_type = Some((value, child)),
+            GCEbmlSpec::DocTypeVersion(version) => versions.version = *version,
+            GCEbmlSpec::DocTypeReadVersion(version) => {
+                versions.read_version = *version;
+                read_version = Some(child);
+            }
+            _ => {}
+        }
+    }
+
+    match doc_type {
+        Some((value, _)) if value == DOC_TYPE => {}
+        Some((value, child)) => {
+            return Err(LibError::WrongDocType {
+                found: value.clone(),
+                offset: child.offset,
+                path: child.path.clone(),
+            });
+        }
+        None => return Err(missing("DocType", header.offset, &header.path)),
+    }
+
+    //The default read version of 1 is always supported.
+    match read_version {
+        Some(child) if !versions.is_readable() => {
+            Err(LibError::UnsupportedReadVersion {
+                version: versions.read_version,
+                supported: DOC_TYPE_VERSION,
+                offset: child.offset,
+                path: child.path.clone(),
+            })
+        }
+        _ => Ok(versions),
+    }
+}
+
+/// Creates a tag iterator over `source` that reads elements unknown to
+/// `GCEbmlSpec` as `RawTag`s, so they can be kept, see `UnknownElement`.
+///
+/// Readers asked to reject unknown elements do so while checking headers
+/// against `ReaderLimits`, before anything reaches the iterator.
+pub(crate) fn tag_iterator<R: Read>(source: R) -> TagIterator<R, GCEbmlSpec> {
+    let mut tag_iterator = TagIterator::new(source, &[]);
+    tag_iterator.allow_errors(&[AllowableErrors::InvalidTagIds]);
+
+    tag_iterator
+}
+
+/// Drains a tag iterator into a tree of elements, keeping everything that
+/// was read before an error occurred.
+///
+/// # Arguments
+///
+/// * `tag_iterator`: The iterator to drain.
+/// * `base`: Path of the parent of the first element read, empty when
+///   reading a whole document.
+/// * `origin`: Offset of the start of the iterator's source within the
+///   file, added to every reported offset.
+///
+/// # Returns
+///
+/// The elements read so far, with any masters still open at the time of
+/// the error closed in place, and the error that ended reading, if any.
+pub(crate) fn read_partial_elements<R: Read>(
+    tag_iterator: &mut TagIterator<R, GCEbmlSpec>,
+    base: &str,
+    origin: u64,
+) -> (Vec<ElementNode>, Option<LibError>) {
+    let mut roots: Vec<ElementNode> = Vec::new();
+    let mut open: Vec<ElementNode> = Vec::new();
+    let mut error = None;
+
+    while let Some(tag) = tag_iterator.next() {
+        let tag = match tag {
+            Ok(tag) => tag,
+            Err(e) => {
+                error = Some(
+                    read_error(e, tag_iterator, &open, &roots, base, origin)
+                );
+                break;
+            }
+        };
+        let offset = origin + tag_iterator.last_emitted_tag_offset() as u64;
+
+        //An ID starting with a zero byte is not a valid VINT.
+        if let GCEbmlSpec::RawTag(0, _) = tag {
+            error = Some(LibError::InvalidVint {
+                offset,
+                path: open.last().map(|p| p.path.clone()).unwrap_or_default(),
+            });
+            break;
+        }
+
+        if let Some(Master::End) = tag.as_master() {
+            let Some(finished) = open.pop() else {
+                continue;
+            };
+            match open.last_mut() {
+                Some(parent) => parent.children.push(finished),
+                None => roots.push(finished),
+            }
+            continue;
+        }
+
+        let path = child_path(&open, &roots, base, tag.get_id());
+        let node = ElementNode { tag, offset, path, children: Vec::new() };
+
+        if let Some(Master::Start) = node.tag.as_master() {
+            open.push(node);
+            continue;
+        }
+
+        match open.last_mut() {
+            Some(parent) => parent.children.push(node),
+            None => roots.push(node),
+        }
+    }
+
+    //Close whatever was left open so the partial tree stays navigable.
+    while let Some(node) = open.pop() {
+        match open.last_mut() {
+            Some(parent) => parent.children.push(node),
+            None => roots.push(node),
+        }
+    }
+
+    (roots, error)
+}
+
+/// Returns the path of the next child with the given ID of the innermost
+/// open element.
+pub(crate) fn child_path(
+    open: &[ElementNode],
+    roots: &[ElementNode],
+    base: &str,
+    id: u64,
+) -> String {
+    let (parent_path, siblings) = match open.last() {
+        Some(parent) => (parent.path.as_str(), &parent.children[..]),
+        None => (base, roots),
+    };
+    let index = siblings
+        .iter()
+        .filter(|sibling| sibling.tag.get_id() == id)
+        .count();
+
+    let mut path = match parent_path {
+        "" => element_name(id),
+        parent => format!("{parent}/{}", element_name(id)),
+    };
+    if index > 0 {
+        path.push_str(&format!("[{index}]"));
+    }
+
+    path
+}
+
+/// Converts an error from the tag iterator into a `LibError` pointing at
+/// the element that caused it.
+fn read_error<R: Read>(
+    error: TagIteratorError,
+    tag_iterator: &mut TagIterator<R, GCEbmlSpec>,
+    open: &[ElementNode],
+    roots: &[ElementNode],
+    base: &str,
+    origin: u64,
+) -> LibError {
+    let at = |position: usize| origin + position as u64;
+    let parent = open.last();
+    let parent_offset = parent.map_or(0, |parent| parent.offset);
+    let path_of = |id: u64| child_path(open, roots, base, id);
+    let truncated = |offset: u64, id: Option<u64>| match parent {
+        Some(parent) => LibError::TruncatedMaster {
+            offset: parent.offset,
+            path: parent.path.clone(),
+        },
+        None => LibError::TruncatedMaster {
+            offset,
+            path: id.map(path_of).unwrap_or_default(),
+        },
+    };
+
+    let corrupted = match error {
+        TagIteratorError::ReadError { source } => return limit_error(source),
+        TagIteratorError::UnexpectedEOF { tag_start, tag_id, .. } => {
+            return truncated(at(tag_start), tag_id);
+        }
+        TagIteratorError::CorruptedTagData { tag_id, .. } => {
+            return LibError::WrongDataType {
+                id: tag_id,
+                offset: parent_offset,
+                path: path_of(tag_id),
+            };
+        }
+        TagIteratorError::CorruptedFileData(corrupted) => corrupted,
+    };
+
+    match corrupted {
+        CorruptedFileError::InvalidTagId { position, tag_id: 0 } => {
+            LibError::InvalidVint {
+                offset: at(position),
+                path: parent.map(|p| p.path.clone()).unwrap_or_default(),
+            }
+        }
+        CorruptedFileError::InvalidTagId { position, tag_id } => {
+            LibError::UnknownElement {
+                id: tag_id,
+                offset: at(position),
+                path: path_of(tag_id),
+            }
+        }
+        CorruptedFileError::InvalidTagData { position, tag_id } => {
+            let numeric = matches!(
+                GCEbmlSpec::get_tag_data_type(tag_id),
+                Some(TagDataType::UnsignedInt)
+                    | Some(TagDataType::Integer)
+                    | Some(TagDataType::Float)
+            );
+            if numeric {
+                LibError::WrongDataType {
+                    id: tag_id,
+                    offset: at(position),
+                    path: path_of(tag_id),
+                }
+            } else {
+                LibError::InvalidVint {
+                    offset: at(position),
+                    path: path_of(tag_id),
+                }
+            }
+        }
+        CorruptedFileError::HierarchyError { found_tag_id, .. } => {
+            /*The misplaced tag has not been consumed yet. Reading it with
+            hierarchy checks relaxed reveals its offset. */
+            tag_iterator.allow_errors(&[AllowableErrors::HierarchyProblems]);
+            let offset = match tag_iterator.next() {
+                Some(Ok(_)) => at(tag_iterator.last_emitted_tag_offset()),
+                _ => parent_offset,
+            };
+            LibError::MisplacedElement {
+                id: found_tag_id,
+                offset,
+                path: path_of(found_tag_id),
+            }
+        }
+        CorruptedFileError::OversizedChildElement { position, tag_id, .. } => {
+            truncated(at(position), Some(tag_id))
+        }
+        CorruptedFileError::InvalidTagSize { position, tag_id, size } => {
+            LibError::ElementTooLarge {
+                id: tag_id,
+                size: size as u64,
+                offset: at(position),
+                path: path_of(tag_id),
+            }
+        }
+    }
+}
+
+/// Decodes a buffer holding one or more complete elements.
+///
+/// Element paths are completed with the path the specification defines
+/// for the first element in the buffer.
+///
+/// # Arguments
+///
+/// * `bytes`: The encoded elements.
+/// * `origin`: Offset of `bytes` within the file they were read from.
+pub(crate) fn decode_elements(
+    bytes: &[u8],
+    origin: u64,
+) -> Result<Vec<ElementNode>, LibError> {
+    let header = parse_element_header(bytes);
+    let base = header
+        .map(|header| parent_path(header.id))
+        .unwrap_or_default();
+
+    /*The tag iterator reports an empty master at the very end of its input
+    as truncated, e.g. the Index of a case without entries. */
+    if let Some(header) = header
+        && header.size == 0
+        && header.header_len == bytes.len() as u64
+        && let Some(tag) = GCEbmlSpec::get_master_tag(header.id, Master::Start)
+    {
+        return Ok(vec![ElementNode {
+            tag,
+            offset: origin,
+            path: child_path(&[], &[], &base, header.id),
+            children: Vec::new(),
+        }]);
+    }
+
+    let mut tag_iterator = tag_iterator(bytes);
+    //No element can be larger than the buffer holding it.
+    tag_iterator.set_max_allowable_tag_size(Some(bytes.len()));
+
+    match read_partial_elements(&mut tag_iterator, &base, origin) {
+        (roots, None) => Ok(roots),
+        (_, Some(error)) => Err(error),
+    }
+}
+
+/// Returns the path the specification defines for the parent of the
+/// element with the given ID, empty for top level elements.
+pub(crate) fn parent_path(id: u64) -> String {
+    element_path(id)
+        .rsplit_once('/')
+        .map(|(parent, _)| parent.to_string())
+        .unwrap_or_default()
+}
+
+pub(crate) fn read_game_case(
+    mut node: ElementNode
+) -> Result<GameCase, LibError> {
+    let unknown = take_unknown(&mut node);
+    let mut index = None;
+    let mut game = None;
+    let mut manuals = None;
+    let mut rom_hacks = None;
+    let mut media = None;
+
+    for child in node.children {
+        match child.tag {
+            GCEbmlSpec::Index(_) => index = Some(read_index(child)),
+            GCEbmlSpec::Game(_) => game = Some(read_game(child)?),
+            GCEbmlSpec::Manuals(_) => manuals = Some(read_manuals(child)),
+            GCEbmlSpec::RomHacks(_) => rom_hacks = Some(read_rom_hacks(child)?),
+            GCEbmlSpec::Media(_) => media = Some(read_media(child)),
+            _ => {}
+        }
+    }
+
+    Ok(GameCase {
+        index: index.ok_or_else(|| missing("Index", node.offset, &node.path))?,
+        game: game.ok_or_else(|| missing("Game", node.offset, &node.path))?,
+        manuals,
+        rom_hacks,
+        media,
+        unknown,
+    })
+}
+
+pub(crate) fn read_index(node: ElementNode) -> Index {
+    let entries = node.children
+        .into_iter()
+        .filter(|child| matches!(child.tag, GCEbmlSpec::IndexEntry(_)))
+        .map(read_index_entry)
+        .collect();
+
+    Index { entries }
+}
+
+pub(crate) fn read_index_entry(node: ElementNode) -> IndexEntry {
+    let mut entry = IndexEntry::default();
+
+    for child in node.children {
+        match child.tag {
+            GCEbmlSpec::IndexTargetID(id) => entry.target_id = id,
+            GCEbmlSpec::IndexPosition(position) => entry.position = position,
+            GCEbmlSpec::IndexTarget(target) => entry.target = target,
+            GCEbmlSpec::IndexTargetCategory(category) => {
+                entry.category = Some(category)
+            }
+            GCEbmlSpec::IndexTargetRegion(region) => {
+                entry.region = Some(region)
+            }
+            GCEbmlSpec::IndexTargetUID(uid) => entry.uid = uid,
+            _ => {}
+        }
+    }
+
+    entry
+}
+
+pub(crate) fn read_game(mut node: ElementNode) -> Result<Game, LibError> {
+    let mut game = Game {
+        unknown: take_unknown(&mut node),
+        ..Default::default()
+    };
+    let mut title = None;
+    let mut game_system = None;
+
+    for child in node.children {
+        match child.tag {
+            GCEbmlSpec::Title(value) => title = Some(value),
+            GCEbmlSpec::Developer(value) => game.developers.push(value),
+            GCEbmlSpec::Genre(value) => game.genre = Some(value),
+            GCEbmlSpec::MinPlayers(value) => game.min_players = Some(value),
+            GCEbmlSpec::MaxPlayers(value) => game.max_players = Some(value),
+            GCEbmlSpec::GameSystem(value) => game_system = Some(value),
+            GCEbmlSpec::Description(value) => game.description = Some(value),
+            GCEbmlSpec::Rating(_) => game.rating = Some(read_rating(child)),
+            GCEbmlSpec::RegionInfo(_) => {
+                game.region_info.push(read_region_info(child))
+            }
+            GCEbmlSpec::GameData(_) => {
+                game.game_data = Some(read_game_data(child)?)
+            }
+            _ => {}
+        }
+    }
+
+    game.title = title
+        .ok_or_else(|| missing("Title", node.offset, &node.path))?;
+    game.game_system = game_system
+        .ok_or_else(|| missing("GameSystem", node.offset, &node.path))?;
+
+    Ok(game)
+}
+
+fn read_rating(mut node: ElementNode) -> Rating {
+    let mut rating = Rating {
+        unknown: take_unknown(&mut node),
+        ..Default::default()
+    };
+
+    for child in node.children {
+        match child.tag {
+            GCEbmlSpec::RatingValue(value) => rating.value = value,
+            GCEbmlSpec::RatingSource(source) => rating.source = source,
+            _ => {}
+        }
+    }
+
+    rating
+}
+
+fn read_region_info(mut node: ElementNode) -> RegionInfo {
+    let mut region_info = RegionInfo {
+        unknown: take_unknown(&mut node),
+        ..Default::default()
+    };
+
+    let mut release_date = Vec::new();
+    let mut precision = ReleaseDate::PRECISION_DAY;
+
+    for child in node.children {
+        match child.tag {
+            GCEbmlSpec::Region(value) => region_info.region = value,
+            GCEbmlSpec::Language(value) => region_info.languages.push(value),
+            GCEbmlSpec::ReleaseDate(value) => release_date = value,
+            GCEbmlSpec::ReleaseDatePrecision(value) => precision = value,
+            GCEbmlSpec::Publisher(value) => region_info.publisher = value,
+            GCEbmlSpec::AgeRating(value) => region_info.age_rating = value,
+            _ => {}
+        }
+    }
+
+    region_info.release_date =
+        ReleaseDate::from_element(&release_date, precision);
+
+    region_info
+}
+
+pub(crate) fn read_game_data(
+    mut node: ElementNode
+) -> Result<GameData, LibError> {
+    let unknown = take_unknown(&mut node);
+    let mut entries = Vec::new();
+
+    for child in node.children {
+        if let GCEbmlSpec::GameDataEntry(_) = child.tag {
+            entries.push(read_game_data_entry(child)?);
+        }
+    }
+
+    Ok(GameData { entries, unknown })
+}
+
+pub(crate) fn read_game_data_entry(
+    mut node: ElementNode
+) -> Result<GameDataEntry, LibError> {
+    let unknown = take_unknown(&mut node);
+    let mut entry_uid = 0;
+    let mut data_format = None;
+    let mut payloads = Vec::new();
+
+    for child in node.children {
+        match child.tag {
+            GCEbmlSpec::EntryUID(uid) => entry_uid = uid,
+            GCEbmlSpec::DataFormat(format) => {
+                data_format = Some((format, child.offset, child.path))
+            }
+            GCEbmlSpec::RawData(_)
+            | GCEbmlSpec::ArchiveData(_)
+            | GCEbmlSpec::ChdData(_)
+            | GCEbmlSpec::BinCueData(_)
+            | GCEbmlSpec::SSMCData(_) => payloads.push(child),
+            _ => {}
+        }
+    }
+
+    let (data_format, format_offset, format_path) = data_format
+        .ok_or_else(|| missing("DataFormat", node.offset, &node.path))?;
+
+    //DataFormat decides which payload element has to be present.
+    let (expected_id, expected_name) = match data_format.as_str() {
+        "RAW" => (master_id(GCEbmlSpec::RawData), "RawData"),
+        "ARCHIVE" => (master_id(GCEbmlSpec::ArchiveData), "ArchiveData"),
+        "CHD" => (master_id(GCEbmlSpec::ChdData), "ChdData"),
+        "BIN/CUE" => (master_id(GCEbmlSpec::BinCueData), "BinCueData"),
+        "SSMC" => (master_id(GCEbmlSpec::SSMCData), "SSMCData"),
+        _ => {
+            return Err(LibError::UnknownDataFormat {
+                value: data_format,
+                offset: format_offset,
+                path: format_path,
+            })
+        }
+    };
+
+    let mut payload = None;
+    for child in payloads {
+        if child.tag.get_id() != expected_id {
+            return Err(LibError::DataFormatMismatch {
+                data_format,
+                element: element_name(child.tag.get_id()),
+                offset: child.offset,
+                path: child.path,
+            });
+        }
+        if payload.is_none() {
+            payload = Some(read_game_data_payload(child)?);
+        }
+    }
+
+    Ok(GameDataEntry {
+        entry_uid,
+        payload: payload
+            .ok_or_else(|| missing(expected_name, node.offset, &node.path))?,
+        unknown,
+    })
+}
+
+/// Reads one of the `*Data` children of a `GameDataEntry`.
+fn read_game_data_payload(
+    node: ElementNode
+) -> Result<GameDataPayload, LibError> {
+    let payload = match node.tag {
+        GCEbmlSpec::ArchiveData(_) => {
+            GameDataPayload::Archive(read_archive_data(node))
+        }
+        GCEbmlSpec::ChdData(_) => GameDataPayload::Chd(read_chd_data(node)?),
+        GCEbmlSpec::BinCueData(_) => {
+            GameDataPayload::BinCue(read_bin_cue_data(node)?)
+        }
+        GCEbmlSpec::SSMCData(_) => GameDataPayload::Ssmc(read_ssmc_data(node)),
+        _ => GameDataPayload::Raw(read_raw_data(node)?),
+    };
+
+    Ok(payload)
+}
+
+pub(crate) fn read_file_properties(mut node: ElementNode) -> FileProperties {
+    let mut properties = FileProperties {
+        unknown: take_unknown(&mut node),
+        ..Default::default()
+    };
+
+    for child in node.children {
+        match child.tag {
+            GCEbmlSpec::ROMFileName(value) => {
+                properties.rom_file_name = Some(value)
+            }
+            GCEbmlSpec::ROMFileSize(value) => properties.rom_file_size = value,
+            GCEbmlSpec::Region(value) => properties.region = Some(value),
+            GCEbmlSpec::Language(value) => properties.languages.push(value),
+            GCEbmlSpec::CRC32(value) => properties.crc32 = Some(value),
+            GCEbmlSpec::MD5(value) => properties.md5 = Some(value),
+            GCEbmlSpec::SHA1(value) => properties.sha1 = Some(value),
+            GCEbmlSpec::SHA256(value) => properties.sha256 = Some(value),
+            GCEbmlSpec::SHA512(value) => properties.sha512 = Some(value),
+            GCEbmlSpec::SSMCIndex(value) => properties.ssmc_index = Some(value),
+            _ => {}
+        }
+    }
+
+    properties
+}
+
+/// Removes and reads the first `FileProperties` child of a master element.
+fn find_file_properties(
+    node: &mut ElementNode
+) -> Result<FileProperties, LibError> {
+    let position = node.children
+        .iter()
+        .position(|child| matches!(child.tag, GCEbmlSpec::FileProperties(_)))
+        .ok_or_else(|| missing("FileProperties", node.offset, &node.path))?;
+
+    Ok(read_file_properties(node.children.remove(position)))
+}
+
+fn read_raw_data(mut node: ElementNode) -> Result<RawData, LibError> {
+    let unknown = take_unknown(&mut node);
+    let file_properties = find_file_properties(&mut node)?;
+    let mut rom_data = None;
+
+    for child in node.children {
+        if let GCEbmlSpec::ROMData(data) = child.tag {
+            rom_data = Some(data);
+        }
+    }
+
+    Ok(RawData {
+        file_properties,
+        rom_data: rom_data
+            .ok_or_else(|| missing("ROMData", node.offset, &node.path))?,
+        unknown,
+    })
+}
+
+fn read_archive_data(mut node: ElementNode) -> ArchiveData {
+    let mut archive_data = ArchiveData {
+        unknown: take_unknown(&mut node),
+        ..Default::default()
+    };
+
+    for child in node.children {
+        match child.tag {
+            GCEbmlSpec::CompressionAlgorithm(value) => {
+                archive_data.compression_algorithm = value
+            }
+            GCEbmlSpec::ArchivedFileCount(value) => {
+                archive_data.archived_file_count = value
+            }
+            GCEbmlSpec::ArchiveFileMetadata(_) => {
+                archive_data.file_metadata.extend(
+                    child.children
+                        .into_iter()
+                        .filter(|grandchild| matches!(
+                            grandchild.tag, GCEbmlSpec::FileProperties(_)
+                        ))
+                        .map(read_file_properties)
+                );
+            }
+            GCEbmlSpec::ArchiveBinary(data) => archive_data.archive_binary = data,
+            _ => {}
+        }
+    }
+
+    archive_data
+}
+
+fn read_chd_data(mut node: ElementNode) -> Result<ChdData, LibError> {
+    let mut chd_data = ChdData {
+        unknown: take_unknown(&mut node),
+        ..Default::default()
+    };
+
+    for child in node.children {
+        match child.tag {
+            GCEbmlSpec::Region(value) => chd_data.region = value,
+            GCEbmlSpec::Language(value) => chd_data.languages.push(value),
+            GCEbmlSpec::CHDCount(value) => chd_data.chd_count = value,
+            GCEbmlSpec::CHDEntry(_) => {
+                chd_data.entries.push(read_chd_entry(child)?)
+            }
+            _ => {}
+        }
+    }
+
+    Ok(chd_data)
+}
+
+fn read_chd_entry(mut node: ElementNode) -> Result<ChdEntry, LibError> {
+    let unknown = take_unknown(&mut node);
+    let file_properties = find_file_properties(&mut node)?;
+    let mut entry = ChdEntry {
+        file_properties,
+        unknown,
+        ..Default::default()
+    };
+
+    for child in node.children {
+        match child.tag {
+            GCEbmlSpec::DiscNum(value) => entry.disc_num = value,
+            GCEbmlSpec::CHDBinary(data) => entry.chd_binary = data,
+            _ => {}
+        }
+    }
+
+    Ok(entry)
+}
+
+fn read_bin_cue_data(mut node: ElementNode) -> Result<BinCueData, LibError> {
+    let mut bin_cue_data = BinCueData {
+        unknown: take_unknown(&mut node),
+        ..Default::default()
+    };
+
+    for child in node.children {
+        match child.tag {
+            GCEbmlSpec::Region(value) => bin_cue_data.region = value,
+            GCEbmlSpec::Language(value) => bin_cue_data.languages.push(value),
+            GCEbmlSpec::BinCueCount(value) => bin_cue_data.bin_cue_count = value,
+            GCEbmlSpec::BinCueEntry(_) => {
+                bin_cue_data.entries.push(read_bin_cue_entry(child)?)
+            }
+            _ => {}
+        }
+    }
+
+    Ok(bin_cue_data)
+}
+
+fn read_bin_cue_entry(mut node: ElementNode) -> Result<BinCueEntry, LibError> {
+    let unknown = take_unknown(&mut node);
+    let file_properties = find_file_properties(&mut node)?;
+    let mut entry = BinCueEntry {
+        file_properties,
+        unknown,
+        ..Default::default()
+    };
+
+    for child in node.children {
+        match child.tag {
+            GCEbmlSpec::DiscNum(value) => entry.disc_num = value,
+            GCEbmlSpec::CueSheet(value) => entry.cue_sheet = value,
+            GCEbmlSpec::BinBinary(data) => entry.bin_binary = data,
+            _ => {}
+        }
+    }
+
+    Ok(entry)
+}
+
+fn read_ssmc_data(mut node: ElementNode) -> SsmcData {
+    let mut ssmc_data = SsmcData {
+        unknown: take_unknown(&mut node),
+        ..Default::default()
+    };
+
+    for child in node.children {
+        match child.tag {
+            GCEbmlSpec::ArchivedFileCount(value) => {
+                ssmc_data.archived_file_count = value
+            }
+            GCEbmlSpec::SSMCEntry(_) => {
+                ssmc_data.entries.extend(
+                    child.children
+                        .into_iter()
+                        .filter(|grandchild| matches!(
+                            grandchild.tag, GCEbmlSpec::FileProperties(_)
+                        ))
+                        .map(read_file_properties)
+                );
+            }
+            GCEbmlSpec::SSMCBinary(data) => ssmc_data.ssmc_binary = data,
+            _ => {}
+        }
+    }
+
+    ssmc_data
+}
+
+pub(crate) fn read_manuals(mut node: ElementNode) -> Manuals {
+    let unknown = take_unknown(&mut node);
+    let entries = node.children
+        .into_iter()
+        .filter(|child| matches!(child.tag, GCEbmlSpec::ManualEntry(_)))
+        .map(read_manual_entry)
+        .collect();
+
+    Manuals { entries, unknown }
+}
+
+pub(crate) fn read_manual_entry(mut node: ElementNode) -> ManualEntry {
+    let mut entry = ManualEntry {
+        unknown: take_unknown(&mut node),
+        ..Default::default()
+    };
+
+    for child in node.children {
+        match child.tag {
+            GCEbmlSpec::EntryUID(uid) => entry.entry_uid = uid,
+            GCEbmlSpec::Region(value) => entry.region = value,
+            GCEbmlSpec::Language(value) => entry.languages.push(value),
+            GCEbmlSpec::PageCount(value) => entry.page_count = value,
+            GCEbmlSpec::Revision(value) => entry.revision = value,
+            GCEbmlSpec::ManualFormat(value) => entry.manual_format = value,
+            GCEbmlSpec::ManualData(data) => entry.manual_data = data,
+            _ => {}
+        }
+    }
+
+    entry
+}
+
+pub(crate) fn read_rom_hacks(
+    mut node: ElementNode
+) -> Result<RomHacks, LibError> {
+    let unknown = take_unknown(&mut node);
+    let mut entries = Vec::new();
+
+    for child in node.children {
+        if let GCEbmlSpec::RomHackEntry(_) = child.tag {
+            entries.push(read_rom_hack_entry(child)?);
+        }
+    }
+
+    Ok(RomHacks { entries, unknown })
+}
+
+pub(crate) fn read_rom_hack_entry(
+    mut node: ElementNode
+) -> Result<RomHackEntry, LibError> {
+    let mut entry = RomHackEntry {
+        unknown: take_unknown(&mut node),
+        ..Default::default()
+    };
+    let mut title = None;
+
+    for child in node.children {
+        match child.tag {
+            GCEbmlSpec::EntryUID(uid) => entry.entry_uid = uid,
+            GCEbmlSpec::Title(value) => title = Some(value),
+            GCEbmlSpec::Developer(value) => entry.developers.push(value),
+            GCEbmlSpec::HackVersion(value) => entry.hack_version = Some(value),
+            GCEbmlSpec::PatchFormat(value) => entry.patch_format = Some(value),
+            GCEbmlSpec::TargetHash(_) => {
+                entry.target_hashes.push(read_target_hash(child))
+            }
+            GCEbmlSpec::HackDescription(value) => {
+                entry.hack_description = Some(value)
+            }
+            GCEbmlSpec::PatchData(data) => entry.patch_data = data,
+            _ => {}
+        }
+    }
+
+    entry.title = title
+        .ok_or_else(|| missing("Title", node.offset, &node.path))?;
+
+    Ok(entry)
+}
+
+fn read_target_hash(mut node: ElementNode) -> TargetHash {
+    let mut target_hash = TargetHash {
+        unknown: take_unknown(&mut node),
+        ..Default::default()
+    };
+
+    for child in node.children {
+        match child.tag {
+            GCEbmlSpec::HashFormat(value) => target_hash.hash_format = value,
+            GCEbmlSpec::HashValue(value) => target_hash.hash_value = value,
+            _ => {}
+        }
+    }
+
+    target_hash
+}
+
+pub(crate) fn read_media(mut node: ElementNode) -> Media {
+    let mut media = Media {
+        unknown: take_unknown(&mut node),
+        ..Default::default()
+    };
+
+    for child in node.children {
+        match child.tag {
+            GCEbmlSpec::ImageCollection(_) => {
+                media.image_collections.push(read_image_collection(child))
+            }
+            GCEbmlSpec::VideoCollection(_) => {
+                media.video_collections.push(read_video_collection(child))
+            }
+            _ => {}
+        }
+    }
+
+    media
+}
+
+pub(crate) fn read_image_collection(mut node: ElementNode) -> ImageCollection {
+    let mut collection = ImageCollection {
+        unknown: take_unknown(&mut node),
+        ..Default::default()
+    };
+
+    for child in node.children {
+        match child.tag {
+            GCEbmlSpec::Category(value) => collection.category = value,
+            GCEbmlSpec::ImageEntry(_) => {
+                collection.entries.push(read_image_entry(child))
+            }
+            _ => {}
+        }
+    }
+
+    collection
+}
+
+pub(crate) fn read_image_entry(mut node: ElementNode) -> ImageEntry {
+    let mut entry = ImageEntry {
+        unknown: take_unknown(&mut node),
+        ..Default::default()
+    };
+
+    for child in node.children {
+        match child.tag {
+            GCEbmlSpec::EntryUID(uid) => entry.entry_uid = uid,
+            GCEbmlSpec::ImageTitle(value) => entry.image_title = Some(value),
+            GCEbmlSpec::Artist(value) => entry.artists.push(value),
+            GCEbmlSpec::Region(value) => entry.region = Some(value),
+            GCEbmlSpec::ImageFormat(value) => entry.image_format = value,
+            GCEbmlSpec::Width(value) => entry.width = value,
+            GCEbmlSpec::Height(value) => entry.height = value,
+            GCEbmlSpec::CategoryDetail(value) => {
+                entry.category_detail = Some(value)
+            }
+            GCEbmlSpec::ImageData(data) => entry.image_data = data,
+            _ => {}
+        }
+    }
+
+    entry
+}
+
+pub(crate) fn read_video_collection(mut node: ElementNode) -> VideoCollection {
+    let mut collection = VideoCollection {
+        unknown: take_unknown(&mut node),
+        ..Default::default()
+    };
+
+    for child in node.children {
+        match child.tag {
+            GCEbmlSpec::Category(value) => collection.category = value,
+            GCEbmlSpec::VideoEntry(_) => {
+                collection.entries.push(read_video_entry(child))
+            }
+            _ => {}
+        }
+    }
+
+    collection
+}
+
+pub(crate) fn read_video_entry(mut node: ElementNode) -> VideoEntry {
+    let mut entry = VideoEntry {
+        unknown: take_unknown(&mut node),
+        ..Default::default()
+    };
+
+    for child in node.children {
+        match child.tag {
+            GCEbmlSpec::EntryUID(uid) => entry.entry_uid = uid,
+            GCEbmlSpec::VideoTitle(value) => entry.video_title = Some(value),
+            GCEbmlSpec::Language(value) => entry.languages.push(value),
+            GCEbmlSpec::Duration(value) => entry.duration = value,
+            GCEbmlSpec::VideoFormat(value) => entry.video_format = value,
+            GCEbmlSpec::Width(value) => entry.width = value,
+            GCEbmlSpec::Height(value) => entry.height = value,
+            GCEbmlSpec::ImageFormat(value) => entry.image_format = value,
+            GCEbmlSpec::Thumbnail(data) => entry.thumbnail = Some(data),
+            GCEbmlSpec::VideoData(data) => entry.video_data = data,
+            _ => {}
+        }
+    }
+
+    entry
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::gc_ebml_spec::utf8_id;
+    use crate::read::GameCaseReader;
+    use crate::write::tests::{encode_case, sample_case};
+
+    /// Returns the offset of the first occurrence of `pattern` in `bytes`.
+    fn find(bytes: &[u8], pattern: &[u8]) -> usize {
+        bytes
+            .windows(pattern.len())
+            .position(|window| window == pattern)
+            .unwrap()
+    }
+
+    /// Encodes the sample case and returns it with the offset of the
+    /// `Title` element.
+    fn case_and_title() -> (Vec<u8>, usize) {
+        let bytes = encode_case(&sample_case());
+        let title = find(&bytes, &[0x2E, 0x8A, 0x00, 0x85, b'Z']);
+
+        (bytes, title)
+    }
+
+    #[test]
+    fn wrong_doc_type_is_reported_at_the_doc_type() {
+        let mut bytes = encode_case(&sample_case());
+        let doc_type = find(&bytes, b"GameCase");
+        bytes[doc_type..doc_type + 4].copy_from_slice(b"Name");
+
+        match process_ebml_data(Cursor::new(&bytes)) {
+            Err(LibError::WrongDocType { found, offset, path }) => {
+                assert_eq!(found, "NameCase");
+                assert_eq!(offset, doc_type as u64 - 3);
+                assert_eq!(path, "EBML/DocType");
+            }
+            other => panic!("expected WrongDocType, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn missing_element_is_reported_at_its_parent() {
+        //The Title becomes an unknown element.
+        let (mut bytes, title) = case_and_title();
+        bytes[title] = 0x2F;
+
+        match process_ebml_data(Cursor::new(&bytes)) {
+            Err(LibError::MissingElement { element, path, .. }) => {
+                assert_eq!(element, "Title");
+                assert_eq!(path, "GameCase/Game");
+            }
+            other => panic!("expected MissingElement, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn misplaced_element_is_reported_where_it_was_found() {
+        //The Title becomes a DataFormat, which belongs in GameDataEntry.
+        let (mut bytes, title) = case_and_title();
+        bytes[title + 2] = 0x0A;
+
+        match process_ebml_data(Cursor::new(&bytes)) {
+            Err(LibError::MisplacedElement { id, offset, path }) => {
+                assert_eq!(id, utf8_id(GCEbmlSpec::DataFormat));
+                assert_eq!(offset, title as u64);
+                assert_eq!(path, "GameCase/Game/DataFormat");
+            }
+            other => panic!("expected MisplacedElement, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn invalid_size_is_reported_as_an_invalid_vint() {
+        let (mut bytes, title) = case_and_title();
+        bytes[title + 3] = 0;
+
+        assert!(matches!(
+            process_ebml_data(Cursor::new(&bytes)),
+            Err(LibError::InvalidVint { .. })
+        ));
+    }
+
+    #[test]
+    fn truncated_file_is_reported_at_the_master_cut_short() {
+        let bytes = encode_case(&sample_case());
+        let cut = &bytes[..bytes.len() - 10];
+
+        match process_ebml_data(Cursor::new(cut)) {
+            Err(LibError::TruncatedMaster { path, .. }) => {
+                assert!(path.starts_with("GameCase/"), "{path}");
+            }
+            other => panic!("expected TruncatedMaster, got {other:?}"),
+        }
+    }
+
+    /// Encodes the sample case with its `DataFormat` set to `format`, which
+    /// must be three bytes long like "RAW".
+    fn case_with_data_format(format: &[u8; 3]) -> Vec<u8> {
+        let mut bytes = encode_case(&sample_case());
+        let data_format = find(&bytes, &[0x2E, 0x8A, 0x0A, 0x83]) + 4;
+        bytes[data_format..data_format + 3].copy_from_slice(format);
+
+        bytes
+    }
+
+    #[test]
+    fn payload_not_matching_the_data_format_is_rejected() {
+        let bytes = case_with_data_format(b"CHD");
+
+        match process_ebml_data(Cursor::new(&bytes)) {
+            Err(LibError::DataFormatMismatch { data_format, element, .. }) => {
+                assert_eq!(data_format, "CHD");
+                assert_eq!(element, "RawData");
+            }
+            other => panic!("expected DataFormatMismatch, got {other:?}"),
+        }
+
+        let bytes = case_with_data_format(b"ISO");
+        assert!(matches!(
+            process_ebml_data(Cursor::new(&bytes)),
+            Err(LibError::UnknownDataFormat { value, .. }) if value == "ISO"
+        ));
+    }
+
+    #[test]
+    fn payload_is_read_as_the_variant_its_data_format_names() {
+        let mut game_case = sample_case();
+        let disc = |disc_num: u64| ChdEntry {
+            disc_num,
+            file_properties: FileProperties {
+                rom_file_size: 4,
+                ..Default::default()
+            },
+            chd_binary: vec![disc_num as u8; 4],
+            ..Default::default()
+        };
+        game_case.game.game_data.as_mut().unwrap().entries[0].payload =
+            GameDataPayload::Chd(ChdData {
+                region: "JPN".to_string(),
+                languages: vec!["ja".to_string()],
+                chd_count: 2,
+                entries: vec![disc(1), disc(2)],
+                ..Default::default()
+            });
+
+        let bytes = encode_case(&game_case);
+        let read = process_ebml_data(Cursor::new(&bytes)).unwrap();
+        assert_eq!(read.game, game_case.game);
+        //The DataFormat is written from the variant.
+        find(&bytes, &[0x2E, 0x8A, 0x0A, 0x83, b'C', b'H', b'D']);
+    }
+
+    /// Returns the sample case with an element unknown to the
+    /// specification after the first two children of `Game`.
+    fn case_with_unknown_child() -> GameCase {
+        let mut game_case = sample_case();
+        game_case.game.unknown = vec![UnknownElement {
+            id: 0x4F01,
+            data: vec![1, 2, 3],
+            position: 2,
+        }];
+
+        game_case
+    }
+
+    #[test]
+    fn unknown_elements_are_kept_byte_for_byte() {
+        let game_case = case_with_unknown_child();
+        let bytes = encode_case(&game_case);
+        let encoded = [0x4F, 0x01, 0x83, 1, 2, 3];
+        assert!(bytes.windows(encoded.len()).any(|w| w == encoded));
+
+        let read = process_ebml_data(Cursor::new(&bytes)).unwrap();
+        assert_eq!(read.game.unknown, game_case.game.unknown);
+        assert_eq!(encode_case(&read), bytes);
+    }
+
+    #[test]
+    fn unknown_elements_are_rejected_when_asked_to() {
+        let bytes = encode_case(&case_with_unknown_child());
+        let limits = ReaderLimits {
+            reject_unknown_elements: true,
+            ..Default::default()
+        };
+
+        match process_ebml_data_with_limits(Cursor::new(&bytes), limits) {
+            Err(LibError::UnknownElement { id, path, .. }) => {
+                assert_eq!(id, 0x4F01);
+                assert_eq!(path, "GameCase/Game/0x4F01");
+            }
+            other => panic!("expected UnknownElement, got {other:?}"),
+        }
+
+        let mut reader =
+            GameCaseReader::open_with_limits(Cursor::new(&bytes), limits)
+                .unwrap();
+        assert!(matches!(
+            reader.read_game_info(),
+            Err(LibError::UnknownElement { id: 0x4F01, .. })
+        ));
+    }
+
+    /// Encodes the sample case declaring `version` and `read_version` in
+    /// its EBML header.
+    fn case_with_versions(version: u8, read_version: u8) -> Vec<u8> {
+        let mut bytes = encode_case(&sample_case());
+        let at = find(&bytes, &[0x42, 0x87, 0x81, 0x01]);
+        bytes[at + 3] = version;
+        let at = find(&bytes, &[0x42, 0x85, 0x81, 0x01]);
+        bytes[at + 3] = read_version;
+
+        bytes
+    }
+
+    #[test]
+    fn newer_version_is_read_if_its_read_version_is_supported() {
+        let bytes = case_with_versions(2, 1);
+
+        assert!(process_ebml_data(Cursor::new(&bytes)).is_ok());
+        let reader = GameCaseReader::open(Cursor::new(&bytes)).unwrap();
+        assert_eq!(
+            reader.doc_type_versions(),
+            DocTypeVersions { version: 2, read_version: 1 }
+        );
+    }
+
+    #[test]
+    fn newer_read_version_is_refused() {
+        let bytes = case_with_versions(2, 2);
+        let read_version = find(&bytes, &[0x42, 0x85, 0x81, 0x02]);
+
+        match process_ebml_data(Cursor::new(&bytes)) {
+            Err(LibError::UnsupportedReadVersion {
+                version,
+                supported,
+                offset,
+                path,
+            }) => {
+                assert_eq!((version, supported), (2, DOC_TYPE_VERSION));
+                assert_eq!(offset, read_version as u64);
+                assert_eq!(path, "EBML/DocTypeReadVersion");
+            }
+            other => panic!("expected UnsupportedReadVersion, got {other:?}"),
+        }
 
-    Ok(true)
-}
\ No newline at end of file
+        assert!(matches!(
+            GameCaseReader::open(Cursor::new(&bytes)),
+            Err(LibError::UnsupportedReadVersion { version: 2, .. })
+        ));
+    }
+}
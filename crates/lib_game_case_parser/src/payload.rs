@@ -0,0 +1,346 @@
+use std::io::{self, Read, Seek, SeekFrom};
+
+use ebml_iterable::specs::{EbmlSpecification, EbmlTag, TagDataType};
+
+use crate::ebml_tools::{
+    encode_element_header, ElementSpan,
+};
+use crate::gc_ebml_spec::{binary_id, element_path, GCEbmlSpec};
+use crate::lib_error_handling::LibError;
+use crate::limits::{LimitTracker, ReaderLimits};
+use crate::parse::{decode_elements, parent_path, ElementNode};
+use crate::read::expect_header;
+
+/// Location of a binary payload, e.g. a ROM or a manual, within a file.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PayloadLocation {
+    /// Element ID of the binary element, e.g. the ID of `ROMData`.
+    pub id: u64,
+    /// Absolute offset of the first byte of the payload.
+    pub offset: u64,
+    /// Length of the payload in bytes.
+    pub size: u64,
+}
+
+/// Callback receiving the number of bytes streamed so far and the total
+/// size of the payload.
+pub type ProgressCallback<'a> = Box<dyn FnMut(u64, u64) + 'a>;
+
+/// A bounded reader over a single binary payload.
+///
+/// Reads never go past the end of the payload, so the reader can be handed
+/// to anything expecting a `Read` without exposing the rest of the file.
+pub struct PayloadReader<'a, R: Read> {
+    inner: io::Take<&'a mut R>,
+    size: u64,
+    progress: Option<ProgressCallback<'a>>,
+}
+
+impl<'a, R: Read> PayloadReader<'a, R> {
+    /// Wraps `source`, which must already be positioned at the start of
+    /// the payload.
+    pub(crate) fn new(source: &'a mut R, size: u64) -> Self {
+        PayloadReader {
+            inner: source.take(size),
+            size,
+            progress: None,
+        }
+    }
+
+    /// Opens a reader over `payload` within `source`, e.g. over a payload
+    /// recovered by `salvage` from a damaged case.
+    pub fn open(
+        source: &'a mut R,
+        payload: &PayloadLocation,
+    ) -> Result<Self, LibError>
+    where
+        R: Seek,
+    {
+        source.seek(SeekFrom::Start(payload.offset))?;
+
+        Ok(PayloadReader::new(source, payload.size))
+    }
+
+    /// Registers a callback that is invoked after every read.
+    ///
+    /// # Arguments
+    ///
+    /// * `progress`: Called with the number of bytes read so far and the
+    ///   total size of the payload.
+    pub fn with_progress<F>(mut self, progress: F) -> Self
+    where
+        F: FnMut(u64, u64) + 'a,
+    {
+        self.progress = Some(Box::new(progress));
+        self
+    }
+
+    /// Total size of the payload in bytes.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Number of payload bytes that have not been read yet.
+    pub fn remaining(&self) -> u64 {
+        self.inner.limit()
+    }
+}
+
+impl<R: Read> Read for PayloadReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+
+        if let Some(progress) = self.progress.as_mut().filter(|_| read > 0) {
+            progress(self.size - self.inner.limit(), self.size);
+        }
+
+        Ok(read)
+    }
+}
+
+/// Returns whether `id` belongs to an element carrying file contents.
+pub(crate) fn is_payload(id: u64) -> bool {
+    [
+        binary_id(GCEbmlSpec::ROMData),
+        binary_id(GCEbmlSpec::ArchiveBinary),
+        binary_id(GCEbmlSpec::CHDBinary),
+        binary_id(GCEbmlSpec::BinBinary),
+        binary_id(GCEbmlSpec::SSMCBinary),
+        binary_id(GCEbmlSpec::ManualData),
+        binary_id(GCEbmlSpec::PatchData),
+        binary_id(GCEbmlSpec::ImageData),
+        binary_id(GCEbmlSpec::Thumbnail),
+        binary_id(GCEbmlSpec::VideoData),
+    ]
+    .contains(&id)
+}
+
+/// Finds every binary payload within the element starting at `offset`.
+///
+/// Only element headers are read, the payloads themselves are skipped over
+/// so the cost does not depend on their size.
+///
+/// # Arguments
+///
+/// * `source`: The file to scan.
+/// * `offset`: Absolute offset of the element to scan.
+/// * `expected`: Element ID the element at `offset` is expected to have.
+///
+/// # Returns
+///
+/// A `Result` containing the payload locations in file order.
+pub(crate) fn locate_payloads<R: Read + Seek>(
+    source: &mut R,
+    offset: u64,
+    expected: u64,
+) -> Result<Vec<PayloadLocation>, LibError> {
+    source.seek(SeekFrom::Start(offset))?;
+    let header = expect_header(source, expected)?;
+
+    let mut payloads = Vec::new();
+    scan_children(source, ElementSpan::new(offset, header), &mut payloads)?;
+
+    Ok(payloads)
+}
+
+fn scan_children<R: Read + Seek>(
+    source: &mut R,
+    element: ElementSpan,
+    payloads: &mut Vec<PayloadLocation>,
+) -> Result<(), LibError> {
+    let mut walker = element.children();
+
+    while let Some(child) = walker.seek_next(source)? {
+        if is_payload(child.header.id) {
+            payloads.push(PayloadLocation {
+                id: child.header.id,
+                offset: child.data_start(),
+                size: child.header.size,
+            });
+        } else if GCEbmlSpec::get_tag_data_type(child.header.id)
+            == Some(TagDataType::Master)
+        {
+            scan_children(source, child, payloads)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads and decodes the element starting at `offset` without its
+/// payloads, checking it against the default `ReaderLimits`.
+///
+/// See `load_metadata_with_limits`.
+pub(crate) fn load_metadata<R: Read + Seek>(
+    source: &mut R,
+    offset: u64,
+    expected: u64,
+) -> Result<ElementNode, LibError> {
+    load_metadata_with_limits(source, offset, expected, ReaderLimits::default())
+}
+
+/// Reads and decodes the element starting at `offset` without its
+/// payloads.
+///
+/// Every payload is decoded as empty and never read, so entries holding
+/// gigabytes of data can be inspected cheaply. Only the offset of the
+/// returned element itself is reliable, the offsets of its children
+/// ignore the skipped payload data.
+///
+/// Every header is checked against `limits`, the end of its parent and
+/// the end of `source` before anything is allocated for the element.
+pub(crate) fn load_metadata_with_limits<R: Read + Seek>(
+    source: &mut R,
+    offset: u64,
+    expected: u64,
+    limits: ReaderLimits,
+) -> Result<ElementNode, LibError> {
+    let len = source.seek(SeekFrom::End(0))?;
+    source.seek(SeekFrom::Start(offset))?;
+    let element = ElementSpan::new(offset, expect_header(source, expected)?);
+    if element.end.is_some_and(|end| end > len) {
+        return Err(LibError::TruncatedMaster {
+            offset,
+            path: element_path(expected),
+        });
+    }
+
+    let mut tracker = LimitTracker::new(limits, offset, &parent_path(expected))
+        .skipping_payloads();
+    let bytes = strip_payloads(source, element, &mut tracker)?;
+    let mut node = decode_elements(&bytes, offset)?
+        .into_iter()
+        .next()
+        .ok_or(LibError::Io(io::ErrorKind::UnexpectedEof.into()))?;
+    clear_payloads(&mut node);
+
+    Ok(node)
+}
+
+/// Re-encodes `element` with the data of every payload
+/// replaced by a single byte.
+///
+/// The tag iterator reports an empty element at the very end of its input
+/// as truncated, so payloads are not left empty here.
+fn strip_payloads<R: Read + Seek>(
+    source: &mut R,
+    element: ElementSpan,
+    tracker: &mut LimitTracker,
+) -> Result<Vec<u8>, LibError> {
+    let header = element.header;
+    tracker.check_header(element.offset, &header)?;
+
+    if is_payload(header.id) {
+        let mut bytes = encode_element_header(header.id, 1);
+        bytes.push(0);
+        return Ok(bytes);
+    }
+
+    if GCEbmlSpec::get_tag_data_type(header.id) != Some(TagDataType::Master) {
+        let mut bytes = vec![0u8; header.total_len() as usize];
+        source.seek(SeekFrom::Start(element.offset))?;
+        source.read_exact(&mut bytes)?;
+        return Ok(bytes);
+    }
+
+    let mut walker = element.children();
+    let mut data = Vec::new();
+
+    while let Some(child) = walker.seek_next(source)? {
+        data.extend(strip_payloads(source, child, tracker)?);
+    }
+
+    let mut bytes = encode_element_header(header.id, data.len() as u64);
+    bytes.extend(data);
+
+    Ok(bytes)
+}
+
+/// Empties the placeholder data `strip_payloads` left in every payload.
+fn clear_payloads(node: &mut ElementNode) {
+    let id = node.tag.get_id();
+    if is_payload(id)
+        && let Some(empty) = GCEbmlSpec::get_binary_tag(id, &[])
+    {
+        node.tag = empty;
+    }
+
+    for child in &mut node.children {
+        clear_payloads(child);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::lib_structs::GameDataPayload;
+    use crate::parse::read_game_data_entry;
+    use crate::read::GameCaseReader;
+    use crate::write::tests::{encode_case, sample_case};
+
+    #[test]
+    fn payload_is_streamed_with_progress() {
+        let bytes = encode_case(&sample_case());
+        let mut reader = GameCaseReader::open(Cursor::new(bytes)).unwrap();
+        let entry = reader.index().entries[0].clone();
+        let rom = reader.payloads(&entry).unwrap()[0];
+        assert_eq!(rom.id, binary_id(GCEbmlSpec::ROMData));
+
+        let mut reports = Vec::new();
+        let mut payload = reader
+            .open_payload(&rom)
+            .unwrap()
+            .with_progress(|read, total| reports.push((read, total)));
+        let mut data = Vec::new();
+        let mut chunk = [0; 128];
+        loop {
+            match payload.read(&mut chunk).unwrap() {
+                0 => break,
+                read => data.extend_from_slice(&chunk[..read]),
+            }
+        }
+        assert_eq!(payload.remaining(), 0);
+        drop(payload);
+
+        //Reading stops at the end of the payload.
+        assert_eq!(data, [7; 300]);
+        assert_eq!(reports, [(128, 300), (256, 300), (300, 300)]);
+    }
+
+    #[test]
+    fn copy_fails_when_the_source_ends_early() {
+        let bytes = encode_case(&sample_case());
+        let len = bytes.len() as u64;
+        let mut reader = GameCaseReader::open(Cursor::new(bytes)).unwrap();
+        let past_the_end = PayloadLocation {
+            id: binary_id(GCEbmlSpec::ROMData),
+            offset: len - 10,
+            size: 20,
+        };
+
+        let mut dest = Vec::new();
+        let copied = reader.copy_payload(&past_the_end, &mut dest, |_, _| {});
+        assert!(matches!(copied, Err(LibError::Io(_))));
+        assert_eq!(dest.len(), 10);
+    }
+
+    #[test]
+    fn metadata_is_loaded_without_payloads() {
+        let bytes = encode_case(&sample_case());
+        let mut source = Cursor::new(bytes);
+        let reader = GameCaseReader::open(&mut source).unwrap();
+        let entry = reader.index().entries[0].clone();
+        let offset = reader.game_case_start() + entry.position;
+
+        let node =
+            load_metadata(&mut source, offset, entry.target_id).unwrap();
+        let entry = read_game_data_entry(node).unwrap();
+        let GameDataPayload::Raw(raw) = entry.payload else {
+            panic!("expected RAW data");
+        };
+        assert!(raw.rom_data.is_empty());
+        assert_eq!(raw.file_properties.rom_file_size, 300);
+    }
+}
@@ -0,0 +1,928 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::str::FromStr;
+
+use crate::date::ReleaseDate;
+use crate::gc_ebml_spec::{master_id, GCEbmlSpec};
+use crate::lib_error_handling::LibError;
+use crate::lib_structs::{
+    ArchiveData, BinCueData, BinCueEntry, ChdData, ChdEntry, FileProperties,
+    Game, GameCase, GameData, GameDataEntry, GameDataPayload, ImageCollection,
+    ImageEntry, Index, IndexEntry, ManualEntry, Manuals, Media, Rating,
+    RawData, RegionInfo, RomHackEntry, RomHacks, SsmcData, TargetHash,
+    VideoCollection, VideoEntry,
+};
+use crate::read::IndexQuery;
+
+/// Names of the elements carrying file contents.
+const PAYLOAD_ELEMENTS: [&str; 10] = [
+    "ROMData",
+    "ArchiveBinary",
+    "CHDBinary",
+    "BinBinary",
+    "SSMCBinary",
+    "ManualData",
+    "PatchData",
+    "ImageData",
+    "Thumbnail",
+    "VideoData",
+];
+
+/// A parsed path expression selecting elements of a case, e.g.
+/// `Game/RegionInfo[Region="Japan"]/Publisher`.
+///
+/// A path is a list of steps separated by `/`, each naming a child element
+/// as the specification does, or `*` for any child. It starts below
+/// `GameCase`, a leading `GameCase/` is accepted and ignored. Every step
+/// may be followed by predicates in square brackets, all of which have to
+/// hold for an element to be selected:
+///
+/// * `[Name="value"]`: A child named `Name` has the value. Numbers may be
+///   written without quotes.
+/// * `[Name!="value"]`: No child named `Name` has the value, which also
+///   holds if there is no such child.
+/// * `[Name]`: A child named `Name` exists.
+/// * `[n]`: The element is the `n`th of its name within its parent,
+///   counting from 0 like the paths in `LibError` and `Diagnostic`.
+///
+/// Dates compare as ISO 8601 text and binary values as lowercase hex.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CasePath {
+    steps: Vec<Step>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Step {
+    /// Element name, or `*` for any element.
+    name: String,
+    predicates: Vec<Predicate>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Predicate {
+    Position(usize),
+    Exists(String),
+    Equals(String, String),
+    NotEquals(String, String),
+}
+
+/// A single element of the typed model selected by a [`CasePath`].
+///
+/// Masters borrow the struct they were decoded into, every other element
+/// its value.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CaseNode<'a> {
+    GameCase(&'a GameCase),
+    Index(&'a Index),
+    IndexEntry(&'a IndexEntry),
+    Game(&'a Game),
+    Rating(&'a Rating),
+    RegionInfo(&'a RegionInfo),
+    GameData(&'a GameData),
+    GameDataEntry(&'a GameDataEntry),
+    RawData(&'a RawData),
+    ArchiveData(&'a ArchiveData),
+    /// An `ArchiveFileMetadata` element, holding a single `FileProperties`.
+    ArchiveFileMetadata(&'a FileProperties),
+    ChdData(&'a ChdData),
+    ChdEntry(&'a ChdEntry),
+    BinCueData(&'a BinCueData),
+    BinCueEntry(&'a BinCueEntry),
+    SsmcData(&'a SsmcData),
+    /// An `SSMCEntry` element, holding a single `FileProperties`.
+    SsmcEntry(&'a FileProperties),
+    FileProperties(&'a FileProperties),
+    Manuals(&'a Manuals),
+    ManualEntry(&'a ManualEntry),
+    RomHacks(&'a RomHacks),
+    RomHackEntry(&'a RomHackEntry),
+    TargetHash(&'a TargetHash),
+    Media(&'a Media),
+    ImageCollection(&'a ImageCollection),
+    ImageEntry(&'a ImageEntry),
+    VideoCollection(&'a VideoCollection),
+    VideoEntry(&'a VideoEntry),
+    Text(&'a str),
+    Uint(u64),
+    Binary(&'a [u8]),
+    Date(&'a ReleaseDate),
+}
+
+impl CasePath {
+    /// Parses a path expression, see [`CasePath`] for the syntax.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the path, or `LibError::InvalidQuery` pointing
+    /// at the first character that could not be parsed.
+    pub fn parse(text: &str) -> Result<Self, LibError> {
+        let mut parser = Parser { text, position: 0 };
+        let mut steps = Vec::new();
+
+        parser.eat('/');
+        loop {
+            steps.push(parser.step()?);
+            if parser.at_end() {
+                break;
+            }
+            parser.expect('/')?;
+        }
+
+        if steps[0].name == "GameCase" && steps[0].predicates.is_empty() {
+            steps.remove(0);
+        }
+
+        Ok(CasePath { steps })
+    }
+
+    /// Selects every element of `game_case` the path matches, in file
+    /// order.
+    pub fn select<'a>(&self, game_case: &'a GameCase) -> Vec<CaseNode<'a>> {
+        let mut selected = vec![CaseNode::GameCase(game_case)];
+
+        for step in &self.steps {
+            let mut next = Vec::new();
+            for node in selected {
+                let mut positions: HashMap<&str, usize> = HashMap::new();
+                for (name, child) in node.children() {
+                    let position = positions.entry(name).or_default();
+                    if step.matches(name, *position, &child) {
+                        next.push(child);
+                    }
+                    *position += 1;
+                }
+            }
+            selected = next;
+        }
+
+        selected
+    }
+
+    /// Returns whether an element named `name`, whose parent is reached
+    /// by the path, has to be loaded to evaluate the path.
+    ///
+    /// # Arguments
+    ///
+    /// * `depth`: Depth of the element below `GameCase`, starting at 0.
+    /// * `name`: Element name.
+    pub(crate) fn reaches(&self, depth: usize, name: &str) -> bool {
+        match self.steps.get(depth) {
+            None => true,
+            Some(step) => {
+                step.name_matches(name) || self.tested(depth, name)
+            }
+        }
+    }
+
+    /// Returns whether the element at `depth` is on the path, rather than
+    /// only tested by a predicate of its parent.
+    pub(crate) fn follows(&self, depth: usize, name: &str) -> bool {
+        self.steps.get(depth).is_none_or(|step| step.name_matches(name))
+    }
+
+    /// Returns whether a predicate of the step above `depth` tests a child
+    /// named `name`.
+    fn tested(&self, depth: usize, name: &str) -> bool {
+        depth
+            .checked_sub(1)
+            .and_then(|parent| self.steps.get(parent))
+            .is_some_and(|step| step.tests(name))
+    }
+
+    /// Returns whether the payloads within the element at `depth` may be
+    /// selected or tested by the path.
+    pub(crate) fn needs_payloads(&self, depth: usize) -> bool {
+        if depth + 1 >= self.steps.len() {
+            return true;
+        }
+
+        self.steps[depth..].iter().enumerate().any(|(offset, step)| {
+            let payload = |name: &str| PAYLOAD_ELEMENTS.contains(&name);
+            (offset > 0 && (step.name == "*" || payload(&step.name)))
+                || step.predicates.iter().any(|predicate| match predicate {
+                    Predicate::Position(_) => false,
+                    Predicate::Exists(name)
+                    | Predicate::Equals(name, _)
+                    | Predicate::NotEquals(name, _) => payload(name),
+                })
+        })
+    }
+
+    /// Builds the `Index` query every entry named `name` at `depth` that
+    /// can be selected has to satisfy.
+    ///
+    /// # Returns
+    ///
+    /// `None` if the path does not narrow down the entries through fields
+    /// the `Index` holds, or if dropping entries would change the result,
+    /// as with a position predicate.
+    pub(crate) fn index_query(
+        &self,
+        depth: usize,
+        name: &str,
+    ) -> Option<IndexQuery> {
+        let step = self.steps.get(depth)?;
+        if !step.name_matches(name) || self.tested(depth, name) {
+            return None;
+        }
+        if step.predicates.iter().any(|p| matches!(p, Predicate::Position(_)))
+        {
+            return None;
+        }
+
+        let target_id = match name {
+            "GameDataEntry" => master_id(GCEbmlSpec::GameDataEntry),
+            "ManualEntry" => master_id(GCEbmlSpec::ManualEntry),
+            "RomHackEntry" => master_id(GCEbmlSpec::RomHackEntry),
+            "ImageEntry" => master_id(GCEbmlSpec::ImageEntry),
+            "VideoEntry" => master_id(GCEbmlSpec::VideoEntry),
+            _ => return None,
+        };
+        let collection = depth
+            .checked_sub(1)
+            .and_then(|parent| self.steps.get(parent))
+            .and_then(|parent| parent.equals("Category"));
+
+        //Mirrors how write::index_targets fills the Index.
+        let mut query = IndexQuery {
+            uid: step.equals("EntryUID").and_then(|uid| uid.parse().ok()),
+            target_id: Some(target_id),
+            ..Default::default()
+        };
+        match name {
+            "GameDataEntry" => query.category = step.equals("DataFormat"),
+            "ManualEntry" => query.region = step.equals("Region"),
+            "ImageEntry" => {
+                query.target = collection;
+                query.category = step.equals("CategoryDetail");
+                query.region = step.equals("Region");
+            }
+            "VideoEntry" => query.category = collection,
+            _ => {}
+        }
+
+        Some(query)
+    }
+}
+
+impl FromStr for CasePath {
+    type Err = LibError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        CasePath::parse(text)
+    }
+}
+
+impl Step {
+    fn name_matches(&self, name: &str) -> bool {
+        self.name == "*" || self.name == name
+    }
+
+    /// Returns whether any predicate looks at children named `name`.
+    fn tests(&self, name: &str) -> bool {
+        self.predicates.iter().any(|predicate| match predicate {
+            Predicate::Position(_) => false,
+            Predicate::Exists(child)
+            | Predicate::Equals(child, _)
+            | Predicate::NotEquals(child, _) => child == name,
+        })
+    }
+
+    /// Returns the value a child named `name` is required to have.
+    fn equals(&self, name: &str) -> Option<String> {
+        self.predicates.iter().find_map(|predicate| match predicate {
+            Predicate::Equals(child, value) if child == name => {
+                Some(value.clone())
+            }
+            _ => None,
+        })
+    }
+
+    fn matches(&self, name: &str, position: usize, node: &CaseNode) -> bool {
+        if !self.name_matches(name) {
+            return false;
+        }
+
+        self.predicates.iter().all(|predicate| match predicate {
+            Predicate::Position(wanted) => position == *wanted,
+            Predicate::Exists(child) => {
+                node.children().iter().any(|(name, _)| name == child)
+            }
+            Predicate::Equals(child, value) => node.has_value(child, value),
+            Predicate::NotEquals(child, value) => {
+                !node.has_value(child, value)
+            }
+        })
+    }
+}
+
+impl<'a> CaseNode<'a> {
+    /// Returns the value of an element other than a master as text, with
+    /// dates as ISO 8601 and binary values as lowercase hex.
+    ///
+    /// # Returns
+    ///
+    /// `None` for masters.
+    pub fn text(&self) -> Option<String> {
+        match self {
+            CaseNode::Text(text) => Some(text.to_string()),
+            CaseNode::Uint(value) => Some(value.to_string()),
+            CaseNode::Binary(data) => Some(hex(data)),
+            CaseNode::Date(ReleaseDate::Date(date)) => Some(date.to_string()),
+            CaseNode::Date(ReleaseDate::Legacy(data)) => Some(hex(data)),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&'a str> {
+        match self {
+            CaseNode::Text(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    pub fn as_uint(&self) -> Option<u64> {
+        match self {
+            CaseNode::Uint(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_bytes(&self) -> Option<&'a [u8]> {
+        match self {
+            CaseNode::Binary(data) => Some(data),
+            _ => None,
+        }
+    }
+
+    /// Returns whether a child named `name` has the value `value`.
+    ///
+    /// Unsigned integers are compared as numbers, so "007" matches 7.
+    fn has_value(&self, name: &str, value: &str) -> bool {
+        self.children()
+            .iter()
+            .filter(|(child, _)| *child == name)
+            .any(|(_, node)| match node {
+                CaseNode::Uint(number) => value.parse() == Ok(*number),
+                CaseNode::Binary(_) | CaseNode::Date(_) => node
+                    .text()
+                    .is_some_and(|text| text.eq_ignore_ascii_case(value)),
+                _ => node.text().as_deref() == Some(value),
+            })
+    }
+
+    /// Returns the children of a master in the order they are written,
+    /// each with its element name. Elements unknown to this library are
+    /// left out.
+    pub fn children(&self) -> Vec<(&'static str, CaseNode<'a>)> {
+        let mut children = Children::default();
+
+        match *self {
+            CaseNode::GameCase(game_case) => {
+                children.push("Index", CaseNode::Index(&game_case.index));
+                children.push("Game", CaseNode::Game(&game_case.game));
+                if let Some(manuals) = &game_case.manuals {
+                    children.push("Manuals", CaseNode::Manuals(manuals));
+                }
+                if let Some(rom_hacks) = &game_case.rom_hacks {
+                    children.push("RomHacks", CaseNode::RomHacks(rom_hacks));
+                }
+                if let Some(media) = &game_case.media {
+                    children.push("Media", CaseNode::Media(media));
+                }
+            }
+            CaseNode::Index(index) => {
+                for entry in &index.entries {
+                    children.push("IndexEntry", CaseNode::IndexEntry(entry));
+                }
+            }
+            CaseNode::IndexEntry(entry) => {
+                children.uint("IndexTargetID", entry.target_id);
+                children.uint("IndexPosition", entry.position);
+                children.text("IndexTarget", &entry.target);
+                children.opt_text("IndexTargetCategory", &entry.category);
+                children.opt_text("IndexTargetRegion", &entry.region);
+                children.uint("IndexTargetUID", entry.uid);
+            }
+            CaseNode::Game(game) => {
+                children.text("Title", &game.title);
+                children.texts("Developer", &game.developers);
+                children.opt_text("Genre", &game.genre);
+                children.opt_uint("MinPlayers", game.min_players);
+                children.opt_uint("MaxPlayers", game.max_players);
+                children.text("GameSystem", &game.game_system);
+                children.opt_text("Description", &game.description);
+                if let Some(rating) = &game.rating {
+                    children.push("Rating", CaseNode::Rating(rating));
+                }
+                for region_info in &game.region_info {
+                    children.push(
+                        "RegionInfo",
+                        CaseNode::RegionInfo(region_info),
+                    );
+                }
+                if let Some(game_data) = &game.game_data {
+                    children.push("GameData", CaseNode::GameData(game_data));
+                }
+            }
+            CaseNode::Rating(rating) => {
+                children.text("RatingValue", &rating.value);
+                children.text("RatingSource", &rating.source);
+            }
+            CaseNode::RegionInfo(region_info) => {
+                children.text("Region", &region_info.region);
+                children.texts("Language", &region_info.languages);
+                children.push(
+                    "ReleaseDate",
+                    CaseNode::Date(&region_info.release_date),
+                );
+                children.text("Publisher", &region_info.publisher);
+                children.text("AgeRating", &region_info.age_rating);
+            }
+            CaseNode::GameData(game_data) => {
+                for entry in &game_data.entries {
+                    children.push(
+                        "GameDataEntry",
+                        CaseNode::GameDataEntry(entry),
+                    );
+                }
+            }
+            CaseNode::GameDataEntry(entry) => {
+                children.uint("EntryUID", entry.entry_uid);
+                children.text("DataFormat", entry.payload.data_format());
+                match &entry.payload {
+                    GameDataPayload::Raw(data) => {
+                        children.push("RawData", CaseNode::RawData(data));
+                    }
+                    GameDataPayload::Archive(data) => {
+                        children.push(
+                            "ArchiveData",
+                            CaseNode::ArchiveData(data),
+                        );
+                    }
+                    GameDataPayload::Chd(data) => {
+                        children.push("ChdData", CaseNode::ChdData(data));
+                    }
+                    GameDataPayload::BinCue(data) => {
+                        children.push("BinCueData", CaseNode::BinCueData(data));
+                    }
+                    GameDataPayload::Ssmc(data) => {
+                        children.push("SSMCData", CaseNode::SsmcData(data));
+                    }
+                }
+            }
+            CaseNode::RawData(data) => {
+                children.properties(&data.file_properties);
+                children.binary("ROMData", &data.rom_data);
+            }
+            CaseNode::ArchiveData(data) => {
+                children.text(
+                    "CompressionAlgorithm",
+                    &data.compression_algorithm,
+                );
+                children.uint("ArchivedFileCount", data.archived_file_count);
+                for properties in &data.file_metadata {
+                    children.push(
+                        "ArchiveFileMetadata",
+                        CaseNode::ArchiveFileMetadata(properties),
+                    );
+                }
+                children.binary("ArchiveBinary", &data.archive_binary);
+            }
+            CaseNode::ArchiveFileMetadata(properties)
+            | CaseNode::SsmcEntry(properties) => {
+                children.properties(properties);
+            }
+            CaseNode::ChdData(data) => {
+                children.text("Region", &data.region);
+                children.texts("Language", &data.languages);
+                children.uint("CHDCount", data.chd_count);
+                for entry in &data.entries {
+                    children.push("CHDEntry", CaseNode::ChdEntry(entry));
+                }
+            }
+            CaseNode::ChdEntry(entry) => {
+                children.uint("DiscNum", entry.disc_num);
+                children.properties(&entry.file_properties);
+                children.binary("CHDBinary", &entry.chd_binary);
+            }
+            CaseNode::BinCueData(data) => {
+                children.text("Region", &data.region);
+                children.texts("Language", &data.languages);
+                children.uint("BinCueCount", data.bin_cue_count);
+                for entry in &data.entries {
+                    children.push("BinCueEntry", CaseNode::BinCueEntry(entry));
+                }
+            }
+            CaseNode::BinCueEntry(entry) => {
+                children.uint("DiscNum", entry.disc_num);
+                children.text("CueSheet", &entry.cue_sheet);
+                children.properties(&entry.file_properties);
+                children.binary("BinBinary", &entry.bin_binary);
+            }
+            CaseNode::SsmcData(data) => {
+                children.uint("ArchivedFileCount", data.archived_file_count);
+                for properties in &data.entries {
+                    children.push("SSMCEntry", CaseNode::SsmcEntry(properties));
+                }
+                children.binary("SSMCBinary", &data.ssmc_binary);
+            }
+            CaseNode::FileProperties(properties) => {
+                children.opt_text("ROMFileName", &properties.rom_file_name);
+                children.uint("ROMFileSize", properties.rom_file_size);
+                children.opt_text("Region", &properties.region);
+                children.texts("Language", &properties.languages);
+                children.opt_binary("CRC32", &properties.crc32);
+                children.opt_binary("MD5", &properties.md5);
+                children.opt_binary("SHA1", &properties.sha1);
+                children.opt_binary("SHA256", &properties.sha256);
+                children.opt_binary("SHA512", &properties.sha512);
+                children.opt_uint("SSMCIndex", properties.ssmc_index);
+            }
+            CaseNode::Manuals(manuals) => {
+                for entry in &manuals.entries {
+                    children.push("ManualEntry", CaseNode::ManualEntry(entry));
+                }
+            }
+            CaseNode::ManualEntry(entry) => {
+                children.uint("EntryUID", entry.entry_uid);
+                children.text("Region", &entry.region);
+                children.texts("Language", &entry.languages);
+                children.uint("PageCount", entry.page_count);
+                children.text("Revision", &entry.revision);
+                children.text("ManualFormat", &entry.manual_format);
+                children.binary("ManualData", &entry.manual_data);
+            }
+            CaseNode::RomHacks(rom_hacks) => {
+                for entry in &rom_hacks.entries {
+                    children.push(
+                        "RomHackEntry",
+                        CaseNode::RomHackEntry(entry),
+                    );
+                }
+            }
+            CaseNode::RomHackEntry(entry) => {
+                children.uint("EntryUID", entry.entry_uid);
+                children.text("Title", &entry.title);
+                children.texts("Developer", &entry.developers);
+                children.opt_text("HackVersion", &entry.hack_version);
+                children.opt_text("PatchFormat", &entry.patch_format);
+                for hash in &entry.target_hashes {
+                    children.push("TargetHash", CaseNode::TargetHash(hash));
+                }
+                children.opt_text("HackDescription", &entry.hack_description);
+                children.binary("PatchData", &entry.patch_data);
+            }
+            CaseNode::TargetHash(hash) => {
+                children.text("HashFormat", &hash.hash_format);
+                children.binary("HashValue", &hash.hash_value);
+            }
+            CaseNode::Media(media) => {
+                for collection in &media.image_collections {
+                    children.push(
+                        "ImageCollection",
+                        CaseNode::ImageCollection(collection),
+                    );
+                }
+                for collection in &media.video_collections {
+                    children.push(
+                        "VideoCollection",
+                        CaseNode::VideoCollection(collection),
+                    );
+                }
+            }
+            CaseNode::ImageCollection(collection) => {
+                children.text("Category", &collection.category);
+                for entry in &collection.entries {
+                    children.push("ImageEntry", CaseNode::ImageEntry(entry));
+                }
+            }
+            CaseNode::ImageEntry(entry) => {
+                children.uint("EntryUID", entry.entry_uid);
+                children.opt_text("ImageTitle", &entry.image_title);
+                children.texts("Artist", &entry.artists);
+                children.opt_text("Region", &entry.region);
+                children.text("ImageFormat", &entry.image_format);
+                children.uint("Width", entry.width);
+                children.uint("Height", entry.height);
+                children.opt_text("CategoryDetail", &entry.category_detail);
+                children.binary("ImageData", &entry.image_data);
+            }
+            CaseNode::VideoCollection(collection) => {
+                children.text("Category", &collection.category);
+                for entry in &collection.entries {
+                    children.push("VideoEntry", CaseNode::VideoEntry(entry));
+                }
+            }
+            CaseNode::VideoEntry(entry) => {
+                children.uint("EntryUID", entry.entry_uid);
+                children.opt_text("VideoTitle", &entry.video_title);
+                children.texts("Language", &entry.languages);
+                children.uint("Duration", entry.duration);
+                children.text("VideoFormat", &entry.video_format);
+                children.uint("Width", entry.width);
+                children.uint("Height", entry.height);
+                children.text("ImageFormat", &entry.image_format);
+                children.opt_binary("Thumbnail", &entry.thumbnail);
+                children.binary("VideoData", &entry.video_data);
+            }
+            CaseNode::Text(_)
+            | CaseNode::Uint(_)
+            | CaseNode::Binary(_)
+            | CaseNode::Date(_) => {}
+        }
+
+        children.0
+    }
+}
+
+/// Collects the children of a `CaseNode`.
+#[derive(Default)]
+struct Children<'a>(Vec<(&'static str, CaseNode<'a>)>);
+
+impl<'a> Children<'a> {
+    fn push(&mut self, name: &'static str, node: CaseNode<'a>) {
+        self.0.push((name, node));
+    }
+
+    fn text(&mut self, name: &'static str, value: &'a str) {
+        self.push(name, CaseNode::Text(value));
+    }
+
+    fn opt_text(&mut self, name: &'static str, value: &'a Option<String>) {
+        if let Some(value) = value {
+            self.text(name, value);
+        }
+    }
+
+    fn texts(&mut self, name: &'static str, values: &'a [String]) {
+        for value in values {
+            self.text(name, value);
+        }
+    }
+
+    fn uint(&mut self, name: &'static str, value: u64) {
+        self.push(name, CaseNode::Uint(value));
+    }
+
+    fn opt_uint(&mut self, name: &'static str, value: Option<u64>) {
+        if let Some(value) = value {
+            self.uint(name, value);
+        }
+    }
+
+    fn binary(&mut self, name: &'static str, value: &'a [u8]) {
+        self.push(name, CaseNode::Binary(value));
+    }
+
+    fn opt_binary(&mut self, name: &'static str, value: &'a Option<Vec<u8>>) {
+        if let Some(value) = value {
+            self.binary(name, value);
+        }
+    }
+
+    fn properties(&mut self, properties: &'a FileProperties) {
+        self.push("FileProperties", CaseNode::FileProperties(properties));
+    }
+}
+
+/// Selects every element of `game_case` matching the path expression
+/// `path`, see [`CasePath`] for the syntax.
+///
+/// # Returns
+///
+/// A `Result` containing the selected elements in file order, or
+/// `LibError::InvalidQuery` if `path` could not be parsed.
+pub fn select<'a>(
+    game_case: &'a GameCase,
+    path: &str,
+) -> Result<Vec<CaseNode<'a>>, LibError> {
+    Ok(CasePath::parse(path)?.select(game_case))
+}
+
+/// Parses the text of a `CasePath`.
+struct Parser<'t> {
+    text: &'t str,
+    position: usize,
+}
+
+impl<'t> Parser<'t> {
+    fn at_end(&self) -> bool {
+        self.position >= self.text.len()
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.text[self.position..].chars().next()
+    }
+
+    fn eat(&mut self, expected: char) -> bool {
+        if self.peek() == Some(expected) {
+            self.position += expected.len_utf8();
+            return true;
+        }
+        false
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), LibError> {
+        match self.eat(expected) {
+            true => Ok(()),
+            false => Err(self.error(match expected {
+                '/' => "expected '/'",
+                ']' => "expected ']'",
+                _ => "expected '\"'",
+            })),
+        }
+    }
+
+    fn error(&self, reason: &'static str) -> LibError {
+        LibError::InvalidQuery { position: self.position, reason }
+    }
+
+    /// Reads a run of characters allowed by `allowed`.
+    fn take_while(&mut self, allowed: impl Fn(char) -> bool) -> &'t str {
+        let start = self.position;
+        while let Some(next) = self.peek().filter(|next| allowed(*next)) {
+            self.position += next.len_utf8();
+        }
+        &self.text[start..self.position]
+    }
+
+    fn name(&mut self) -> Result<String, LibError> {
+        let name = self.take_while(|c| c.is_ascii_alphanumeric() || c == '_');
+        match name.is_empty() {
+            true => Err(self.error("expected an element name")),
+            false => Ok(name.to_string()),
+        }
+    }
+
+    fn step(&mut self) -> Result<Step, LibError> {
+        let name = match self.eat('*') {
+            true => "*".to_string(),
+            false => self.name()?,
+        };
+
+        let mut predicates = Vec::new();
+        while self.eat('[') {
+            predicates.push(self.predicate()?);
+            self.expect(']')?;
+        }
+
+        Ok(Step { name, predicates })
+    }
+
+    fn predicate(&mut self) -> Result<Predicate, LibError> {
+        let digits = self.take_while(|c| c.is_ascii_digit());
+        if !digits.is_empty() {
+            return digits
+                .parse()
+                .map(Predicate::Position)
+                .map_err(|_| self.error("position is too large"));
+        }
+
+        let name = self.name()?;
+        if self.eat('=') {
+            return Ok(Predicate::Equals(name, self.value()?));
+        }
+        if self.eat('!') {
+            if !self.eat('=') {
+                return Err(self.error("expected '='"));
+            }
+            return Ok(Predicate::NotEquals(name, self.value()?));
+        }
+
+        Ok(Predicate::Exists(name))
+    }
+
+    /// Reads a quoted string, in which `\"` and `\\` are escapes, or a
+    /// bare value such as a number.
+    fn value(&mut self) -> Result<String, LibError> {
+        if !self.eat('"') {
+            let value = self.take_while(|c| {
+                c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.')
+            });
+            return match value.is_empty() {
+                true => Err(self.error("expected a value")),
+                false => Ok(value.to_string()),
+            };
+        }
+
+        let mut value = String::new();
+        loop {
+            match self.peek() {
+                None => return Err(self.error("unterminated string")),
+                Some('"') => break,
+                Some('\\') => {
+                    self.position += 1;
+                    match self.peek() {
+                        Some(escaped @ ('"' | '\\')) => {
+                            value.push(escaped);
+                            self.position += 1;
+                        }
+                        _ => return Err(self.error("invalid escape")),
+                    }
+                }
+                Some(next) => {
+                    value.push(next);
+                    self.position += next.len_utf8();
+                }
+            }
+        }
+        self.expect('"')?;
+
+        Ok(value)
+    }
+}
+
+/// Formats `data` as lowercase hex.
+fn hex(data: &[u8]) -> String {
+    data.iter().fold(String::new(), |mut text, byte| {
+        let _ = write!(text, "{byte:02x}");
+        text
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::read::GameCaseReader;
+    use crate::write::tests::{encode_case, sample_case};
+
+    const FRONT_BOX_ART: &str = concat!(
+        r#"Media/ImageCollection[Category="BoxArt"]"#,
+        r#"/ImageEntry[CategoryDetail="Front"]"#,
+    );
+
+    //The sample case with a front and a back box art.
+    fn case_with_box_art() -> GameCase {
+        let mut game_case = sample_case();
+        let media = game_case.media.as_mut().unwrap();
+        let entries = &mut media.image_collections[0].entries;
+        entries[0].category_detail = Some("Back".to_string());
+        entries.push(ImageEntry {
+            entry_uid: 4,
+            category_detail: Some("Front".to_string()),
+            image_format: "PNG".to_string(),
+            image_data: vec![5; 16],
+            ..Default::default()
+        });
+
+        game_case
+    }
+
+    #[test]
+    fn query_selects_the_front_box_art() {
+        let game_case = case_with_box_art();
+
+        let selected = select(&game_case, FRONT_BOX_ART).unwrap();
+
+        let [CaseNode::ImageEntry(front)] = selected[..] else {
+            panic!("expected the front box art, got {selected:?}");
+        };
+        assert_eq!(front.entry_uid, 4);
+    }
+
+    #[test]
+    fn reader_loads_what_the_query_selects() {
+        let game_case = case_with_box_art();
+        let path = CasePath::parse(FRONT_BOX_ART).unwrap();
+        let mut reader =
+            GameCaseReader::open(Cursor::new(encode_case(&game_case)))
+                .unwrap();
+
+        let loaded = reader.read_path(&path).unwrap();
+
+        //The back box art is left out through the Index.
+        let media = loaded.media.as_ref().unwrap();
+        let [front] = &media.image_collections[0].entries[..] else {
+            panic!("expected only the front box art to be loaded");
+        };
+        assert_eq!(front.entry_uid, 4);
+        assert_eq!(path.select(&loaded), path.select(&game_case));
+        assert!(loaded.game.game_data.unwrap().entries.is_empty());
+    }
+
+    #[test]
+    fn values_are_selected_below_predicates() {
+        let game_case = sample_case();
+
+        let publishers =
+            select(&game_case, r#"Game/RegionInfo[Region="USA"]/Publisher"#)
+                .unwrap();
+        let nothing =
+            select(&game_case, r#"Game/RegionInfo[Region="Japan"]/Publisher"#)
+                .unwrap();
+
+        assert_eq!(publishers, [CaseNode::Text("Nintendo")]);
+        assert!(nothing.is_empty());
+    }
+
+    #[test]
+    fn invalid_query_points_at_the_error() {
+        assert!(matches!(
+            select(&sample_case(), "Game/RegionInfo[Region=]"),
+            Err(LibError::InvalidQuery { position: 23, .. })
+        ));
+    }
+}
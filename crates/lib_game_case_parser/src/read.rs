@@ -0,0 +1,657 @@
+use std::collections::HashMap;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+use ebml_iterable::specs::{EbmlSpecification, EbmlTag, Master};
+
+use crate::ebml_tools::{read_element_header, ElementHeader, ElementSpan};
+use crate::gc_ebml_spec::{
+    container_ids, element_name, element_path, is_entry_id, master_id,
+    utf8_id, DocTypeVersions, GCEbmlSpec,
+};
+use crate::lib_error_handling::LibError;
+use crate::lib_structs::{
+    Game, GameCase, GameDataEntry, ImageEntry, Index, IndexEntry, ManualEntry,
+    RomHackEntry, VideoEntry,
+};
+use crate::limits::{limit_error, LimitTracker, LimitedReader, ReaderLimits};
+use crate::payload::{
+    load_metadata_with_limits, locate_payloads, PayloadLocation, PayloadReader,
+};
+use crate::parse::{
+    check_ebml_header, decode_elements, parent_path, read_game,
+    read_game_case, read_game_data_entry, read_image_entry, read_index,
+    read_manual_entry, read_rom_hack_entry, read_video_entry, ElementNode,
+};
+use crate::query::CasePath;
+use crate::verify::{verify_files, FileVerification};
+
+/// Elements `GameCaseReader::read_path` always loads, as a case cannot be
+/// decoded without them: `Index`, `Title` and `GameSystem`.
+fn required_ids() -> [u64; 3] {
+    [
+        master_id(GCEbmlSpec::Index),
+        utf8_id(GCEbmlSpec::Title),
+        utf8_id(GCEbmlSpec::GameSystem),
+    ]
+}
+
+/// Returns the absolute offset of the element `entry` points at within a
+/// case whose `GameCase` starts at `game_case_start`.
+///
+/// # Returns
+///
+/// A `Result` containing the offset, or `LibError::EntryNotFound` if the
+/// `IndexPosition` points past the largest possible file.
+pub(crate) fn entry_offset(
+    game_case_start: u64,
+    entry: &IndexEntry,
+) -> Result<u64, LibError> {
+    game_case_start
+        .checked_add(entry.position)
+        .ok_or(LibError::EntryNotFound(entry.uid))
+}
+
+/// A single entry loaded through the `Index`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CaseEntry {
+    GameData(Box<GameDataEntry>),
+    Manual(ManualEntry),
+    RomHack(RomHackEntry),
+    Image(ImageEntry),
+    Video(VideoEntry),
+}
+
+impl CaseEntry {
+    /// Returns the `EntryUID` of the wrapped entry.
+    pub fn entry_uid(&self) -> u64 {
+        match self {
+            CaseEntry::GameData(entry) => entry.entry_uid,
+            CaseEntry::Manual(entry) => entry.entry_uid,
+            CaseEntry::RomHack(entry) => entry.entry_uid,
+            CaseEntry::Image(entry) => entry.entry_uid,
+            CaseEntry::Video(entry) => entry.entry_uid,
+        }
+    }
+}
+
+/// Criteria for selecting entries from the `Index`.
+///
+/// Every field that is set has to match for an entry to be selected, unset
+/// fields match anything.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct IndexQuery {
+    pub uid: Option<u64>,
+    /// Element ID of the target, e.g. the ID of `ImageEntry`.
+    pub target_id: Option<u64>,
+    /// Content type, e.g. "Manual" or "BoxArt".
+    pub target: Option<String>,
+    pub category: Option<String>,
+    pub region: Option<String>,
+}
+
+impl IndexQuery {
+    /// Checks whether `entry` satisfies every criterion that is set.
+    pub fn matches(&self, entry: &IndexEntry) -> bool {
+        self.uid.is_none_or(|uid| uid == entry.uid)
+            && self.target_id.is_none_or(|id| id == entry.target_id)
+            && self.target.as_ref().is_none_or(|target| *target == entry.target)
+            && (self.category.is_none() || self.category == entry.category)
+            && (self.region.is_none() || self.region == entry.region)
+    }
+}
+
+/// Random access reader for `.gcase` files.
+///
+/// Opening a case only reads the EBML header and the `Index`. Individual
+/// entries are loaded on demand by seeking straight to the position stored
+/// in the `Index`, so large cases never have to be scanned as a whole.
+pub struct GameCaseReader<R: Read + Seek> {
+    source: R,
+    game_case_start: u64,
+    index: Index,
+    doc_type_versions: DocTypeVersions,
+    limits: ReaderLimits,
+}
+
+impl<R: Read + Seek> GameCaseReader<R> {
+    /// Opens a case by reading its header and `Index`.
+    ///
+    /// # Arguments
+    ///
+    /// * `source`: A seekable reader positioned at the start of the file.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the reader, or a `LibError` if the file does
+    /// not start with an EBML header followed by a `GameCase` whose first
+    /// child is the `Index`.
+    pub fn open(source: R) -> Result<Self, LibError> {
+        GameCaseReader::open_with_limits(source, ReaderLimits::default())
+    }
+
+    /// Opens a case by reading its header and `Index`, checking every
+    /// element loaded through the reader against `limits`.
+    ///
+    /// # Arguments
+    ///
+    /// * `source`: A seekable reader positioned at the start of the file.
+    /// * `limits`: The limits every loaded element is checked against.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the reader, or a `LibError` if the header or
+    /// `Index` break a limit or the file is not laid out as `open`
+    /// expects.
+    pub fn open_with_limits(
+        mut source: R,
+        limits: ReaderLimits,
+    ) -> Result<Self, LibError> {
+        let start = source.stream_position()?;
+        let header = load_element_with_limits(
+            &mut source,
+            start,
+            master_id(GCEbmlSpec::EBML),
+            limits,
+        )?;
+        let doc_type_versions = check_ebml_header(&header)?;
+
+        let game_case_start = source.stream_position()?;
+        expect_header(&mut source, master_id(GCEbmlSpec::GameCase))?;
+
+        let index_start = source.stream_position()?;
+        let index = read_index(load_element_with_limits(
+            &mut source,
+            index_start,
+            master_id(GCEbmlSpec::Index),
+            limits,
+        )?);
+
+        Ok(GameCaseReader {
+            source,
+            game_case_start,
+            index,
+            doc_type_versions,
+            limits,
+        })
+    }
+
+    /// Returns the `Index` read when the case was opened.
+    pub fn index(&self) -> &Index {
+        &self.index
+    }
+
+    /// Returns the `DocTypeVersion` and `DocTypeReadVersion` declared by
+    /// the file.
+    ///
+    /// A `version` newer than `DOC_TYPE_VERSION` means the file may hold
+    /// elements this library does not know.
+    pub fn doc_type_versions(&self) -> DocTypeVersions {
+        self.doc_type_versions
+    }
+
+    /// Returns the absolute offset of the `GameCase` element, which every
+    /// `IndexPosition` is relative to.
+    pub fn game_case_start(&self) -> u64 {
+        self.game_case_start
+    }
+
+    /// Returns every index entry matching `query`, in index order.
+    pub fn find<'a>(
+        &'a self,
+        query: &'a IndexQuery,
+    ) -> impl Iterator<Item = &'a IndexEntry> + 'a {
+        self.index.entries.iter().filter(|entry| query.matches(entry))
+    }
+
+    /// Loads the entry an index entry points to.
+    pub fn read_entry(
+        &mut self,
+        entry: &IndexEntry
+    ) -> Result<CaseEntry, LibError> {
+        let node = load_element_with_limits(
+            &mut self.source,
+            entry_offset(self.game_case_start, entry)?,
+            entry.target_id,
+            self.limits,
+        )?;
+
+        decode_entry(node)
+    }
+
+    /// Loads the entry with the given `EntryUID`.
+    pub fn read_by_uid(&mut self, uid: u64) -> Result<CaseEntry, LibError> {
+        let entry = self.index.entries
+            .iter()
+            .find(|entry| entry.uid == uid)
+            .cloned()
+            .ok_or(LibError::EntryNotFound(uid))?;
+
+        self.read_entry(&entry)
+    }
+
+    /// Loads the first entry matching `query`.
+    ///
+    /// # Returns
+    ///
+    /// `Ok(None)` if no index entry matches the query.
+    pub fn read_first(
+        &mut self,
+        query: &IndexQuery
+    ) -> Result<Option<CaseEntry>, LibError> {
+        let entry = self.find(query).next().cloned();
+
+        entry.map(|entry| self.read_entry(&entry)).transpose()
+    }
+
+    /// Loads the `Game` metadata, such as the title and developers.
+    ///
+    /// `GameData` is skipped, so no ROM is loaded. Its entries are read
+    /// through the `Index` instead and `game_data` is always `None`.
+    pub fn read_game_info(&mut self) -> Result<Game, LibError> {
+        let game_id = master_id(GCEbmlSpec::Game);
+        let game_offset = find_child(
+            &mut self.source,
+            self.game_case_start,
+            master_id(GCEbmlSpec::GameCase),
+            game_id,
+        )?
+        .ok_or_else(|| LibError::MissingElement {
+            element: "Game",
+            offset: self.game_case_start,
+            path: element_path(master_id(GCEbmlSpec::GameCase)),
+        })?;
+
+        self.source.seek(SeekFrom::Start(game_offset))?;
+        let header = expect_header(&mut self.source, game_id)?;
+        let mut walker = ElementSpan::new(game_offset, header).children();
+        let mut children = Vec::new();
+
+        while let Some(child) = walker.seek_next(&mut self.source)? {
+            if child.header.id != master_id(GCEbmlSpec::GameData) {
+                children.push(load_element_with_limits(
+                    &mut self.source,
+                    child.offset,
+                    child.header.id,
+                    self.limits,
+                )?);
+            }
+        }
+
+        read_game(ElementNode {
+            tag: GCEbmlSpec::Game(Master::Start),
+            offset: game_offset,
+            path: element_path(game_id),
+            children,
+        })
+    }
+
+    /// Loads the parts of the case `path` can select or test, for use with
+    /// [`CasePath::select`].
+    ///
+    /// Only element headers are read for the rest of the case. Entries are
+    /// looked up through the `Index`, so a path such as
+    /// `Manuals/ManualEntry[Region="Japan"]` loads nothing but the
+    /// Japanese manuals, and payloads are only loaded when the path reaches
+    /// into them. Entries missing from the `Index` are not found when the
+    /// path narrows them down, see `repair_index`.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing a case that gives the same result for `path`
+    /// as the complete case would, or a `LibError` if the file could not
+    /// be read.
+    pub fn read_path(&mut self, path: &CasePath) -> Result<GameCase, LibError> {
+        self.source.seek(SeekFrom::Start(self.game_case_start))?;
+        let header =
+            expect_header(&mut self.source, master_id(GCEbmlSpec::GameCase))?;
+
+        let game_case = ElementSpan::new(self.game_case_start, header);
+        read_game_case(self.load_reachable(path, game_case, 0, true)?)
+    }
+
+    /// Loads a container master with the children `path` can reach.
+    ///
+    /// # Arguments
+    ///
+    /// * `depth`: Depth of the children below `GameCase`.
+    /// * `followed`: Whether the path leads to the master itself.
+    fn load_reachable(
+        &mut self,
+        path: &CasePath,
+        element: ElementSpan,
+        depth: usize,
+        followed: bool,
+    ) -> Result<ElementNode, LibError> {
+        let ElementSpan { offset, header, .. } = element;
+        let mut walker = element.children();
+        let mut children = Vec::new();
+        let mut queries = HashMap::new();
+
+        while let Some(span) = walker.seek_next(&mut self.source)? {
+            let (position, child) = (span.offset, span.header);
+            let name = element_name(child.id);
+            let reached = followed && path.reaches(depth, &name);
+            let follows = followed && path.follows(depth, &name);
+
+            //Masters holding entries are walked through instead of loaded.
+            if container_ids().contains(&child.id) {
+                children.push(self.load_reachable(
+                    path,
+                    span,
+                    depth + 1,
+                    follows,
+                )?);
+            } else if is_entry_id(child.id) {
+                let query = queries
+                    .entry(child.id)
+                    .or_insert_with(|| path.index_query(depth, &name));
+                let indexed = query.as_ref().is_none_or(|query| {
+                    self.find(query).any(|entry| {
+                        self.game_case_start.checked_add(entry.position)
+                            == Some(position)
+                    })
+                });
+
+                if reached && indexed {
+                    children.push(match follows && path.needs_payloads(depth) {
+                        true => load_element_with_limits(
+                            &mut self.source,
+                            position,
+                            child.id,
+                            self.limits,
+                        )?,
+                        false => load_metadata_with_limits(
+                            &mut self.source,
+                            position,
+                            child.id,
+                            self.limits,
+                        )?,
+                    });
+                }
+            } else if reached || required_ids().contains(&child.id) {
+                children.push(load_element_with_limits(
+                    &mut self.source,
+                    position,
+                    child.id,
+                    self.limits,
+                )?);
+            }
+        }
+
+        Ok(ElementNode {
+            tag: GCEbmlSpec::get_master_tag(header.id, Master::Start)
+                .ok_or_else(|| LibError::UnknownElement {
+                    id: header.id,
+                    offset,
+                    path: element_path(header.id),
+                })?,
+            offset,
+            path: element_path(header.id),
+            children,
+        })
+    }
+
+    /// Lists the binary payloads stored within an indexed entry.
+    ///
+    /// Only element headers are read, so this is cheap even for entries
+    /// holding several gigabytes of data.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the payload locations in file order, e.g. one
+    /// `CHDBinary` per disc of a CHD entry.
+    pub fn payloads(
+        &mut self,
+        entry: &IndexEntry
+    ) -> Result<Vec<PayloadLocation>, LibError> {
+        locate_payloads(
+            &mut self.source,
+            entry_offset(self.game_case_start, entry)?,
+            entry.target_id,
+        )
+    }
+
+    /// Opens a bounded reader over a single payload.
+    ///
+    /// The payload is streamed from the source as it is read and is never
+    /// buffered as a whole.
+    pub fn open_payload(
+        &mut self,
+        payload: &PayloadLocation
+    ) -> Result<PayloadReader<'_, R>, LibError> {
+        PayloadReader::open(&mut self.source, payload)
+    }
+
+    /// Copies a single payload to `dest`.
+    ///
+    /// # Arguments
+    ///
+    /// * `payload`: The payload to copy.
+    /// * `dest`: Where the payload is written to.
+    /// * `progress`: Called with the number of bytes copied so far and the
+    ///   total size of the payload.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the number of bytes copied, or a `LibError` if
+    /// the source ends before the payload does.
+    pub fn copy_payload<W, F>(
+        &mut self,
+        payload: &PayloadLocation,
+        dest: &mut W,
+        progress: F,
+    ) -> Result<u64, LibError>
+    where
+        W: Write,
+        F: FnMut(u64, u64),
+    {
+        let mut reader = self.open_payload(payload)?.with_progress(progress);
+        let copied = io::copy(&mut reader, dest)?;
+
+        if copied != payload.size {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+
+        Ok(copied)
+    }
+
+    /// Checks every stored file against the digests in its
+    /// `FileProperties`.
+    ///
+    /// Payloads are streamed through the hashers, so memory use does not
+    /// depend on the size of the files. Only the digests present in the
+    /// file are computed.
+    ///
+    /// # Arguments
+    ///
+    /// * `progress`: Called with the number of bytes hashed so far and the
+    ///   total number of bytes to hash.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing one `FileVerification` per stored file, in
+    /// file order.
+    pub fn verify<F>(
+        &mut self,
+        progress: F
+    ) -> Result<Vec<FileVerification>, LibError>
+    where
+        F: FnMut(u64, u64),
+    {
+        verify_files(&mut self.source, self.game_case_start, progress)
+    }
+
+    /// Consumes the reader and returns the underlying source.
+    pub fn into_inner(self) -> R {
+        self.source
+    }
+}
+
+/// Reads an element header and checks that it has the expected ID.
+pub(crate) fn expect_header<R: Read + Seek>(
+    source: &mut R,
+    expected: u64,
+) -> Result<ElementHeader, LibError> {
+    let offset = source.stream_position()?;
+    let header = read_element_header(source)?
+        .ok_or(io::Error::from(io::ErrorKind::UnexpectedEof))?;
+
+    if header.id != expected {
+        return Err(LibError::UnexpectedElement {
+            expected,
+            found: header.id,
+            offset,
+            path: element_path(expected),
+        });
+    }
+
+    Ok(header)
+}
+
+/// Finds the first child with the ID `id` of the element at `offset`.
+///
+/// # Returns
+///
+/// A `Result` containing the absolute offset of the child, or `None` if
+/// the element has no such child.
+pub(crate) fn find_child<R: Read + Seek>(
+    source: &mut R,
+    offset: u64,
+    expected: u64,
+    id: u64,
+) -> Result<Option<u64>, LibError> {
+    source.seek(SeekFrom::Start(offset))?;
+    let header = expect_header(source, expected)?;
+    let mut walker = ElementSpan::new(offset, header).children();
+
+    while let Some(child) = walker.seek_next(source)? {
+        if child.header.id == id {
+            return Ok(Some(child.offset));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Reads and decodes the complete element starting at `offset`, checking
+/// it against the default `ReaderLimits`.
+pub(crate) fn load_element<R: Read + Seek>(
+    source: &mut R,
+    offset: u64,
+    expected: u64,
+) -> Result<ElementNode, LibError> {
+    load_element_with_limits(source, offset, expected, ReaderLimits::default())
+}
+
+/// Reads and decodes the complete element starting at `offset`.
+///
+/// The element is checked against `limits` while it is read, and memory
+/// grows with the bytes actually read rather than the size the element
+/// claims.
+pub(crate) fn load_element_with_limits<R: Read + Seek>(
+    source: &mut R,
+    offset: u64,
+    expected: u64,
+    limits: ReaderLimits,
+) -> Result<ElementNode, LibError> {
+    source.seek(SeekFrom::Start(offset))?;
+    let header = expect_header(source, expected)?;
+
+    let tracker = LimitTracker::new(limits, offset, &parent_path(expected));
+    let mut bytes = Vec::new();
+    source.seek(SeekFrom::Start(offset))?;
+    LimitedReader::new(source.take(header.total_len()), tracker)
+        .read_to_end(&mut bytes)
+        .map_err(limit_error)?;
+    if (bytes.len() as u64) < header.total_len() {
+        return Err(LibError::Io(io::ErrorKind::UnexpectedEof.into()));
+    }
+
+    decode_elements(&bytes, offset)?
+        .into_iter()
+        .next()
+        .ok_or(LibError::Io(io::ErrorKind::UnexpectedEof.into()))
+}
+
+/// Converts a decoded entry element into its typed form.
+pub(crate) fn decode_entry(node: ElementNode) -> Result<CaseEntry, LibError> {
+    let entry = match node.tag {
+        GCEbmlSpec::GameDataEntry(_) => {
+            CaseEntry::GameData(Box::new(read_game_data_entry(node)?))
+        }
+        GCEbmlSpec::ManualEntry(_) => {
+            CaseEntry::Manual(read_manual_entry(node))
+        }
+        GCEbmlSpec::RomHackEntry(_) => {
+            CaseEntry::RomHack(read_rom_hack_entry(node)?)
+        }
+        GCEbmlSpec::ImageEntry(_) => CaseEntry::Image(read_image_entry(node)),
+        GCEbmlSpec::VideoEntry(_) => CaseEntry::Video(read_video_entry(node)),
+        other => {
+            return Err(LibError::UnexpectedElement {
+                expected: master_id(GCEbmlSpec::ManualEntry),
+                found: other.get_id(),
+                offset: node.offset,
+                path: node.path,
+            })
+        }
+    };
+
+    Ok(entry)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::write::tests::{encode_case, sample_case};
+
+    #[test]
+    fn case_without_entries_opens() {
+        let game_case = GameCase {
+            game: Game { game_data: None, ..sample_case().game },
+            ..Default::default()
+        };
+
+        let mut reader =
+            GameCaseReader::open(Cursor::new(encode_case(&game_case)))
+                .unwrap();
+
+        assert!(reader.index().entries.is_empty());
+        assert_eq!(reader.read_game_info().unwrap().title, "Zelda");
+    }
+
+    #[test]
+    fn entries_are_found_by_uid() {
+        let bytes = encode_case(&sample_case());
+        let mut reader = GameCaseReader::open(Cursor::new(bytes)).unwrap();
+
+        let entry = reader.read_by_uid(2).unwrap();
+        assert!(matches!(entry, CaseEntry::Manual(_)));
+        assert!(matches!(
+            reader.read_by_uid(9),
+            Err(LibError::EntryNotFound(9))
+        ));
+    }
+
+    #[test]
+    fn position_past_any_file_is_not_found() {
+        let bytes = encode_case(&sample_case());
+        let mut reader = GameCaseReader::open(Cursor::new(bytes)).unwrap();
+        let entry = IndexEntry {
+            position: u64::MAX,
+            ..reader.index().entries[0].clone()
+        };
+
+        assert!(matches!(
+            reader.read_entry(&entry),
+            Err(LibError::EntryNotFound(1))
+        ));
+        assert!(matches!(
+            reader.payloads(&entry),
+            Err(LibError::EntryNotFound(1))
+        ));
+    }
+}
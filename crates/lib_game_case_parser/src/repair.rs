@@ -0,0 +1,799 @@
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use crate::ebml_tools::{
+    encode_element_header, encode_void, parse_element_header,
+    read_element_header, ElementSpan, VOID_ID,
+};
+use crate::edit::{list_children, replace_file};
+use crate::gc_ebml_spec::{element_path, master_id, GCEbmlSpec};
+use crate::lib_error_handling::LibError;
+use crate::lib_structs::IndexEntry;
+use crate::parse::{
+    check_ebml_header, read_game_data, read_image_collection, read_index,
+    read_manuals, read_rom_hacks, read_video_collection,
+};
+use crate::payload::load_metadata;
+use crate::read::{expect_header, load_element};
+use crate::write::{
+    encode_index, game_data_target, image_target, locate_entries,
+    manual_target, rom_hack_target, video_target, DEFAULT_GAME_PADDING,
+    DEFAULT_INDEX_PADDING,
+};
+
+/// A difference between the stored `Index` and the entries of a case.
+///
+/// Positions are those of the file as it is, relative to the start of the
+/// `GameCase` element like `IndexPosition`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum IndexMismatch {
+    /// The case has no `Index` at all.
+    MissingIndex,
+    /// An entry no `IndexEntry` points at.
+    Unindexed { actual: IndexEntry },
+    /// An `IndexEntry` whose position or description differs from the
+    /// entry it refers to.
+    Stale { stored: IndexEntry, actual: IndexEntry },
+    /// An `IndexEntry` that does not point at any entry.
+    Dangling { stored: IndexEntry },
+}
+
+impl fmt::Display for IndexMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IndexMismatch::MissingIndex => write!(f, "the Index is missing"),
+            IndexMismatch::Unindexed { actual } => write!(
+                f,
+                "{} UID {} at {} is not indexed",
+                actual.target, actual.uid, actual.position,
+            ),
+            IndexMismatch::Stale { stored, actual }
+                if stored.position != actual.position => write!(
+                f,
+                "{} UID {} is stored at {} but found at {}",
+                actual.target, actual.uid, stored.position, actual.position,
+            ),
+            IndexMismatch::Stale { stored, actual } => write!(
+                f,
+                "{} UID {} at {} is indexed as {:?}, expected {:?}",
+                actual.target, actual.uid, actual.position, stored, actual,
+            ),
+            IndexMismatch::Dangling { stored } => write!(
+                f,
+                "{} UID {} at {} points at no entry",
+                stored.target, stored.uid, stored.position,
+            ),
+        }
+    }
+}
+
+/// Compares the stored `Index` of a case against its entries.
+///
+/// This is the dry run of `repair_index`, nothing is written. Entries are
+/// found by scanning the children of `GameCase` and their payloads are
+/// never read.
+///
+/// # Arguments
+///
+/// * `source`: The case, which must start at the beginning of `source`.
+///
+/// # Returns
+///
+/// A `Result` containing every mismatch found, in the order of the entries
+/// they concern. An empty list means the `Index` is correct.
+pub fn check_index<R: Read + Seek>(
+    source: &mut R,
+) -> Result<Vec<IndexMismatch>, LibError> {
+    let layout = scan_case(source)?;
+
+    Ok(compare(layout.stored.as_deref(), &layout.actual_entries()))
+}
+
+/// Lists the `EntryUID` of every entry of a case and of every stored
+/// `IndexEntry`, so that none of them is handed out again.
+///
+/// # Arguments
+///
+/// * `source`: The case, which must start at the beginning of `source`.
+pub(crate) fn used_uids<R: Read + Seek>(
+    source: &mut R,
+) -> Result<Vec<u64>, LibError> {
+    let layout = scan_case(source)?;
+
+    Ok(layout.children
+        .iter()
+        .flat_map(|child| &child.entries)
+        .map(|(_, target)| target.uid)
+        .chain(layout.stored.iter().flatten().map(|stored| stored.uid))
+        .collect())
+}
+
+/// Writes a copy of a case with a regenerated `Index`.
+///
+/// Every child of `GameCase` other than the `Index` and the `Void` padding
+/// next to it is copied as is, so payloads are streamed rather than loaded.
+/// The new `Index` is written first and followed by the default padding.
+///
+/// # Arguments
+///
+/// * `source`: The case, which must start at the beginning of `source`.
+/// * `dest`: Where the repaired case is written to.
+///
+/// # Returns
+///
+/// A `Result` containing the mismatches of the stored `Index`, as
+/// reported by `check_index`.
+pub fn repair_index<R: Read + Seek, W: Write>(
+    source: &mut R,
+    dest: &mut W,
+) -> Result<Vec<IndexMismatch>, LibError> {
+    rebuild_case(source, dest, &CaseChanges::default())
+}
+
+/// Changes made to a case while `rebuild_case` copies it.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct CaseChanges {
+    /// Replaces every child of `Game` in front of `GameData`, and is
+    /// followed by the default padding.
+    pub game_metadata: Option<Vec<u8>>,
+    pub addition: Option<Addition>,
+}
+
+/// A new entry added by `rebuild_case`.
+#[derive(Clone, Debug)]
+pub(crate) struct Addition {
+    /// A `Manuals`, `RomHacks` or `Media` element holding only the entry,
+    /// as encoded by `encode_extras`. It is merged into the element of the
+    /// case with the same ID, if there is one.
+    pub bytes: Vec<u8>,
+    /// Category of the collection holding the entry, for images and
+    /// videos. The entry joins the collection of the case with the same
+    /// category, if there is one.
+    pub category: Option<String>,
+    /// The index entry of the new entry, with a zero position.
+    pub target: IndexEntry,
+}
+
+/// A part of a rebuilt `GameCase`.
+enum Piece {
+    /// Bytes copied from the source as they are.
+    Copy { offset: u64, len: u64 },
+    /// Newly encoded bytes holding no entry.
+    Bytes(Vec<u8>),
+    /// Newly encoded bytes holding the added entry.
+    Added(Vec<u8>),
+}
+
+impl Piece {
+    fn len(&self) -> u64 {
+        match self {
+            Piece::Copy { len, .. } => *len,
+            Piece::Bytes(bytes) | Piece::Added(bytes) => bytes.len() as u64,
+        }
+    }
+}
+
+/// Writes a copy of a case with `changes` applied and a regenerated
+/// `Index`, see `repair_index`.
+///
+/// Only the `Index`, the metadata of `Game` and the masters an added entry
+/// is merged into are encoded anew. Everything else, payloads included,
+/// is copied from `source` without being loaded, so cases of any size can
+/// be rewritten.
+///
+/// # Returns
+///
+/// A `Result` containing the mismatches of the stored `Index`.
+pub(crate) fn rebuild_case<R: Read + Seek, W: Write>(
+    source: &mut R,
+    dest: &mut W,
+    changes: &CaseChanges,
+) -> Result<Vec<IndexMismatch>, LibError> {
+    let layout = scan_case(source)?;
+    let mismatches =
+        compare(layout.stored.as_deref(), &layout.actual_entries());
+
+    let mut pieces = vec![Piece::Bytes(encode_void(DEFAULT_INDEX_PADDING))];
+    let mut addition = changes.addition.as_ref();
+
+    for child in &layout.children {
+        match child.id {
+            id if id == master_id(GCEbmlSpec::Index) || id == VOID_ID => {}
+            id if id == master_id(GCEbmlSpec::Game) => {
+                match &changes.game_metadata {
+                    Some(metadata) => pieces.extend(
+                        game_pieces(source, child.offset, metadata)?
+                    ),
+                    None => pieces.push(Piece::Copy {
+                        offset: child.offset,
+                        len: child.len,
+                    }),
+                }
+            }
+            id => match addition.filter(|addition| {
+                parse_element_header(&addition.bytes)
+                    .is_some_and(|header| header.id == id)
+            }) {
+                Some(merged) => {
+                    pieces.extend(merge(
+                        source,
+                        child.offset,
+                        &merged.bytes,
+                        merged.category.as_deref(),
+                    )?);
+                    addition = None;
+                }
+                None => pieces.push(Piece::Copy {
+                    offset: child.offset,
+                    len: child.len,
+                }),
+            },
+        }
+    }
+    if let Some(addition) = addition {
+        pieces.push(Piece::Added(addition.bytes.clone()));
+    }
+
+    //Entries keep their order, so they are located piece by piece.
+    let entries: Vec<&(u64, IndexEntry)> = layout.children
+        .iter()
+        .flat_map(|child| &child.entries)
+        .collect();
+    let mut body_len = 0;
+    let mut targets = Vec::new();
+    let mut entry_offsets = Vec::new();
+
+    for piece in &pieces {
+        match piece {
+            Piece::Copy { offset, len } => {
+                for (entry, target) in entries.iter().copied() {
+                    if (*offset..offset + len).contains(entry) {
+                        targets.push(target.clone());
+                        entry_offsets.push(body_len + (entry - offset));
+                    }
+                }
+            }
+            Piece::Added(bytes) => {
+                let before = entry_offsets.len();
+                locate_entries(bytes, body_len, &mut entry_offsets);
+                if let Some(addition) = &changes.addition {
+                    targets.extend(
+                        (before..entry_offsets.len())
+                            .map(|_| addition.target.clone()),
+                    );
+                }
+            }
+            Piece::Bytes(_) => {}
+        }
+        body_len += piece.len();
+    }
+
+    let index = encode_index(&targets, &entry_offsets)?;
+
+    copy_range(source, dest, 0, layout.game_case_start)?;
+    dest.write_all(&encode_element_header(
+        master_id(GCEbmlSpec::GameCase),
+        index.len() as u64 + body_len,
+    ))?;
+    dest.write_all(&index)?;
+    for piece in &pieces {
+        match piece {
+            Piece::Copy { offset, len } => {
+                copy_range(source, dest, *offset, *len)?
+            }
+            Piece::Bytes(bytes) | Piece::Added(bytes) => {
+                dest.write_all(bytes)?
+            }
+        }
+    }
+
+    //Anything following the GameCase element is kept as well.
+    let file_end = source.seek(SeekFrom::End(0))?;
+    copy_range(
+        source,
+        dest,
+        layout.game_case_end,
+        file_end - layout.game_case_end,
+    )?;
+    dest.flush()?;
+
+    Ok(mismatches)
+}
+
+/// Returns the pieces of the `Game` at `offset` with its metadata replaced
+/// by `metadata` and the default padding. `GameData` and whatever follows
+/// it are copied.
+fn game_pieces<R: Read + Seek>(
+    source: &mut R,
+    offset: u64,
+    metadata: &[u8],
+) -> Result<Vec<Piece>, LibError> {
+    source.seek(SeekFrom::Start(offset))?;
+    let header = expect_header(source, master_id(GCEbmlSpec::Game))?;
+    let span = ElementSpan::new(offset, header);
+    let game_end = offset + header.total_len();
+    let game_data_start = list_children(source, span)?
+        .into_iter()
+        .find(|(_, id, _)| *id == master_id(GCEbmlSpec::GameData))
+        .map_or(game_end, |(child, _, _)| child);
+
+    let mut pieces = vec![
+        Piece::Bytes(metadata.to_vec()),
+        Piece::Bytes(encode_void(DEFAULT_GAME_PADDING)),
+        Piece::Copy {
+            offset: game_data_start,
+            len: game_end - game_data_start,
+        },
+    ];
+    let data_len = pieces.iter().map(Piece::len).sum();
+    pieces.insert(0, Piece::Bytes(encode_element_header(header.id, data_len)));
+
+    Ok(pieces)
+}
+
+/// Returns the pieces of the master at `offset` with the children of the
+/// encoded master `addition` added to it.
+///
+/// A collection within `addition` is merged into the collection of the
+/// master with the same ID and `category` in turn, any other child is
+/// added behind the children of the master.
+fn merge<R: Read + Seek>(
+    source: &mut R,
+    offset: u64,
+    addition: &[u8],
+    category: Option<&str>,
+) -> Result<Vec<Piece>, LibError> {
+    source.seek(SeekFrom::Start(offset))?;
+    let header = read_element_header(source)?
+        .ok_or(io::Error::from(io::ErrorKind::UnexpectedEof))?;
+    let children = list_children(source, ElementSpan::new(offset, header))?;
+
+    let mut merged = vec![None; children.len()];
+    let mut added = Vec::new();
+    let mut rest = parse_element_header(addition)
+        .map_or(&[][..], |added| &addition[added.header_len as usize..]);
+
+    while let Some(child) = parse_element_header(rest) {
+        let (bytes, next) = rest.split_at(child.total_len() as usize);
+        let mut found = None;
+        if is_collection(child.id) {
+            for (i, (offset, id, _)) in children.iter().enumerate() {
+                if *id == child.id
+                    && merged[i].is_none()
+                    && Some(collection_category(source, *offset, *id)?)
+                        .as_deref() == category
+                {
+                    found = Some(i);
+                    break;
+                }
+            }
+        }
+
+        match found {
+            Some(i) => merged[i] = Some(bytes),
+            None => added.push(Piece::Added(bytes.to_vec())),
+        }
+        rest = next;
+    }
+
+    let mut pieces = Vec::new();
+    for ((offset, _, len), merged) in children.into_iter().zip(merged) {
+        match merged {
+            Some(bytes) => {
+                pieces.extend(merge(source, offset, bytes, category)?)
+            }
+            None => pieces.push(Piece::Copy { offset, len }),
+        }
+    }
+    pieces.extend(added);
+
+    let data_len = pieces.iter().map(Piece::len).sum();
+    pieces.insert(0, Piece::Bytes(encode_element_header(header.id, data_len)));
+
+    Ok(pieces)
+}
+
+fn is_collection(id: u64) -> bool {
+    id == master_id(GCEbmlSpec::ImageCollection)
+        || id == master_id(GCEbmlSpec::VideoCollection)
+}
+
+/// Returns the category of the `ImageCollection` or `VideoCollection` at
+/// `offset`.
+fn collection_category<R: Read + Seek>(
+    source: &mut R,
+    offset: u64,
+    id: u64,
+) -> Result<String, LibError> {
+    let collection = load_metadata(source, offset, id)?;
+
+    Ok(match id {
+        id if id == master_id(GCEbmlSpec::ImageCollection) => {
+            read_image_collection(collection).category
+        }
+        _ => read_video_collection(collection).category,
+    })
+}
+
+/// Checks the `Index` of the case at `path` and repairs it if needed.
+///
+/// The repaired case is written to a temporary file next to the original,
+/// which it then replaces. Nothing is written if the `Index` is correct.
+///
+/// # Arguments
+///
+/// * `path`: The `.gcase` file to repair.
+/// * `dry_run`: Only report the mismatches without changing the file.
+///
+/// # Returns
+///
+/// A `Result` containing the mismatches found in the stored `Index`.
+pub fn repair_index_file(
+    path: &Path,
+    dry_run: bool,
+) -> Result<Vec<IndexMismatch>, LibError> {
+    let mut source = BufReader::new(File::open(path)?);
+    let mismatches = check_index(&mut source)?;
+    if dry_run || mismatches.is_empty() {
+        return Ok(mismatches);
+    }
+
+    replace_file(path, |dest| {
+        let mut dest = BufWriter::new(dest);
+        repair_index(&mut source, &mut dest)?;
+        dest.flush()?;
+        Ok(())
+    })?;
+
+    Ok(mismatches)
+}
+
+/// A child of `GameCase` and the entries found within it.
+struct CaseChild {
+    offset: u64,
+    id: u64,
+    len: u64,
+    /// Absolute offset and index entry, with a zero position, of every
+    /// entry within the child.
+    entries: Vec<(u64, IndexEntry)>,
+}
+
+/// The children of `GameCase` and the `Index` stored among them.
+struct CaseLayout {
+    game_case_start: u64,
+    game_case_end: u64,
+    children: Vec<CaseChild>,
+    stored: Option<Vec<IndexEntry>>,
+}
+
+impl CaseLayout {
+    /// Returns the index entries matching the entries as they are stored.
+    fn actual_entries(&self) -> Vec<IndexEntry> {
+        self.children
+            .iter()
+            .flat_map(|child| &child.entries)
+            .map(|(offset, target)| IndexEntry {
+                position: offset - self.game_case_start,
+                ..target.clone()
+            })
+            .collect()
+    }
+}
+
+/// Reads the EBML header and lists the children of `GameCase` along with
+/// the entries they hold.
+///
+/// Entries are loaded without their payloads, see `load_metadata`, and a
+/// `GameCase` running past the end of the file is rejected, so a damaged
+/// case is never copied as though it was complete.
+fn scan_case<R: Read + Seek>(source: &mut R) -> Result<CaseLayout, LibError> {
+    let file_len = source.seek(SeekFrom::End(0))?;
+    source.rewind()?;
+    let header = load_element(source, 0, master_id(GCEbmlSpec::EBML))?;
+    check_ebml_header(&header)?;
+
+    let game_case_start = source.stream_position()?;
+    let case_header = expect_header(source, master_id(GCEbmlSpec::GameCase))?;
+    let game_case_end = game_case_start
+        .checked_add(case_header.total_len())
+        .filter(|end| *end <= file_len)
+        .ok_or_else(|| LibError::TruncatedMaster {
+            offset: game_case_start,
+            path: element_path(master_id(GCEbmlSpec::GameCase)),
+        })?;
+
+    let mut children = Vec::new();
+    let mut stored = None;
+
+    let game_case = ElementSpan::new(game_case_start, case_header);
+    for (offset, id, len) in list_children(source, game_case)? {
+        let entries = match id {
+            id if id == master_id(GCEbmlSpec::Index) => {
+                if stored.is_none() {
+                    let index = read_index(load_element(source, offset, id)?);
+                    stored = Some(index.entries);
+                }
+                Vec::new()
+            }
+            id if id == master_id(GCEbmlSpec::Game) => {
+                game_entries(source, offset)?
+            }
+            id if id == master_id(GCEbmlSpec::Manuals) => {
+                let manuals = read_manuals(load_metadata(source, offset, id)?);
+                let entry_id = master_id(GCEbmlSpec::ManualEntry);
+                entry_offsets(source, offset, entry_id)?
+                    .into_iter()
+                    .zip(manuals.entries.iter().map(manual_target))
+                    .collect()
+            }
+            id if id == master_id(GCEbmlSpec::RomHacks) => {
+                let rom_hacks =
+                    read_rom_hacks(load_metadata(source, offset, id)?)?;
+                let entry_id = master_id(GCEbmlSpec::RomHackEntry);
+                entry_offsets(source, offset, entry_id)?
+                    .into_iter()
+                    .zip(rom_hacks.entries.iter().map(rom_hack_target))
+                    .collect()
+            }
+            id if id == master_id(GCEbmlSpec::Media) => {
+                media_entries(source, offset)?
+            }
+            _ => Vec::new(),
+        };
+
+        children.push(CaseChild { offset, id, len, entries });
+    }
+
+    Ok(CaseLayout { game_case_start, game_case_end, children, stored })
+}
+
+/// Lists the `GameDataEntry` elements within the `Game` at `offset`.
+fn game_entries<R: Read + Seek>(
+    source: &mut R,
+    offset: u64,
+) -> Result<Vec<(u64, IndexEntry)>, LibError> {
+    let game_data_id = master_id(GCEbmlSpec::GameData);
+    let mut entries = Vec::new();
+
+    for (child, id, _) in child_headers(source, offset)? {
+        if id != game_data_id {
+            continue;
+        }
+
+        let game_data = read_game_data(load_metadata(source, child, id)?)?;
+        let entry_id = master_id(GCEbmlSpec::GameDataEntry);
+        entries.extend(
+            entry_offsets(source, child, entry_id)?
+                .into_iter()
+                .zip(game_data.entries.iter().map(game_data_target)),
+        );
+    }
+
+    Ok(entries)
+}
+
+/// Lists the `ImageEntry` and `VideoEntry` elements within the `Media` at
+/// `offset`.
+fn media_entries<R: Read + Seek>(
+    source: &mut R,
+    offset: u64,
+) -> Result<Vec<(u64, IndexEntry)>, LibError> {
+    let image_id = master_id(GCEbmlSpec::ImageCollection);
+    let video_id = master_id(GCEbmlSpec::VideoCollection);
+    let mut entries = Vec::new();
+
+    for (child, id, _) in child_headers(source, offset)? {
+        if id == image_id {
+            let collection =
+                read_image_collection(load_metadata(source, child, id)?);
+            let entry_id = master_id(GCEbmlSpec::ImageEntry);
+            entries.extend(
+                entry_offsets(source, child, entry_id)?
+                    .into_iter()
+                    .zip(collection.entries.iter().map(|entry| {
+                        image_target(&collection.category, entry)
+                    })),
+            );
+        } else if id == video_id {
+            let collection =
+                read_video_collection(load_metadata(source, child, id)?);
+            let entry_id = master_id(GCEbmlSpec::VideoEntry);
+            entries.extend(
+                entry_offsets(source, child, entry_id)?
+                    .into_iter()
+                    .zip(collection.entries.iter().map(|entry| {
+                        video_target(&collection.category, entry)
+                    })),
+            );
+        }
+    }
+
+    Ok(entries)
+}
+
+/// Returns the absolute offsets of the children with the ID `entry_id` of
+/// the element at `offset`.
+fn entry_offsets<R: Read + Seek>(
+    source: &mut R,
+    offset: u64,
+    entry_id: u64,
+) -> Result<Vec<u64>, LibError> {
+    Ok(child_headers(source, offset)?
+        .into_iter()
+        .filter(|(_, id, _)| *id == entry_id)
+        .map(|(offset, _, _)| offset)
+        .collect())
+}
+
+/// Lists the children of the master element at `offset` as their offset,
+/// ID and total length.
+fn child_headers<R: Read + Seek>(
+    source: &mut R,
+    offset: u64,
+) -> Result<Vec<(u64, u64, u64)>, LibError> {
+    source.seek(SeekFrom::Start(offset))?;
+    let header = read_element_header(source)?
+        .ok_or(io::Error::from(io::ErrorKind::UnexpectedEof))?;
+
+    list_children(source, ElementSpan::new(offset, header))
+}
+
+/// Pairs the stored index entries with the actual ones.
+///
+/// A stored entry refers to the actual entry with the same `TargetID` and
+/// UID, or failing that to the one at its position.
+fn compare(
+    stored: Option<&[IndexEntry]>,
+    actual: &[IndexEntry],
+) -> Vec<IndexMismatch> {
+    let Some(stored) = stored else {
+        let mut mismatches = vec![IndexMismatch::MissingIndex];
+        mismatches.extend(actual.iter().map(|actual| {
+            IndexMismatch::Unindexed { actual: actual.clone() }
+        }));
+        return mismatches;
+    };
+
+    let mut used = vec![false; stored.len()];
+    let mut mismatches = Vec::new();
+
+    for actual in actual {
+        let matching = |stored: &IndexEntry, by_uid: bool| {
+            stored.target_id == actual.target_id
+                && if by_uid {
+                    stored.uid == actual.uid
+                } else {
+                    stored.position == actual.position
+                }
+        };
+        let found = [true, false].into_iter().find_map(|by_uid| {
+            stored
+                .iter()
+                .enumerate()
+                .position(|(i, stored)| !used[i] && matching(stored, by_uid))
+        });
+
+        match found {
+            Some(i) => {
+                used[i] = true;
+                if stored[i] != *actual {
+                    mismatches.push(IndexMismatch::Stale {
+                        stored: stored[i].clone(),
+                        actual: actual.clone(),
+                    });
+                }
+            }
+            None => mismatches.push(IndexMismatch::Unindexed {
+                actual: actual.clone(),
+            }),
+        }
+    }
+
+    mismatches.extend(
+        stored
+            .iter()
+            .zip(used)
+            .filter(|(_, used)| !used)
+            .map(|(stored, _)| IndexMismatch::Dangling {
+                stored: stored.clone(),
+            }),
+    );
+
+    mismatches
+}
+
+/// Copies `len` bytes starting at `offset` from `source` to `dest`.
+fn copy_range<R: Read + Seek, W: Write>(
+    source: &mut R,
+    dest: &mut W,
+    offset: u64,
+    len: u64,
+) -> Result<(), LibError> {
+    source.seek(SeekFrom::Start(offset))?;
+    let copied = io::copy(&mut source.take(len), dest)?;
+    if copied != len {
+        return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::io::Cursor;
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::write::tests::{encode_case, rewrite_index, sample_case};
+
+    fn temp_case(name: &str, bytes: &[u8]) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "game_case_parser_{name}_{}.gcase",
+            std::process::id()
+        ));
+        fs::write(&path, bytes).unwrap();
+        path
+    }
+
+    /// Leaves the entry `2` out of the `Index`, moves the last entry and
+    /// adds one that does not exist.
+    fn damage_index(bytes: &mut [u8]) {
+        rewrite_index(bytes, |entries| {
+            entries.retain(|entry| entry.uid != 2);
+            entries.last_mut().unwrap().position += 1;
+            let mut dangling = entries[0].clone();
+            dangling.uid = 99;
+            entries.push(dangling);
+        });
+    }
+
+    #[test]
+    fn valid_index_has_no_mismatches() {
+        let bytes = encode_case(&sample_case());
+        let mismatches = check_index(&mut Cursor::new(bytes)).unwrap();
+        assert!(mismatches.is_empty(), "{mismatches:?}");
+    }
+
+    #[test]
+    fn dry_run_reports_mismatches_without_writing() {
+        let mut bytes = encode_case(&sample_case());
+        damage_index(&mut bytes);
+        let path = temp_case("repair_dry_run", &bytes);
+
+        let mismatches = repair_index_file(&path, true).unwrap();
+        let unchanged = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(unchanged, bytes);
+        assert_eq!(mismatches.len(), 3, "{mismatches:?}");
+        assert!(mismatches.iter().any(|mismatch| matches!(
+            mismatch,
+            IndexMismatch::Unindexed { actual } if actual.uid == 2
+        )));
+        assert!(mismatches
+            .iter()
+            .any(|mismatch| matches!(mismatch, IndexMismatch::Stale { .. })));
+        assert!(mismatches.iter().any(|mismatch| matches!(
+            mismatch,
+            IndexMismatch::Dangling { stored } if stored.uid == 99
+        )));
+    }
+
+    #[test]
+    fn repair_regenerates_the_index() {
+        let mut bytes = encode_case(&sample_case());
+        damage_index(&mut bytes);
+        let path = temp_case("repair_index", &bytes);
+
+        let mismatches = repair_index_file(&path, false).unwrap();
+        let repaired = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(mismatches.len(), 3, "{mismatches:?}");
+        let remaining = check_index(&mut Cursor::new(repaired)).unwrap();
+        assert!(remaining.is_empty(), "{remaining:?}");
+    }
+}
@@ -0,0 +1,1170 @@
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+use ebml_iterable::specs::{EbmlSpecification, EbmlTag, TagDataType};
+
+use crate::ebml_tools::{
+    encode_element_header, read_element_header, ElementHeader, ElementSpan,
+};
+use crate::gc_ebml_spec::{
+    binary_id, element_name, element_path, master_id, uint_id, utf8_id,
+    GCEbmlSpec,
+};
+use crate::lib_error_handling::LibError;
+use crate::lib_structs::{
+    GameCase, ImageCollection, ImageEntry, Index, Manuals, Media, RomHacks,
+    VideoCollection, VideoEntry,
+};
+use crate::limits::{limit_error, LimitTracker, LimitedReader, ReaderLimits};
+use crate::parse::{
+    check_ebml_header, decode_elements, read_game, read_game_data_entry,
+    read_image_collection, read_image_entry, read_index, read_manual_entry,
+    read_rom_hack_entry, read_video_collection, read_video_entry,
+    ElementNode,
+};
+use crate::payload::{
+    load_metadata_with_limits, locate_payloads, PayloadLocation,
+};
+use crate::read::load_element_with_limits;
+use crate::verify::{
+    digest_file, find_stored_files, read_stored_file, stored_file_payload,
+    FileStatus, FileVerification,
+};
+
+/// The category given to images and videos whose collection could not be
+/// recovered.
+pub const UNKNOWN_CATEGORY: &str = "Unknown";
+
+/// Number of bytes searched for known element IDs at a time.
+const SCAN_CHUNK: usize = 64 * 1024;
+
+/// An element recovered from a damaged case.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SalvagedElement {
+    /// Absolute offset of the element within the file.
+    pub offset: u64,
+    /// Name of the element, e.g. "ManualEntry" or "ROMData".
+    pub element: String,
+    /// The `EntryUID` of the element, if it is an entry.
+    pub uid: Option<u64>,
+    /// Every file held by the element, checked against its stored digests.
+    pub files: Vec<FileVerification>,
+    /// Where the payloads of the element are stored in file order, or the
+    /// data of a payload found on its own, e.g. a `ROMData` whose
+    /// `RawData` is damaged. Open them with `PayloadReader::open`.
+    pub payloads: Vec<PayloadLocation>,
+}
+
+impl SalvagedElement {
+    /// Returns whether every file of the element matched a stored digest.
+    ///
+    /// Elements without any stored digest, including payloads found on
+    /// their own, are intact as far as can be told but not verified.
+    pub fn verified(&self) -> bool {
+        !self.files.is_empty()
+            && self.files.iter().all(|file| file.status() == FileStatus::Pass)
+    }
+}
+
+impl fmt::Display for SalvagedElement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at byte {}", self.element, self.offset)?;
+        if let Some(uid) = self.uid {
+            write!(f, " (UID {uid})")?;
+        }
+        if !self.payloads.is_empty() {
+            let size: u64 =
+                self.payloads.iter().map(|payload| payload.size).sum();
+            write!(f, ", {size} bytes")?;
+        }
+        if self.verified() {
+            f.write_str(", verified")?;
+        } else if !self.payloads.is_empty() {
+            f.write_str(", unverified")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Why an element could not be recovered.
+#[derive(Clone, Debug, PartialEq)]
+pub enum LossReason {
+    /// The element extends past the end of the file.
+    Truncated,
+    /// The element could not be decoded.
+    Damaged(String),
+    /// The element decoded, but a file within it does not match its
+    /// stored size or digests.
+    DigestMismatch(Vec<FileVerification>),
+    /// The `Index` lists the entry, but it was not found in the file.
+    NotFound,
+}
+
+impl fmt::Display for LossReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LossReason::Truncated => f.write_str("truncated"),
+            LossReason::Damaged(error) => write!(f, "damaged: {error}"),
+            LossReason::DigestMismatch(files) => {
+                f.write_str("digest mismatch")?;
+                for file in files {
+                    write!(f, "\n    {file}")?;
+                }
+                Ok(())
+            }
+            LossReason::NotFound => f.write_str("not found"),
+        }
+    }
+}
+
+/// An element of a damaged case that could not be recovered.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LostElement {
+    /// Absolute offset of the element within the file. For entries only
+    /// known from the `Index` it is `None` if the start of the `GameCase`
+    /// element could not be found.
+    pub offset: Option<u64>,
+    /// Name of the element, e.g. "ManualEntry".
+    pub element: String,
+    /// The `EntryUID` of the element, if it is known.
+    pub uid: Option<u64>,
+    pub reason: LossReason,
+}
+
+impl fmt::Display for LostElement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.element)?;
+        if let Some(offset) = self.offset {
+            write!(f, " at byte {offset}")?;
+        }
+        if let Some(uid) = self.uid {
+            write!(f, " (UID {uid})")?;
+        }
+
+        write!(f, ": {}", self.reason)
+    }
+}
+
+/// The outcome of salvaging a damaged case.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SalvageReport {
+    /// Every recovered entry arranged as a case. The `Game` metadata is
+    /// left empty if it was lost and the `Index` is left empty to be
+    /// regenerated by the writer.
+    ///
+    /// Entries are recovered without their payloads, which stay empty, so
+    /// cases of any size can be salvaged. Their data is streamed from the
+    /// damaged file using the `payloads` of the matching element in
+    /// `recovered`.
+    pub game_case: GameCase,
+    /// Every recovered element in file order.
+    pub recovered: Vec<SalvagedElement>,
+    /// Every element found to be damaged in file order, followed by the
+    /// entries the `Index` lists but that were not found.
+    pub lost: Vec<LostElement>,
+}
+
+/// Recovers whatever can be read from a damaged case.
+///
+/// Rather than reading the case from the start until the first error, the
+/// whole file is searched for the IDs of entries, collections and payloads.
+/// Each element found this way is decoded on its own, so damage only costs
+/// the elements it touches. The payloads of game data are checked against
+/// the digests stored in their `FileProperties`, and entries whose data
+/// does not match are reported as lost. Payloads found on their own, e.g.
+/// a `ROMData` whose `RawData` is damaged, have no digests to check and
+/// are only reported by location.
+///
+/// Every element is checked against the default `ReaderLimits`, see
+/// `salvage_with_limits`.
+///
+/// # Arguments
+///
+/// * `source`: The damaged case, starting at the beginning of `source`.
+///
+/// # Returns
+///
+/// A `Result` containing the recovered case and a report of what was
+/// recovered and what was lost. Only I/O errors are returned as errors.
+pub fn salvage<R: Read + Seek>(
+    source: &mut R,
+) -> Result<SalvageReport, LibError> {
+    salvage_with_limits(source, ReaderLimits::default())
+}
+
+/// Recovers whatever can be read from a damaged case like `salvage`,
+/// checking every element against `limits`.
+///
+/// Payloads are never loaded, so `max_binary_size` only applies to
+/// unknown binary elements. An element breaking the limits is reported as
+/// damaged.
+///
+/// # Arguments
+///
+/// * `source`: The damaged case, starting at the beginning of `source`.
+/// * `limits`: The limits every recovered element is checked against.
+///
+/// # Returns
+///
+/// A `Result` containing the recovered case and a report of what was
+/// recovered and what was lost. Only I/O errors are returned as errors.
+pub fn salvage_with_limits<R: Read + Seek>(
+    source: &mut R,
+    limits: ReaderLimits,
+) -> Result<SalvageReport, LibError> {
+    let len = source.seek(SeekFrom::End(0))?;
+    let mut salvager = Salvager {
+        source,
+        len,
+        limits,
+        anchors: anchors(),
+        game_case_start: None,
+        game_found: false,
+        index: None,
+        collections: Vec::new(),
+        report: SalvageReport::default(),
+    };
+
+    let mut cursor = salvager.check_ebml_header()?;
+    while let Some((offset, id)) = salvager.next_anchor(cursor)? {
+        cursor = salvager
+            .salvage_at(offset, id)?
+            .unwrap_or(offset + 1);
+    }
+    salvager.report_unfound();
+
+    Ok(salvager.report)
+}
+
+/// Recovers whatever can be read from the damaged case at `path`.
+///
+/// See `salvage` for how elements are recovered.
+pub fn salvage_file(path: &Path) -> Result<SalvageReport, LibError> {
+    salvage(&mut BufReader::new(File::open(path)?))
+}
+
+/// The elements searched for, paired with their encoded IDs.
+///
+/// `GameCase` and `Game` are included to find the `GameCase` start and
+/// the game metadata. Containers of entries are left out, as the entries
+/// within them are found on their own.
+fn anchors() -> Vec<(u64, Vec<u8>)> {
+    [
+        master_id(GCEbmlSpec::GameCase),
+        master_id(GCEbmlSpec::Index),
+        master_id(GCEbmlSpec::Game),
+        master_id(GCEbmlSpec::GameDataEntry),
+        master_id(GCEbmlSpec::RawData),
+        master_id(GCEbmlSpec::CHDEntry),
+        master_id(GCEbmlSpec::BinCueEntry),
+        master_id(GCEbmlSpec::ManualEntry),
+        master_id(GCEbmlSpec::RomHackEntry),
+        master_id(GCEbmlSpec::ImageCollection),
+        master_id(GCEbmlSpec::ImageEntry),
+        master_id(GCEbmlSpec::VideoCollection),
+        master_id(GCEbmlSpec::VideoEntry),
+        binary_id(GCEbmlSpec::ROMData),
+        binary_id(GCEbmlSpec::ArchiveBinary),
+        binary_id(GCEbmlSpec::CHDBinary),
+        binary_id(GCEbmlSpec::BinBinary),
+        binary_id(GCEbmlSpec::SSMCBinary),
+        binary_id(GCEbmlSpec::ManualData),
+        binary_id(GCEbmlSpec::PatchData),
+        binary_id(GCEbmlSpec::ImageData),
+        binary_id(GCEbmlSpec::Thumbnail),
+        binary_id(GCEbmlSpec::VideoData),
+    ]
+    .into_iter()
+    .map(|id| {
+        let bytes = id
+            .to_be_bytes()
+            .into_iter()
+            .skip_while(|byte| *byte == 0)
+            .collect();
+        (id, bytes)
+    })
+    .collect()
+}
+
+/// An image or video collection that could not be recovered as a whole.
+///
+/// Entries found within it are still given its category.
+struct OpenCollection {
+    entry_id: u64,
+    end: u64,
+    category: String,
+}
+
+struct Salvager<'a, R: Read + Seek> {
+    source: &'a mut R,
+    len: u64,
+    limits: ReaderLimits,
+    anchors: Vec<(u64, Vec<u8>)>,
+    game_case_start: Option<u64>,
+    game_found: bool,
+    index: Option<Index>,
+    collections: Vec<OpenCollection>,
+    report: SalvageReport,
+}
+
+impl<R: Read + Seek> Salvager<'_, R> {
+    /// Checks the EBML header at the start of the file.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the offset to start searching from, which is
+    /// past the header if its size could be read.
+    fn check_ebml_header(&mut self) -> Result<u64, LibError> {
+        let ebml_id = master_id(GCEbmlSpec::EBML);
+        let header = match self.header_at(0)? {
+            Some(header) if header.id == ebml_id => header,
+            _ => {
+                let reason = LossReason::Damaged(
+                    "no EBML header at the start of the file".to_string()
+                );
+                self.lose(0, ebml_id, None, reason);
+                return Ok(0);
+            }
+        };
+
+        if header.total_len() > self.len {
+            self.lose(0, ebml_id, None, LossReason::Truncated);
+            return Ok(header.header_len);
+        }
+
+        let checked = load_element_with_limits(
+            self.source,
+            0,
+            ebml_id,
+            self.limits,
+        )
+        .and_then(|node| check_ebml_header(&node));
+        if let Err(error) = checked {
+            let reason = damage(error)?;
+            self.lose(0, ebml_id, None, reason);
+        }
+
+        Ok(header.total_len())
+    }
+
+    /// Finds the next element ID worth trying at or after `from`.
+    fn next_anchor(
+        &mut self,
+        from: u64,
+    ) -> Result<Option<(u64, u64)>, LibError> {
+        //Chunks overlap so IDs spanning two chunks are still found.
+        let overlap = self
+            .anchors
+            .iter()
+            .map(|(_, bytes)| bytes.len() - 1)
+            .max()
+            .unwrap_or(0);
+        let mut buffer = vec![0u8; SCAN_CHUNK];
+        let mut position = from;
+
+        while position < self.len {
+            let len = ((self.len - position) as usize).min(SCAN_CHUNK);
+            self.source.seek(SeekFrom::Start(position))?;
+            self.source.read_exact(&mut buffer[..len])?;
+
+            for start in 0..len {
+                let found = self
+                    .anchors
+                    .iter()
+                    .find(|(_, bytes)| buffer[start..len].starts_with(bytes));
+                if let Some((id, _)) = found {
+                    return Ok(Some((position + start as u64, *id)));
+                }
+            }
+
+            if len < SCAN_CHUNK {
+                break;
+            }
+            position += (len - overlap) as u64;
+        }
+
+        Ok(None)
+    }
+
+    /// Tries to recover the element with the ID `id` at `offset`.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the offset to continue searching from, or
+    /// `None` to continue right after `offset`, e.g. to search the inside
+    /// of a damaged element.
+    fn salvage_at(
+        &mut self,
+        offset: u64,
+        id: u64,
+    ) -> Result<Option<u64>, LibError> {
+        let Some(header) = self.header_at(offset)? else {
+            return Ok(None);
+        };
+        let end = self.end_within_file(offset, &header);
+
+        //Payloads carry no structure to tell a real one from a chance match
+        //of its ID, so only those that fit within the file are kept.
+        if stored_file_payload(id).is_none()
+            && GCEbmlSpec::get_tag_data_type(id)
+                == Some(TagDataType::Binary)
+        {
+            let Some(end) = end else {
+                return Ok(None);
+            };
+            self.recover(SalvagedElement {
+                offset,
+                element: element_name(id),
+                uid: None,
+                files: Vec::new(),
+                payloads: vec![PayloadLocation {
+                    id,
+                    offset: offset + header.header_len,
+                    size: header.size,
+                }],
+            });
+            return Ok(Some(end));
+        }
+
+        if !self.is_plausible(offset, &header)? {
+            return Ok(None);
+        }
+        if id == master_id(GCEbmlSpec::ImageCollection)
+            || id == master_id(GCEbmlSpec::VideoCollection)
+        {
+            return self.salvage_collection(offset, &header);
+        }
+        let Some(end) = end else {
+            self.lose_entry(offset, id, LossReason::Truncated)?;
+            return Ok(None);
+        };
+
+        if id == master_id(GCEbmlSpec::GameCase) {
+            self.game_case_start.get_or_insert(offset);
+            return Ok(Some(offset + header.header_len));
+        }
+        if id == master_id(GCEbmlSpec::Game) {
+            return self.salvage_game(offset, &header, end);
+        }
+        if stored_file_payload(id).is_some() {
+            return self.salvage_stored_file(offset, &header, end);
+        }
+
+        //Payloads are skipped, so entries of any size are recovered.
+        match load_metadata_with_limits(self.source, offset, id, self.limits)
+        {
+            Ok(node) => self.salvage_node(node),
+            Err(error) => {
+                let reason = damage(error)?;
+                self.lose_entry(offset, id, reason)?;
+                Ok(None)
+            }
+        }
+    }
+
+    /// Adds a decoded `Index` or entry, decoded without its payloads, to
+    /// the report.
+    fn salvage_node(
+        &mut self,
+        node: ElementNode,
+    ) -> Result<Option<u64>, LibError> {
+        let offset = node.offset;
+        let id = node.tag.get_id();
+        let end = self
+            .header_at(offset)?
+            .and_then(|header| self.end_within_file(offset, &header))
+            .unwrap_or(offset);
+        let mut element = SalvagedElement {
+            offset,
+            element: element_name(id),
+            uid: None,
+            files: Vec::new(),
+            payloads: self.payloads_of(offset, id)?,
+        };
+
+        match node.tag {
+            GCEbmlSpec::Index(_) => {
+                if self.index.is_none() {
+                    self.index = Some(read_index(node));
+                    self.recover(element);
+                }
+            }
+            GCEbmlSpec::GameDataEntry(_) => {
+                let entry = match read_game_data_entry(node) {
+                    Ok(entry) => entry,
+                    Err(error) => {
+                        let reason = damage(error)?;
+                        self.lose_entry(offset, id, reason)?;
+                        return Ok(None);
+                    }
+                };
+                element.uid = Some(entry.entry_uid);
+                element.files = match self.check_files(offset) {
+                    Ok(files) => files,
+                    Err(error) => {
+                        let reason = damage(error)?;
+                        self.lose(offset, id, element.uid, reason);
+                        return Ok(None);
+                    }
+                };
+
+                if element.files
+                    .iter()
+                    .any(|file| file.status() == FileStatus::Fail)
+                {
+                    let reason = LossReason::DigestMismatch(element.files);
+                    self.lose(offset, id, element.uid, reason);
+                    return Ok(Some(end));
+                }
+
+                self.report.game_case.game.game_data
+                    .get_or_insert_with(Default::default)
+                    .entries
+                    .push(entry);
+                self.recover(element);
+            }
+            GCEbmlSpec::ManualEntry(_) => {
+                let entry = read_manual_entry(node);
+                element.uid = Some(entry.entry_uid);
+
+                self.report.game_case.manuals
+                    .get_or_insert_with(Manuals::default)
+                    .entries
+                    .push(entry);
+                self.recover(element);
+            }
+            GCEbmlSpec::RomHackEntry(_) => {
+                let entry = match read_rom_hack_entry(node) {
+                    Ok(entry) => entry,
+                    Err(error) => {
+                        let reason = damage(error)?;
+                        self.lose_entry(offset, id, reason)?;
+                        return Ok(None);
+                    }
+                };
+                element.uid = Some(entry.entry_uid);
+
+                self.report.game_case.rom_hacks
+                    .get_or_insert_with(RomHacks::default)
+                    .entries
+                    .push(entry);
+                self.recover(element);
+            }
+            GCEbmlSpec::ImageEntry(_) => {
+                let entry = read_image_entry(node);
+                let category = self.category_of(offset, id);
+                self.add_image(element, category, entry);
+            }
+            GCEbmlSpec::VideoEntry(_) => {
+                let entry = read_video_entry(node);
+                let category = self.category_of(offset, id);
+                self.add_video(element, category, entry);
+            }
+            _ => return Ok(None),
+        }
+
+        Ok(Some(end))
+    }
+
+    /// Recovers the metadata of `Game`, everything in front of its
+    /// `GameData`.
+    ///
+    /// Every child is checked against the limits while it is read, so a
+    /// damaged size can not make it load more than the file holds or more
+    /// than a reader would.
+    ///
+    /// # Arguments
+    ///
+    /// * `end`: Offset of the end of `Game`, which lies within the file.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the offset the metadata ends at, so the game
+    /// data is searched for entries next.
+    fn salvage_game(
+        &mut self,
+        offset: u64,
+        header: &ElementHeader,
+        end: u64,
+    ) -> Result<Option<u64>, LibError> {
+        let game_id = master_id(GCEbmlSpec::Game);
+        let mut position = offset + header.header_len;
+        let mut metadata = Vec::new();
+
+        //Reading stops at the first child that cannot be read, keeping
+        //whatever came before it.
+        while position < end {
+            let Some(child) = self.header_at(position)? else {
+                break;
+            };
+            let child_end = position
+                .checked_add(child.total_len())
+                .filter(|child_end| *child_end <= end);
+            let Some(child_end) = child_end else {
+                break;
+            };
+            if child.id == master_id(GCEbmlSpec::GameData) {
+                break;
+            }
+
+            let tracker = LimitTracker::new(
+                self.limits,
+                position,
+                &element_path(game_id),
+            );
+            let kept = metadata.len();
+            self.source.seek(SeekFrom::Start(position))?;
+            let read = LimitedReader::new(
+                (&mut *self.source).take(child.total_len()),
+                tracker,
+            )
+            .read_to_end(&mut metadata);
+            if let Err(error) = read {
+                damage(limit_error(error))?;
+                metadata.truncate(kept);
+                break;
+            }
+            position = child_end;
+        }
+
+        //Only the first Game is used, later ones are chance matches.
+        if self.game_found {
+            return Ok(Some(position));
+        }
+        self.game_found = true;
+
+        let mut bytes = encode_element_header(game_id, metadata.len() as u64);
+        bytes.extend(metadata);
+        let game = decode_elements(&bytes, offset).and_then(|nodes| {
+            nodes
+                .into_iter()
+                .next()
+                .ok_or(LibError::Io(io::ErrorKind::UnexpectedEof.into()))
+                .and_then(read_game)
+        });
+
+        match game {
+            Ok(game) => {
+                let game_data = self.report.game_case.game.game_data.take();
+                self.report.game_case.game = game;
+                self.report.game_case.game.game_data = game_data;
+                self.recover(SalvagedElement {
+                    offset,
+                    element: element_name(game_id),
+                    uid: None,
+                    files: Vec::new(),
+                    payloads: Vec::new(),
+                });
+            }
+            Err(error) => {
+                let reason = damage(error)?;
+                self.lose(offset, game_id, None, reason);
+            }
+        }
+
+        Ok(Some(position))
+    }
+
+    /// Recovers an image or video collection as a whole, or remembers its
+    /// category for the entries within it if that fails.
+    fn salvage_collection(
+        &mut self,
+        offset: u64,
+        header: &ElementHeader,
+    ) -> Result<Option<u64>, LibError> {
+        let is_image = header.id == master_id(GCEbmlSpec::ImageCollection);
+        let entry_id = if is_image {
+            master_id(GCEbmlSpec::ImageEntry)
+        } else {
+            master_id(GCEbmlSpec::VideoEntry)
+        };
+
+        //A truncated collection may still hold complete entries.
+        let end = self.end_within_file(offset, header);
+        if end.is_none() {
+            self.lose(offset, header.id, None, LossReason::Truncated);
+        } else if let Ok(node) = load_metadata_with_limits(
+            self.source,
+            offset,
+            header.id,
+            self.limits,
+        ) {
+            //Offsets within the decoded collection ignore its payloads.
+            let mut offsets = Vec::new();
+            let mut walker = ElementSpan::new(offset, *header).children();
+            while let Some(child) = walker.seek_next(self.source)? {
+                if child.header.id == entry_id {
+                    offsets.push(child.offset);
+                }
+            }
+
+            if is_image {
+                let collection = read_image_collection(node);
+                let entries = collection.entries.into_iter().zip(offsets);
+                for (entry, offset) in entries {
+                    let element = self.entry_element(offset, entry_id)?;
+                    let category = collection.category.clone();
+                    self.add_image(element, category, entry);
+                }
+            } else {
+                let collection = read_video_collection(node);
+                let entries = collection.entries.into_iter().zip(offsets);
+                for (entry, offset) in entries {
+                    let element = self.entry_element(offset, entry_id)?;
+                    let category = collection.category.clone();
+                    self.add_video(element, category, entry);
+                }
+            }
+
+            return Ok(end);
+        }
+
+        //The entries are searched for individually, each reporting its own
+        //damage.
+        if let Some(category) = self.read_category(offset, header)? {
+            self.collections.push(OpenCollection {
+                entry_id,
+                end: offset.saturating_add(header.total_len()),
+                category,
+            });
+        }
+
+        Ok(None)
+    }
+
+    /// Recovers a `RawData`, `CHDEntry` or `BinCueEntry` whose
+    /// `GameDataEntry` is damaged, reporting where its payload is stored.
+    fn salvage_stored_file(
+        &mut self,
+        offset: u64,
+        header: &ElementHeader,
+        end: u64,
+    ) -> Result<Option<u64>, LibError> {
+        let Some(payload_element) = stored_file_payload(header.id) else {
+            return Ok(None);
+        };
+
+        let file = match read_stored_file(
+            self.source,
+            ElementSpan::new(offset, *header),
+            payload_element,
+            element_path(header.id),
+        ) {
+            Ok(file) => file,
+            Err(error) => {
+                let reason = damage(error)?;
+                self.lose(offset, header.id, None, reason);
+                return Ok(None);
+            }
+        };
+
+        let payload = file.payload;
+        let digests = digest_file(self.source, &file, |_, _| {})?;
+        let verification = file.into_verification(digests);
+
+        if verification.status() == FileStatus::Fail {
+            let reason = LossReason::DigestMismatch(vec![verification]);
+            self.lose(offset, header.id, None, reason);
+        } else {
+            self.recover(SalvagedElement {
+                offset,
+                element: element_name(header.id),
+                uid: None,
+                files: vec![verification],
+                payloads: vec![payload],
+            });
+        }
+
+        Ok(Some(end))
+    }
+
+    /// Checks every file of the `GameDataEntry` at `offset` against its
+    /// stored digests.
+    fn check_files(
+        &mut self,
+        offset: u64,
+    ) -> Result<Vec<FileVerification>, LibError> {
+        let Some(header) = self.header_at(offset)? else {
+            return Ok(Vec::new());
+        };
+
+        let mut files = Vec::new();
+        find_stored_files(
+            self.source,
+            ElementSpan::new(offset, header),
+            &element_path(header.id),
+            &mut files,
+        )?;
+
+        let mut verifications = Vec::with_capacity(files.len());
+        for file in files {
+            let digests = digest_file(self.source, &file, |_, _| {})?;
+            verifications.push(file.into_verification(digests));
+        }
+
+        Ok(verifications)
+    }
+
+    /// Reads the `Category` of a damaged collection, if it is intact.
+    fn read_category(
+        &mut self,
+        offset: u64,
+        header: &ElementHeader,
+    ) -> Result<Option<String>, LibError> {
+        let category_id = utf8_id(GCEbmlSpec::Category);
+        let category = self.read_child(offset, header, category_id)?;
+
+        Ok(category.and_then(|bytes| String::from_utf8(bytes).ok()))
+    }
+
+    /// Reads the data of the first child with the ID `id` of a damaged
+    /// element, if every child in front of it is intact.
+    ///
+    /// Only values up to `max_string_size` are read, a larger one is taken
+    /// for damage.
+    fn read_child(
+        &mut self,
+        offset: u64,
+        header: &ElementHeader,
+        id: u64,
+    ) -> Result<Option<Vec<u8>>, LibError> {
+        let end = offset.saturating_add(header.total_len()).min(self.len);
+        let mut position = offset + header.header_len;
+
+        while position < end {
+            let Some(child) = self.header_at(position)? else {
+                return Ok(None);
+            };
+            let child_end = position
+                .checked_add(child.total_len())
+                .filter(|child_end| *child_end <= end);
+            let Some(child_end) = child_end else {
+                return Ok(None);
+            };
+
+            if child.id == id {
+                if child.size > self.limits.max_string_size {
+                    return Ok(None);
+                }
+
+                let mut bytes = vec![0u8; child.size as usize];
+                self.source.seek(SeekFrom::Start(
+                    position + child.header_len
+                ))?;
+                self.source.read_exact(&mut bytes)?;
+
+                return Ok(Some(bytes));
+            }
+            position = child_end;
+        }
+
+        Ok(None)
+    }
+
+    /// Returns the category of the damaged collection an entry was found
+    /// in.
+    fn category_of(&self, offset: u64, entry_id: u64) -> String {
+        self.collections
+            .iter()
+            .rev()
+            .find(|collection| {
+                collection.entry_id == entry_id && offset < collection.end
+            })
+            .map_or_else(
+                || UNKNOWN_CATEGORY.to_string(),
+                |collection| collection.category.clone(),
+            )
+    }
+
+    /// Returns the element reported for the entry with the ID `id` at
+    /// `offset`, along with the location of its payloads.
+    fn entry_element(
+        &mut self,
+        offset: u64,
+        id: u64,
+    ) -> Result<SalvagedElement, LibError> {
+        Ok(SalvagedElement {
+            offset,
+            element: element_name(id),
+            uid: None,
+            files: Vec::new(),
+            payloads: self.payloads_of(offset, id)?,
+        })
+    }
+
+    /// Locates the payloads of the element with the ID `id` at `offset`,
+    /// which was already decoded. Damage found while doing so leaves the
+    /// element without payloads.
+    fn payloads_of(
+        &mut self,
+        offset: u64,
+        id: u64,
+    ) -> Result<Vec<PayloadLocation>, LibError> {
+        match locate_payloads(self.source, offset, id) {
+            Ok(payloads) => Ok(payloads),
+            Err(error) => damage(error).map(|_| Vec::new()),
+        }
+    }
+
+    fn add_image(
+        &mut self,
+        element: SalvagedElement,
+        category: String,
+        entry: ImageEntry,
+    ) {
+        self.recover(SalvagedElement {
+            uid: Some(entry.entry_uid),
+            ..element
+        });
+
+        let collections = &mut self.report.game_case.media
+            .get_or_insert_with(Media::default)
+            .image_collections;
+        match collections.iter_mut().find(|c| c.category == category) {
+            Some(collection) => collection.entries.push(entry),
+            None => collections.push(ImageCollection {
+                category,
+                entries: vec![entry],
+                ..Default::default()
+            }),
+        }
+    }
+
+    fn add_video(
+        &mut self,
+        element: SalvagedElement,
+        category: String,
+        entry: VideoEntry,
+    ) {
+        self.recover(SalvagedElement {
+            uid: Some(entry.entry_uid),
+            ..element
+        });
+
+        let collections = &mut self.report.game_case.media
+            .get_or_insert_with(Media::default)
+            .video_collections;
+        match collections.iter_mut().find(|c| c.category == category) {
+            Some(collection) => collection.entries.push(entry),
+            None => collections.push(VideoCollection {
+                category,
+                entries: vec![entry],
+                ..Default::default()
+            }),
+        }
+    }
+
+    /// Reports every entry the recovered `Index` lists but that was not
+    /// recovered.
+    fn report_unfound(&mut self) {
+        let Some(index) = self.index.take() else {
+            return;
+        };
+
+        for stored in index.entries {
+            let element = element_name(stored.target_id);
+            //Entries already reported as damaged are not repeated.
+            let found = self.report.recovered.iter().any(|recovered| {
+                recovered.element == element
+                    && recovered.uid == Some(stored.uid)
+            }) || self.report.lost.iter().any(|lost| {
+                lost.element == element && lost.uid == Some(stored.uid)
+            });
+            if found {
+                continue;
+            }
+
+            self.report.lost.push(LostElement {
+                offset: self
+                    .game_case_start
+                    .map(|start| start + stored.position),
+                element,
+                uid: Some(stored.uid),
+                reason: LossReason::NotFound,
+            });
+        }
+    }
+
+    /// Returns whether the element at `offset` looks like a real element
+    /// rather than a chance match of its ID, i.e. whether it starts with a
+    /// child defined by the specification.
+    fn is_plausible(
+        &mut self,
+        offset: u64,
+        header: &ElementHeader,
+    ) -> Result<bool, LibError> {
+        if header.size == 0 {
+            return Ok(false);
+        }
+
+        let child = self.header_at(offset + header.header_len)?;
+
+        Ok(child.is_some_and(|child| {
+            child.total_len() <= header.size
+                && GCEbmlSpec::get_tag_data_type(child.id).is_some()
+        }))
+    }
+
+    /// Returns the end of the element at `offset`, or `None` if it does
+    /// not fit within the file.
+    fn end_within_file(
+        &self,
+        offset: u64,
+        header: &ElementHeader,
+    ) -> Option<u64> {
+        offset
+            .checked_add(header.total_len())
+            .filter(|end| *end <= self.len)
+    }
+
+    /// Reads the element header at `offset`, or `None` if there is no
+    /// valid header.
+    fn header_at(
+        &mut self,
+        offset: u64,
+    ) -> Result<Option<ElementHeader>, LibError> {
+        self.source.seek(SeekFrom::Start(offset))?;
+
+        match read_element_header(self.source) {
+            Ok(header) => Ok(header),
+            Err(e) if matches!(
+                e.kind(),
+                io::ErrorKind::InvalidData | io::ErrorKind::UnexpectedEof
+            ) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn recover(&mut self, element: SalvagedElement) {
+        self.report.recovered.push(element);
+    }
+
+    /// Reports a damaged entry along with its `EntryUID`, if that can still
+    /// be read.
+    fn lose_entry(
+        &mut self,
+        offset: u64,
+        id: u64,
+        reason: LossReason,
+    ) -> Result<(), LibError> {
+        let uid_id = uint_id(GCEbmlSpec::EntryUID);
+        let uid = match self.header_at(offset)? {
+            Some(header) => self.read_child(offset, &header, uid_id)?,
+            None => None,
+        };
+        let uid = uid
+            .filter(|bytes| bytes.len() <= 8)
+            .map(|bytes| {
+                bytes.iter().fold(0, |uid, byte| (uid << 8) | *byte as u64)
+            });
+
+        self.lose(offset, id, uid, reason);
+
+        Ok(())
+    }
+
+    fn lose(
+        &mut self,
+        offset: u64,
+        id: u64,
+        uid: Option<u64>,
+        reason: LossReason,
+    ) {
+        self.report.lost.push(LostElement {
+            offset: Some(offset),
+            element: element_name(id),
+            uid,
+            reason,
+        });
+    }
+}
+
+/// Turns an error raised while decoding a damaged element into the reason
+/// it is lost.
+///
+/// I/O errors other than those caused by the damage itself are returned as
+/// they are.
+fn damage(error: LibError) -> Result<LossReason, LibError> {
+    match error {
+        LibError::Io(e) if !matches!(
+            e.kind(),
+            io::ErrorKind::InvalidData | io::ErrorKind::UnexpectedEof
+        ) => Err(LibError::Io(e)),
+        error => Ok(LossReason::Damaged(error.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::payload::PayloadReader;
+    use crate::write::tests::{encode_case, sample_case};
+
+    fn recovered_uids(report: &SalvageReport) -> Vec<(String, u64)> {
+        report.recovered
+            .iter()
+            .filter_map(|element| {
+                element.uid.map(|uid| (element.element.clone(), uid))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn truncated_case_keeps_complete_entries() {
+        let bytes = encode_case(&sample_case());
+        //Cuts into the data of the image, the last entry.
+        let truncated = &bytes[..bytes.len() - 4];
+
+        let report = salvage(&mut Cursor::new(truncated)).unwrap();
+
+        assert_eq!(recovered_uids(&report), [
+            ("GameDataEntry".to_string(), 1),
+            ("ManualEntry".to_string(), 2),
+        ]);
+        assert_eq!(report.game_case.game.title, "Zelda");
+        assert!(report.game_case.media.is_none());
+        assert!(report.lost.iter().any(|lost| {
+            lost.element == "ImageEntry"
+                && lost.uid == Some(3)
+                && lost.reason == LossReason::Truncated
+        }));
+    }
+
+    #[test]
+    fn payloads_are_located_rather_than_loaded() {
+        let bytes = encode_case(&sample_case());
+        //The ROM is larger than this, but is never loaded.
+        let limits = ReaderLimits {
+            max_binary_size: 100,
+            ..Default::default()
+        };
+
+        let mut source = Cursor::new(&bytes);
+        let report = salvage_with_limits(&mut source, limits).unwrap();
+
+        assert!(report.lost.is_empty(), "{:?}", report.lost);
+        assert_eq!(recovered_uids(&report), [
+            ("GameDataEntry".to_string(), 1),
+            ("ManualEntry".to_string(), 2),
+            ("ImageEntry".to_string(), 3),
+        ]);
+        let rom = &report.recovered
+            .iter()
+            .find(|element| element.uid == Some(1))
+            .unwrap()
+            .payloads;
+        assert_eq!(rom.len(), 1);
+
+        let mut data = Vec::new();
+        PayloadReader::open(&mut source, &rom[0])
+            .unwrap()
+            .read_to_end(&mut data)
+            .unwrap();
+        assert_eq!(data, [7; 300]);
+    }
+}
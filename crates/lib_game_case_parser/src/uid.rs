@@ -0,0 +1,316 @@
+use std::collections::HashSet;
+use std::io;
+
+use sha2::{Digest, Sha256};
+
+use crate::lib_error_handling::LibError;
+use crate::lib_structs::GameCase;
+use crate::read::CaseEntry;
+use crate::write::{encode_entry, EntryRef};
+
+/// How [`UidAllocator`] picks a new `EntryUID`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum UidStrategy {
+    /// One more than the largest `EntryUID` in use, starting at 1.
+    #[default]
+    Sequential,
+    /// A random 64-bit value from the operating system.
+    Random,
+    /// The first eight bytes of the SHA-256 digest of the entry, encoded
+    /// with an `EntryUID` of 0. The same entry always gets the same
+    /// `EntryUID` unless it is already taken, in which case the digest is
+    /// taken again with a counter appended.
+    ContentHash,
+}
+
+/// Hands out `EntryUID`s that are not used by any other entry of a case.
+///
+/// An `EntryUID` of 0 is never handed out, so it can be used to mark
+/// entries that still need one, see `assign`.
+#[derive(Clone, Debug, Default)]
+pub struct UidAllocator {
+    strategy: UidStrategy,
+    used: HashSet<u64>,
+    largest: u64,
+}
+
+impl UidAllocator {
+    pub fn new(strategy: UidStrategy) -> Self {
+        UidAllocator {
+            strategy,
+            ..Default::default()
+        }
+    }
+
+    /// Creates an allocator that avoids every `EntryUID` of `game_case`.
+    pub fn for_case(strategy: UidStrategy, game_case: &GameCase) -> Self {
+        let mut allocator = UidAllocator::new(strategy);
+        for uid in entry_uids(game_case) {
+            allocator.reserve(uid);
+        }
+        allocator
+    }
+
+    pub fn strategy(&self) -> UidStrategy {
+        self.strategy
+    }
+
+    /// Marks `uid` as used so it is never handed out.
+    ///
+    /// # Returns
+    ///
+    /// `true` if `uid` was not used before.
+    pub fn reserve(&mut self, uid: u64) -> bool {
+        self.largest = self.largest.max(uid);
+        self.used.insert(uid)
+    }
+
+    /// Returns an unused, non-zero `EntryUID` for `entry` and marks it as
+    /// used.
+    ///
+    /// # Arguments
+    ///
+    /// * `entry`: The entry to allocate for. Only `UidStrategy::ContentHash`
+    ///   looks at it and ignores its current `EntryUID`.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the new `EntryUID`, or a `LibError` if the
+    /// operating system could not supply random bytes or the entry could
+    /// not be encoded.
+    pub fn allocate(&mut self, entry: &CaseEntry) -> Result<u64, LibError> {
+        if self.strategy == UidStrategy::ContentHash && entry.entry_uid() != 0
+        {
+            let mut entry = entry.clone();
+            match &mut entry {
+                CaseEntry::GameData(entry) => entry.entry_uid = 0,
+                CaseEntry::Manual(entry) => entry.entry_uid = 0,
+                CaseEntry::RomHack(entry) => entry.entry_uid = 0,
+                CaseEntry::Image(entry) => entry.entry_uid = 0,
+                CaseEntry::Video(entry) => entry.entry_uid = 0,
+            }
+            return self.allocate_for(EntryRef::from(&entry));
+        }
+
+        self.allocate_for(EntryRef::from(entry))
+    }
+
+    /// Gives every entry of `game_case` without an `EntryUID`, or with one
+    /// already used by an entry in front of it, a fresh `EntryUID`.
+    ///
+    /// Entries are visited in the order they are written, so the first
+    /// entry using an `EntryUID` keeps it.
+    ///
+    /// # Arguments
+    ///
+    /// * `game_case`: The case whose entries are updated.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the number of entries given a new `EntryUID`.
+    pub fn assign(
+        &mut self,
+        game_case: &mut GameCase,
+    ) -> Result<usize, LibError> {
+        for uid in entry_uids(game_case) {
+            self.reserve(uid);
+        }
+
+        let mut seen = HashSet::new();
+        let mut assigned = 0;
+        //Inserting first keeps the first entry with a given EntryUID.
+        let mut needs_uid = |uid: u64| !seen.insert(uid) || uid == 0;
+
+        if let Some(game_data) = &mut game_case.game.game_data {
+            for entry in &mut game_data.entries {
+                if needs_uid(entry.entry_uid) {
+                    entry.entry_uid = 0;
+                    entry.entry_uid =
+                        self.allocate_for(EntryRef::GameData(entry))?;
+                    assigned += 1;
+                }
+            }
+        }
+
+        if let Some(manuals) = &mut game_case.manuals {
+            for entry in &mut manuals.entries {
+                if needs_uid(entry.entry_uid) {
+                    entry.entry_uid = 0;
+                    entry.entry_uid =
+                        self.allocate_for(EntryRef::Manual(entry))?;
+                    assigned += 1;
+                }
+            }
+        }
+
+        if let Some(rom_hacks) = &mut game_case.rom_hacks {
+            for entry in &mut rom_hacks.entries {
+                if needs_uid(entry.entry_uid) {
+                    entry.entry_uid = 0;
+                    entry.entry_uid =
+                        self.allocate_for(EntryRef::RomHack(entry))?;
+                    assigned += 1;
+                }
+            }
+        }
+
+        if let Some(media) = &mut game_case.media {
+            for collection in &mut media.image_collections {
+                for entry in &mut collection.entries {
+                    if needs_uid(entry.entry_uid) {
+                        entry.entry_uid = 0;
+                        entry.entry_uid =
+                            self.allocate_for(EntryRef::Image(entry))?;
+                        assigned += 1;
+                    }
+                }
+            }
+
+            for collection in &mut media.video_collections {
+                for entry in &mut collection.entries {
+                    if needs_uid(entry.entry_uid) {
+                        entry.entry_uid = 0;
+                        entry.entry_uid =
+                            self.allocate_for(EntryRef::Video(entry))?;
+                        assigned += 1;
+                    }
+                }
+            }
+        }
+
+        Ok(assigned)
+    }
+
+    pub(crate) fn allocate_for(
+        &mut self,
+        entry: EntryRef<'_>,
+    ) -> Result<u64, LibError> {
+        let uid = match self.strategy {
+            UidStrategy::Sequential => self.next_sequential(),
+            UidStrategy::Random => loop {
+                let uid = getrandom::u64().map_err(io::Error::from)?;
+                if uid != 0 && !self.used.contains(&uid) {
+                    break uid;
+                }
+            },
+            UidStrategy::ContentHash => {
+                let bytes = encode_entry(entry)?;
+                (0u64..)
+                    .map(|counter| content_uid(&bytes, counter))
+                    .find(|uid| *uid != 0 && !self.used.contains(uid))
+                    .unwrap_or_default()
+            }
+        };
+
+        self.reserve(uid);
+        Ok(uid)
+    }
+
+    fn next_sequential(&self) -> u64 {
+        match self.largest {
+            u64::MAX => (1..u64::MAX)
+                .find(|uid| !self.used.contains(uid))
+                .unwrap_or_default(),
+            largest => largest + 1,
+        }
+    }
+}
+
+/// Derives an `EntryUID` from the encoded entry, appending `counter` to the
+/// digested bytes unless it is 0.
+fn content_uid(bytes: &[u8], counter: u64) -> u64 {
+    let mut hasher = Sha256::new();
+    hasher.update(bytes);
+    if counter != 0 {
+        hasher.update(counter.to_be_bytes());
+    }
+
+    let digest = hasher.finalize();
+    let mut uid = [0u8; 8];
+    uid.copy_from_slice(&digest[..8]);
+    u64::from_be_bytes(uid)
+}
+
+/// Returns the `EntryUID` of every entry within `game_case`.
+pub(crate) fn entry_uids(game_case: &GameCase) -> Vec<u64> {
+    let mut uids = Vec::new();
+
+    if let Some(game_data) = &game_case.game.game_data {
+        uids.extend(game_data.entries.iter().map(|entry| entry.entry_uid));
+    }
+    if let Some(manuals) = &game_case.manuals {
+        uids.extend(manuals.entries.iter().map(|entry| entry.entry_uid));
+    }
+    if let Some(rom_hacks) = &game_case.rom_hacks {
+        uids.extend(rom_hacks.entries.iter().map(|entry| entry.entry_uid));
+    }
+    if let Some(media) = &game_case.media {
+        for collection in &media.image_collections {
+            uids.extend(collection.entries.iter().map(|entry| entry.entry_uid));
+        }
+        for collection in &media.video_collections {
+            uids.extend(collection.entries.iter().map(|entry| entry.entry_uid));
+        }
+    }
+
+    uids
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::write::tests::sample_case;
+
+    fn first_manual(game_case: &GameCase) -> CaseEntry {
+        let manuals = game_case.manuals.as_ref().unwrap();
+        CaseEntry::Manual(manuals.entries[0].clone())
+    }
+
+    #[test]
+    fn sequential_uids_follow_the_largest_in_use() {
+        let game_case = sample_case();
+        let mut allocator =
+            UidAllocator::for_case(UidStrategy::Sequential, &game_case);
+        let manual = first_manual(&game_case);
+
+        assert_eq!(allocator.allocate(&manual).unwrap(), 4);
+        assert!(allocator.reserve(10));
+        assert!(!allocator.reserve(10));
+        assert_eq!(allocator.allocate(&manual).unwrap(), 11);
+    }
+
+    #[test]
+    fn content_hash_ignores_the_current_uid() {
+        let game_case = sample_case();
+        let manual = first_manual(&game_case);
+        let mut renumbered = manual.clone();
+        if let CaseEntry::Manual(entry) = &mut renumbered {
+            entry.entry_uid = 40;
+        }
+
+        let mut allocator = UidAllocator::new(UidStrategy::ContentHash);
+        let uid = allocator.allocate(&manual).unwrap();
+        assert_ne!(uid, 0);
+
+        //The same content gets the same EntryUID unless it is taken.
+        let mut fresh = UidAllocator::new(UidStrategy::ContentHash);
+        assert_eq!(fresh.allocate(&renumbered).unwrap(), uid);
+        assert_ne!(allocator.allocate(&renumbered).unwrap(), uid);
+    }
+
+    #[test]
+    fn assign_replaces_missing_and_duplicate_uids() {
+        let mut game_case = sample_case();
+        let manuals = game_case.manuals.as_mut().unwrap();
+        manuals.entries[0].entry_uid = 1;
+        let media = game_case.media.as_mut().unwrap();
+        media.image_collections[0].entries[0].entry_uid = 0;
+
+        let mut allocator = UidAllocator::new(UidStrategy::Sequential);
+        let assigned = allocator.assign(&mut game_case).unwrap();
+
+        //The ROM came first and keeps EntryUID 1.
+        assert_eq!(assigned, 2);
+        assert_eq!(entry_uids(&game_case), [1, 2, 3]);
+    }
+}
@@ -0,0 +1,1163 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, Read};
+
+use ebml_iterable::specs::{EbmlSpecification, EbmlTag, Master};
+
+use crate::date::ReleaseDate;
+use crate::ebml_tools::{ChildWalker, ElementSpan, CRC32_ID, VOID_ID};
+use crate::gc_ebml_spec::{
+    binary_id, element_name, is_entry_id, master_id, uint_id, utf8_id,
+    GCEbmlSpec, DOC_TYPE, DOC_TYPE_VERSION,
+};
+use crate::lib_error_handling::LibError;
+use crate::limits::{limit_error, LimitTracker, ReaderLimits};
+use crate::parse::{child_path, decode_elements, ElementNode};
+use crate::payload::is_payload;
+
+/// How serious a [`Diagnostic`] is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// The file can be read, but does not follow a recommendation of the
+    /// specification.
+    Warning,
+    /// The file breaks a rule of the specification.
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// A single problem found while validating a file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// Byte offset of the offending element from the start of the file.
+    pub offset: u64,
+    /// Path of the offending element, e.g. `GameCase/Game/Title`.
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} at byte {} ({}): {}",
+            self.severity, self.offset, self.path, self.message
+        )
+    }
+}
+
+/// Cardinality of a child element, as listed in SPECIFICATION.md.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Cardinality {
+    /// `1`
+    One,
+    /// `0..1`
+    ZeroOrOne,
+    /// `1..n`
+    OneOrMore,
+    /// `0..n`
+    ZeroOrMore,
+}
+
+impl Cardinality {
+    fn allows(self, count: usize) -> bool {
+        match self {
+            Cardinality::One => count == 1,
+            Cardinality::ZeroOrOne => count <= 1,
+            Cardinality::OneOrMore => count >= 1,
+            Cardinality::ZeroOrMore => true,
+        }
+    }
+
+    fn describe(self) -> &'static str {
+        match self {
+            Cardinality::One => "exactly once",
+            Cardinality::ZeroOrOne => "at most once",
+            Cardinality::OneOrMore => "at least once",
+            Cardinality::ZeroOrMore => "any number of times",
+        }
+    }
+}
+
+struct ChildRule {
+    id: u64,
+    cardinality: Cardinality,
+}
+
+fn master(
+    tag: fn(Master<GCEbmlSpec>) -> GCEbmlSpec,
+    cardinality: Cardinality,
+) -> ChildRule {
+    ChildRule { id: master_id(tag), cardinality }
+}
+
+fn uint(tag: fn(u64) -> GCEbmlSpec, cardinality: Cardinality) -> ChildRule {
+    ChildRule { id: uint_id(tag), cardinality }
+}
+
+fn utf8(tag: fn(String) -> GCEbmlSpec, cardinality: Cardinality) -> ChildRule {
+    ChildRule { id: utf8_id(tag), cardinality }
+}
+
+fn binary(
+    tag: fn(Vec<u8>) -> GCEbmlSpec,
+    cardinality: Cardinality,
+) -> ChildRule {
+    ChildRule { id: binary_id(tag), cardinality }
+}
+
+use Cardinality::{One, OneOrMore, ZeroOrMore, ZeroOrOne};
+
+/// Returns the rules for the children of the master element `id`.
+fn child_rules(id: u64) -> Option<Vec<ChildRule>> {
+    let rules = match GCEbmlSpec::get_master_tag(id, Master::Start)? {
+        GCEbmlSpec::EBML(_) => vec![
+            uint(GCEbmlSpec::EBMLVersion, One),
+            uint(GCEbmlSpec::EBMLReadVersion, ZeroOrOne),
+            uint(GCEbmlSpec::EBMLMaxIDLength, ZeroOrOne),
+            uint(GCEbmlSpec::EBMLMaxSizeLength, ZeroOrOne),
+            utf8(GCEbmlSpec::DocType, One),
+            uint(GCEbmlSpec::DocTypeVersion, One),
+            uint(GCEbmlSpec::DocTypeReadVersion, One),
+        ],
+        GCEbmlSpec::GameCase(_) => vec![
+            master(GCEbmlSpec::Index, One),
+            master(GCEbmlSpec::Game, One),
+            master(GCEbmlSpec::Manuals, ZeroOrOne),
+            master(GCEbmlSpec::RomHacks, ZeroOrOne),
+            master(GCEbmlSpec::Media, ZeroOrOne),
+        ],
+        //A case without entries has an empty Index.
+        GCEbmlSpec::Index(_) => {
+            vec![master(GCEbmlSpec::IndexEntry, ZeroOrMore)]
+        }
+        GCEbmlSpec::IndexEntry(_) => vec![
+            uint(GCEbmlSpec::IndexTargetID, One),
+            uint(GCEbmlSpec::IndexPosition, One),
+            utf8(GCEbmlSpec::IndexTarget, One),
+            utf8(GCEbmlSpec::IndexTargetCategory, ZeroOrOne),
+            utf8(GCEbmlSpec::IndexTargetRegion, ZeroOrOne),
+            uint(GCEbmlSpec::IndexTargetUID, One),
+        ],
+        GCEbmlSpec::Game(_) => vec![
+            utf8(GCEbmlSpec::Title, One),
+            utf8(GCEbmlSpec::Developer, OneOrMore),
+            utf8(GCEbmlSpec::Genre, ZeroOrOne),
+            uint(GCEbmlSpec::MinPlayers, ZeroOrOne),
+            uint(GCEbmlSpec::MaxPlayers, ZeroOrOne),
+            utf8(GCEbmlSpec::GameSystem, One),
+            utf8(GCEbmlSpec::Description, ZeroOrOne),
+            master(GCEbmlSpec::Rating, ZeroOrOne),
+            master(GCEbmlSpec::RegionInfo, ZeroOrMore),
+            master(GCEbmlSpec::GameData, ZeroOrOne),
+        ],
+        GCEbmlSpec::Rating(_) => vec![
+            utf8(GCEbmlSpec::RatingValue, One),
+            utf8(GCEbmlSpec::RatingSource, One),
+        ],
+        GCEbmlSpec::RegionInfo(_) => vec![
+            utf8(GCEbmlSpec::Region, One),
+            utf8(GCEbmlSpec::Language, OneOrMore),
+            binary(GCEbmlSpec::ReleaseDate, One),
+            uint(GCEbmlSpec::ReleaseDatePrecision, ZeroOrOne),
+            utf8(GCEbmlSpec::Publisher, One),
+            utf8(GCEbmlSpec::AgeRating, One),
+        ],
+        GCEbmlSpec::GameData(_) => {
+            vec![master(GCEbmlSpec::GameDataEntry, OneOrMore)]
+        }
+        GCEbmlSpec::GameDataEntry(_) => vec![
+            uint(GCEbmlSpec::EntryUID, One),
+            utf8(GCEbmlSpec::DataFormat, One),
+            master(GCEbmlSpec::RawData, ZeroOrOne),
+            master(GCEbmlSpec::ArchiveData, ZeroOrOne),
+            master(GCEbmlSpec::ChdData, ZeroOrOne),
+            master(GCEbmlSpec::BinCueData, ZeroOrOne),
+            master(GCEbmlSpec::SSMCData, ZeroOrOne),
+        ],
+        GCEbmlSpec::RawData(_) => vec![
+            master(GCEbmlSpec::FileProperties, One),
+            binary(GCEbmlSpec::ROMData, One),
+        ],
+        /*Region and Language are checked separately, as entries of a CHD or
+        BIN/CUE collection may inherit them from the collection. */
+        GCEbmlSpec::FileProperties(_) => vec![
+            utf8(GCEbmlSpec::ROMFileName, ZeroOrOne),
+            uint(GCEbmlSpec::ROMFileSize, One),
+            utf8(GCEbmlSpec::Region, ZeroOrOne),
+            utf8(GCEbmlSpec::Language, ZeroOrMore),
+            binary(GCEbmlSpec::CRC32, ZeroOrOne),
+            binary(GCEbmlSpec::MD5, ZeroOrOne),
+            binary(GCEbmlSpec::SHA1, ZeroOrOne),
+            binary(GCEbmlSpec::SHA256, ZeroOrOne),
+            binary(GCEbmlSpec::SHA512, ZeroOrOne),
+            uint(GCEbmlSpec::SSMCIndex, ZeroOrOne),
+        ],
+        GCEbmlSpec::ArchiveData(_) => vec![
+            utf8(GCEbmlSpec::CompressionAlgorithm, One),
+            uint(GCEbmlSpec::ArchivedFileCount, One),
+            master(GCEbmlSpec::ArchiveFileMetadata, OneOrMore),
+            binary(GCEbmlSpec::ArchiveBinary, One),
+        ],
+        GCEbmlSpec::ArchiveFileMetadata(_) | GCEbmlSpec::SSMCEntry(_) => {
+            vec![master(GCEbmlSpec::FileProperties, One)]
+        }
+        GCEbmlSpec::ChdData(_) => vec![
+            utf8(GCEbmlSpec::Region, One),
+            utf8(GCEbmlSpec::Language, OneOrMore),
+            uint(GCEbmlSpec::CHDCount, One),
+            master(GCEbmlSpec::CHDEntry, OneOrMore),
+        ],
+        GCEbmlSpec::CHDEntry(_) => vec![
+            uint(GCEbmlSpec::DiscNum, One),
+            master(GCEbmlSpec::FileProperties, One),
+            binary(GCEbmlSpec::CHDBinary, One),
+        ],
+        GCEbmlSpec::BinCueData(_) => vec![
+            utf8(GCEbmlSpec::Region, One),
+            utf8(GCEbmlSpec::Language, OneOrMore),
+            uint(GCEbmlSpec::BinCueCount, One),
+            master(GCEbmlSpec::BinCueEntry, OneOrMore),
+        ],
+        GCEbmlSpec::BinCueEntry(_) => vec![
+            uint(GCEbmlSpec::DiscNum, One),
+            utf8(GCEbmlSpec::CueSheet, One),
+            master(GCEbmlSpec::FileProperties, One),
+            binary(GCEbmlSpec::BinBinary, One),
+        ],
+        GCEbmlSpec::SSMCData(_) => vec![
+            uint(GCEbmlSpec::ArchivedFileCount, One),
+            master(GCEbmlSpec::SSMCEntry, OneOrMore),
+            binary(GCEbmlSpec::SSMCBinary, One),
+        ],
+        GCEbmlSpec::Manuals(_) => {
+            vec![master(GCEbmlSpec::ManualEntry, OneOrMore)]
+        }
+        GCEbmlSpec::ManualEntry(_) => vec![
+            uint(GCEbmlSpec::EntryUID, One),
+            utf8(GCEbmlSpec::Region, One),
+            utf8(GCEbmlSpec::Language, OneOrMore),
+            uint(GCEbmlSpec::PageCount, One),
+            utf8(GCEbmlSpec::Revision, One),
+            utf8(GCEbmlSpec::ManualFormat, One),
+            binary(GCEbmlSpec::ManualData, One),
+        ],
+        GCEbmlSpec::RomHacks(_) => {
+            vec![master(GCEbmlSpec::RomHackEntry, ZeroOrMore)]
+        }
+        GCEbmlSpec::RomHackEntry(_) => vec![
+            uint(GCEbmlSpec::EntryUID, One),
+            utf8(GCEbmlSpec::Title, One),
+            utf8(GCEbmlSpec::Developer, ZeroOrMore),
+            utf8(GCEbmlSpec::HackVersion, ZeroOrOne),
+            utf8(GCEbmlSpec::PatchFormat, ZeroOrOne),
+            master(GCEbmlSpec::TargetHash, ZeroOrMore),
+            utf8(GCEbmlSpec::HackDescription, ZeroOrOne),
+            binary(GCEbmlSpec::PatchData, One),
+        ],
+        GCEbmlSpec::TargetHash(_) => vec![
+            utf8(GCEbmlSpec::HashFormat, One),
+            binary(GCEbmlSpec::HashValue, One),
+        ],
+        GCEbmlSpec::Media(_) => vec![
+            master(GCEbmlSpec::ImageCollection, ZeroOrMore),
+            master(GCEbmlSpec::VideoCollection, ZeroOrMore),
+        ],
+        GCEbmlSpec::ImageCollection(_) => vec![
+            utf8(GCEbmlSpec::Category, One),
+            master(GCEbmlSpec::ImageEntry, OneOrMore),
+        ],
+        GCEbmlSpec::ImageEntry(_) => vec![
+            uint(GCEbmlSpec::EntryUID, One),
+            utf8(GCEbmlSpec::ImageTitle, ZeroOrOne),
+            utf8(GCEbmlSpec::Artist, ZeroOrMore),
+            utf8(GCEbmlSpec::Region, ZeroOrOne),
+            utf8(GCEbmlSpec::ImageFormat, One),
+            uint(GCEbmlSpec::Width, One),
+            uint(GCEbmlSpec::Height, One),
+            utf8(GCEbmlSpec::CategoryDetail, ZeroOrOne),
+            binary(GCEbmlSpec::ImageData, One),
+        ],
+        GCEbmlSpec::VideoCollection(_) => vec![
+            utf8(GCEbmlSpec::Category, One),
+            master(GCEbmlSpec::VideoEntry, OneOrMore),
+        ],
+        GCEbmlSpec::VideoEntry(_) => vec![
+            uint(GCEbmlSpec::EntryUID, One),
+            utf8(GCEbmlSpec::VideoTitle, ZeroOrOne),
+            utf8(GCEbmlSpec::Language, ZeroOrMore),
+            uint(GCEbmlSpec::Duration, One),
+            utf8(GCEbmlSpec::VideoFormat, One),
+            uint(GCEbmlSpec::Width, One),
+            uint(GCEbmlSpec::Height, One),
+            utf8(GCEbmlSpec::ImageFormat, One),
+            binary(GCEbmlSpec::Thumbnail, ZeroOrOne),
+            binary(GCEbmlSpec::VideoData, One),
+        ],
+        _ => return None,
+    };
+
+    Some(rules)
+}
+
+/// Valid values of `DataFormat`, paired with the master element each one
+/// requires.
+fn data_formats() -> [(&'static str, u64); 5] {
+    [
+        ("RAW", master_id(GCEbmlSpec::RawData)),
+        ("ARCHIVE", master_id(GCEbmlSpec::ArchiveData)),
+        ("CHD", master_id(GCEbmlSpec::ChdData)),
+        ("BIN/CUE", master_id(GCEbmlSpec::BinCueData)),
+        ("SSMC", master_id(GCEbmlSpec::SSMCData)),
+    ]
+}
+
+/// Returns the length in bytes a digest element must have, or `None` if
+/// `id` is not a digest.
+fn digest_length(id: u64) -> Option<usize> {
+    [
+        (binary_id(GCEbmlSpec::CRC32), 4),
+        (binary_id(GCEbmlSpec::MD5), 16),
+        (binary_id(GCEbmlSpec::SHA1), 20),
+        (binary_id(GCEbmlSpec::SHA256), 32),
+        (binary_id(GCEbmlSpec::SHA512), 64),
+    ]
+    .into_iter()
+    .find_map(|(digest, length)| (digest == id).then_some(length))
+}
+
+/// Collection masters paired with their count element and the children
+/// that count refers to.
+fn counted_children() -> [(u64, u64, u64); 4] {
+    [
+        (
+            master_id(GCEbmlSpec::ArchiveData),
+            uint_id(GCEbmlSpec::ArchivedFileCount),
+            master_id(GCEbmlSpec::ArchiveFileMetadata),
+        ),
+        (
+            master_id(GCEbmlSpec::ChdData),
+            uint_id(GCEbmlSpec::CHDCount),
+            master_id(GCEbmlSpec::CHDEntry),
+        ),
+        (
+            master_id(GCEbmlSpec::BinCueData),
+            uint_id(GCEbmlSpec::BinCueCount),
+            master_id(GCEbmlSpec::BinCueEntry),
+        ),
+        (
+            master_id(GCEbmlSpec::SSMCData),
+            uint_id(GCEbmlSpec::ArchivedFileCount),
+            master_id(GCEbmlSpec::SSMCEntry),
+        ),
+    ]
+}
+
+/// Checks a `.gcase` stream against the rules of SPECIFICATION.md,
+/// checking it against the default `ReaderLimits`.
+///
+/// See `validate_with_limits`.
+pub fn validate<R: Read>(source: R) -> Result<Vec<Diagnostic>, LibError> {
+    validate_with_limits(source, ReaderLimits::default())
+}
+
+/// Checks a `.gcase` stream against the rules of SPECIFICATION.md.
+///
+/// Validation does not stop at the first problem. Every cardinality and
+/// ordering rule is checked, along with the values the specification
+/// restricts, such as `DataFormat`, digest lengths and `Index` positions.
+/// Only element headers and metadata are read into memory, payloads are
+/// skipped over.
+///
+/// # Arguments
+///
+/// * `source`: Any reader positioned at the start of the EBML header.
+/// * `limits`: The limits every element header is checked against.
+///
+/// # Returns
+///
+/// A `Result` containing every diagnostic found, ordered by offset, or a
+/// `LibError` if the stream could not be read. An empty list means the
+/// file conforms to the specification.
+pub fn validate_with_limits<R: Read>(
+    mut source: R,
+    limits: ReaderLimits,
+) -> Result<Vec<Diagnostic>, LibError> {
+    let mut tracker = LimitTracker::new(limits, 0, "").streaming_payloads();
+    let mut roots = Vec::new();
+    let result = read_children(
+        &mut source,
+        &mut ChildWalker::top_level(0),
+        &mut tracker,
+        &mut roots,
+        "",
+    );
+
+    let mut validator = Validator::default();
+    validator.check_document(&roots);
+
+    match result {
+        Ok(()) => {}
+        Err(LibError::Io(error)) => return Err(LibError::Io(error)),
+        Err(error) => {
+            let offset = error.offset().unwrap_or_default();
+            let path = error.path().unwrap_or_default().to_string();
+            validator.report(
+                Severity::Error,
+                offset,
+                &path,
+                format!("Unreadable data, validation stopped: {error}"),
+            );
+        }
+    }
+
+    let mut diagnostics = validator.diagnostics;
+    diagnostics.sort_by_key(|diagnostic| diagnostic.offset);
+
+    Ok(diagnostics)
+}
+
+/// Reads the elements `walker` finds into `nodes`, keeping everything read
+/// before an error.
+///
+/// Masters are walked rather than loaded as a whole. The data of payloads,
+/// `Void` elements and elements unknown to the specification is skipped
+/// and decoded as empty, every other element is decoded once its header
+/// passed `tracker`.
+///
+/// # Arguments
+///
+/// * `source`: The stream, positioned at the next child.
+/// * `walker`: Walks the children of the master being read.
+/// * `tracker`: Checks every header against the limits.
+/// * `nodes`: Receives the children read.
+/// * `path`: Path of the enclosing master, empty at the top level.
+fn read_children<R: Read>(
+    source: &mut R,
+    walker: &mut ChildWalker,
+    tracker: &mut LimitTracker,
+    nodes: &mut Vec<ElementNode>,
+    path: &str,
+) -> Result<(), LibError> {
+    while let Some(child) = walker.next(source)? {
+        let ElementSpan { offset, header, .. } = child;
+        let id = header.id;
+        tracker.check_header(offset, &header)?;
+        let path = child_path(&[], nodes, path, id);
+
+        if let Some(tag) = GCEbmlSpec::get_master_tag(id, Master::Start) {
+            let walker = &mut child.children_at(path.clone());
+            nodes.push(ElementNode { tag, offset, path, children: Vec::new() });
+            let node = nodes.last_mut().expect("the master was just added");
+            read_children(
+                source,
+                walker,
+                tracker,
+                &mut node.children,
+                &node.path,
+            )?;
+            continue;
+        }
+
+        let skipped = match GCEbmlSpec::get_tag_data_type(id) {
+            None => Some(GCEbmlSpec::RawTag(id, Vec::new())),
+            _ if is_payload(id) || id == VOID_ID => {
+                GCEbmlSpec::get_binary_tag(id, &[])
+            }
+            _ => None,
+        };
+        if let Some(tag) = skipped {
+            let mut data = source.take(header.size);
+            let read =
+                io::copy(&mut data, &mut io::sink()).map_err(limit_error)?;
+            if read != header.size {
+                return Err(walker.truncated());
+            }
+            nodes.push(ElementNode { tag, offset, path, children: Vec::new() });
+            continue;
+        }
+
+        let mut bytes = header.encode();
+        let read = source
+            .take(header.size)
+            .read_to_end(&mut bytes)
+            .map_err(limit_error)?;
+        if read as u64 != header.size {
+            return Err(walker.truncated());
+        }
+        let mut node = decode_elements(&bytes, offset)?
+            .into_iter()
+            .next()
+            .ok_or(LibError::Io(io::ErrorKind::UnexpectedEof.into()))?;
+        node.path = path;
+        nodes.push(node);
+    }
+
+    Ok(())
+}
+
+#[derive(Default)]
+struct Validator {
+    diagnostics: Vec<Diagnostic>,
+    /// Element ID and `EntryUID` of every element inside the `GameCase`,
+    /// keyed by offset relative to the `GameCase`.
+    elements: HashMap<u64, (u64, Option<u64>)>,
+    /// Offset, path and `EntryUID` of every entry that should be indexed.
+    entries: Vec<(u64, String, Option<u64>)>,
+}
+
+impl Validator {
+    fn report(
+        &mut self,
+        severity: Severity,
+        offset: u64,
+        path: &str,
+        message: String,
+    ) {
+        self.diagnostics.push(Diagnostic {
+            severity,
+            offset,
+            path: path.to_string(),
+            message,
+        });
+    }
+
+    fn check_document(&mut self, roots: &[ElementNode]) {
+        match roots.first() {
+            Some(first) if id_of(first) == master_id(GCEbmlSpec::EBML) => {
+                self.check_element(first);
+                self.check_doc_type(first);
+            }
+            Some(first) => self.report(
+                Severity::Error,
+                first.offset,
+                "",
+                "The EBML header must be the first element".to_string(),
+            ),
+            None => {
+                self.report(
+                    Severity::Error,
+                    0,
+                    "",
+                    "The file does not contain any elements".to_string(),
+                );
+                return;
+            }
+        }
+
+        let mut game_cases = roots
+            .iter()
+            .filter(|node| id_of(node) == master_id(GCEbmlSpec::GameCase));
+
+        match game_cases.next() {
+            Some(game_case) => {
+                self.collect_elements(game_case, game_case.offset);
+                self.check_element(game_case);
+                self.check_entry_uids();
+                self.check_index(game_case);
+            }
+            None => self.report(
+                Severity::Error,
+                0,
+                "",
+                "Missing mandatory element GameCase".to_string(),
+            ),
+        }
+
+        for extra in game_cases {
+            self.report(
+                Severity::Error,
+                extra.offset,
+                "GameCase",
+                "GameCase must appear exactly once".to_string(),
+            );
+        }
+
+        for root in roots {
+            let id = id_of(root);
+            let expected = [
+                master_id(GCEbmlSpec::EBML),
+                master_id(GCEbmlSpec::GameCase),
+            ];
+            if !expected.contains(&id) && !is_global(id) {
+                self.report(
+                    Severity::Warning,
+                    root.offset,
+                    &format!("0x{id:X}"),
+                    "Unexpected top level element".to_string(),
+                );
+            }
+        }
+    }
+
+    fn check_doc_type(&mut self, header: &ElementNode) {
+        let doc_type = header.children
+            .iter()
+            .find(|child| id_of(child) == utf8_id(GCEbmlSpec::DocType));
+
+        if let Some(doc_type) = doc_type {
+            let value = doc_type.tag.as_utf8().unwrap_or_default();
+            if value != DOC_TYPE {
+                self.report(
+                    Severity::Error,
+                    doc_type.offset,
+                    &doc_type.path,
+                    format!("DocType is \"{value}\", expected \"{DOC_TYPE}\""),
+                );
+            }
+        }
+
+        let version_of = |id: u64| {
+            header.children
+                .iter()
+                .find(|child| id_of(child) == id)
+                .and_then(|child| {
+                    child.tag.as_unsigned_int().map(|value| (*value, child))
+                })
+        };
+        let version = version_of(uint_id(GCEbmlSpec::DocTypeVersion))
+            .map_or(1, |(value, _)| value);
+
+        if let Some((read_version, child)) =
+            version_of(uint_id(GCEbmlSpec::DocTypeReadVersion))
+        {
+            if read_version > DOC_TYPE_VERSION {
+                self.report(
+                    Severity::Error,
+                    child.offset,
+                    &child.path,
+                    format!(
+                        "DocTypeReadVersion {read_version} is newer than the \
+                        supported version {DOC_TYPE_VERSION}"
+                    ),
+                );
+            }
+            if read_version > version {
+                self.report(
+                    Severity::Warning,
+                    child.offset,
+                    &child.path,
+                    format!(
+                        "DocTypeReadVersion {read_version} is newer than \
+                        DocTypeVersion {version}"
+                    ),
+                );
+            }
+        }
+    }
+
+    /// Records the offset of every element so `Index` positions can be
+    /// resolved.
+    fn collect_elements(&mut self, node: &ElementNode, base: u64) {
+        let uid = node.children
+            .iter()
+            .find(|child| matches!(child.tag, GCEbmlSpec::EntryUID(_)))
+            .and_then(|child| child.tag.as_unsigned_int().copied());
+
+        self.elements.insert(node.offset - base, (id_of(node), uid));
+        //Entries carry an EntryUID and should each be indexed.
+        if is_entry_id(id_of(node)) {
+            self.entries.push((node.offset, node.path.clone(), uid));
+        }
+
+        for child in &node.children {
+            self.collect_elements(child, base);
+        }
+    }
+
+    /// Checks the children of a master element and recurses into them.
+    fn check_element(&mut self, node: &ElementNode) {
+        let path = node.path.as_str();
+        let Some(rules) = child_rules(id_of(node)) else {
+            return;
+        };
+
+        for rule in &rules {
+            let count = node.children
+                .iter()
+                .filter(|child| id_of(child) == rule.id)
+                .count();
+
+            if !rule.cardinality.allows(count) {
+                let name = element_name(rule.id);
+                let message = if count == 0 {
+                    format!("Missing mandatory element {name}")
+                } else {
+                    format!(
+                        "{name} must appear {}, found {count}",
+                        rule.cardinality.describe()
+                    )
+                };
+                self.report(Severity::Error, node.offset, path, message);
+            }
+        }
+
+        for child in &node.children {
+            let id = id_of(child);
+            if is_global(id) {
+                continue;
+            }
+
+            match rules.iter().find(|rule| rule.id == id) {
+                Some(_) => {
+                    self.check_value(child);
+                    self.check_element(child);
+                }
+                None if matches!(child.tag, GCEbmlSpec::RawTag(..)) => {
+                    self.report(
+                        Severity::Warning,
+                        child.offset,
+                        &child.path,
+                        "Unknown element, kept but not interpreted"
+                            .to_string(),
+                    )
+                }
+                None => self.report(
+                    Severity::Warning,
+                    child.offset,
+                    &child.path,
+                    "Element is not allowed here and will be ignored"
+                        .to_string(),
+                ),
+            }
+        }
+
+        self.check_rules_for(node);
+    }
+
+    /// Checks restrictions on the value of a single element.
+    fn check_value(&mut self, node: &ElementNode) {
+        let path = node.path.as_str();
+        let id = id_of(node);
+
+        if let Some(length) = digest_length(id) {
+            let actual = node.tag.as_binary().map_or(0, |data| data.len());
+            if actual != length {
+                self.report(
+                    Severity::Error,
+                    node.offset,
+                    path,
+                    format!(
+                        "Digest must be {length} bytes long, found {actual}"
+                    ),
+                );
+            }
+        }
+
+        if id == binary_id(GCEbmlSpec::ReleaseDate) {
+            let data = node.tag.as_binary().unwrap_or_default();
+            if !matches!(data.len(), 0 | 8) {
+                let message = match ReleaseDate::from_bytes(data) {
+                    ReleaseDate::Date(_) => "ReleaseDate is text instead of \
+                        a Date",
+                    ReleaseDate::Legacy(_) => "ReleaseDate is neither a Date \
+                        nor an ISO 8601 date",
+                };
+                self.report(
+                    Severity::Warning,
+                    node.offset,
+                    path,
+                    message.to_string(),
+                );
+            }
+        }
+
+        if id == uint_id(GCEbmlSpec::ReleaseDatePrecision)
+            && let Some(&precision) = node.tag.as_unsigned_int()
+            && precision > ReleaseDate::PRECISION_YEAR
+        {
+            self.report(
+                Severity::Warning,
+                node.offset,
+                path,
+                format!("Unknown ReleaseDatePrecision {precision}, taken as \
+                    a year"),
+            );
+        }
+    }
+
+    /// Checks rules that span several children of `node`.
+    fn check_rules_for(&mut self, node: &ElementNode) {
+        let path = node.path.as_str();
+        let id = id_of(node);
+
+        if id == master_id(GCEbmlSpec::GameCase) {
+            let first = node.children
+                .iter()
+                .find(|child| !is_global(id_of(child)));
+            let index = master_id(GCEbmlSpec::Index);
+            if let Some(first) = first.filter(|f| id_of(f) != index) {
+                self.report(
+                    Severity::Error,
+                    first.offset,
+                    path,
+                    "Index must be the first child of GameCase".to_string(),
+                );
+            }
+        }
+
+        if id == master_id(GCEbmlSpec::GameDataEntry) {
+            self.check_data_format(node);
+        }
+
+        if let Some((_, count_id, entry_id)) =
+            counted_children().into_iter().find(|counted| counted.0 == id)
+        {
+            let declared = node.children
+                .iter()
+                .find(|child| id_of(child) == count_id)
+                .and_then(|child| child.tag.as_unsigned_int().copied());
+            let actual = node.children
+                .iter()
+                .filter(|child| id_of(child) == entry_id)
+                .count() as u64;
+
+            if let Some(declared) = declared.filter(|d| *d != actual) {
+                self.report(
+                    Severity::Warning,
+                    node.offset,
+                    path,
+                    format!(
+                        "Declared count {declared} does not match the {actual} \
+                        entries present"
+                    ),
+                );
+            }
+        }
+
+        self.check_file_properties(node);
+    }
+
+    fn check_data_format(&mut self, entry: &ElementNode) {
+        let path = entry.path.as_str();
+        let Some(position) = entry.children
+            .iter()
+            .position(|child| id_of(child) == utf8_id(GCEbmlSpec::DataFormat))
+        else {
+            return;
+        };
+        let data_format = &entry.children[position];
+        let value = data_format.tag.as_utf8().unwrap_or_default();
+        let formats = data_formats();
+
+        let payloads: Vec<&ElementNode> = entry.children
+            .iter()
+            .filter(|child| formats.iter().any(|f| f.1 == id_of(child)))
+            .collect();
+
+        let early = payloads
+            .iter()
+            .find(|payload| payload.offset < data_format.offset);
+        if let Some(early) = early {
+            self.report(
+                Severity::Error,
+                early.offset,
+                path,
+                "DataFormat must come before the data it describes".to_string(),
+            );
+        }
+
+        let Some(&(_, expected)) = formats.iter().find(|f| f.0 == value)
+        else {
+            self.report(
+                Severity::Error,
+                data_format.offset,
+                &data_format.path,
+                format!(
+                    "\"{value}\" is not one of RAW, ARCHIVE, CHD, BIN/CUE, SSMC"
+                ),
+            );
+            return;
+        };
+
+        if !payloads.iter().any(|payload| id_of(payload) == expected) {
+            let name = element_name(expected);
+            self.report(
+                Severity::Error,
+                entry.offset,
+                path,
+                format!("DataFormat \"{value}\" requires a {name} element"),
+            );
+        }
+
+        for payload in payloads.iter().filter(|p| id_of(p) != expected) {
+            let name = element_name(id_of(payload));
+            self.report(
+                Severity::Error,
+                payload.offset,
+                &payload.path,
+                format!("{name} does not match DataFormat \"{value}\""),
+            );
+        }
+    }
+
+    /// Checks `FileProperties` children whose presence depends on where
+    /// the `FileProperties` element is used.
+    fn check_file_properties(&mut self, parent: &ElementNode) {
+        let parent_id = id_of(parent);
+        let Some(properties) = parent.children
+            .iter()
+            .find(|child| id_of(child) == master_id(GCEbmlSpec::FileProperties))
+        else {
+            return;
+        };
+        let path = &properties.path;
+        let has = |id: u64| {
+            properties.children.iter().any(|child| id_of(child) == id)
+        };
+
+        //CHD and BIN/CUE entries inherit these from their collection.
+        let inherits = parent_id == master_id(GCEbmlSpec::CHDEntry)
+            || parent_id == master_id(GCEbmlSpec::BinCueEntry);
+        if !inherits {
+            if !has(utf8_id(GCEbmlSpec::Region)) {
+                self.report(
+                    Severity::Error,
+                    properties.offset,
+                    path,
+                    "Missing mandatory element Region".to_string(),
+                );
+            }
+            if !has(utf8_id(GCEbmlSpec::Language)) {
+                self.report(
+                    Severity::Error,
+                    properties.offset,
+                    path,
+                    "Missing mandatory element Language".to_string(),
+                );
+            }
+        }
+
+        let archived = parent_id == master_id(GCEbmlSpec::ArchiveFileMetadata)
+            || parent_id == master_id(GCEbmlSpec::SSMCEntry);
+        if archived && !has(utf8_id(GCEbmlSpec::ROMFileName)) {
+            self.report(
+                Severity::Error,
+                properties.offset,
+                path,
+                "ROMFileName is mandatory for archived files".to_string(),
+            );
+        }
+    }
+
+    /// Checks that no two entries share an `EntryUID`.
+    fn check_entry_uids(&mut self) {
+        let mut first_use = HashMap::new();
+        let entries = std::mem::take(&mut self.entries);
+
+        for (offset, path, uid) in &entries {
+            let Some(uid) = *uid else {
+                continue;
+            };
+            if uid == 0 {
+                self.report(
+                    Severity::Warning,
+                    *offset,
+                    path,
+                    "EntryUID 0 cannot be told apart from a missing \
+                    EntryUID".to_string(),
+                );
+            }
+            if let Some(&first) = first_use.get(&uid) {
+                self.report(
+                    Severity::Error,
+                    *offset,
+                    path,
+                    format!(
+                        "EntryUID {uid} is already used by the entry at \
+                        byte {first}"
+                    ),
+                );
+            } else {
+                first_use.insert(uid, *offset);
+            }
+        }
+
+        self.entries = entries;
+    }
+
+    /// Checks that every `IndexEntry` points at the element it describes,
+    /// that no entry is listed twice and that every entry is listed.
+    fn check_index(&mut self, game_case: &ElementNode) {
+        let Some(index) = game_case.children
+            .iter()
+            .find(|child| id_of(child) == master_id(GCEbmlSpec::Index))
+        else {
+            return;
+        };
+
+        let mut indexed = HashMap::new();
+        for entry in &index.children {
+            let value = |id: u64| {
+                entry.children
+                    .iter()
+                    .find(|child| id_of(child) == id)
+                    .and_then(|child| child.tag.as_unsigned_int().copied())
+            };
+            let (Some(target_id), Some(position), Some(uid)) = (
+                value(uint_id(GCEbmlSpec::IndexTargetID)),
+                value(uint_id(GCEbmlSpec::IndexPosition)),
+                value(uint_id(GCEbmlSpec::IndexTargetUID)),
+            ) else {
+                continue;
+            };
+            let path = &entry.path;
+
+            match self.elements.get(&position).copied() {
+                None => self.report(
+                    Severity::Error,
+                    entry.offset,
+                    path,
+                    format!(
+                        "IndexPosition {position} does not point at an element"
+                    ),
+                ),
+                Some((found, _)) if found != target_id => self.report(
+                    Severity::Error,
+                    entry.offset,
+                    path,
+                    format!(
+                        "IndexPosition {position} points at element \
+                        0x{found:X}, expected 0x{target_id:X}"
+                    ),
+                ),
+                Some((_, found_uid)) if found_uid != Some(uid) => self.report(
+                    Severity::Error,
+                    entry.offset,
+                    path,
+                    format!(
+                        "Element at IndexPosition {position} does not have \
+                        EntryUID {uid}"
+                    ),
+                ),
+                Some(_) => match indexed.get(&position).copied() {
+                    Some(first) => self.report(
+                        Severity::Warning,
+                        entry.offset,
+                        path,
+                        format!(
+                            "The entry at IndexPosition {position} is \
+                            already listed by the IndexEntry at byte {first}"
+                        ),
+                    ),
+                    None => {
+                        indexed.insert(position, entry.offset);
+                    }
+                },
+            }
+        }
+
+        let base = game_case.offset;
+        for (offset, path, uid) in std::mem::take(&mut self.entries) {
+            if !indexed.contains_key(&(offset - base)) {
+                let uid = uid.map_or("-".to_string(), |uid| uid.to_string());
+                self.report(
+                    Severity::Warning,
+                    offset,
+                    &path,
+                    format!("Entry with EntryUID {uid} is not in the Index"),
+                );
+            }
+        }
+    }
+}
+
+fn id_of(node: &ElementNode) -> u64 {
+    node.tag.get_id()
+}
+
+/// Returns whether `id` belongs to a global element allowed anywhere in
+/// the document, `Void` or `CRC-32`.
+fn is_global(id: u64) -> bool {
+    id == VOID_ID || id == CRC32_ID
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::write::tests::{encode_case, rewrite_index, sample_case};
+
+    #[test]
+    fn written_case_conforms() {
+        let bytes = encode_case(&sample_case());
+
+        assert_eq!(validate(Cursor::new(&bytes)).unwrap(), []);
+    }
+
+    #[test]
+    fn case_without_entries_conforms() {
+        let mut game_case = sample_case();
+        game_case.game.game_data = None;
+        game_case.manuals = None;
+        game_case.rom_hacks = None;
+        game_case.media = None;
+        let bytes = encode_case(&game_case);
+
+        assert_eq!(validate(Cursor::new(&bytes)).unwrap(), []);
+    }
+
+    #[test]
+    fn stale_index_position_is_reported() {
+        let mut bytes = encode_case(&sample_case());
+        rewrite_index(&mut bytes, |entries| entries[0].position += 1);
+
+        let diagnostics = validate(Cursor::new(&bytes)).unwrap();
+        assert_eq!(diagnostics.len(), 2);
+        assert!(diagnostics[0].message.contains("does not point at"));
+        assert!(diagnostics[1].message.contains("is not in the Index"));
+    }
+
+    #[test]
+    fn reused_entry_uid_is_reported() {
+        //The EntryUID of the manual, which follows the Index, becomes 1.
+        let mut bytes = encode_case(&sample_case());
+        let manual_uid = bytes
+            .windows(4)
+            .rposition(|window| window == [0x5B, 0x0D, 0x81, 0x02])
+            .unwrap();
+        bytes[manual_uid + 3] = 1;
+
+        let diagnostics = validate(Cursor::new(&bytes)).unwrap();
+        let reused = diagnostics
+            .iter()
+            .find(|d| d.message.contains("already used"))
+            .unwrap();
+        assert_eq!(reused.severity, Severity::Error);
+        assert_eq!(reused.path, "GameCase/Manuals/ManualEntry");
+    }
+
+    #[test]
+    fn payloads_are_skipped_rather_than_checked_against_the_limits() {
+        let bytes = encode_case(&sample_case());
+        let limits = ReaderLimits {
+            max_binary_size: 100,
+            ..Default::default()
+        };
+
+        //The 300 byte ROM is over the limit, but never loaded.
+        let diagnostics =
+            validate_with_limits(Cursor::new(&bytes), limits).unwrap();
+        assert_eq!(diagnostics, []);
+
+        let limits = ReaderLimits { max_string_size: 4, ..limits };
+        let diagnostics =
+            validate_with_limits(Cursor::new(&bytes), limits).unwrap();
+        let stopped = diagnostics
+            .iter()
+            .find(|d| d.message.contains("validation stopped"))
+            .unwrap();
+        assert_eq!(stopped.severity, Severity::Error);
+        assert_eq!(stopped.path, "EBML/DocType");
+    }
+}
@@ -0,0 +1,445 @@
+use std::fmt;
+use std::io::{self, Read, Seek, SeekFrom};
+
+use ebml_iterable::specs::{EbmlSpecification, TagDataType};
+
+use crate::digest::{DigestAlgorithm, MultiHasher};
+use crate::ebml_tools::ElementSpan;
+use crate::gc_ebml_spec::{binary_id, element_name, master_id, GCEbmlSpec};
+use crate::lib_error_handling::LibError;
+use crate::lib_structs::FileProperties;
+use crate::parse::read_file_properties;
+use crate::payload::{PayloadLocation, PayloadReader};
+use crate::read::{expect_header, load_element};
+
+/// Outcome of verifying a single file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FileStatus {
+    /// Every stored digest and the stored size match the payload.
+    Pass,
+    /// At least one stored digest or the stored size does not match.
+    Fail,
+    /// The `FileProperties` carry no digest, so nothing could be checked.
+    Missing,
+}
+
+impl fmt::Display for FileStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let status = match self {
+            FileStatus::Pass => "pass",
+            FileStatus::Fail => "FAIL",
+            FileStatus::Missing => "missing",
+        };
+
+        f.write_str(status)
+    }
+}
+
+/// A stored digest compared against the one computed from the payload.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DigestResult {
+    pub algorithm: DigestAlgorithm,
+    pub expected: Vec<u8>,
+    pub actual: Vec<u8>,
+}
+
+impl DigestResult {
+    pub fn passed(&self) -> bool {
+        self.expected == self.actual
+    }
+}
+
+impl fmt::Display for DigestResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.passed() {
+            write!(f, "{} ok", self.algorithm)
+        } else {
+            write!(
+                f,
+                "{} mismatch: expected {}, computed {}",
+                self.algorithm,
+                hex(&self.expected),
+                hex(&self.actual),
+            )
+        }
+    }
+}
+
+/// The verification result of one file stored in a case.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FileVerification {
+    /// Path of the element holding the file, e.g.
+    /// `GameCase/Game/GameData/GameDataEntry/ChdData/CHDEntry[1]`.
+    pub path: String,
+    /// The `ROMFileName` of the file, if it has one.
+    pub file_name: Option<String>,
+    /// The `ROMFileSize` stored for the file.
+    pub expected_size: u64,
+    /// Where the file's contents are stored.
+    pub payload: PayloadLocation,
+    /// One result per digest stored in the `FileProperties`.
+    pub digests: Vec<DigestResult>,
+}
+
+impl FileVerification {
+    pub fn status(&self) -> FileStatus {
+        if self.expected_size != self.payload.size
+            || self.digests.iter().any(|digest| !digest.passed())
+        {
+            FileStatus::Fail
+        } else if self.digests.is_empty() {
+            FileStatus::Missing
+        } else {
+            FileStatus::Pass
+        }
+    }
+}
+
+impl fmt::Display for FileVerification {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.status(), self.path)?;
+        if let Some(file_name) = &self.file_name {
+            write!(f, " ({file_name})")?;
+        }
+        if self.expected_size != self.payload.size {
+            write!(
+                f,
+                "\n    size mismatch: expected {}, found {}",
+                self.expected_size,
+                self.payload.size,
+            )?;
+        }
+        for digest in &self.digests {
+            write!(f, "\n    {digest}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A file found within the case whose digests still have to be computed.
+pub(crate) struct StoredFile {
+    pub path: String,
+    pub properties: FileProperties,
+    pub payload: PayloadLocation,
+}
+
+impl StoredFile {
+    /// Pairs the file with the results of checking its digests.
+    pub(crate) fn into_verification(
+        self,
+        digests: Vec<DigestResult>,
+    ) -> FileVerification {
+        FileVerification {
+            path: self.path,
+            file_name: self.properties.rom_file_name,
+            expected_size: self.properties.rom_file_size,
+            payload: self.payload,
+            digests,
+        }
+    }
+}
+
+/// Verifies every file stored in the case against its `FileProperties`.
+///
+/// Files are stored in `RawData`, `CHDEntry` and `BinCueEntry` elements.
+/// The files described by `ArchiveFileMetadata` and `SSMCEntry` are only
+/// available once their container is extracted and are not checked.
+///
+/// # Arguments
+///
+/// * `source`: The file to verify.
+/// * `game_case_start`: Absolute offset of the `GameCase` element.
+/// * `progress`: Called with the number of bytes hashed so far and the
+///   total number of bytes to hash.
+///
+/// # Returns
+///
+/// A `Result` containing one `FileVerification` per file in file order.
+pub(crate) fn verify_files<R, F>(
+    source: &mut R,
+    game_case_start: u64,
+    mut progress: F,
+) -> Result<Vec<FileVerification>, LibError>
+where
+    R: Read + Seek,
+    F: FnMut(u64, u64),
+{
+    source.seek(SeekFrom::Start(game_case_start))?;
+    let header = expect_header(source, master_id(GCEbmlSpec::GameCase))?;
+
+    let mut files = Vec::new();
+    find_stored_files(
+        source,
+        ElementSpan::new(game_case_start, header),
+        "GameCase",
+        &mut files,
+    )?;
+
+    //Only the digests that are stored are computed.
+    let total: u64 = files
+        .iter()
+        .filter(|file| stored_algorithms(&file.properties).next().is_some())
+        .map(|file| file.payload.size)
+        .sum();
+    let mut hashed = 0;
+
+    let mut results = Vec::with_capacity(files.len());
+    for file in files {
+        let digests = digest_file(source, &file, |read, _| {
+            progress(hashed + read, total)
+        })?;
+        if !digests.is_empty() {
+            hashed += file.payload.size;
+        }
+
+        results.push(file.into_verification(digests));
+    }
+
+    Ok(results)
+}
+
+/// Computes the digests stored for a file from its payload.
+///
+/// # Arguments
+///
+/// * `source`: The file holding the payload.
+/// * `file`: The file to check.
+/// * `progress`: Called with the number of bytes of the payload hashed so
+///   far and the size of the payload.
+///
+/// # Returns
+///
+/// A `Result` containing one `DigestResult` per digest stored in the
+/// `FileProperties`, empty without reading anything if none is stored.
+pub(crate) fn digest_file<R, F>(
+    source: &mut R,
+    file: &StoredFile,
+    progress: F,
+) -> Result<Vec<DigestResult>, LibError>
+where
+    R: Read + Seek,
+    F: FnMut(u64, u64),
+{
+    let algorithms: Vec<_> = stored_algorithms(&file.properties).collect();
+    if algorithms.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    source.seek(SeekFrom::Start(file.payload.offset))?;
+    let mut hasher = MultiHasher::new(&algorithms);
+    let mut reader = PayloadReader::new(source, file.payload.size)
+        .with_progress(progress);
+
+    let copied = io::copy(&mut reader, &mut hasher)?;
+    if copied != file.payload.size {
+        return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+    }
+
+    Ok(hasher
+        .finalize()
+        .into_iter()
+        .map(|(algorithm, actual)| DigestResult {
+            algorithm,
+            expected: algorithm
+                .stored(&file.properties)
+                .unwrap_or_default()
+                .to_vec(),
+            actual,
+        })
+        .collect())
+}
+
+/// Returns the algorithms of every digest stored in `properties`.
+fn stored_algorithms(
+    properties: &FileProperties
+) -> impl Iterator<Item = DigestAlgorithm> + '_ {
+    DigestAlgorithm::ALL
+        .into_iter()
+        .filter(|algorithm| algorithm.stored(properties).is_some())
+}
+
+/// Returns the ID and name of the payload stored next to the
+/// `FileProperties` of a file holding element, or `None` if `id` is not
+/// such an element.
+pub(crate) fn stored_file_payload(id: u64) -> Option<(u64, &'static str)> {
+    if id == master_id(GCEbmlSpec::RawData) {
+        Some((binary_id(GCEbmlSpec::ROMData), "ROMData"))
+    } else if id == master_id(GCEbmlSpec::CHDEntry) {
+        Some((binary_id(GCEbmlSpec::CHDBinary), "CHDBinary"))
+    } else if id == master_id(GCEbmlSpec::BinCueEntry) {
+        Some((binary_id(GCEbmlSpec::BinBinary), "BinBinary"))
+    } else {
+        None
+    }
+}
+
+/// Walks the element headers below `path` and collects every stored file.
+pub(crate) fn find_stored_files<R: Read + Seek>(
+    source: &mut R,
+    element: ElementSpan,
+    path: &str,
+    files: &mut Vec<StoredFile>,
+) -> Result<(), LibError> {
+    let mut walker = element.children_at(path.to_string());
+    let mut seen = Vec::new();
+
+    while let Some(child) = walker.seek_next(source)? {
+        let id = child.header.id;
+        let index = seen.iter().filter(|seen| **seen == id).count();
+        seen.push(id);
+        let mut child_path = format!("{path}/{}", element_name(id));
+        if index > 0 {
+            child_path.push_str(&format!("[{index}]"));
+        }
+
+        if let Some(payload_element) = stored_file_payload(id) {
+            files.push(read_stored_file(
+                source,
+                child,
+                payload_element,
+                child_path,
+            )?);
+        } else if GCEbmlSpec::get_tag_data_type(id)
+            == Some(TagDataType::Master)
+        {
+            find_stored_files(source, child, &child_path, files)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads the `FileProperties` and locates the payload of a file holding
+/// element without loading the payload itself.
+pub(crate) fn read_stored_file<R: Read + Seek>(
+    source: &mut R,
+    element: ElementSpan,
+    (payload_id, payload_name): (u64, &'static str),
+    path: String,
+) -> Result<StoredFile, LibError> {
+    let mut walker = element.children_at(path.clone());
+    let mut properties = None;
+    let mut payload = None;
+
+    while let Some(child) = walker.seek_next(source)? {
+        let id = child.header.id;
+
+        if id == master_id(GCEbmlSpec::FileProperties) && properties.is_none()
+        {
+            properties = Some(read_file_properties(load_element(
+                source,
+                child.offset,
+                id,
+            )?));
+        } else if id == payload_id && payload.is_none() {
+            payload = Some(PayloadLocation {
+                id,
+                offset: child.data_start(),
+                size: child.header.size,
+            });
+        }
+    }
+
+    let missing = |name| LibError::MissingElement {
+        element: name,
+        offset: element.offset,
+        path: path.clone(),
+    };
+    let properties = properties.ok_or_else(|| missing("FileProperties"))?;
+    let payload = payload.ok_or_else(|| missing(payload_name))?;
+
+    Ok(StoredFile { path, properties, payload })
+}
+
+/// Formats bytes as lower case hexadecimal.
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::lib_structs::{GameCase, GameDataPayload};
+    use crate::read::GameCaseReader;
+    use crate::write::tests::{encode_case, sample_case};
+
+    const ALGORITHMS: [DigestAlgorithm; 2] =
+        [DigestAlgorithm::Crc32, DigestAlgorithm::Sha1];
+
+    //The sample case with the digests of its ROM stored.
+    fn case_with_digests() -> GameCase {
+        let mut case = sample_case();
+        let entry = &mut case.game.game_data.as_mut().unwrap().entries[0];
+        let GameDataPayload::Raw(raw) = &mut entry.payload else {
+            panic!("the sample ROM is stored raw");
+        };
+
+        let mut hasher = MultiHasher::new(&ALGORITHMS);
+        hasher.update(&raw.rom_data);
+        for (algorithm, value) in hasher.finalize() {
+            algorithm.store(&mut raw.file_properties, value);
+        }
+
+        case
+    }
+
+    fn verify(bytes: Vec<u8>) -> Vec<FileVerification> {
+        let mut reader = GameCaseReader::open(Cursor::new(bytes)).unwrap();
+        reader.verify(|_, _| {}).unwrap()
+    }
+
+    #[test]
+    fn intact_file_passes() {
+        let mut reports = Vec::new();
+        let mut reader =
+            GameCaseReader::open(Cursor::new(encode_case(&case_with_digests())))
+                .unwrap();
+        let results = reader
+            .verify(|hashed, total| reports.push((hashed, total)))
+            .unwrap();
+
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].status(), FileStatus::Pass);
+        assert_eq!(results[0].file_name.as_deref(), Some("zelda.nes"));
+        let algorithms: Vec<_> = results[0]
+            .digests
+            .iter()
+            .map(|digest| digest.algorithm)
+            .collect();
+        assert_eq!(algorithms, ALGORITHMS);
+        assert_eq!(reports.last(), Some(&(300, 300)));
+    }
+
+    #[test]
+    fn corrupted_byte_fails() {
+        let mut bytes = encode_case(&case_with_digests());
+        let mut reader =
+            GameCaseReader::open(Cursor::new(bytes.clone())).unwrap();
+        let entry = reader.index().entries[0].clone();
+        let rom = reader.payloads(&entry).unwrap()[0];
+        bytes[rom.offset as usize + 100] ^= 0xFF;
+
+        let results = verify(bytes);
+
+        assert_eq!(results[0].status(), FileStatus::Fail);
+        assert!(results[0].digests.iter().all(|digest| !digest.passed()));
+        assert_eq!(results[0].expected_size, results[0].payload.size);
+    }
+
+    #[test]
+    fn file_without_digests_is_missing() {
+        let mut progressed = false;
+        let mut reader =
+            GameCaseReader::open(Cursor::new(encode_case(&sample_case())))
+                .unwrap();
+        let results = reader.verify(|_, _| progressed = true).unwrap();
+
+        assert_eq!(results[0].status(), FileStatus::Missing);
+        assert!(results[0].digests.is_empty());
+        //Nothing is hashed when no digest is stored.
+        assert!(!progressed);
+    }
+}
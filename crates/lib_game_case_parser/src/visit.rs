@@ -0,0 +1,584 @@
+use std::collections::HashMap;
+use std::io::{self, Read};
+
+use ebml_iterable::specs::{EbmlSpecification, EbmlTag, TagDataType};
+
+use crate::date::ReleaseDate;
+use crate::ebml_tools::{
+    ChildWalker, ElementHeader, ElementSpan, CRC32_ID, VOID_ID,
+};
+use crate::gc_ebml_spec::{
+    binary_id, element_name, is_entry_id, master_id, uint_id, utf8_id,
+    DocTypeVersions, GCEbmlSpec,
+};
+use crate::lib_error_handling::LibError;
+use crate::lib_structs::{FileProperties, IndexEntry};
+use crate::limits::{limit_error, LimitTracker, LimitedReader, ReaderLimits};
+use crate::parse::{
+    check_ebml_header, decode_elements, read_file_properties,
+    read_index_entry, ElementNode,
+};
+use crate::payload::is_payload;
+use crate::query::CaseNode;
+
+/// Size of the chunks payloads are handed to `on_binary_chunk` in.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Receives the contents of a case as it is read by [`visit`].
+///
+/// Every method does nothing by default, so a visitor only implements the
+/// events it is interested in. Events arrive in file order.
+pub trait GameCaseVisitor {
+    /// Called once the EBML header has been checked.
+    fn on_header(&mut self, _versions: DocTypeVersions) {}
+
+    /// Called for every `IndexEntry`.
+    fn on_index_entry(&mut self, _entry: &IndexEntry) {}
+
+    /// Called for every value of `Game` and of its `Rating` and
+    /// `RegionInfo` children, such as `Title` or `Publisher`.
+    fn on_game_field(&mut self, _field: &Field<'_>) {}
+
+    /// Called when an entry, such as a `ManualEntry`, starts.
+    fn on_entry_start(&mut self, _entry: &EntryStart<'_>) {}
+
+    /// Called for every value within an entry that is neither a payload
+    /// nor part of a `FileProperties`, such as `EntryUID` or `Region`.
+    fn on_entry_field(&mut self, _field: &Field<'_>) {}
+
+    /// Called for every `FileProperties`.
+    ///
+    /// # Arguments
+    ///
+    /// * `properties`: The decoded `FileProperties`.
+    /// * `path`: Path of the `FileProperties` element.
+    fn on_file_properties(
+        &mut self,
+        _properties: &FileProperties,
+        _path: &str,
+    ) {}
+
+    /// Called with consecutive chunks of every payload, such as `ROMData`.
+    /// A payload is never held in memory as a whole.
+    fn on_binary_chunk(&mut self, _chunk: &BinaryChunk<'_>) {}
+
+    /// Called when an entry ends, after all of its other events.
+    fn on_entry_end(&mut self, _entry: &EntryEnd<'_>) {}
+}
+
+/// A single value passed to a [`GameCaseVisitor`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Field<'a> {
+    /// Element ID, e.g. the ID of `Publisher`.
+    pub id: u64,
+    /// Element name, e.g. "Publisher".
+    pub name: &'a str,
+    pub offset: u64,
+    pub path: &'a str,
+    /// The value, one of `CaseNode::Text`, `Uint`, `Binary` or `Date`.
+    pub value: CaseNode<'a>,
+}
+
+/// The start of an entry passed to [`GameCaseVisitor::on_entry_start`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EntryStart<'a> {
+    /// Element ID, e.g. the ID of `ManualEntry`.
+    pub id: u64,
+    pub name: &'a str,
+    pub offset: u64,
+    /// Total length of the entry, header included.
+    pub size: u64,
+    pub path: &'a str,
+    /// `Category` of the enclosing `ImageCollection` or `VideoCollection`,
+    /// if it was written before the entry.
+    pub collection: Option<&'a str>,
+}
+
+/// The end of an entry passed to [`GameCaseVisitor::on_entry_end`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EntryEnd<'a> {
+    pub id: u64,
+    pub name: &'a str,
+    pub offset: u64,
+    pub path: &'a str,
+    /// The `EntryUID` of the entry, `None` if it has none.
+    pub entry_uid: Option<u64>,
+}
+
+/// A chunk of a payload passed to [`GameCaseVisitor::on_binary_chunk`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BinaryChunk<'a> {
+    /// Element ID, e.g. the ID of `ImageData`.
+    pub id: u64,
+    pub name: &'a str,
+    /// Offset of the payload element.
+    pub offset: u64,
+    pub path: &'a str,
+    /// Size of the whole payload.
+    pub size: u64,
+    /// Position of the chunk within the payload.
+    pub position: u64,
+    pub data: &'a [u8],
+}
+
+/// Reads an EBML `.gcase` stream and reports its contents to `visitor`,
+/// checking every element against the default `ReaderLimits`.
+///
+/// See [`visit_with_limits`].
+pub fn visit<R: Read, V: GameCaseVisitor>(
+    source: R,
+    visitor: &mut V,
+) -> Result<(), LibError> {
+    visit_with_limits(source, visitor, ReaderLimits::default())
+}
+
+/// Reads an EBML `.gcase` stream and reports its contents to `visitor`.
+///
+/// Unlike [`process_ebml_data`](crate::process_ebml_data), no tree is
+/// built. Payloads are streamed in chunks and are not subject to
+/// `max_binary_size`, and every other element is decoded on its own and
+/// dropped once it was reported, so memory use does not grow with the
+/// size of the case. `Void` and `CRC-32` elements and elements unknown to
+/// this library are skipped.
+///
+/// Apart from the EBML header and the limits, the case is not checked,
+/// see `validate`.
+///
+/// # Arguments
+///
+/// * `source`: Any reader positioned at the start of the EBML header.
+/// * `visitor`: Receives the events.
+/// * `limits`: The limits every element is checked against.
+///
+/// # Returns
+///
+/// A `Result` that holds a `LibError` if the stream is not a readable
+/// GameCase document, a master is truncated, or an element breaks a
+/// limit. The events up to that point have already been delivered.
+pub fn visit_with_limits<R: Read, V: GameCaseVisitor>(
+    source: R,
+    visitor: &mut V,
+    limits: ReaderLimits,
+) -> Result<(), LibError> {
+    let tracker = LimitTracker::new(limits, 0, "").streaming_payloads();
+    let mut walker = Walker {
+        source: LimitedReader::new(source, tracker),
+        visitor,
+        top: ChildWalker::top_level(0),
+        open: Vec::new(),
+        roots: HashMap::new(),
+        collection: None,
+        header_seen: false,
+        game_case_seen: false,
+    };
+
+    walker.walk()
+}
+
+/// A master `Walker` is within.
+struct Frame {
+    id: u64,
+    offset: u64,
+    path: String,
+    children: ChildWalker,
+    siblings: HashMap<u64, usize>,
+    entry_uid: Option<u64>,
+}
+
+/// Drives a `GameCaseVisitor` from a stream, one element at a time.
+///
+/// `TagIterator` is not used here, as it buffers every element it emits
+/// as a whole and has no way to hand out part of one, so payloads could
+/// not be streamed. Element headers are walked with the `ChildWalker`
+/// every other reader uses, and only non-payload leaves and small masters
+/// such as `IndexEntry` are decoded, each on its own.
+struct Walker<'v, R: Read, V: GameCaseVisitor> {
+    source: LimitedReader<R>,
+    visitor: &'v mut V,
+    top: ChildWalker,
+    open: Vec<Frame>,
+    roots: HashMap<u64, usize>,
+    /// `Category` of the innermost open collection.
+    collection: Option<String>,
+    header_seen: bool,
+    game_case_seen: bool,
+}
+
+impl<R: Read, V: GameCaseVisitor> Walker<'_, R, V> {
+    fn walk(&mut self) -> Result<(), LibError> {
+        loop {
+            let children = match self.open.last_mut() {
+                Some(frame) => &mut frame.children,
+                None => &mut self.top,
+            };
+
+            match children.next(&mut self.source)? {
+                Some(child) => {
+                    let path = self.child_path(child.header.id);
+                    self.element(child, path)?;
+                }
+                None if self.open.is_empty() => break,
+                None => self.close(),
+            }
+        }
+
+        if !self.header_seen {
+            return Err(LibError::MissingElement {
+                element: "EBML",
+                offset: 0,
+                path: String::new(),
+            });
+        }
+        if !self.game_case_seen {
+            return Err(LibError::MissingElement {
+                element: "GameCase",
+                offset: 0,
+                path: String::new(),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Reports the element whose header was just read.
+    fn element(
+        &mut self,
+        child: ElementSpan,
+        path: String,
+    ) -> Result<(), LibError> {
+        let ElementSpan { offset, header, .. } = child;
+        let id = header.id;
+
+        if !self.header_seen && id != master_id(GCEbmlSpec::EBML) {
+            return Err(LibError::MissingElement {
+                element: "EBML",
+                offset: 0,
+                path: String::new(),
+            });
+        }
+        if id == master_id(GCEbmlSpec::EBML) {
+            let node = self.load(&header, offset)?;
+            let versions = check_ebml_header(&node)?;
+            self.header_seen = true;
+            self.visitor.on_header(versions);
+            return Ok(());
+        }
+        if id == master_id(GCEbmlSpec::IndexEntry) {
+            let entry = read_index_entry(self.load(&header, offset)?);
+            self.visitor.on_index_entry(&entry);
+            return Ok(());
+        }
+        if id == master_id(GCEbmlSpec::FileProperties) {
+            let node = self.load(&header, offset)?;
+            self.visitor.on_file_properties(&read_file_properties(node), &path);
+            return Ok(());
+        }
+        if is_payload(id) {
+            return self.stream(&header, offset, &path);
+        }
+        if id == VOID_ID || id == CRC32_ID {
+            return self.skip(&header);
+        }
+
+        match GCEbmlSpec::get_tag_data_type(id) {
+            Some(TagDataType::Master) => {
+                self.open(child, path);
+                Ok(())
+            }
+            None => self.skip(&header),
+            Some(_) => {
+                let node = self.load(&header, offset)?;
+                self.field(&node, path);
+                Ok(())
+            }
+        }
+    }
+
+    /// Enters a master, reporting it if it is an entry.
+    fn open(&mut self, child: ElementSpan, path: String) {
+        let ElementSpan { offset, header, .. } = child;
+        let id = header.id;
+
+        if id == master_id(GCEbmlSpec::GameCase) {
+            self.game_case_seen = true;
+        }
+        if id == master_id(GCEbmlSpec::ImageCollection)
+            || id == master_id(GCEbmlSpec::VideoCollection)
+        {
+            self.collection = None;
+        }
+        if is_entry_id(id) {
+            self.visitor.on_entry_start(&EntryStart {
+                id,
+                name: &element_name(id),
+                offset,
+                size: header.total_len(),
+                path: &path,
+                collection: self.collection.as_deref(),
+            });
+        }
+
+        self.open.push(Frame {
+            id,
+            offset,
+            children: child.children_at(path.clone()),
+            path,
+            siblings: HashMap::new(),
+            entry_uid: None,
+        });
+    }
+
+    /// Leaves the innermost open master.
+    fn close(&mut self) {
+        let Some(frame) = self.open.pop() else {
+            return;
+        };
+
+        if is_entry_id(frame.id) {
+            self.visitor.on_entry_end(&EntryEnd {
+                id: frame.id,
+                name: &element_name(frame.id),
+                offset: frame.offset,
+                path: &frame.path,
+                entry_uid: frame.entry_uid,
+            });
+        }
+    }
+
+    /// Reports a decoded value as a game or entry field.
+    fn field(&mut self, node: &ElementNode, path: String) {
+        let id = node.tag.get_id();
+        let date;
+        let value = if id == binary_id(GCEbmlSpec::ReleaseDate) {
+            date = ReleaseDate::from_bytes(node.tag.as_binary().unwrap_or(&[]));
+            CaseNode::Date(&date)
+        } else if let Some(text) = node.tag.as_utf8() {
+            CaseNode::Text(text)
+        } else if let Some(value) = node.tag.as_unsigned_int() {
+            CaseNode::Uint(*value)
+        } else if let Some(data) = node.tag.as_binary() {
+            CaseNode::Binary(data)
+        } else {
+            return;
+        };
+
+        let parent = self.open.last_mut();
+        let parent_id = parent.as_ref().map(|parent| parent.id);
+        if id == utf8_id(GCEbmlSpec::Category)
+            && (parent_id == Some(master_id(GCEbmlSpec::ImageCollection))
+                || parent_id == Some(master_id(GCEbmlSpec::VideoCollection)))
+        {
+            self.collection = value.as_str().map(str::to_string);
+        }
+        if let Some(parent) = parent
+            && is_entry_id(parent.id)
+            && id == uint_id(GCEbmlSpec::EntryUID)
+        {
+            parent.entry_uid = value.as_uint();
+        }
+
+        let field = Field {
+            id,
+            name: &element_name(id),
+            offset: node.offset,
+            path: &path,
+            value,
+        };
+        if self.open.iter().any(|frame| is_entry_id(frame.id)) {
+            self.visitor.on_entry_field(&field);
+        } else if self
+            .open
+            .iter()
+            .any(|frame| frame.id == master_id(GCEbmlSpec::Game))
+        {
+            self.visitor.on_game_field(&field);
+        }
+    }
+
+    /// Hands a payload to the visitor in chunks of `CHUNK_SIZE`.
+    fn stream(
+        &mut self,
+        header: &ElementHeader,
+        offset: u64,
+        path: &str,
+    ) -> Result<(), LibError> {
+        let name = element_name(header.id);
+        let mut chunk = vec![0u8; CHUNK_SIZE.min(header.size as usize)];
+        let mut position = 0;
+
+        while position < header.size {
+            let wanted = (header.size - position).min(chunk.len() as u64);
+            let read = self
+                .source
+                .read(&mut chunk[..wanted as usize])
+                .map_err(limit_error)?;
+            if read == 0 {
+                return Err(self.truncated());
+            }
+
+            self.visitor.on_binary_chunk(&BinaryChunk {
+                id: header.id,
+                name: &name,
+                offset,
+                path,
+                size: header.size,
+                position,
+                data: &chunk[..read],
+            });
+            position += read as u64;
+        }
+
+        Ok(())
+    }
+
+    /// Skips the data of the element whose header was just read.
+    fn skip(&mut self, header: &ElementHeader) -> Result<(), LibError> {
+        let skipped = io::copy(
+            &mut (&mut self.source).take(header.size),
+            &mut io::sink(),
+        )
+        .map_err(limit_error)?;
+
+        self.check_read(skipped, header.size)
+    }
+
+    /// Reads the data of the element whose header was just read and
+    /// decodes the whole element.
+    fn load(
+        &mut self,
+        header: &ElementHeader,
+        offset: u64,
+    ) -> Result<ElementNode, LibError> {
+        let mut bytes = header.encode();
+        let read = (&mut self.source)
+            .take(header.size)
+            .read_to_end(&mut bytes)
+            .map_err(limit_error)?;
+        self.check_read(read as u64, header.size)?;
+
+        decode_elements(&bytes, offset)?
+            .into_iter()
+            .next()
+            .ok_or(LibError::Io(io::ErrorKind::UnexpectedEof.into()))
+    }
+
+    /// Fails if the stream ended before all `expected` data bytes of an
+    /// element were read.
+    fn check_read(&self, read: u64, expected: u64) -> Result<(), LibError> {
+        if read < expected {
+            return Err(self.truncated());
+        }
+
+        Ok(())
+    }
+
+    /// Returns the error reporting the innermost open master as cut short.
+    fn truncated(&self) -> LibError {
+        match self.open.last() {
+            Some(frame) => frame.children.truncated(),
+            None => self.top.truncated(),
+        }
+    }
+
+    /// Returns the path of the next child with the given ID of the
+    /// innermost open master.
+    fn child_path(&mut self, id: u64) -> String {
+        let (parent, siblings) = match self.open.last_mut() {
+            Some(frame) => (frame.path.as_str(), &mut frame.siblings),
+            None => ("", &mut self.roots),
+        };
+        let index = siblings.entry(id).or_default();
+
+        let mut path = match parent {
+            "" => element_name(id),
+            parent => format!("{parent}/{}", element_name(id)),
+        };
+        if *index > 0 {
+            path.push_str(&format!("[{index}]"));
+        }
+        *index += 1;
+
+        path
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::lib_structs::GameDataPayload;
+    use crate::write::tests::{encode_case, sample_case};
+
+    /// Records the events it receives.
+    #[derive(Default)]
+    struct Recorder {
+        title: Option<String>,
+        events: Vec<String>,
+        rom: Vec<u8>,
+        rom_chunks: Vec<(u64, usize)>,
+    }
+
+    impl GameCaseVisitor for Recorder {
+        fn on_game_field(&mut self, field: &Field<'_>) {
+            if field.name == "Title" {
+                self.title = field.value.as_str().map(str::to_string);
+            }
+        }
+
+        fn on_entry_start(&mut self, entry: &EntryStart<'_>) {
+            self.events.push(format!("start {}", entry.name));
+        }
+
+        fn on_binary_chunk(&mut self, chunk: &BinaryChunk<'_>) {
+            if chunk.id == binary_id(GCEbmlSpec::ROMData) {
+                assert_eq!(chunk.position, self.rom.len() as u64);
+                self.rom.extend_from_slice(chunk.data);
+                self.rom_chunks.push((chunk.position, chunk.data.len()));
+            }
+        }
+
+        fn on_entry_end(&mut self, entry: &EntryEnd<'_>) {
+            self.events.push(format!("end {:?}", entry.entry_uid));
+        }
+    }
+
+    #[test]
+    fn payloads_arrive_in_chunks() {
+        let rom: Vec<u8> = (0..150_000).map(|i| i as u8).collect();
+        let mut game_case = sample_case();
+        let entry = &mut game_case.game.game_data.as_mut().unwrap().entries[0];
+        let GameDataPayload::Raw(raw) = &mut entry.payload else {
+            panic!("the sample ROM is stored raw");
+        };
+        raw.file_properties.rom_file_size = rom.len() as u64;
+        raw.rom_data = rom.clone();
+        let bytes = encode_case(&game_case);
+
+        //Payloads are streamed, so they are not held to max_binary_size.
+        let limits = ReaderLimits {
+            max_binary_size: 1000,
+            ..Default::default()
+        };
+        let mut recorder = Recorder::default();
+        visit_with_limits(Cursor::new(&bytes), &mut recorder, limits).unwrap();
+
+        assert_eq!(recorder.title.as_deref(), Some("Zelda"));
+        assert_eq!(
+            recorder.rom_chunks,
+            [(0, CHUNK_SIZE), (65_536, CHUNK_SIZE), (131_072, 18_928)]
+        );
+        assert!(recorder.rom == rom);
+        assert_eq!(
+            recorder.events,
+            [
+                "start GameDataEntry",
+                "end Some(1)",
+                "start ManualEntry",
+                "end Some(2)",
+                "start ImageEntry",
+                "end Some(3)",
+            ]
+        );
+    }
+}
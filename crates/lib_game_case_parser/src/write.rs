@@ -0,0 +1,1307 @@
+use std::collections::HashSet;
+use std::io::{self, Write};
+
+use ebml_iterable::specs::Master;
+
+use crate::date::ReleaseDate;
+use crate::ebml_tools::{
+    encode_element_header, encode_void, parse_element_header,
+};
+use crate::gc_ebml_spec::{
+    binary_id, container_ids, is_entry_id, master_id, uint_id, utf8_id,
+    DocTypeVersions, GCEbmlSpec, DOC_TYPE, EBML_VERSION,
+};
+use crate::lib_error_handling::LibError;
+use crate::lib_structs::{
+    ArchiveData, BinCueData, ChdData, FileProperties, Game, GameCase,
+    GameDataEntry, GameDataPayload, ImageEntry, IndexEntry, ManualEntry,
+    Media, RawData, RomHackEntry, SsmcData, UnknownElement, VideoEntry,
+};
+use crate::read::CaseEntry;
+
+/// Default length of the `Void` element reserved after the `Index`.
+pub const DEFAULT_INDEX_PADDING: u64 = 4096;
+
+/// Default length of the `Void` element reserved after the `Game` metadata.
+pub const DEFAULT_GAME_PADDING: u64 = 4096;
+
+/// Writes [`GameCase`] documents as spec compliant `.gcase` files.
+///
+/// The `Index` of the written file is always generated from the entries of
+/// the case, so the `index` member of the supplied `GameCase` is ignored.
+///
+/// `Void` padding is reserved after the `Index` and after the metadata of
+/// `Game`, in front of `GameData`. It lets both grow later on without
+/// moving any entry, see [`crate::edit`].
+///
+/// Payloads are written straight from the case, so writing takes no more
+/// memory than the case itself.
+pub struct GameCaseWriter<W: Write> {
+    dest: W,
+    index_padding: u64,
+    game_padding: u64,
+}
+
+impl<W: Write> GameCaseWriter<W> {
+    pub fn new(dest: W) -> Self {
+        GameCaseWriter {
+            dest,
+            index_padding: DEFAULT_INDEX_PADDING,
+            game_padding: DEFAULT_GAME_PADDING,
+        }
+    }
+
+    /// Sets the total length of the `Void` element written after the
+    /// `Index`. Zero disables the padding and a length of one is rounded up
+    /// to two, the shortest possible `Void`.
+    pub fn with_index_padding(mut self, len: u64) -> Self {
+        self.index_padding = len;
+        self
+    }
+
+    /// Sets the total length of the `Void` element written after the
+    /// `Game` metadata, see `with_index_padding`.
+    pub fn with_game_padding(mut self, len: u64) -> Self {
+        self.game_padding = len;
+        self
+    }
+
+    /// Writes the EBML header followed by the complete `GameCase` element.
+    ///
+    /// # Arguments
+    ///
+    /// * `game_case`: The case to serialize.
+    ///
+    /// # Returns
+    ///
+    /// An empty `Result`, or a `LibError` if encoding or writing failed.
+    /// Nothing is written if two entries share an `EntryUID`, see
+    /// [`crate::uid::UidAllocator`] for assigning unique ones.
+    pub fn write(&mut self, game_case: &GameCase) -> Result<(), LibError> {
+        let targets = index_targets(game_case);
+        check_unique_uids(&targets)?;
+
+        let versions = self.doc_type_versions();
+        let header = encode(|writer| write_ebml_header(writer, versions))?;
+
+        let body = body_writer(game_case, self.game_padding);
+        let layout = measure(&body)?;
+        let index_padding = encode_void(self.index_padding);
+
+        //Entries are located past the padding that follows the Index.
+        let entry_offsets: Vec<u64> = layout.entries
+            .iter()
+            .map(|offset| index_padding.len() as u64 + offset)
+            .collect();
+        let index = encode_index(&targets, &entry_offsets)?;
+
+        /*The GameCase element is assembled by hand since the Index is
+        already encoded and the size of the body is known. */
+        let dest = &mut self.dest;
+        dest.write_all(&header)?;
+        dest.write_all(&encode_element_header(
+            master_id(GCEbmlSpec::GameCase),
+            index.len() as u64 + index_padding.len() as u64 + layout.len,
+        ))?;
+        dest.write_all(&index)?;
+        dest.write_all(&index_padding)?;
+        write_measured(dest, layout, &body)?.flush()?;
+
+        Ok(())
+    }
+
+    /// Returns the `DocTypeVersion` and `DocTypeReadVersion` recorded in
+    /// the EBML header of written files.
+    ///
+    /// Every file is written for the newest version of the format this
+    /// library knows, so readers can refuse it if they are older.
+    pub fn doc_type_versions(&self) -> DocTypeVersions {
+        DocTypeVersions::CURRENT
+    }
+
+    /// Consumes the writer and returns the underlying destination.
+    pub fn into_inner(self) -> W {
+        self.dest
+    }
+}
+
+/// Writes elements without buffering them, putting the unknown children
+/// of every master back where they were read, see `UnknownElement`.
+///
+/// Every known child written through the functions of this module is
+/// counted, so the unknown children can be slotted in between them.
+///
+/// The size of a master is written in front of its children, so elements
+/// are written in two passes over the same writing code. The first pass
+/// only measures the size of every master, see `measure`, and the second
+/// writes the elements using those sizes, see `write_measured`.
+pub(crate) struct ElementWriter<'a, W: Write> {
+    /// The destination, `None` while measuring.
+    dest: Option<W>,
+    /// Number of bytes written, or measured, so far.
+    position: u64,
+    open: Vec<OpenMaster<'a>>,
+    /// Data sizes of the masters in the order they are started.
+    sizes: Vec<u64>,
+    started: usize,
+    /// Offsets of the entries started so far.
+    entries: Vec<u64>,
+}
+
+/// A master whose children are being written.
+struct OpenMaster<'a> {
+    id: u64,
+    /// The unknown children still to be written.
+    unknown: &'a [UnknownElement],
+    /// Number of known children written so far.
+    written: u64,
+    /// Position of the master within `ElementWriter::sizes`, `None` for
+    /// the implicit master holding the top level elements.
+    slot: Option<usize>,
+    /// Offset of the first byte of the master's data.
+    data_start: u64,
+}
+
+/// The sizes and entry offsets of elements found by `measure`.
+pub(crate) struct Layout {
+    sizes: Vec<u64>,
+    /// Total length of the measured elements.
+    pub len: u64,
+    /// Offset of every entry in document order, relative to the first
+    /// measured element.
+    pub entries: Vec<u64>,
+}
+
+impl<'a, W: Write> ElementWriter<'a, W> {
+    fn new(dest: Option<W>, sizes: Vec<u64>) -> Self {
+        ElementWriter {
+            dest,
+            position: 0,
+            open: vec![OpenMaster {
+                id: 0,
+                unknown: &[],
+                written: 0,
+                slot: None,
+                data_start: 0,
+            }],
+            sizes,
+            started: 0,
+            entries: Vec::new(),
+        }
+    }
+
+    /// Writes `bytes`, or only counts them while measuring.
+    fn emit(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.position += bytes.len() as u64;
+        match &mut self.dest {
+            Some(dest) => dest.write_all(bytes),
+            None => Ok(()),
+        }
+    }
+
+    /// Writes an element holding `data` as it is.
+    fn element(&mut self, id: u64, data: &[u8]) -> Result<(), LibError> {
+        self.emit(&id_bytes(id))?;
+        self.emit(&size_vint(data.len() as u64))?;
+        self.emit(data)?;
+        Ok(())
+    }
+
+    /// Writes the unknown children of the innermost open master that belong
+    /// in front of its next known child, which is then counted.
+    fn next_child(&mut self) -> Result<(), LibError> {
+        let Some(master) = self.open.last_mut() else {
+            return Ok(());
+        };
+
+        let pending: &'a [UnknownElement] = master.unknown;
+        let due = pending
+            .iter()
+            .take_while(|element| element.position <= master.written)
+            .count();
+        let (due, rest) = pending.split_at(due);
+        master.unknown = rest;
+        master.written += 1;
+
+        write_unknown(self, due)
+    }
+
+    /// Sets the unknown children of the innermost open master, of which
+    /// `written` known children were written elsewhere.
+    fn resume(&mut self, unknown: &'a [UnknownElement], written: u64) {
+        if let Some(master) = self.open.last_mut() {
+            master.unknown = unknown;
+            master.written = written;
+        }
+    }
+
+    /// Starts a master, recording its offset if it is an entry.
+    fn start_master(
+        &mut self,
+        id: u64,
+        unknown: &'a [UnknownElement],
+    ) -> Result<(), LibError> {
+        self.next_child()?;
+        if is_entry_id(id) {
+            self.entries.push(self.position);
+        }
+
+        let slot = self.started;
+        self.started += 1;
+        let size = match self.dest {
+            Some(_) => self.sizes.get(slot).copied().unwrap_or_default(),
+            None => {
+                self.sizes.push(0);
+                0
+            }
+        };
+
+        /*Masters use a fixed width size so their size can be patched in
+        place later on. */
+        self.emit(&encode_element_header(id, size))?;
+        self.open.push(OpenMaster {
+            id,
+            unknown,
+            written: 0,
+            slot: Some(slot),
+            data_start: self.position,
+        });
+        Ok(())
+    }
+
+    /// Ends the innermost open master after writing the unknown children
+    /// that follow its last known child.
+    fn end_master(&mut self) -> Result<(), LibError> {
+        let Some(master) = self.open.pop() else {
+            return Ok(());
+        };
+        write_unknown(self, master.unknown)?;
+
+        if let (None, Some(slot)) = (&self.dest, master.slot) {
+            self.sizes[slot] = self.position - master.data_start;
+        }
+        Ok(())
+    }
+
+    /// Ends every open master, including the implicit top level one.
+    fn finish(&mut self) -> Result<(), LibError> {
+        while !self.open.is_empty() {
+            self.end_master()?;
+        }
+        Ok(())
+    }
+}
+
+/// Runs `write_fn` without writing anything to find the size of every
+/// master it writes.
+pub(crate) fn measure<'a, W, F>(write_fn: &F) -> Result<Layout, LibError>
+where
+    W: Write,
+    F: Fn(&mut ElementWriter<'a, W>) -> Result<(), LibError>,
+{
+    let mut writer = ElementWriter::new(None, Vec::new());
+    write_fn(&mut writer)?;
+    writer.finish()?;
+
+    Ok(Layout {
+        sizes: writer.sizes,
+        len: writer.position,
+        entries: writer.entries,
+    })
+}
+
+/// Runs `write_fn` again to write the elements measured by `measure` to
+/// `dest`.
+///
+/// # Returns
+///
+/// A `Result` containing `dest`, or a `LibError` if writing failed.
+pub(crate) fn write_measured<'a, W, F>(
+    dest: W,
+    layout: Layout,
+    write_fn: &F,
+) -> Result<W, LibError>
+where
+    W: Write,
+    F: Fn(&mut ElementWriter<'a, W>) -> Result<(), LibError>,
+{
+    let mut writer = ElementWriter::new(Some(dest), layout.sizes);
+    write_fn(&mut writer)?;
+    writer.finish()?;
+
+    //ElementWriter::new was given a destination, which is never taken.
+    Ok(writer.dest.expect("measured elements are written to a destination"))
+}
+
+/// Runs `write_fn` against an in-memory writer and returns the bytes.
+fn encode<'a, F>(write_fn: F) -> Result<Vec<u8>, LibError>
+where
+    F: Fn(&mut ElementWriter<'a, Vec<u8>>) -> Result<(), LibError>,
+{
+    let layout = measure(&write_fn)?;
+    write_measured(Vec::with_capacity(layout.len as usize), layout, &write_fn)
+}
+
+/// Length of the `GameCase` element header as written by this module.
+fn game_case_header_len() -> u64 {
+    encode_element_header(master_id(GCEbmlSpec::GameCase), 0).len() as u64
+}
+
+/// Lists an index entry for every indexable entry of `game_case`.
+///
+/// Entries are listed in the order they are written, with `position` left
+/// at zero to be filled in once the layout of the file is known.
+pub(crate) fn index_targets(game_case: &GameCase) -> Vec<IndexEntry> {
+    let mut targets = Vec::new();
+
+    if let Some(game_data) = &game_case.game.game_data {
+        targets.extend(game_data.entries.iter().map(game_data_target));
+    }
+
+    if let Some(manuals) = &game_case.manuals {
+        targets.extend(manuals.entries.iter().map(manual_target));
+    }
+
+    if let Some(rom_hacks) = &game_case.rom_hacks {
+        targets.extend(rom_hacks.entries.iter().map(rom_hack_target));
+    }
+
+    if let Some(media) = &game_case.media {
+        for collection in &media.image_collections {
+            targets.extend(
+                collection.entries
+                    .iter()
+                    .map(|entry| image_target(&collection.category, entry)),
+            );
+        }
+
+        for collection in &media.video_collections {
+            targets.extend(
+                collection.entries
+                    .iter()
+                    .map(|entry| video_target(&collection.category, entry)),
+            );
+        }
+    }
+
+    targets
+}
+
+pub(crate) fn game_data_target(entry: &GameDataEntry) -> IndexEntry {
+    IndexEntry {
+        target_id: master_id(GCEbmlSpec::GameDataEntry),
+        target: "GameData".to_string(),
+        category: Some(entry.payload.data_format().to_string()),
+        region: game_data_region(entry),
+        uid: entry.entry_uid,
+        ..Default::default()
+    }
+}
+
+pub(crate) fn manual_target(entry: &ManualEntry) -> IndexEntry {
+    IndexEntry {
+        target_id: master_id(GCEbmlSpec::ManualEntry),
+        target: "Manual".to_string(),
+        region: Some(entry.region.clone()),
+        uid: entry.entry_uid,
+        ..Default::default()
+    }
+}
+
+pub(crate) fn rom_hack_target(entry: &RomHackEntry) -> IndexEntry {
+    IndexEntry {
+        target_id: master_id(GCEbmlSpec::RomHackEntry),
+        target: "RomHack".to_string(),
+        uid: entry.entry_uid,
+        ..Default::default()
+    }
+}
+
+/// Images are indexed under the category of their collection, e.g.
+/// "BoxArt", with the detail such as "Front" as the index category.
+pub(crate) fn image_target(category: &str, entry: &ImageEntry) -> IndexEntry {
+    IndexEntry {
+        target_id: master_id(GCEbmlSpec::ImageEntry),
+        target: category.to_string(),
+        category: entry.category_detail.clone(),
+        region: entry.region.clone(),
+        uid: entry.entry_uid,
+        ..Default::default()
+    }
+}
+
+pub(crate) fn video_target(category: &str, entry: &VideoEntry) -> IndexEntry {
+    IndexEntry {
+        target_id: master_id(GCEbmlSpec::VideoEntry),
+        target: "Video".to_string(),
+        category: Some(category.to_string()),
+        uid: entry.entry_uid,
+        ..Default::default()
+    }
+}
+
+/// Returns the region a game data entry should be indexed under.
+fn game_data_region(entry: &GameDataEntry) -> Option<String> {
+    match &entry.payload {
+        GameDataPayload::Raw(raw_data) => {
+            raw_data.file_properties.region.clone()
+        }
+        GameDataPayload::Chd(chd_data) => Some(chd_data.region.clone()),
+        GameDataPayload::BinCue(bin_cue_data) => {
+            Some(bin_cue_data.region.clone())
+        }
+        GameDataPayload::Archive(_) | GameDataPayload::Ssmc(_) => None,
+    }
+}
+
+/// Finds the offsets of all indexable entries within encoded elements.
+///
+/// # Arguments
+///
+/// * `bytes`: A sequence of encoded sibling elements.
+/// * `base`: The offset of `bytes` relative to the start of the document.
+/// * `found`: Receives the offset of every entry in document order.
+pub(crate) fn locate_entries(bytes: &[u8], base: u64, found: &mut Vec<u64>) {
+    let mut position = 0usize;
+    while let Some(header) = parse_element_header(&bytes[position..]) {
+        let data_start = position + header.header_len as usize;
+        let data_end = position + header.total_len() as usize;
+
+        if is_entry_id(header.id) {
+            found.push(base + position as u64);
+        } else if container_ids().contains(&header.id) {
+            locate_entries(
+                &bytes[data_start..data_end],
+                base + data_start as u64,
+                found,
+            );
+        }
+
+        position = data_end;
+    }
+}
+
+/// Encodes the `Index` element for the given targets.
+///
+/// `IndexPosition` values are relative to the start of the `GameCase`
+/// element, which puts the `Index` itself in front of every target. As the
+/// encoded length of the positions depends on their values the index is
+/// re-encoded until its length no longer changes.
+pub(crate) fn encode_index(
+    targets: &[IndexEntry],
+    entry_offsets: &[u64],
+) -> Result<Vec<u8>, LibError> {
+    let header_len = game_case_header_len();
+    let mut index_len = 0u64;
+
+    loop {
+        let entries: Vec<IndexEntry> = targets
+            .iter()
+            .zip(entry_offsets)
+            .map(|(target, offset)| IndexEntry {
+                position: header_len + index_len + offset,
+                ..target.clone()
+            })
+            .collect();
+
+        let index = encode(|writer| write_index(writer, &entries))?;
+        if index.len() as u64 == index_len {
+            return Ok(index);
+        }
+        index_len = index.len() as u64;
+    }
+}
+
+/// Encodes an element ID, which keeps its VINT marker bit.
+fn id_bytes(id: u64) -> Vec<u8> {
+    id.to_be_bytes()
+        .into_iter()
+        .skip_while(|byte| *byte == 0)
+        .collect()
+}
+
+/// Encodes a data size of `len` as the shortest VINT that avoids the
+/// reserved all-ones value, which readers treat as an unknown size.
+fn size_vint(len: u64) -> Vec<u8> {
+    let width = (1..8)
+        .find(|width| len < (1u64 << (7 * width)) - 1)
+        .unwrap_or(8);
+
+    let mut bytes = len.to_be_bytes()[8 - width..].to_vec();
+    bytes[0] |= 0x80 >> (width - 1);
+    bytes
+}
+
+/// Starts a master element without unknown children to keep.
+fn start<W: Write>(
+    writer: &mut ElementWriter<'_, W>,
+    tag: fn(Master<GCEbmlSpec>) -> GCEbmlSpec,
+) -> Result<(), LibError> {
+    start_with(writer, tag, &[])
+}
+
+/// Starts a master element, writing `unknown` back between the children
+/// that follow.
+fn start_with<'a, W: Write>(
+    writer: &mut ElementWriter<'a, W>,
+    tag: fn(Master<GCEbmlSpec>) -> GCEbmlSpec,
+    unknown: &'a [UnknownElement],
+) -> Result<(), LibError> {
+    writer.start_master(master_id(tag), unknown)
+}
+
+/// Ends a master element after writing the unknown children that follow
+/// its last known child.
+fn end<W: Write>(
+    writer: &mut ElementWriter<'_, W>,
+    tag: fn(Master<GCEbmlSpec>) -> GCEbmlSpec,
+) -> Result<(), LibError> {
+    debug_assert_eq!(
+        writer.open.last().map(|master| master.id),
+        Some(master_id(tag)),
+    );
+    writer.end_master()
+}
+
+/// Writes an unsigned integer using the shortest of 1, 2, 4 or 8 bytes.
+fn write_uint<W: Write>(
+    writer: &mut ElementWriter<'_, W>,
+    tag: fn(u64) -> GCEbmlSpec,
+    value: u64,
+) -> Result<(), LibError> {
+    writer.next_child()?;
+    let len = match value {
+        0..=0xFF => 1,
+        0x100..=0xFFFF => 2,
+        0x1_0000..=0xFFFF_FFFF => 4,
+        _ => 8,
+    };
+    writer.element(uint_id(tag), &value.to_be_bytes()[8 - len..])
+}
+
+fn write_utf8<W: Write>(
+    writer: &mut ElementWriter<'_, W>,
+    tag: fn(String) -> GCEbmlSpec,
+    value: &str,
+) -> Result<(), LibError> {
+    writer.next_child()?;
+    writer.element(utf8_id(tag), value.as_bytes())
+}
+
+fn write_binary<W: Write>(
+    writer: &mut ElementWriter<'_, W>,
+    tag: fn(Vec<u8>) -> GCEbmlSpec,
+    data: &[u8],
+) -> Result<(), LibError> {
+    writer.next_child()?;
+    writer.element(binary_id(tag), data)
+}
+
+fn write_unknown<W: Write>(
+    writer: &mut ElementWriter<'_, W>,
+    unknown: &[UnknownElement],
+) -> Result<(), LibError> {
+    for element in unknown {
+        writer.element(element.id, &element.data)?;
+    }
+    Ok(())
+}
+
+fn write_ebml_header<W: Write>(
+    writer: &mut ElementWriter<'_, W>,
+    versions: DocTypeVersions,
+) -> Result<(), LibError> {
+    start(writer, GCEbmlSpec::EBML)?;
+    write_uint(writer, GCEbmlSpec::EBMLVersion, EBML_VERSION)?;
+    write_utf8(writer, GCEbmlSpec::DocType, DOC_TYPE)?;
+    write_uint(writer, GCEbmlSpec::DocTypeVersion, versions.version)?;
+    write_uint(writer, GCEbmlSpec::DocTypeReadVersion, versions.read_version)?;
+    end(writer, GCEbmlSpec::EBML)
+}
+
+fn write_index<W: Write>(
+    writer: &mut ElementWriter<'_, W>,
+    entries: &[IndexEntry],
+) -> Result<(), LibError> {
+    start(writer, GCEbmlSpec::Index)?;
+    for entry in entries {
+        start(writer, GCEbmlSpec::IndexEntry)?;
+        write_uint(writer, GCEbmlSpec::IndexTargetID, entry.target_id)?;
+        write_uint(writer, GCEbmlSpec::IndexPosition, entry.position)?;
+        write_utf8(writer, GCEbmlSpec::IndexTarget, &entry.target)?;
+        if let Some(category) = &entry.category {
+            write_utf8(writer, GCEbmlSpec::IndexTargetCategory, category)?;
+        }
+        if let Some(region) = &entry.region {
+            write_utf8(writer, GCEbmlSpec::IndexTargetRegion, region)?;
+        }
+        write_uint(writer, GCEbmlSpec::IndexTargetUID, entry.uid)?;
+        end(writer, GCEbmlSpec::IndexEntry)?;
+    }
+    end(writer, GCEbmlSpec::Index)
+}
+
+/// A borrowed entry of any type, see `encode_entry`.
+#[derive(Clone, Copy)]
+pub(crate) enum EntryRef<'a> {
+    GameData(&'a GameDataEntry),
+    Manual(&'a ManualEntry),
+    RomHack(&'a RomHackEntry),
+    Image(&'a ImageEntry),
+    Video(&'a VideoEntry),
+}
+
+impl<'a> From<&'a CaseEntry> for EntryRef<'a> {
+    fn from(entry: &'a CaseEntry) -> Self {
+        match entry {
+            CaseEntry::GameData(entry) => EntryRef::GameData(entry),
+            CaseEntry::Manual(entry) => EntryRef::Manual(entry),
+            CaseEntry::RomHack(entry) => EntryRef::RomHack(entry),
+            CaseEntry::Image(entry) => EntryRef::Image(entry),
+            CaseEntry::Video(entry) => EntryRef::Video(entry),
+        }
+    }
+}
+
+/// Encodes a single entry inside the masters that hold it, e.g. `Manuals`
+/// for a `ManualEntry`, with the header of `GameCase` stripped.
+pub(crate) fn encode_entry(entry: EntryRef<'_>) -> Result<Vec<u8>, LibError> {
+    encode(|writer| match entry {
+        EntryRef::GameData(entry) => {
+            start(writer, GCEbmlSpec::Game)?;
+            start(writer, GCEbmlSpec::GameData)?;
+            write_game_data_entry(writer, entry)?;
+            end(writer, GCEbmlSpec::GameData)?;
+            end(writer, GCEbmlSpec::Game)
+        }
+        EntryRef::Manual(entry) => {
+            start(writer, GCEbmlSpec::Manuals)?;
+            write_manual_entry(writer, entry)?;
+            end(writer, GCEbmlSpec::Manuals)
+        }
+        EntryRef::RomHack(entry) => {
+            start(writer, GCEbmlSpec::RomHacks)?;
+            write_rom_hack_entry(writer, entry)?;
+            end(writer, GCEbmlSpec::RomHacks)
+        }
+        EntryRef::Image(entry) => {
+            start(writer, GCEbmlSpec::Media)?;
+            start(writer, GCEbmlSpec::ImageCollection)?;
+            write_image_entry(writer, entry)?;
+            end(writer, GCEbmlSpec::ImageCollection)?;
+            end(writer, GCEbmlSpec::Media)
+        }
+        EntryRef::Video(entry) => {
+            start(writer, GCEbmlSpec::Media)?;
+            start(writer, GCEbmlSpec::VideoCollection)?;
+            write_video_entry(writer, entry)?;
+            end(writer, GCEbmlSpec::VideoCollection)?;
+            end(writer, GCEbmlSpec::Media)
+        }
+    })
+}
+
+/// Returns an error if two entries of `targets` share an `EntryUID`.
+pub(crate) fn check_unique_uids(
+    targets: &[IndexEntry]
+) -> Result<(), LibError> {
+    let mut seen = HashSet::new();
+
+    match targets.iter().find(|target| !seen.insert(target.uid)) {
+        Some(target) => Err(LibError::DuplicateEntryUid(target.uid)),
+        None => Ok(()),
+    }
+}
+
+/// Writes every child of `GameCase` that follows the `Index`.
+fn write_body<'a, W: Write>(
+    writer: &mut ElementWriter<'a, W>,
+    game_case: &'a GameCase,
+    game_padding: u64,
+) -> Result<(), LibError> {
+    //The Index is encoded on its own, but is the first child of GameCase.
+    writer.resume(&game_case.unknown, 1);
+    write_game(writer, &game_case.game, game_padding)?;
+    write_extras(writer, game_case)
+}
+
+/// Returns a function writing the body of `game_case`, see `write_body`.
+fn body_writer<'a, W: Write>(
+    game_case: &'a GameCase,
+    game_padding: u64,
+) -> impl Fn(&mut ElementWriter<'a, W>) -> Result<(), LibError> {
+    move |writer| write_body(writer, game_case, game_padding)
+}
+
+/// Encodes the `Manuals`, `RomHacks` and `Media` of `game_case`, the
+/// children of `GameCase` that follow `Game`.
+pub(crate) fn encode_extras(game_case: &GameCase) -> Result<Vec<u8>, LibError> {
+    encode(|writer| write_extras(writer, game_case))
+}
+
+/// Encodes an `Index` element holding `entries` as they are.
+pub(crate) fn encode_index_entries(
+    entries: &[IndexEntry]
+) -> Result<Vec<u8>, LibError> {
+    encode(|writer| write_index(writer, entries))
+}
+
+fn write_extras<'a, W: Write>(
+    writer: &mut ElementWriter<'a, W>,
+    game_case: &'a GameCase,
+) -> Result<(), LibError> {
+    if let Some(manuals) = &game_case.manuals {
+        start_with(writer, GCEbmlSpec::Manuals, &manuals.unknown)?;
+        for entry in &manuals.entries {
+            write_manual_entry(writer, entry)?;
+        }
+        end(writer, GCEbmlSpec::Manuals)?;
+    }
+
+    if let Some(rom_hacks) = &game_case.rom_hacks {
+        start_with(writer, GCEbmlSpec::RomHacks, &rom_hacks.unknown)?;
+        for entry in &rom_hacks.entries {
+            write_rom_hack_entry(writer, entry)?;
+        }
+        end(writer, GCEbmlSpec::RomHacks)?;
+    }
+
+    if let Some(media) = &game_case.media {
+        write_media(writer, media)?;
+    }
+
+    Ok(())
+}
+
+fn write_game<'a, W: Write>(
+    writer: &mut ElementWriter<'a, W>,
+    game: &'a Game,
+    padding: u64,
+) -> Result<(), LibError> {
+    start_with(writer, GCEbmlSpec::Game, &game.unknown)?;
+    write_game_metadata(writer, game)?;
+
+    writer.emit(&encode_void(padding))?;
+
+    if let Some(game_data) = &game.game_data {
+        start_with(writer, GCEbmlSpec::GameData, &game_data.unknown)?;
+        for entry in &game_data.entries {
+            write_game_data_entry(writer, entry)?;
+        }
+        end(writer, GCEbmlSpec::GameData)?;
+    }
+
+    end(writer, GCEbmlSpec::Game)
+}
+
+/// Encodes every child of `Game` except `GameData`.
+///
+/// # Returns
+///
+/// A `Result` containing the encoded children, which are written in front
+/// of `GameData`.
+pub(crate) fn encode_game_metadata(game: &Game) -> Result<Vec<u8>, LibError> {
+    let mut bytes = encode(|writer| {
+        start_with(writer, GCEbmlSpec::Game, &game.unknown)?;
+        write_game_metadata(writer, game)?;
+        end(writer, GCEbmlSpec::Game)
+    })?;
+    bytes.drain(
+        ..encode_element_header(master_id(GCEbmlSpec::Game), 0).len()
+    );
+
+    Ok(bytes)
+}
+
+fn write_game_metadata<'a, W: Write>(
+    writer: &mut ElementWriter<'a, W>,
+    game: &'a Game,
+) -> Result<(), LibError> {
+    write_utf8(writer, GCEbmlSpec::Title, &game.title)?;
+    for developer in &game.developers {
+        write_utf8(writer, GCEbmlSpec::Developer, developer)?;
+    }
+    if let Some(genre) = &game.genre {
+        write_utf8(writer, GCEbmlSpec::Genre, genre)?;
+    }
+    if let Some(min_players) = game.min_players {
+        write_uint(writer, GCEbmlSpec::MinPlayers, min_players)?;
+    }
+    if let Some(max_players) = game.max_players {
+        write_uint(writer, GCEbmlSpec::MaxPlayers, max_players)?;
+    }
+    write_utf8(writer, GCEbmlSpec::GameSystem, &game.game_system)?;
+    if let Some(description) = &game.description {
+        write_utf8(writer, GCEbmlSpec::Description, description)?;
+    }
+
+    if let Some(rating) = &game.rating {
+        start_with(writer, GCEbmlSpec::Rating, &rating.unknown)?;
+        write_utf8(writer, GCEbmlSpec::RatingValue, &rating.value)?;
+        write_utf8(writer, GCEbmlSpec::RatingSource, &rating.source)?;
+        end(writer, GCEbmlSpec::Rating)?;
+    }
+
+    for region_info in &game.region_info {
+        start_with(writer, GCEbmlSpec::RegionInfo, &region_info.unknown)?;
+        write_utf8(writer, GCEbmlSpec::Region, &region_info.region)?;
+        for language in &region_info.languages {
+            write_utf8(writer, GCEbmlSpec::Language, language)?;
+        }
+        write_binary(
+            writer,
+            GCEbmlSpec::ReleaseDate,
+            &region_info.release_date.to_bytes()
+        )?;
+        let precision = region_info.release_date.precision();
+        if precision != ReleaseDate::PRECISION_DAY {
+            write_uint(writer, GCEbmlSpec::ReleaseDatePrecision, precision)?;
+        }
+        write_utf8(writer, GCEbmlSpec::Publisher, &region_info.publisher)?;
+        write_utf8(writer, GCEbmlSpec::AgeRating, &region_info.age_rating)?;
+        end(writer, GCEbmlSpec::RegionInfo)?;
+    }
+
+    Ok(())
+}
+
+pub(crate) fn write_game_data_entry<'a, W: Write>(
+    writer: &mut ElementWriter<'a, W>,
+    entry: &'a GameDataEntry,
+) -> Result<(), LibError> {
+    start_with(writer, GCEbmlSpec::GameDataEntry, &entry.unknown)?;
+    write_uint(writer, GCEbmlSpec::EntryUID, entry.entry_uid)?;
+    write_utf8(
+        writer,
+        GCEbmlSpec::DataFormat,
+        entry.payload.data_format(),
+    )?;
+    match &entry.payload {
+        GameDataPayload::Raw(raw_data) => write_raw_data(writer, raw_data)?,
+        GameDataPayload::Archive(archive_data) => {
+            write_archive_data(writer, archive_data)?
+        }
+        GameDataPayload::Chd(chd_data) => write_chd_data(writer, chd_data)?,
+        GameDataPayload::BinCue(bin_cue_data) => {
+            write_bin_cue_data(writer, bin_cue_data)?
+        }
+        GameDataPayload::Ssmc(ssmc_data) => {
+            write_ssmc_data(writer, ssmc_data)?
+        }
+    }
+    end(writer, GCEbmlSpec::GameDataEntry)
+}
+
+fn write_file_properties<'a, W: Write>(
+    writer: &mut ElementWriter<'a, W>,
+    properties: &'a FileProperties,
+) -> Result<(), LibError> {
+    start_with(writer, GCEbmlSpec::FileProperties, &properties.unknown)?;
+    if let Some(file_name) = &properties.rom_file_name {
+        write_utf8(writer, GCEbmlSpec::ROMFileName, file_name)?;
+    }
+    write_uint(writer, GCEbmlSpec::ROMFileSize, properties.rom_file_size)?;
+    if let Some(region) = &properties.region {
+        write_utf8(writer, GCEbmlSpec::Region, region)?;
+    }
+    for language in &properties.languages {
+        write_utf8(writer, GCEbmlSpec::Language, language)?;
+    }
+    if let Some(crc32) = &properties.crc32 {
+        write_binary(writer, GCEbmlSpec::CRC32, crc32)?;
+    }
+    if let Some(md5) = &properties.md5 {
+        write_binary(writer, GCEbmlSpec::MD5, md5)?;
+    }
+    if let Some(sha1) = &properties.sha1 {
+        write_binary(writer, GCEbmlSpec::SHA1, sha1)?;
+    }
+    if let Some(sha256) = &properties.sha256 {
+        write_binary(writer, GCEbmlSpec::SHA256, sha256)?;
+    }
+    if let Some(sha512) = &properties.sha512 {
+        write_binary(writer, GCEbmlSpec::SHA512, sha512)?;
+    }
+    if let Some(ssmc_index) = properties.ssmc_index {
+        write_uint(writer, GCEbmlSpec::SSMCIndex, ssmc_index)?;
+    }
+    end(writer, GCEbmlSpec::FileProperties)
+}
+
+fn write_raw_data<'a, W: Write>(
+    writer: &mut ElementWriter<'a, W>,
+    raw_data: &'a RawData,
+) -> Result<(), LibError> {
+    start_with(writer, GCEbmlSpec::RawData, &raw_data.unknown)?;
+    write_file_properties(writer, &raw_data.file_properties)?;
+    write_binary(writer, GCEbmlSpec::ROMData, &raw_data.rom_data)?;
+    end(writer, GCEbmlSpec::RawData)
+}
+
+fn write_archive_data<'a, W: Write>(
+    writer: &mut ElementWriter<'a, W>,
+    archive_data: &'a ArchiveData,
+) -> Result<(), LibError> {
+    start_with(writer, GCEbmlSpec::ArchiveData, &archive_data.unknown)?;
+    write_utf8(
+        writer,
+        GCEbmlSpec::CompressionAlgorithm,
+        &archive_data.compression_algorithm,
+    )?;
+    write_uint(
+        writer,
+        GCEbmlSpec::ArchivedFileCount,
+        archive_data.archived_file_count,
+    )?;
+    for properties in &archive_data.file_metadata {
+        start(writer, GCEbmlSpec::ArchiveFileMetadata)?;
+        write_file_properties(writer, properties)?;
+        end(writer, GCEbmlSpec::ArchiveFileMetadata)?;
+    }
+    write_binary(
+        writer, GCEbmlSpec::ArchiveBinary, &archive_data.archive_binary
+    )?;
+    end(writer, GCEbmlSpec::ArchiveData)
+}
+
+fn write_chd_data<'a, W: Write>(
+    writer: &mut ElementWriter<'a, W>,
+    chd_data: &'a ChdData,
+) -> Result<(), LibError> {
+    start_with(writer, GCEbmlSpec::ChdData, &chd_data.unknown)?;
+    write_utf8(writer, GCEbmlSpec::Region, &chd_data.region)?;
+    for language in &chd_data.languages {
+        write_utf8(writer, GCEbmlSpec::Language, language)?;
+    }
+    write_uint(writer, GCEbmlSpec::CHDCount, chd_data.chd_count)?;
+    for entry in &chd_data.entries {
+        start_with(writer, GCEbmlSpec::CHDEntry, &entry.unknown)?;
+        write_uint(writer, GCEbmlSpec::DiscNum, entry.disc_num)?;
+        write_file_properties(writer, &entry.file_properties)?;
+        write_binary(writer, GCEbmlSpec::CHDBinary, &entry.chd_binary)?;
+        end(writer, GCEbmlSpec::CHDEntry)?;
+    }
+    end(writer, GCEbmlSpec::ChdData)
+}
+
+fn write_bin_cue_data<'a, W: Write>(
+    writer: &mut ElementWriter<'a, W>,
+    bin_cue_data: &'a BinCueData,
+) -> Result<(), LibError> {
+    start_with(writer, GCEbmlSpec::BinCueData, &bin_cue_data.unknown)?;
+    write_utf8(writer, GCEbmlSpec::Region, &bin_cue_data.region)?;
+    for language in &bin_cue_data.languages {
+        write_utf8(writer, GCEbmlSpec::Language, language)?;
+    }
+    write_uint(writer, GCEbmlSpec::BinCueCount, bin_cue_data.bin_cue_count)?;
+    for entry in &bin_cue_data.entries {
+        start_with(writer, GCEbmlSpec::BinCueEntry, &entry.unknown)?;
+        write_uint(writer, GCEbmlSpec::DiscNum, entry.disc_num)?;
+        write_utf8(writer, GCEbmlSpec::CueSheet, &entry.cue_sheet)?;
+        write_file_properties(writer, &entry.file_properties)?;
+        write_binary(writer, GCEbmlSpec::BinBinary, &entry.bin_binary)?;
+        end(writer, GCEbmlSpec::BinCueEntry)?;
+    }
+    end(writer, GCEbmlSpec::BinCueData)
+}
+
+fn write_ssmc_data<'a, W: Write>(
+    writer: &mut ElementWriter<'a, W>,
+    ssmc_data: &'a SsmcData,
+) -> Result<(), LibError> {
+    start_with(writer, GCEbmlSpec::SSMCData, &ssmc_data.unknown)?;
+    write_uint(
+        writer,
+        GCEbmlSpec::ArchivedFileCount,
+        ssmc_data.archived_file_count,
+    )?;
+    for properties in &ssmc_data.entries {
+        start(writer, GCEbmlSpec::SSMCEntry)?;
+        write_file_properties(writer, properties)?;
+        end(writer, GCEbmlSpec::SSMCEntry)?;
+    }
+    write_binary(writer, GCEbmlSpec::SSMCBinary, &ssmc_data.ssmc_binary)?;
+    end(writer, GCEbmlSpec::SSMCData)
+}
+
+pub(crate) fn write_manual_entry<'a, W: Write>(
+    writer: &mut ElementWriter<'a, W>,
+    entry: &'a ManualEntry,
+) -> Result<(), LibError> {
+    start_with(writer, GCEbmlSpec::ManualEntry, &entry.unknown)?;
+    write_uint(writer, GCEbmlSpec::EntryUID, entry.entry_uid)?;
+    write_utf8(writer, GCEbmlSpec::Region, &entry.region)?;
+    for language in &entry.languages {
+        write_utf8(writer, GCEbmlSpec::Language, language)?;
+    }
+    write_uint(writer, GCEbmlSpec::PageCount, entry.page_count)?;
+    write_utf8(writer, GCEbmlSpec::Revision, &entry.revision)?;
+    write_utf8(writer, GCEbmlSpec::ManualFormat, &entry.manual_format)?;
+    write_binary(writer, GCEbmlSpec::ManualData, &entry.manual_data)?;
+    end(writer, GCEbmlSpec::ManualEntry)
+}
+
+pub(crate) fn write_rom_hack_entry<'a, W: Write>(
+    writer: &mut ElementWriter<'a, W>,
+    entry: &'a RomHackEntry,
+) -> Result<(), LibError> {
+    start_with(writer, GCEbmlSpec::RomHackEntry, &entry.unknown)?;
+    write_uint(writer, GCEbmlSpec::EntryUID, entry.entry_uid)?;
+    write_utf8(writer, GCEbmlSpec::Title, &entry.title)?;
+    for developer in &entry.developers {
+        write_utf8(writer, GCEbmlSpec::Developer, developer)?;
+    }
+    if let Some(hack_version) = &entry.hack_version {
+        write_utf8(writer, GCEbmlSpec::HackVersion, hack_version)?;
+    }
+    if let Some(patch_format) = &entry.patch_format {
+        write_utf8(writer, GCEbmlSpec::PatchFormat, patch_format)?;
+    }
+    for target_hash in &entry.target_hashes {
+        start_with(writer, GCEbmlSpec::TargetHash, &target_hash.unknown)?;
+        write_utf8(writer, GCEbmlSpec::HashFormat, &target_hash.hash_format)?;
+        write_binary(writer, GCEbmlSpec::HashValue, &target_hash.hash_value)?;
+        end(writer, GCEbmlSpec::TargetHash)?;
+    }
+    if let Some(description) = &entry.hack_description {
+        write_utf8(writer, GCEbmlSpec::HackDescription, description)?;
+    }
+    write_binary(writer, GCEbmlSpec::PatchData, &entry.patch_data)?;
+    end(writer, GCEbmlSpec::RomHackEntry)
+}
+
+fn write_media<'a, W: Write>(
+    writer: &mut ElementWriter<'a, W>,
+    media: &'a Media,
+) -> Result<(), LibError> {
+    start_with(writer, GCEbmlSpec::Media, &media.unknown)?;
+    for collection in &media.image_collections {
+        start_with(writer, GCEbmlSpec::ImageCollection, &collection.unknown)?;
+        write_utf8(writer, GCEbmlSpec::Category, &collection.category)?;
+        for entry in &collection.entries {
+            write_image_entry(writer, entry)?;
+        }
+        end(writer, GCEbmlSpec::ImageCollection)?;
+    }
+    for collection in &media.video_collections {
+        start_with(writer, GCEbmlSpec::VideoCollection, &collection.unknown)?;
+        write_utf8(writer, GCEbmlSpec::Category, &collection.category)?;
+        for entry in &collection.entries {
+            write_video_entry(writer, entry)?;
+        }
+        end(writer, GCEbmlSpec::VideoCollection)?;
+    }
+    end(writer, GCEbmlSpec::Media)
+}
+
+pub(crate) fn write_image_entry<'a, W: Write>(
+    writer: &mut ElementWriter<'a, W>,
+    entry: &'a ImageEntry,
+) -> Result<(), LibError> {
+    start_with(writer, GCEbmlSpec::ImageEntry, &entry.unknown)?;
+    write_uint(writer, GCEbmlSpec::EntryUID, entry.entry_uid)?;
+    if let Some(image_title) = &entry.image_title {
+        write_utf8(writer, GCEbmlSpec::ImageTitle, image_title)?;
+    }
+    for artist in &entry.artists {
+        write_utf8(writer, GCEbmlSpec::Artist, artist)?;
+    }
+    if let Some(region) = &entry.region {
+        write_utf8(writer, GCEbmlSpec::Region, region)?;
+    }
+    write_utf8(writer, GCEbmlSpec::ImageFormat, &entry.image_format)?;
+    write_uint(writer, GCEbmlSpec::Width, entry.width)?;
+    write_uint(writer, GCEbmlSpec::Height, entry.height)?;
+    if let Some(category_detail) = &entry.category_detail {
+        write_utf8(writer, GCEbmlSpec::CategoryDetail, category_detail)?;
+    }
+    write_binary(writer, GCEbmlSpec::ImageData, &entry.image_data)?;
+    end(writer, GCEbmlSpec::ImageEntry)
+}
+
+pub(crate) fn write_video_entry<'a, W: Write>(
+    writer: &mut ElementWriter<'a, W>,
+    entry: &'a VideoEntry,
+) -> Result<(), LibError> {
+    start_with(writer, GCEbmlSpec::VideoEntry, &entry.unknown)?;
+    write_uint(writer, GCEbmlSpec::EntryUID, entry.entry_uid)?;
+    if let Some(video_title) = &entry.video_title {
+        write_utf8(writer, GCEbmlSpec::VideoTitle, video_title)?;
+    }
+    for language in &entry.languages {
+        write_utf8(writer, GCEbmlSpec::Language, language)?;
+    }
+    write_uint(writer, GCEbmlSpec::Duration, entry.duration)?;
+    write_utf8(writer, GCEbmlSpec::VideoFormat, &entry.video_format)?;
+    write_uint(writer, GCEbmlSpec::Width, entry.width)?;
+    write_uint(writer, GCEbmlSpec::Height, entry.height)?;
+    write_utf8(writer, GCEbmlSpec::ImageFormat, &entry.image_format)?;
+    if let Some(thumbnail) = &entry.thumbnail {
+        write_binary(writer, GCEbmlSpec::Thumbnail, thumbnail)?;
+    }
+    write_binary(writer, GCEbmlSpec::VideoData, &entry.video_data)?;
+    end(writer, GCEbmlSpec::VideoEntry)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::io::{Cursor, Seek, SeekFrom};
+
+    use super::*;
+    use crate::date::GameDate;
+    use crate::ebml_tools::{ElementSpan, VOID_ID};
+    use crate::edit::list_children;
+    use crate::lib_structs::{
+        FileProperties, Game, GameData, GameDataEntry, GameDataPayload,
+        ImageCollection, ImageEntry, ManualEntry, Manuals, Media, RawData,
+        RegionInfo,
+    };
+    use crate::parse::process_ebml_data;
+    use crate::read::{expect_header, GameCaseReader};
+
+    /// A case with a ROM, a manual and an image, one entry of each.
+    pub(crate) fn sample_case() -> GameCase {
+        GameCase {
+            game: Game {
+                title: "Zelda".to_string(),
+                developers: vec!["Nintendo".to_string()],
+                game_system: "NES".to_string(),
+                description: Some("An adventure".to_string()),
+                region_info: vec![RegionInfo {
+                    region: "USA".to_string(),
+                    languages: vec!["en".to_string()],
+                    release_date: GameDate::from_year_month(1987, 8)
+                        .unwrap()
+                        .into(),
+                    publisher: "Nintendo".to_string(),
+                    age_rating: "E".to_string(),
+                    ..Default::default()
+                }],
+                game_data: Some(GameData {
+                    entries: vec![GameDataEntry {
+                        entry_uid: 1,
+                        payload: GameDataPayload::Raw(RawData {
+                            file_properties: FileProperties {
+                                rom_file_name: Some("zelda.nes".to_string()),
+                                rom_file_size: 300,
+                                region: Some("USA".to_string()),
+                                languages: vec!["en".to_string()],
+                                ..Default::default()
+                            },
+                            rom_data: vec![7; 300],
+                            ..Default::default()
+                        }),
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            manuals: Some(Manuals {
+                entries: vec![ManualEntry {
+                    entry_uid: 2,
+                    region: "USA".to_string(),
+                    languages: vec!["en".to_string()],
+                    page_count: 40,
+                    revision: "A".to_string(),
+                    manual_format: "PDF".to_string(),
+                    manual_data: vec![1; 50],
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }),
+            media: Some(Media {
+                image_collections: vec![ImageCollection {
+                    category: "BoxArt".to_string(),
+                    entries: vec![ImageEntry {
+                        entry_uid: 3,
+                        image_format: "PNG".to_string(),
+                        width: 2,
+                        height: 2,
+                        image_data: vec![9; 16],
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn encode_case(game_case: &GameCase) -> Vec<u8> {
+        let mut writer = GameCaseWriter::new(Vec::new());
+        writer.write(game_case).unwrap();
+        writer.into_inner()
+    }
+
+    /// Replaces the `Index` of the case in `bytes` with the entries `edit`
+    /// makes of it, e.g. to make it stale. The new `Index` takes the space
+    /// of the old one and of the `Void` padding following it.
+    pub(crate) fn rewrite_index<F>(bytes: &mut [u8], edit: F)
+    where
+        F: FnOnce(&mut Vec<IndexEntry>),
+    {
+        let mut source = Cursor::new(&*bytes);
+        let reader = GameCaseReader::open(&mut source).unwrap();
+        let game_case_start = reader.game_case_start();
+        let mut entries = reader.index().entries.clone();
+        edit(&mut entries);
+
+        source.seek(SeekFrom::Start(game_case_start)).unwrap();
+        let header =
+            expect_header(&mut source, master_id(GCEbmlSpec::GameCase))
+                .unwrap();
+        let span = ElementSpan::new(game_case_start, header);
+        let children = list_children(&mut source, span).unwrap();
+        let (index_start, _, mut index_len) = children[0];
+        if let Some(&(_, VOID_ID, void_len)) = children.get(1) {
+            index_len += void_len;
+        }
+
+        let mut index = encode_index_entries(&entries).unwrap();
+        index.extend(encode_void(index_len - index.len() as u64));
+        let index_start = index_start as usize;
+        bytes[index_start..index_start + index.len()].copy_from_slice(&index);
+    }
+
+    #[test]
+    fn written_case_reads_back_unchanged() {
+        let mut game_case = sample_case();
+        let read = process_ebml_data(Cursor::new(encode_case(&game_case)))
+            .unwrap();
+
+        let uids: Vec<u64> =
+            read.index.entries.iter().map(|entry| entry.uid).collect();
+        assert_eq!(uids, [1, 2, 3]);
+
+        //The Index is generated by the writer.
+        game_case.index = read.index.clone();
+        assert_eq!(read, game_case);
+    }
+
+    #[test]
+    fn index_positions_point_at_their_entries() {
+        let bytes = encode_case(&sample_case());
+        let mut reader = GameCaseReader::open(Cursor::new(bytes)).unwrap();
+
+        for index_entry in reader.index().entries.clone() {
+            let entry = reader.read_entry(&index_entry).unwrap();
+            assert_eq!(entry.entry_uid(), index_entry.uid);
+        }
+    }
+
+    #[test]
+    fn duplicate_uids_are_not_written() {
+        let mut game_case = sample_case();
+        if let Some(manuals) = &mut game_case.manuals {
+            manuals.entries[0].entry_uid = 1;
+        }
+
+        let mut writer = GameCaseWriter::new(Vec::new());
+        let error = writer.write(&game_case).unwrap_err();
+
+        assert!(matches!(error, LibError::DuplicateEntryUid(1)));
+        assert!(writer.into_inner().is_empty());
+    }
+}